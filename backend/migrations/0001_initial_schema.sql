@@ -1,32 +1,8 @@
--- IT-Engineering Collaboration Dashboard Database Schema
--- SQLite version
-
--- Drop existing tables if they exist (for clean migration)
-DROP TABLE IF EXISTS notification_preferences;
-DROP TABLE IF EXISTS notifications;
-DROP TABLE IF EXISTS glossary_history;
-DROP TABLE IF EXISTS glossary_terms;
-DROP TABLE IF EXISTS glossary_categories;
-DROP TABLE IF EXISTS user_quick_links;
-DROP TABLE IF EXISTS quick_links;
-DROP TABLE IF EXISTS user_locations;
-DROP TABLE IF EXISTS check_in_records;
-DROP TABLE IF EXISTS bookings;
-DROP TABLE IF EXISTS equipment;
-DROP TABLE IF EXISTS task_assignees;
-DROP TABLE IF EXISTS task_history;
-DROP TABLE IF EXISTS tasks;
-DROP TABLE IF EXISTS project_members;
-DROP TABLE IF EXISTS projects;
-DROP TABLE IF EXISTS event_attendees;
-DROP TABLE IF EXISTS events;
-DROP TABLE IF EXISTS sessions;
-DROP TABLE IF EXISTS users;
-
--- Also drop old tables from previous schema
-DROP TABLE IF EXISTS bugs;
-DROP TABLE IF EXISTS developers;
-DROP TABLE IF EXISTS projects;
+-- 0001: initial schema
+--
+-- Baseline of the IT-Engineering Collaboration Dashboard schema. Every
+-- statement is idempotent so databases created by the old schema.sql
+-- bootstrap can adopt the migration history without losing data.
 
 -- 1. Users & Authentication
 
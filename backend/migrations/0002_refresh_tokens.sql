@@ -0,0 +1,18 @@
+-- 0002: rotating refresh tokens
+--
+-- sessions.token now holds the SHA-256 hash of the session's current refresh
+-- token. Existing rows hold plaintext session tokens that can never match a
+-- hash, so they are dropped and everyone signs in again.
+
+DELETE FROM sessions;
+
+-- Hashes of refresh tokens that have already been exchanged. Seeing one again
+-- means the token leaked, and the session it belonged to is revoked.
+CREATE TABLE IF NOT EXISTS rotated_refresh_tokens (
+    token_hash TEXT PRIMARY KEY,
+    session_id TEXT NOT NULL,
+    rotated_at TEXT DEFAULT (datetime('now')),
+    FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_rotated_refresh_tokens_session_id ON rotated_refresh_tokens(session_id);
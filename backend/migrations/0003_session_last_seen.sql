@@ -0,0 +1,9 @@
+-- 0003: session last-seen time
+--
+-- Updated whenever the session's refresh token is exchanged. Access tokens
+-- are not checked against the database, so this is accurate to within one
+-- access token lifetime.
+
+ALTER TABLE sessions ADD COLUMN last_used_at TEXT;
+
+UPDATE sessions SET last_used_at = created_at WHERE last_used_at IS NULL;
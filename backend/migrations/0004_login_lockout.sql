@@ -0,0 +1,24 @@
+-- 0004: login lockout
+--
+-- Failed password attempts are counted per account. Each failure delays the
+-- next attempt exponentially, and reaching the limit locks the account until
+-- `locked_until`. Both reset on a successful login or an admin unlock.
+--
+-- `auth_audit_log` records failed logins, lockouts and unlocks.
+
+ALTER TABLE users ADD COLUMN failed_login_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN last_failed_login_at TEXT;
+ALTER TABLE users ADD COLUMN locked_until TEXT;
+
+CREATE TABLE IF NOT EXISTS auth_audit_log (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    event TEXT NOT NULL CHECK (event IN ('login_failed', 'account_locked', 'account_unlocked')),
+    actor_id TEXT,
+    ip_address TEXT,
+    created_at TEXT DEFAULT (datetime('now')),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (actor_id) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_auth_audit_log_user_id ON auth_audit_log(user_id);
@@ -0,0 +1,16 @@
+-- 0005: password reset tokens
+--
+-- One row per reset link sent. Only the SHA-256 hash of the token is stored.
+-- A token works once (`used_at` is set when it is redeemed) and only until
+-- `expires_at`. Requesting a new link deletes the user's unused ones.
+
+CREATE TABLE IF NOT EXISTS password_reset_tokens (
+    token_hash TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    used_at TEXT,
+    created_at TEXT DEFAULT (datetime('now')),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_password_reset_tokens_user_id ON password_reset_tokens(user_id);
@@ -0,0 +1,15 @@
+-- 0006: event recurrence
+--
+-- A recurring series is one `events` row with `is_recurring = 1` and an
+-- RRULE in `recurrence_rule`; its occurrences are expanded when events are
+-- listed. Dates removed from a series are listed in `recurrence_exdates`.
+-- An occurrence edited on its own becomes a row of its own, linked to the
+-- series by `parent_event_id`, with the date it replaces in `original_date`.
+
+ALTER TABLE events ADD COLUMN recurrence_exdates TEXT;
+ALTER TABLE events ADD COLUMN original_date TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_events_parent ON events(parent_event_id);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_events_override
+    ON events(parent_event_id, original_date)
+    WHERE original_date IS NOT NULL;
@@ -0,0 +1,13 @@
+-- 0007: calendar feed tokens
+--
+-- Secret for each user's iCalendar feed URL (`/api/calendar/{token}.ics`),
+-- which calendar clients fetch without logging in. Only the SHA-256 hash of
+-- the token is stored, one per user; creating a new feed URL replaces the old.
+
+CREATE TABLE IF NOT EXISTS calendar_feed_tokens (
+    user_id TEXT PRIMARY KEY,
+    token_hash TEXT UNIQUE NOT NULL,
+    created_at TEXT DEFAULT (datetime('now')),
+    last_used_at TEXT,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
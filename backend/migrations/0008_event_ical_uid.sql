@@ -0,0 +1,12 @@
+-- 0008: event iCalendar UIDs
+--
+-- Events imported from an .ics file keep the `UID` they had there, so
+-- importing the same file again updates them instead of adding copies. Only
+-- a series or single event holds the UID; occurrences edited on their own
+-- are found through their series.
+
+ALTER TABLE events ADD COLUMN ical_uid TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_events_ical_uid
+    ON events(ical_uid)
+    WHERE ical_uid IS NOT NULL;
@@ -0,0 +1,11 @@
+-- 0009: event RSVP responses
+--
+-- Invitees answer an invitation themselves. Besides the status, a response
+-- can carry a comment for the organizer and a proposed alternative time.
+-- `responded_at` stays NULL while the invitation is pending.
+
+ALTER TABLE event_attendees ADD COLUMN response_comment TEXT;
+ALTER TABLE event_attendees ADD COLUMN responded_at TEXT;
+ALTER TABLE event_attendees ADD COLUMN proposed_date TEXT;
+ALTER TABLE event_attendees ADD COLUMN proposed_start_time TEXT;
+ALTER TABLE event_attendees ADD COLUMN proposed_end_time TEXT;
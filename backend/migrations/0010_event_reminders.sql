@@ -0,0 +1,36 @@
+-- 0010: event reminders
+--
+-- A background task notifies organizers and attendees shortly before events
+-- start. The minutes before start come from the event itself
+-- (`events.reminder_minutes`), else the user's setting for that event type,
+-- else a built-in default. Both are comma-separated lists of minutes; an
+-- empty list turns reminders off.
+--
+-- `sent_reminders` records each reminder once it is sent, so none is sent
+-- twice, including across restarts. Keyed by the start time, so moving an
+-- event sends its reminders again.
+
+ALTER TABLE events ADD COLUMN reminder_minutes TEXT;
+
+CREATE TABLE IF NOT EXISTS reminder_preferences (
+    user_id TEXT NOT NULL,
+    event_type TEXT NOT NULL CHECK (event_type IN ('meeting', 'deadline', 'delivery')),
+    reminder_minutes TEXT NOT NULL,
+    updated_at TEXT DEFAULT (datetime('now')),
+    PRIMARY KEY (user_id, event_type),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS sent_reminders (
+    id TEXT PRIMARY KEY,
+    event_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    starts_at TEXT NOT NULL,
+    minutes_before INTEGER NOT NULL,
+    sent_at TEXT DEFAULT (datetime('now')),
+    FOREIGN KEY (event_id) REFERENCES events(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    UNIQUE (event_id, user_id, starts_at, minutes_before)
+);
+
+CREATE INDEX IF NOT EXISTS idx_sent_reminders_starts ON sent_reminders(starts_at);
@@ -0,0 +1,25 @@
+-- 0011: time zones
+--
+-- Timestamps are stored in UTC as `YYYY-MM-DD HH:MM:SS`, the format written
+-- by `datetime('now')`, so that they compare correctly as text. Older code
+-- also wrote RFC 3339 strings (`2026-01-05T09:00:00+00:00`, `...Z`); those
+-- are converted to UTC in the same format.
+--
+-- Users choose the time zone that decides what "today" means for them.
+-- Event dates and times are wall-clock times in the event's own zone, which
+-- defaults to the organizer's. Existing rows keep their meaning as UTC.
+
+ALTER TABLE users ADD COLUMN timezone TEXT NOT NULL DEFAULT 'UTC';
+ALTER TABLE events ADD COLUMN timezone TEXT NOT NULL DEFAULT 'UTC';
+
+UPDATE sessions SET expires_at = datetime(expires_at)
+WHERE expires_at LIKE '%T%' AND datetime(expires_at) IS NOT NULL;
+
+UPDATE check_in_records SET check_in_time = datetime(check_in_time)
+WHERE check_in_time LIKE '%T%' AND datetime(check_in_time) IS NOT NULL;
+
+UPDATE check_in_records SET check_out_time = datetime(check_out_time)
+WHERE check_out_time LIKE '%T%' AND datetime(check_out_time) IS NOT NULL;
+
+UPDATE user_locations SET last_check_in = datetime(last_check_in)
+WHERE last_check_in LIKE '%T%' AND datetime(last_check_in) IS NOT NULL;
@@ -0,0 +1,40 @@
+-- 0012: event templates
+--
+-- Defaults for events that are created again and again, such as a weekly
+-- sync or a regular delivery. Creating an event from a template copies its
+-- fields and attendees; anything sent with the event overrides them.
+--
+-- `personal` templates are seen only by their creator. `department` ones are
+-- shared with everyone in the template's department, or everyone when that
+-- is 'Both'.
+
+CREATE TABLE IF NOT EXISTS event_templates (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    title TEXT,
+    description TEXT,
+    event_type TEXT NOT NULL CHECK (event_type IN ('deadline', 'meeting', 'delivery')),
+    start_time TEXT,
+    duration_minutes INTEGER CHECK (duration_minutes > 0),
+    location TEXT,
+    meeting_url TEXT,
+    department TEXT NOT NULL CHECK (department IN ('IT', 'Engineering', 'Both')),
+    recurrence_rule TEXT,
+    reminder_minutes TEXT,
+    visibility TEXT NOT NULL DEFAULT 'personal' CHECK (visibility IN ('personal', 'department')),
+    created_by TEXT NOT NULL,
+    created_at TEXT DEFAULT (datetime('now')),
+    updated_at TEXT DEFAULT (datetime('now')),
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_event_templates_created_by ON event_templates(created_by);
+CREATE INDEX IF NOT EXISTS idx_event_templates_department ON event_templates(visibility, department);
+
+CREATE TABLE IF NOT EXISTS event_template_attendees (
+    template_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    PRIMARY KEY (template_id, user_id),
+    FOREIGN KEY (template_id) REFERENCES event_templates(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
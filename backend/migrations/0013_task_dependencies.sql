@@ -0,0 +1,19 @@
+-- 0013: task dependencies
+--
+-- A row says `task_id` cannot be completed until `depends_on_id` is, e.g.
+-- "commission the AHU controller" depends on "install the network switch".
+-- The API refuses edges that would form a cycle, so the graph stays acyclic.
+
+CREATE TABLE IF NOT EXISTS task_dependencies (
+    task_id TEXT NOT NULL,
+    depends_on_id TEXT NOT NULL,
+    created_by TEXT,
+    created_at TEXT DEFAULT (datetime('now')),
+    PRIMARY KEY (task_id, depends_on_id),
+    CHECK (task_id != depends_on_id),
+    FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE,
+    FOREIGN KEY (depends_on_id) REFERENCES tasks(id) ON DELETE CASCADE,
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_dependencies_depends_on ON task_dependencies(depends_on_id);
@@ -0,0 +1,30 @@
+-- 0014: task checklists
+--
+-- Steps of a task, such as the points of a BMS point-to-point verification,
+-- each with its own completion state, position and optional assignee.
+--
+-- With `checklist_auto_complete` set, ticking the last open item completes
+-- the task, and unticking one on a completed task reopens it.
+
+CREATE TABLE IF NOT EXISTS task_checklist_items (
+    id TEXT PRIMARY KEY,
+    task_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0,
+    is_completed INTEGER NOT NULL DEFAULT 0,
+    assignee_id TEXT,
+    completed_by TEXT,
+    completed_at TEXT,
+    created_by TEXT,
+    created_at TEXT DEFAULT (datetime('now')),
+    updated_at TEXT DEFAULT (datetime('now')),
+    FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE,
+    FOREIGN KEY (assignee_id) REFERENCES users(id) ON DELETE SET NULL,
+    FOREIGN KEY (completed_by) REFERENCES users(id) ON DELETE SET NULL,
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_checklist_items_task ON task_checklist_items(task_id, position);
+CREATE INDEX IF NOT EXISTS idx_task_checklist_items_assignee ON task_checklist_items(assignee_id);
+
+ALTER TABLE tasks ADD COLUMN checklist_auto_complete INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,47 @@
+-- 0015: recurring tasks
+--
+-- Routine work such as monthly filter checks or quarterly UPS tests. A
+-- recurring task holds the schedule (an RRULE whose dates are deadlines) and
+-- the fields of its instances. A background generator creates each instance
+-- `lead_days` before its deadline and advances `last_generated_date`, so
+-- completing, deleting or skipping an instance never recreates it.
+--
+-- `skipped_dates` lists occurrences that are not created, comma-separated
+-- like `events.recurrence_exdates`.
+
+CREATE TABLE IF NOT EXISTS recurring_tasks (
+    id TEXT PRIMARY KEY,
+    title TEXT NOT NULL,
+    description TEXT,
+    urgency TEXT NOT NULL CHECK (urgency IN ('urgent', 'high', 'medium', 'low')),
+    department TEXT NOT NULL CHECK (department IN ('IT', 'Engineering', 'Both')),
+    project_id TEXT,
+    recurrence_rule TEXT NOT NULL,
+    start_date TEXT NOT NULL,
+    lead_days INTEGER NOT NULL DEFAULT 7 CHECK (lead_days >= 0),
+    skipped_dates TEXT,
+    last_generated_date TEXT,
+    is_active INTEGER NOT NULL DEFAULT 1,
+    created_by TEXT NOT NULL,
+    created_at TEXT DEFAULT (datetime('now')),
+    updated_at TEXT DEFAULT (datetime('now')),
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE SET NULL,
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_recurring_tasks_active ON recurring_tasks(is_active);
+
+CREATE TABLE IF NOT EXISTS recurring_task_assignees (
+    recurring_task_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    PRIMARY KEY (recurring_task_id, user_id),
+    FOREIGN KEY (recurring_task_id) REFERENCES recurring_tasks(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+-- Instances keep their link, and their occurrence date, for as long as the
+-- recurring task exists
+ALTER TABLE tasks ADD COLUMN recurring_task_id TEXT REFERENCES recurring_tasks(id) ON DELETE SET NULL;
+ALTER TABLE tasks ADD COLUMN occurrence_date TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_tasks_recurring_occurrence ON tasks(recurring_task_id, occurrence_date);
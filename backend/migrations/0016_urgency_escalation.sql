@@ -0,0 +1,34 @@
+-- 0016: urgency escalation
+--
+-- Rules that raise a task's urgency as its deadline approaches or passes.
+-- Each department has its own set: a task gets the highest urgency among the
+-- rules of its department whose `hours_before_deadline` has been reached. A
+-- negative value applies once the deadline is that many hours overdue.
+--
+-- Escalation only raises urgency, and each level once: `tasks.escalated_urgency`
+-- is the last level applied, so a task lowered by hand stays lowered until a
+-- higher rule is reached or its deadline changes. Completed tasks are left
+-- alone.
+
+CREATE TABLE IF NOT EXISTS urgency_escalation_rules (
+    id TEXT PRIMARY KEY,
+    department TEXT NOT NULL CHECK (department IN ('IT', 'Engineering', 'Both')),
+    hours_before_deadline INTEGER NOT NULL,
+    urgency TEXT NOT NULL CHECK (urgency IN ('high', 'medium', 'urgent')),
+    created_by TEXT,
+    created_at TEXT DEFAULT (datetime('now')),
+    updated_at TEXT DEFAULT (datetime('now')),
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL,
+    UNIQUE (department, hours_before_deadline)
+);
+
+-- Defaults: medium three days out, high the day before, urgent in the last
+-- four hours
+INSERT OR IGNORE INTO urgency_escalation_rules (id, department, hours_before_deadline, urgency)
+SELECT lower(hex(randomblob(16))), d.department, r.hours, r.urgency
+FROM (SELECT 'IT' AS department UNION ALL SELECT 'Engineering' UNION ALL SELECT 'Both') d,
+     (SELECT 72 AS hours, 'medium' AS urgency
+      UNION ALL SELECT 24, 'high'
+      UNION ALL SELECT 4, 'urgent') r;
+
+ALTER TABLE tasks ADD COLUMN escalated_urgency TEXT;
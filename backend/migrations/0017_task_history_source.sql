@@ -0,0 +1,32 @@
+-- 0017: task history source
+--
+-- Changes made by the system rather than a user, such as urgency escalation,
+-- are recorded with no `user_id` and their origin in `source`. SQLite cannot
+-- drop a NOT NULL constraint, so the table is rebuilt; copying the rowids
+-- keeps the order of changes recorded in the same second.
+
+CREATE TABLE task_history_new (
+    id TEXT PRIMARY KEY,
+    task_id TEXT NOT NULL,
+    user_id TEXT,
+    source TEXT NOT NULL DEFAULT 'user' CHECK (source IN ('user', 'escalation')),
+    action TEXT NOT NULL CHECK (action IN ('created', 'updated', 'status_changed', 'deleted')),
+    field_changed TEXT,
+    old_value TEXT,
+    new_value TEXT,
+    created_at TEXT DEFAULT (datetime('now')),
+    FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id),
+    CHECK (user_id IS NOT NULL OR source != 'user')
+);
+
+INSERT INTO task_history_new (rowid, id, task_id, user_id, action, field_changed, old_value, new_value, created_at)
+SELECT rowid, id, task_id, user_id, action, field_changed, old_value, new_value, created_at
+FROM task_history;
+
+DROP TABLE task_history;
+ALTER TABLE task_history_new RENAME TO task_history;
+
+CREATE INDEX IF NOT EXISTS idx_task_history_task ON task_history(task_id);
+CREATE INDEX IF NOT EXISTS idx_task_history_user ON task_history(user_id);
+CREATE INDEX IF NOT EXISTS idx_task_history_created ON task_history(created_at);
@@ -0,0 +1,11 @@
+-- 0018: task deadlines in UTC
+--
+-- A deadline is either a date (`YYYY-MM-DD`) or, with a time, a UTC
+-- timestamp in the format of `datetime('now')`. Deadlines were stored as
+-- clients sent them, including RFC 3339 strings with an offset; those are
+-- converted to UTC like the timestamps in 0011.
+
+UPDATE tasks SET deadline = datetime(deadline)
+WHERE length(trim(deadline)) > 10
+  AND datetime(deadline) IS NOT NULL
+  AND deadline != datetime(deadline);
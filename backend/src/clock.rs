@@ -0,0 +1,39 @@
+//! The current time, behind a trait so that code driven by it can be tested
+//! without waiting.
+
+use chrono::NaiveDateTime;
+
+pub trait Clock: Send + Sync {
+    /// The current time in UTC
+    fn now(&self) -> NaiveDateTime;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> NaiveDateTime {
+        crate::timezone::now_utc()
+    }
+}
+
+/// A clock that only moves when told to.
+#[cfg(test)]
+pub struct FixedClock(std::sync::Mutex<NaiveDateTime>);
+
+#[cfg(test)]
+impl FixedClock {
+    pub fn new(now: NaiveDateTime) -> Self {
+        FixedClock(std::sync::Mutex::new(now))
+    }
+
+    pub fn set(&self, now: NaiveDateTime) {
+        *self.0.lock().unwrap() = now;
+    }
+}
+
+#[cfg(test)]
+impl Clock for FixedClock {
+    fn now(&self) -> NaiveDateTime {
+        *self.0.lock().unwrap()
+    }
+}
@@ -0,0 +1,495 @@
+//! Application Configuration Module
+//!
+//! Settings are resolved in order of increasing precedence:
+//! 1. Built-in defaults
+//! 2. An optional TOML file (`CONFIG_FILE`, or `config.toml` if present)
+//! 3. Environment variables, including those loaded from `.env`
+//!
+//! The resulting `Config` is validated once at startup and shared with the
+//! handlers as `web::Data<Config>`.
+
+use derive_more::Display;
+use serde::Deserialize;
+use std::env;
+use std::path::Path;
+
+/// The placeholder secret the project used to ship with. Only accepted in dev mode.
+pub const DEFAULT_JWT_SECRET: &str = "your-secret-key-change-in-production";
+
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+const MIN_JWT_SECRET_LEN: usize = 32;
+
+#[derive(Debug, Display)]
+pub enum ConfigError {
+    #[display(fmt = "Failed to read config file {}: {}", _0, _1)]
+    Io(String, std::io::Error),
+
+    #[display(fmt = "Failed to parse config file {}: {}", _0, _1)]
+    Parse(String, toml::de::Error),
+
+    #[display(fmt = "Invalid value for {}: {}", _0, _1)]
+    InvalidEnv(&'static str, String),
+
+    #[display(fmt = "Invalid configuration: {}", _0)]
+    Invalid(String),
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Where outgoing email goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MailerBackend {
+    /// Write each message to the server log
+    Log,
+    /// Write each message to a file in `mail_dir`
+    File,
+}
+
+impl std::str::FromStr for MailerBackend {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "log" => Ok(MailerBackend::Log),
+            "file" => Ok(MailerBackend::File),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub host: String,
+    pub port: u16,
+    pub database_url: String,
+    pub jwt_secret: String,
+    pub access_token_ttl_minutes: i64,
+    pub session_lifetime_hours: i64,
+    pub session_cleanup_interval_secs: u64,
+    /// How often due event reminders are sent
+    pub reminder_interval_secs: u64,
+    /// How often instances of recurring tasks are created
+    pub task_generation_interval_secs: u64,
+    /// How often task urgencies are escalated as deadlines approach
+    pub escalation_interval_secs: u64,
+    pub login_max_failures: i64,
+    pub login_lockout_minutes: i64,
+    pub rate_limit_per_minute: u32,
+    pub password_reset_ttl_minutes: i64,
+    /// Base URL of the web app, used for links in emails
+    pub frontend_url: String,
+    pub mailer: MailerBackend,
+    pub mail_from: String,
+    pub mail_dir: String,
+    pub dev_mode: bool,
+    pub seed_database: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+            database_url: "sqlite://database.db".to_string(),
+            jwt_secret: DEFAULT_JWT_SECRET.to_string(),
+            access_token_ttl_minutes: 15,
+            session_lifetime_hours: 24,
+            session_cleanup_interval_secs: 3600,
+            reminder_interval_secs: 60,
+            task_generation_interval_secs: 3600,
+            escalation_interval_secs: 300,
+            login_max_failures: 5,
+            login_lockout_minutes: 15,
+            rate_limit_per_minute: 300,
+            password_reset_ttl_minutes: 60,
+            frontend_url: "http://localhost:3000".to_string(),
+            mailer: MailerBackend::Log,
+            mail_from: "noreply@company.com".to_string(),
+            mail_dir: "mail".to_string(),
+            dev_mode: false,
+            seed_database: false,
+        }
+    }
+}
+
+impl Config {
+    /// Loads `.env`, the optional TOML file and the environment, then validates the result.
+    pub fn load() -> Result<Self, ConfigError> {
+        dotenvy::dotenv().ok();
+
+        let mut config = match env::var("CONFIG_FILE") {
+            Ok(path) => Self::from_file(&path)?,
+            Err(_) if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Self::from_file(DEFAULT_CONFIG_FILE)?
+            }
+            Err(_) => Self::default(),
+        };
+
+        config.apply_env()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn from_file(path: &str) -> Result<Self, ConfigError> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_string(), e))?;
+        toml::from_str(&contents).map_err(|e| ConfigError::Parse(path.to_string(), e))
+    }
+
+    fn apply_env(&mut self) -> Result<(), ConfigError> {
+        if let Ok(host) = env::var("HOST") {
+            self.host = host;
+        }
+        if let Some(port) = parse_env("PORT")? {
+            self.port = port;
+        }
+        if let Ok(database_url) = env::var("DATABASE_URL") {
+            self.database_url = database_url;
+        }
+        if let Ok(jwt_secret) = env::var("JWT_SECRET") {
+            self.jwt_secret = jwt_secret;
+        }
+        if let Some(minutes) = parse_env("ACCESS_TOKEN_TTL_MINUTES")? {
+            self.access_token_ttl_minutes = minutes;
+        }
+        if let Some(hours) = parse_env("SESSION_LIFETIME_HOURS")? {
+            self.session_lifetime_hours = hours;
+        }
+        if let Some(secs) = parse_env("SESSION_CLEANUP_INTERVAL_SECS")? {
+            self.session_cleanup_interval_secs = secs;
+        }
+        if let Some(secs) = parse_env("REMINDER_INTERVAL_SECS")? {
+            self.reminder_interval_secs = secs;
+        }
+        if let Some(secs) = parse_env("TASK_GENERATION_INTERVAL_SECS")? {
+            self.task_generation_interval_secs = secs;
+        }
+        if let Some(secs) = parse_env("ESCALATION_INTERVAL_SECS")? {
+            self.escalation_interval_secs = secs;
+        }
+        if let Some(failures) = parse_env("LOGIN_MAX_FAILURES")? {
+            self.login_max_failures = failures;
+        }
+        if let Some(minutes) = parse_env("LOGIN_LOCKOUT_MINUTES")? {
+            self.login_lockout_minutes = minutes;
+        }
+        if let Some(limit) = parse_env("RATE_LIMIT_PER_MINUTE")? {
+            self.rate_limit_per_minute = limit;
+        }
+        if let Some(minutes) = parse_env("PASSWORD_RESET_TTL_MINUTES")? {
+            self.password_reset_ttl_minutes = minutes;
+        }
+        if let Ok(frontend_url) = env::var("FRONTEND_URL") {
+            self.frontend_url = frontend_url;
+        }
+        if let Some(mailer) = parse_env("MAILER")? {
+            self.mailer = mailer;
+        }
+        if let Ok(mail_from) = env::var("MAIL_FROM") {
+            self.mail_from = mail_from;
+        }
+        if let Ok(mail_dir) = env::var("MAIL_DIR") {
+            self.mail_dir = mail_dir;
+        }
+        if let Some(dev_mode) = parse_bool_env("DEV_MODE")? {
+            self.dev_mode = dev_mode;
+        }
+        if let Some(seed) = parse_bool_env("SEED_DATABASE")? {
+            self.seed_database = seed;
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.host.trim().is_empty() {
+            return Err(ConfigError::Invalid("host must not be empty".into()));
+        }
+        if self.port == 0 {
+            return Err(ConfigError::Invalid("port must be non-zero".into()));
+        }
+        if !self.database_url.starts_with("sqlite:") {
+            return Err(ConfigError::Invalid(
+                "database_url must be a sqlite: URL".into(),
+            ));
+        }
+        if self.access_token_ttl_minutes <= 0 {
+            return Err(ConfigError::Invalid(
+                "access_token_ttl_minutes must be positive".into(),
+            ));
+        }
+        if self.session_lifetime_hours <= 0 {
+            return Err(ConfigError::Invalid(
+                "session_lifetime_hours must be positive".into(),
+            ));
+        }
+        if self.session_cleanup_interval_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "session_cleanup_interval_secs must be positive".into(),
+            ));
+        }
+        if self.reminder_interval_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "reminder_interval_secs must be positive".into(),
+            ));
+        }
+        if self.task_generation_interval_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "task_generation_interval_secs must be positive".into(),
+            ));
+        }
+        if self.escalation_interval_secs == 0 {
+            return Err(ConfigError::Invalid(
+                "escalation_interval_secs must be positive".into(),
+            ));
+        }
+        if self.login_max_failures <= 0 {
+            return Err(ConfigError::Invalid(
+                "login_max_failures must be positive".into(),
+            ));
+        }
+        if self.login_lockout_minutes <= 0 {
+            return Err(ConfigError::Invalid(
+                "login_lockout_minutes must be positive".into(),
+            ));
+        }
+        if self.rate_limit_per_minute == 0 {
+            return Err(ConfigError::Invalid(
+                "rate_limit_per_minute must be positive".into(),
+            ));
+        }
+        if self.password_reset_ttl_minutes <= 0 {
+            return Err(ConfigError::Invalid(
+                "password_reset_ttl_minutes must be positive".into(),
+            ));
+        }
+        if self.mailer == MailerBackend::File && self.mail_dir.trim().is_empty() {
+            return Err(ConfigError::Invalid(
+                "mail_dir must not be empty with the file mailer".into(),
+            ));
+        }
+        if self.jwt_secret.is_empty() {
+            return Err(ConfigError::Invalid("jwt_secret must not be empty".into()));
+        }
+        if !self.dev_mode {
+            if self.jwt_secret == DEFAULT_JWT_SECRET {
+                return Err(ConfigError::Invalid(
+                    "refusing to start with the default JWT secret; set JWT_SECRET or DEV_MODE=true"
+                        .into(),
+                ));
+            }
+            if self.jwt_secret.len() < MIN_JWT_SECRET_LEN {
+                return Err(ConfigError::Invalid(format!(
+                    "jwt_secret must be at least {} characters outside dev mode",
+                    MIN_JWT_SECRET_LEN
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn bind_address(&self) -> (String, u16) {
+        (self.host.clone(), self.port)
+    }
+
+    /// Lifetime of a signed access token.
+    pub fn access_token_ttl(&self) -> chrono::Duration {
+        chrono::Duration::minutes(self.access_token_ttl_minutes)
+    }
+
+    /// Lifetime of a session, i.e. how long its refresh token stays usable.
+    pub fn session_lifetime(&self) -> chrono::Duration {
+        chrono::Duration::hours(self.session_lifetime_hours)
+    }
+
+    /// How long a password reset link stays usable.
+    pub fn password_reset_ttl(&self) -> chrono::Duration {
+        chrono::Duration::minutes(self.password_reset_ttl_minutes)
+    }
+
+    /// How long an account stays locked after too many failed logins.
+    pub fn login_lockout(&self) -> chrono::Duration {
+        chrono::Duration::minutes(self.login_lockout_minutes)
+    }
+}
+
+fn parse_env<T: std::str::FromStr>(key: &'static str) -> Result<Option<T>, ConfigError> {
+    match env::var(key) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| ConfigError::InvalidEnv(key, value)),
+        Err(_) => Ok(None),
+    }
+}
+
+fn parse_bool_env(key: &'static str) -> Result<Option<bool>, ConfigError> {
+    match env::var(key) {
+        Ok(value) => match value.trim().to_lowercase().as_str() {
+            "1" | "true" | "yes" => Ok(Some(true)),
+            "0" | "false" | "no" | "" => Ok(Some(false)),
+            _ => Err(ConfigError::InvalidEnv(key, value)),
+        },
+        Err(_) => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// The environment is shared by the whole process, so tests that set
+    /// variables take turns.
+    static ENV: Mutex<()> = Mutex::new(());
+
+    fn production() -> Config {
+        Config {
+            jwt_secret: "a".repeat(MIN_JWT_SECRET_LEN),
+            ..Config::default()
+        }
+    }
+
+    fn invalid(config: &Config) -> String {
+        match config.validate() {
+            Err(ConfigError::Invalid(message)) => message,
+            other => panic!("expected an invalid configuration, got {:?}", other),
+        }
+    }
+
+    /// Writes `contents` to a file of its own and returns the path.
+    fn config_file(contents: &str) -> String {
+        let path = env::temp_dir().join(format!("config-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, contents).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    /// Runs `f` with the variables set, then removes them again.
+    fn with_env<T>(vars: &[(&str, &str)], f: impl FnOnce() -> T) -> T {
+        let _guard = ENV.lock().unwrap_or_else(|e| e.into_inner());
+        // SAFETY: only these tests touch the environment, one at a time
+        unsafe {
+            for (key, value) in vars {
+                env::set_var(key, value);
+            }
+        }
+        let result = f();
+        unsafe {
+            for (key, _) in vars {
+                env::remove_var(key);
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn a_long_secret_is_accepted() {
+        assert!(production().validate().is_ok());
+    }
+
+    #[test]
+    fn the_default_secret_is_only_accepted_in_dev_mode() {
+        let config = Config::default();
+        assert!(invalid(&config).contains("default JWT secret"));
+
+        let config = Config {
+            dev_mode: true,
+            ..Config::default()
+        };
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn a_short_secret_is_only_accepted_in_dev_mode() {
+        let mut config = Config {
+            jwt_secret: "a".repeat(MIN_JWT_SECRET_LEN - 1),
+            ..Config::default()
+        };
+        assert!(invalid(&config).contains("at least 32 characters"));
+
+        config.dev_mode = true;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn an_empty_secret_is_never_accepted() {
+        let config = Config {
+            jwt_secret: String::new(),
+            dev_mode: true,
+            ..Config::default()
+        };
+        assert_eq!(invalid(&config), "jwt_secret must not be empty");
+    }
+
+    #[test]
+    fn port_zero_is_rejected() {
+        let config = Config {
+            port: 0,
+            ..production()
+        };
+        assert_eq!(invalid(&config), "port must be non-zero");
+    }
+
+    #[test]
+    fn the_file_overrides_only_the_settings_it_names() {
+        let path = config_file("port = 9000\nmailer = \"file\"\n");
+        let config = Config::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.port, 9000);
+        assert_eq!(config.mailer, MailerBackend::File);
+        assert_eq!(config.host, "127.0.0.1");
+        assert_eq!(config.jwt_secret, DEFAULT_JWT_SECRET);
+    }
+
+    #[test]
+    fn unreadable_files_are_reported() {
+        let path = config_file("port = \"eighty\"\n");
+        let result = Config::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(ConfigError::Parse(p, _)) if p == path));
+
+        assert!(matches!(
+            Config::from_file(&path),
+            Err(ConfigError::Io(p, _)) if p == path
+        ));
+    }
+
+    #[test]
+    fn the_environment_overrides_the_file() {
+        let path = config_file("port = 9000\nhost = \"0.0.0.0\"\njwt_secret = \"from-file\"\n");
+        let mut config = Config::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let secret = "b".repeat(MIN_JWT_SECRET_LEN);
+        with_env(&[("PORT", "9100"), ("JWT_SECRET", &secret)], || {
+            config.apply_env()
+        })
+        .unwrap();
+
+        assert_eq!(config.port, 9100);
+        assert_eq!(config.jwt_secret, secret);
+        assert_eq!(config.host, "0.0.0.0");
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn bad_environment_values_are_rejected() {
+        let result = with_env(&[("PORT", "abc")], || Config::default().apply_env());
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidEnv("PORT", value)) if value == "abc"
+        ));
+
+        let result = with_env(&[("PORT", "70000")], || Config::default().apply_env());
+        assert!(matches!(result, Err(ConfigError::InvalidEnv("PORT", _))));
+
+        let result = with_env(&[("DEV_MODE", "maybe")], || Config::default().apply_env());
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidEnv("DEV_MODE", _))
+        ));
+    }
+}
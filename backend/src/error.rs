@@ -1,12 +1,23 @@
-use actix_web::{HttpResponse, ResponseError};
+use actix_web::{
+    HttpResponse, ResponseError,
+    http::{StatusCode, header},
+};
 use derive_more::Display;
+use serde_json::Value;
 use sqlx::Error as SqlxError;
 
+/// Application-wide error type.
+///
+/// Every variant renders as the standard error envelope:
+/// `{"success": false, "error": {"code": "...", "message": "...", "details": ...}}`
 #[derive(Debug, Display)]
 pub enum AppError {
     #[display(fmt = "Database Error: {}", _0)]
     Database(SqlxError),
 
+    #[display(fmt = "Internal Error: {}", _0)]
+    Internal(String),
+
     #[display(fmt = "Not Found: {}", _0)]
     NotFound(String),
 
@@ -16,20 +27,160 @@ pub enum AppError {
     #[display(fmt = "{}", _0)]
     BadRequest(String),
 
+    #[display(fmt = "Validation Error: {}", _0)]
+    Validation(String),
+
+    #[display(fmt = "Unauthorized: {}", _0)]
+    Unauthorized(String),
+
     #[display(fmt = "Forbidden: {}", _0)]
     Forbidden(String),
+
+    #[display(fmt = "Conflict: {}", _0)]
+    Conflict(String),
+
+    #[display(fmt = "Too Many Requests: {}", _0)]
+    TooManyRequests(String),
+
+    /// Any error with a more specific code, structured details or a
+    /// `Retry-After` header. Built with `AppError::with_code`,
+    /// `AppError::with_details` and `AppError::with_retry_after`.
+    #[display(fmt = "{}: {}", code, message)]
+    Detailed {
+        status: StatusCode,
+        code: String,
+        message: String,
+        details: Option<Value>,
+        retry_after: Option<u64>,
+    },
+}
+
+impl AppError {
+    /// Machine-readable error code sent to clients.
+    pub fn code(&self) -> &str {
+        match self {
+            AppError::Database(_) | AppError::Internal(_) | AppError::LockError(_) => {
+                "INTERNAL_ERROR"
+            }
+            AppError::NotFound(_) => "NOT_FOUND",
+            AppError::BadRequest(_) => "BAD_REQUEST",
+            AppError::Validation(_) => "VALIDATION_ERROR",
+            AppError::Unauthorized(_) => "UNAUTHORIZED",
+            AppError::Forbidden(_) => "FORBIDDEN",
+            AppError::Conflict(_) => "CONFLICT",
+            AppError::TooManyRequests(_) => "TOO_MANY_REQUESTS",
+            AppError::Detailed { code, .. } => code,
+        }
+    }
+
+    /// Human-readable message sent to clients. Internal failures are not leaked.
+    pub fn message(&self) -> String {
+        match self {
+            AppError::Database(_) => "Database error".to_string(),
+            AppError::LockError(_) => "Mutex lock error".to_string(),
+            AppError::Internal(msg)
+            | AppError::NotFound(msg)
+            | AppError::BadRequest(msg)
+            | AppError::Validation(msg)
+            | AppError::Unauthorized(msg)
+            | AppError::Forbidden(msg)
+            | AppError::Conflict(msg)
+            | AppError::TooManyRequests(msg) => msg.clone(),
+            AppError::Detailed { message, .. } => message.clone(),
+        }
+    }
+
+    fn details(&self) -> Option<&Value> {
+        match self {
+            AppError::Detailed { details, .. } => details.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Seconds the client should wait before retrying, if any.
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            AppError::Detailed { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Keeps the status and message but replaces the error code.
+    pub fn with_code(self, code: &str) -> AppError {
+        AppError::Detailed {
+            status: self.status_code(),
+            code: code.to_string(),
+            message: self.message(),
+            details: self.details().cloned(),
+            retry_after: self.retry_after(),
+        }
+    }
+
+    /// Attaches structured details to the error envelope.
+    pub fn with_details(self, details: Value) -> AppError {
+        AppError::Detailed {
+            status: self.status_code(),
+            code: self.code().to_string(),
+            message: self.message(),
+            details: Some(details),
+            retry_after: self.retry_after(),
+        }
+    }
+
+    /// Sends a `Retry-After` header and a `retryAfter` detail, in seconds.
+    pub fn with_retry_after(self, secs: u64) -> AppError {
+        AppError::Detailed {
+            status: self.status_code(),
+            code: self.code().to_string(),
+            message: self.message(),
+            details: Some(serde_json::json!({ "retryAfter": secs })),
+            retry_after: Some(secs),
+        }
+    }
 }
 
 impl std::error::Error for AppError {}
 impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::Database(_) | AppError::Internal(_) | AppError::LockError(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::BadRequest(_) | AppError::Validation(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Detailed { status, .. } => *status,
+        }
+    }
+
     fn error_response(&self) -> HttpResponse {
         match self {
-            AppError::Database(_) => HttpResponse::InternalServerError().body("Database Error"),
-            AppError::NotFound(msg) => HttpResponse::NotFound().body(msg.clone()),
-            AppError::LockError(_) => HttpResponse::InternalServerError().body("Mutex Lock Error"),
-            AppError::BadRequest(msg) => HttpResponse::BadRequest().body(msg.clone()),
-            AppError::Forbidden(msg) => HttpResponse::Forbidden().body(msg.clone()),
+            AppError::Database(e) => log::error!("Database error: {:?}", e),
+            AppError::Internal(msg) | AppError::LockError(msg) => log::error!("{}", msg),
+            _ => {}
+        }
+
+        let mut response = HttpResponse::build(self.status_code());
+        if let Some(secs) = self.retry_after() {
+            response.insert_header((header::RETRY_AFTER, secs.to_string()));
         }
+        response.json(serde_json::json!({
+            "success": false,
+            "error": {
+                "code": self.code(),
+                "message": self.message(),
+                "details": self.details()
+            }
+        }))
+    }
+}
+
+impl From<SqlxError> for AppError {
+    fn from(err: SqlxError) -> Self {
+        AppError::Database(err)
     }
 }
 
@@ -38,3 +189,139 @@ impl<T> From<std::sync::PoisonError<T>> for AppError {
         AppError::LockError(err.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::body::to_bytes;
+
+    async fn envelope(error: &AppError) -> (StatusCode, Option<String>, Value) {
+        let response = error.error_response();
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(header::RETRY_AFTER)
+            .map(|value| value.to_str().unwrap().to_string());
+        let body = to_bytes(response.into_body()).await.unwrap();
+        (status, retry_after, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[test]
+    fn variants_map_to_their_status_and_code() {
+        let cases = [
+            (
+                AppError::NotFound("x".into()),
+                StatusCode::NOT_FOUND,
+                "NOT_FOUND",
+            ),
+            (
+                AppError::BadRequest("x".into()),
+                StatusCode::BAD_REQUEST,
+                "BAD_REQUEST",
+            ),
+            (
+                AppError::Validation("x".into()),
+                StatusCode::BAD_REQUEST,
+                "VALIDATION_ERROR",
+            ),
+            (
+                AppError::Unauthorized("x".into()),
+                StatusCode::UNAUTHORIZED,
+                "UNAUTHORIZED",
+            ),
+            (
+                AppError::Forbidden("x".into()),
+                StatusCode::FORBIDDEN,
+                "FORBIDDEN",
+            ),
+            (
+                AppError::Conflict("x".into()),
+                StatusCode::CONFLICT,
+                "CONFLICT",
+            ),
+            (
+                AppError::TooManyRequests("x".into()),
+                StatusCode::TOO_MANY_REQUESTS,
+                "TOO_MANY_REQUESTS",
+            ),
+            (
+                AppError::Internal("x".into()),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "INTERNAL_ERROR",
+            ),
+            (
+                AppError::Database(SqlxError::RowNotFound),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "INTERNAL_ERROR",
+            ),
+        ];
+        for (error, status, code) in cases {
+            assert_eq!(error.status_code(), status, "{}", error);
+            assert_eq!(error.code(), code, "{}", error);
+        }
+    }
+
+    #[actix_web::test]
+    async fn errors_render_the_standard_envelope() {
+        let (status, retry_after, body) =
+            envelope(&AppError::NotFound("Task not found".into())).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(retry_after, None);
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "success": false,
+                "error": {
+                    "code": "NOT_FOUND",
+                    "message": "Task not found",
+                    "details": null
+                }
+            })
+        );
+    }
+
+    #[actix_web::test]
+    async fn internal_failures_are_not_leaked() {
+        let (_, _, body) = envelope(&AppError::Database(SqlxError::RowNotFound)).await;
+        assert_eq!(body["error"]["message"], "Database error");
+
+        let (_, _, body) = envelope(&AppError::LockError("poisoned".into())).await;
+        assert_eq!(body["error"]["message"], "Mutex lock error");
+    }
+
+    #[actix_web::test]
+    async fn with_code_keeps_the_status_and_message() {
+        let error = AppError::Forbidden("Not yours".into()).with_code("NOT_TASK_OWNER");
+        let (status, _, body) = envelope(&error).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["error"]["code"], "NOT_TASK_OWNER");
+        assert_eq!(body["error"]["message"], "Not yours");
+    }
+
+    #[actix_web::test]
+    async fn with_details_keeps_an_earlier_code() {
+        let error = AppError::Conflict("Blocked".into())
+            .with_code("TASK_BLOCKED")
+            .with_details(serde_json::json!({ "blockedBy": ["a"] }));
+        let (status, _, body) = envelope(&error).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"]["code"], "TASK_BLOCKED");
+        assert_eq!(body["error"]["details"]["blockedBy"][0], "a");
+    }
+
+    #[actix_web::test]
+    async fn with_retry_after_sets_the_header_and_detail() {
+        let error = AppError::TooManyRequests("Slow down".into()).with_retry_after(42);
+        assert_eq!(error.retry_after(), Some(42));
+
+        let (status, retry_after, body) = envelope(&error).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(retry_after.as_deref(), Some("42"));
+        assert_eq!(body["error"]["code"], "TOO_MANY_REQUESTS");
+        assert_eq!(body["error"]["details"]["retryAfter"], 42);
+
+        // A later code keeps the header
+        let (_, retry_after, _) = envelope(&error.with_code("ACCOUNT_LOCKED")).await;
+        assert_eq!(retry_after.as_deref(), Some("42"));
+    }
+}
@@ -0,0 +1,434 @@
+//! Urgency escalation.
+//!
+//! A background task started in `main` calls [`escalate_tasks`] every
+//! `escalation_interval_secs`, and the task lists that depend on urgency
+//! call it before they read, so a task never shows a stale urgency. Each
+//! open task gets the highest urgency among its department's rules
+//! (`urgency_escalation_rules`) whose `hours_before_deadline` has been
+//! reached. Escalation only raises urgency, and to each level once:
+//! `tasks.escalated_urgency` keeps the last level applied, so a task lowered
+//! by hand stays lowered until a higher rule is reached or its deadline
+//! changes.
+//!
+//! Each change is recorded in `task_history` as `updated` with field
+//! `urgency`, with no user and `source` `escalation`, and the task's
+//! assignees are notified.
+//! The update is conditional on the urgency read, so runs that overlap
+//! record and notify each change once.
+//!
+//! A deadline with a time is in UTC. A date-only deadline ends at midnight
+//! at the end of that day in the creator's time zone.
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use sqlx::{Acquire, SqliteConnection, SqlitePool};
+use std::collections::HashMap;
+
+use crate::clock::Clock;
+use crate::error::AppError;
+use crate::models::tasks::EscalationRule;
+use crate::routes::notifications::create_notification;
+use crate::routes::task_history::{ESCALATION_SOURCE, record_system_change};
+use crate::timezone::{self, TimeZone};
+
+pub const URGENCIES: [&str; 4] = ["low", "medium", "high", "urgent"];
+
+/// Urgencies a rule can raise a task to
+pub const RULE_URGENCIES: [&str; 3] = ["medium", "high", "urgent"];
+
+/// Rules reach at most 30 days either side of the deadline
+pub const MAX_RULE_HOURS: i64 = 30 * 24;
+
+pub const RULE_COLUMNS: &str =
+    "id, department, hours_before_deadline, urgency, created_by, created_at, updated_at";
+
+/// Position in [`URGENCIES`], lowest first; unknown values rank lowest.
+pub fn rank(urgency: &str) -> usize {
+    URGENCIES
+        .iter()
+        .position(|u| *u == urgency)
+        .unwrap_or_default()
+}
+
+/// The UTC instant a task is due. `zone` is used for date-only deadlines.
+pub fn deadline_instant(deadline: &str, zone: &TimeZone) -> Option<NaiveDateTime> {
+    let deadline = deadline.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(deadline, "%Y-%m-%d") {
+        return Some(zone.day_bounds(date).1);
+    }
+    if let Ok(at) = DateTime::parse_from_rfc3339(deadline) {
+        return Some(at.naive_utc());
+    }
+    [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(deadline, format).ok())
+}
+
+/// The day a task is due in `zone`, and for a deadline with a time, the
+/// local time.
+pub fn local_deadline(deadline: &str, zone: &TimeZone) -> Option<(NaiveDate, Option<NaiveTime>)> {
+    if let Ok(date) = NaiveDate::parse_from_str(deadline.trim(), "%Y-%m-%d") {
+        return Some((date, None));
+    }
+    let local = zone.to_local(deadline_instant(deadline, zone)?);
+    Some((local.date(), Some(local.time())))
+}
+
+/// The highest urgency among the `rules` in force at `now` for a task due
+/// at `deadline`, if any is.
+pub fn rule_urgency(
+    rules: &[EscalationRule],
+    deadline: NaiveDateTime,
+    now: NaiveDateTime,
+) -> Option<&str> {
+    rules
+        .iter()
+        .filter(|rule| deadline - Duration::hours(rule.hours_before_deadline) <= now)
+        .map(|rule| rule.urgency.as_str())
+        .max_by_key(|urgency| rank(urgency))
+}
+
+/// Raises the urgency of every open task whose rules call for it, returning
+/// how many were raised.
+pub async fn escalate_tasks(pool: &SqlitePool, clock: &dyn Clock) -> Result<usize, AppError> {
+    let now = clock.now();
+    let mut conn = pool.acquire().await?;
+
+    let mut rules: HashMap<String, Vec<EscalationRule>> = HashMap::new();
+    for rule in sqlx::query_as::<_, EscalationRule>(&format!(
+        "SELECT {} FROM urgency_escalation_rules",
+        RULE_COLUMNS
+    ))
+    .fetch_all(&mut *conn)
+    .await?
+    {
+        rules.entry(rule.department.clone()).or_default().push(rule);
+    }
+    if rules.is_empty() {
+        return Ok(0);
+    }
+
+    let tasks = sqlx::query_as::<
+        _,
+        (
+            String,
+            String,
+            String,
+            String,
+            String,
+            String,
+            Option<String>,
+        ),
+    >(
+        "SELECT id, title, urgency, department, deadline, created_by, escalated_urgency FROM tasks
+         WHERE status != 'completed' AND COALESCE(is_completed, 0) = 0 AND urgency != 'urgent'",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let mut zones: HashMap<String, TimeZone> = HashMap::new();
+    let mut escalated = 0;
+
+    for (task_id, title, urgency, department, deadline, created_by, escalated_urgency) in tasks {
+        let Some(department_rules) = rules.get(&department) else {
+            continue;
+        };
+        if !zones.contains_key(&created_by) {
+            let zone = timezone::user_time_zone(&mut *conn, &created_by).await?;
+            zones.insert(created_by.clone(), zone);
+        }
+        let Some(due) = deadline_instant(&deadline, &zones[&created_by]) else {
+            continue;
+        };
+        let Some(target) = rule_urgency(department_rules, due, now) else {
+            continue;
+        };
+        if rank(target) <= rank(&urgency)
+            || escalated_urgency.is_some_and(|applied| rank(target) <= rank(&applied))
+        {
+            continue;
+        }
+        let task = EscalatedTask {
+            id: &task_id,
+            title: &title,
+            from: &urgency,
+            to: target,
+            overdue: due <= now,
+        };
+        if escalate(&mut conn, &task).await? {
+            escalated += 1;
+        }
+    }
+    Ok(escalated)
+}
+
+struct EscalatedTask<'a> {
+    id: &'a str,
+    title: &'a str,
+    from: &'a str,
+    to: &'a str,
+    overdue: bool,
+}
+
+/// Applies one change unless another run got there first. Returns whether
+/// it did.
+async fn escalate(conn: &mut SqliteConnection, task: &EscalatedTask<'_>) -> Result<bool, AppError> {
+    let mut tx = conn.begin().await?;
+    let updated = sqlx::query(
+        "UPDATE tasks SET urgency = ?, escalated_urgency = ?, updated_at = datetime('now') WHERE id = ? AND urgency = ?",
+    )
+    .bind(task.to)
+    .bind(task.to)
+    .bind(task.id)
+    .bind(task.from)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    if updated == 0 {
+        return Ok(false);
+    }
+
+    record_system_change(
+        &mut *tx,
+        task.id,
+        ESCALATION_SOURCE,
+        "urgency",
+        Some(task.from),
+        Some(task.to),
+    )
+    .await?;
+
+    let assignees: Vec<String> =
+        sqlx::query_scalar("SELECT user_id FROM task_assignees WHERE task_id = ?")
+            .bind(task.id)
+            .fetch_all(&mut *tx)
+            .await?;
+    let reason = if task.overdue {
+        "is overdue"
+    } else {
+        "is due soon"
+    };
+    let notification_type = if task.to == "urgent" {
+        "urgent"
+    } else {
+        "info"
+    };
+    for user_id in &assignees {
+        create_notification(
+            &mut *tx,
+            user_id,
+            notification_type,
+            &format!("Urgency raised: {}", task.title),
+            &format!(
+                "{} {} and was raised from {} to {}",
+                task.title, reason, task.from, task.to
+            ),
+            Some("task"),
+            Some(task.id),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use uuid::Uuid;
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn rule(hours_before_deadline: i64, urgency: &str) -> EscalationRule {
+        EscalationRule {
+            id: Uuid::new_v4().to_string(),
+            department: "IT".to_string(),
+            hours_before_deadline,
+            urgency: urgency.to_string(),
+            created_by: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    async fn test_pool() -> SqlitePool {
+        let pool = crate::migrations::test_pool().await;
+        for id in ["lead", "technician"] {
+            sqlx::query(
+                "INSERT INTO users (id, email, password_hash, first_name, last_name, department, role) VALUES (?, ?, 'x', 'Test', 'User', 'IT', 'Member')",
+            )
+            .bind(id)
+            .bind(format!("{}@company.com", id))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        pool
+    }
+
+    async fn add_task(pool: &SqlitePool, urgency: &str, deadline: &str) -> String {
+        let id = Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO tasks (id, title, urgency, department, created_by, deadline) VALUES (?, 'Replace UPS batteries', ?, 'IT', 'lead', ?)",
+        )
+        .bind(&id)
+        .bind(urgency)
+        .bind(deadline)
+        .execute(pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO task_assignees (id, task_id, user_id) VALUES (?, ?, 'technician')",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(&id)
+        .execute(pool)
+        .await
+        .unwrap();
+        id
+    }
+
+    async fn urgency(pool: &SqlitePool, task_id: &str) -> String {
+        sqlx::query_scalar("SELECT urgency FROM tasks WHERE id = ?")
+            .bind(task_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn highest_rule_in_force_wins() {
+        let rules = [rule(72, "medium"), rule(24, "high"), rule(-24, "urgent")];
+        let deadline = at("2026-03-10 12:00");
+
+        assert_eq!(rule_urgency(&rules, deadline, at("2026-03-07 11:59")), None);
+        assert_eq!(
+            rule_urgency(&rules, deadline, at("2026-03-07 12:00")),
+            Some("medium")
+        );
+        assert_eq!(
+            rule_urgency(&rules, deadline, at("2026-03-10 13:00")),
+            Some("high")
+        );
+        assert_eq!(
+            rule_urgency(&rules, deadline, at("2026-03-11 12:00")),
+            Some("urgent")
+        );
+    }
+
+    #[test]
+    fn deadlines_are_read_in_utc_or_the_zone_of_the_date() {
+        let utc = TimeZone::utc();
+        assert_eq!(
+            deadline_instant("2026-03-10", &utc),
+            Some(at("2026-03-11 00:00"))
+        );
+        assert_eq!(
+            deadline_instant("2026-03-10 14:30:00", &utc),
+            Some(at("2026-03-10 14:30"))
+        );
+        assert_eq!(
+            deadline_instant("2026-03-10T16:30:00+02:00", &utc),
+            Some(at("2026-03-10 14:30"))
+        );
+        assert_eq!(deadline_instant("next week", &utc), None);
+    }
+
+    #[test]
+    fn timed_deadlines_fall_on_the_local_day() {
+        let singapore = TimeZone::named("Asia/Singapore").unwrap();
+        let date = |value| NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap();
+        // 01:00 on 11 March in Singapore
+        assert_eq!(
+            local_deadline("2026-03-10 17:00:00", &singapore),
+            Some((date("2026-03-11"), NaiveTime::from_hms_opt(1, 0, 0)))
+        );
+        assert_eq!(
+            local_deadline("2026-03-10", &singapore),
+            Some((date("2026-03-10"), None))
+        );
+        assert_eq!(local_deadline("next week", &singapore), None);
+    }
+
+    #[tokio::test]
+    async fn raises_urgency_once_and_notifies_assignees() {
+        let pool = test_pool().await;
+        // Default rules: medium 72 hours out, high at 24, urgent at 4
+        let task_id = add_task(&pool, "low", "2026-03-10 12:00:00").await;
+        let clock = FixedClock::new(at("2026-03-07 11:00"));
+
+        assert_eq!(escalate_tasks(&pool, &clock).await.unwrap(), 0);
+
+        clock.set(at("2026-03-10 10:00"));
+        assert_eq!(escalate_tasks(&pool, &clock).await.unwrap(), 1);
+        assert_eq!(escalate_tasks(&pool, &clock).await.unwrap(), 0);
+        assert_eq!(urgency(&pool, &task_id).await, "urgent");
+
+        let history: (Option<String>, String, String, String, String) = sqlx::query_as(
+            "SELECT user_id, source, field_changed, old_value, new_value FROM task_history WHERE task_id = ?",
+        )
+        .bind(&task_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            history,
+            (
+                None,
+                "escalation".to_string(),
+                "urgency".to_string(),
+                "low".to_string(),
+                "urgent".to_string()
+            )
+        );
+        let notified: Vec<(String, String)> =
+            sqlx::query_as("SELECT user_id, type FROM notifications")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(notified, [("technician".to_string(), "urgent".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn a_task_lowered_by_hand_waits_for_the_next_rule() {
+        let pool = test_pool().await;
+        let task_id = add_task(&pool, "low", "2026-03-10 12:00:00").await;
+        let clock = FixedClock::new(at("2026-03-09 20:00"));
+        assert_eq!(escalate_tasks(&pool, &clock).await.unwrap(), 1);
+        assert_eq!(urgency(&pool, &task_id).await, "high");
+
+        sqlx::query("UPDATE tasks SET urgency = 'low' WHERE id = ?")
+            .bind(&task_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(escalate_tasks(&pool, &clock).await.unwrap(), 0);
+
+        clock.set(at("2026-03-10 08:00"));
+        assert_eq!(escalate_tasks(&pool, &clock).await.unwrap(), 1);
+        assert_eq!(urgency(&pool, &task_id).await, "urgent");
+    }
+
+    #[tokio::test]
+    async fn leaves_completed_and_higher_tasks_alone() {
+        let pool = test_pool().await;
+        let done = add_task(&pool, "low", "2026-03-10").await;
+        sqlx::query("UPDATE tasks SET status = 'completed' WHERE id = ?")
+            .bind(&done)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let high = add_task(&pool, "high", "2026-03-10").await;
+        let clock = FixedClock::new(at("2026-03-09 12:00"));
+
+        assert_eq!(escalate_tasks(&pool, &clock).await.unwrap(), 0);
+        assert_eq!(urgency(&pool, &done).await, "low");
+        assert_eq!(urgency(&pool, &high).await, "high");
+    }
+}
@@ -0,0 +1,306 @@
+//! Dynamic WHERE clauses for list endpoints.
+//!
+//! Handlers collect optional filters into a [`Filters`] and apply them to a
+//! `sqlx::QueryBuilder`. Column names and SQL fragments must be `'static`, so
+//! only code can shape the query; every user-supplied value goes through
+//! `push_bind` and is never spliced into the SQL text.
+
+use sqlx::{QueryBuilder, Sqlite};
+
+/// A value bound to a `?` placeholder.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterValue {
+    Text(String),
+    Int(i64),
+    Bool(bool),
+}
+
+impl From<String> for FilterValue {
+    fn from(v: String) -> Self {
+        FilterValue::Text(v)
+    }
+}
+
+impl From<&str> for FilterValue {
+    fn from(v: &str) -> Self {
+        FilterValue::Text(v.to_string())
+    }
+}
+
+impl From<&String> for FilterValue {
+    fn from(v: &String) -> Self {
+        FilterValue::Text(v.clone())
+    }
+}
+
+impl From<i64> for FilterValue {
+    fn from(v: i64) -> Self {
+        FilterValue::Int(v)
+    }
+}
+
+impl From<i32> for FilterValue {
+    fn from(v: i32) -> Self {
+        FilterValue::Int(v.into())
+    }
+}
+
+impl From<bool> for FilterValue {
+    fn from(v: bool) -> Self {
+        FilterValue::Bool(v)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Condition {
+    /// SQL fragment with one `?` per entry in `values`.
+    sql: String,
+    values: Vec<FilterValue>,
+}
+
+/// An ordered list of conditions joined with `AND`.
+#[derive(Debug, Clone, Default)]
+pub struct Filters {
+    conditions: Vec<Condition>,
+}
+
+impl Filters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `column = ?`
+    pub fn eq(&mut self, column: &'static str, value: impl Into<FilterValue>) -> &mut Self {
+        self.condition_owned(format!("{} = ?", column), vec![value.into()])
+    }
+
+    /// `column = ?`, skipped when `value` is `None`.
+    pub fn eq_opt<V: Into<FilterValue>>(
+        &mut self,
+        column: &'static str,
+        value: Option<V>,
+    ) -> &mut Self {
+        if let Some(value) = value {
+            self.eq(column, value);
+        }
+        self
+    }
+
+    /// `(col1 LIKE ? OR col2 LIKE ? ...)` matching `term` anywhere in any column.
+    /// `%`, `_` and `\` in `term` are matched literally.
+    pub fn search(&mut self, columns: &[&'static str], term: &str) -> &mut Self {
+        if columns.is_empty() {
+            return self;
+        }
+        let pattern = format!("%{}%", escape_like(term));
+        let sql = columns
+            .iter()
+            .map(|c| format!("{} LIKE ? ESCAPE '\\'", c))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        let values = vec![FilterValue::Text(pattern); columns.len()];
+        self.condition_owned(format!("({})", sql), values)
+    }
+
+    /// Arbitrary fragment with one `?` per value, e.g.
+    /// `"(department = ? OR department = 'Both')"`.
+    pub fn condition(&mut self, sql: &'static str, values: Vec<FilterValue>) -> &mut Self {
+        self.condition_owned(sql.to_string(), values)
+    }
+
+    /// Fragment without placeholders, e.g. `"is_read = 0"`.
+    pub fn raw(&mut self, sql: &'static str) -> &mut Self {
+        self.condition_owned(sql.to_string(), Vec::new())
+    }
+
+    fn condition_owned(&mut self, sql: String, values: Vec<FilterValue>) -> &mut Self {
+        debug_assert_eq!(
+            sql.matches('?').count(),
+            values.len(),
+            "placeholder count mismatch in filter `{}`",
+            sql
+        );
+        self.conditions.push(Condition { sql, values });
+        self
+    }
+
+    /// Appends ` WHERE a AND b ...`, or nothing when there are no conditions.
+    pub fn push_where(&self, qb: &mut QueryBuilder<'_, Sqlite>) {
+        for (i, condition) in self.conditions.iter().enumerate() {
+            qb.push(if i == 0 { " WHERE " } else { " AND " });
+
+            let mut values = condition.values.iter();
+            let mut parts = condition.sql.split('?').peekable();
+            while let Some(part) = parts.next() {
+                qb.push(part);
+                if parts.peek().is_some() {
+                    match values.next() {
+                        Some(FilterValue::Text(v)) => qb.push_bind(v.clone()),
+                        Some(FilterValue::Int(v)) => qb.push_bind(*v),
+                        Some(FilterValue::Bool(v)) => qb.push_bind(*v),
+                        None => unreachable!("checked in condition_owned"),
+                    };
+                }
+            }
+        }
+    }
+}
+
+/// Appends ` LIMIT ? OFFSET ?` with both values bound.
+pub fn push_limit(qb: &mut QueryBuilder<'_, Sqlite>, limit: i64, offset: i64) {
+    qb.push(" LIMIT ").push_bind(limit);
+    qb.push(" OFFSET ").push_bind(offset);
+}
+
+fn escape_like(term: &str) -> String {
+    let mut out = String::with_capacity(term.len());
+    for c in term.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::SqlitePool;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    const INJECTION: &str = "x' OR '1'='1";
+
+    async fn pool() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query(
+            "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT NOT NULL, status TEXT NOT NULL);
+             INSERT INTO items (name, status) VALUES
+                ('alpha', 'open'),
+                ('O''Brien', 'open'),
+                ('100% done', 'closed'),
+                ('1000 done', 'closed');",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool
+    }
+
+    async fn names(pool: &SqlitePool, filters: &Filters) -> Vec<String> {
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT name FROM items");
+        filters.push_where(&mut qb);
+        qb.push(" ORDER BY id");
+        qb.build_query_scalar::<String>()
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn values_never_appear_in_sql() {
+        let mut filters = Filters::new();
+        filters
+            .eq("status", INJECTION)
+            .search(&["name", "status"], INJECTION);
+
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT name FROM items");
+        filters.push_where(&mut qb);
+
+        assert_eq!(
+            qb.sql(),
+            "SELECT name FROM items WHERE status = ? AND \
+             (name LIKE ? ESCAPE '\\' OR status LIKE ? ESCAPE '\\')"
+        );
+    }
+
+    #[test]
+    fn empty_filters_push_nothing() {
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT name FROM items");
+        Filters::new().push_where(&mut qb);
+        assert_eq!(qb.sql(), "SELECT name FROM items");
+    }
+
+    #[actix_web::test]
+    async fn injection_in_eq_is_a_literal() {
+        let pool = pool().await;
+        let mut filters = Filters::new();
+        filters.eq("status", INJECTION);
+        assert!(names(&pool, &filters).await.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn injection_in_search_is_a_literal() {
+        let pool = pool().await;
+        let mut filters = Filters::new();
+        filters.search(&["name"], INJECTION);
+        assert!(names(&pool, &filters).await.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn drop_table_payload_does_not_execute() {
+        let pool = pool().await;
+        let mut filters = Filters::new();
+        filters.eq("name", "'; DROP TABLE items; --");
+        assert!(names(&pool, &filters).await.is_empty());
+        assert_eq!(names(&pool, &Filters::new()).await.len(), 4);
+    }
+
+    #[actix_web::test]
+    async fn apostrophes_match_exactly() {
+        let pool = pool().await;
+        let mut filters = Filters::new();
+        filters.eq("name", "O'Brien");
+        assert_eq!(names(&pool, &filters).await, vec!["O'Brien"]);
+
+        let mut filters = Filters::new();
+        filters.search(&["name"], "'Bri");
+        assert_eq!(names(&pool, &filters).await, vec!["O'Brien"]);
+    }
+
+    #[actix_web::test]
+    async fn like_wildcards_in_search_are_literal() {
+        let pool = pool().await;
+        let mut filters = Filters::new();
+        filters.search(&["name"], "100%");
+        assert_eq!(names(&pool, &filters).await, vec!["100% done"]);
+    }
+
+    #[actix_web::test]
+    async fn conditions_are_and_ed() {
+        let pool = pool().await;
+        let mut filters = Filters::new();
+        filters
+            .eq_opt("status", Some("closed"))
+            .eq_opt::<&str>("name", None)
+            .condition(
+                "(name = ? OR name = ?)",
+                vec!["alpha".into(), "1000 done".into()],
+            );
+        assert_eq!(names(&pool, &filters).await, vec!["1000 done"]);
+    }
+
+    #[actix_web::test]
+    async fn limit_and_offset_are_bound() {
+        let pool = pool().await;
+        let mut filters = Filters::new();
+        filters.raw("status = 'closed'");
+
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT name FROM items");
+        filters.push_where(&mut qb);
+        qb.push(" ORDER BY id");
+        push_limit(&mut qb, 1, 1);
+        assert!(qb.sql().ends_with(" LIMIT ? OFFSET ?"));
+
+        let rows = qb
+            .build_query_scalar::<String>()
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rows, vec!["1000 done"]);
+    }
+}
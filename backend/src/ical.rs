@@ -0,0 +1,396 @@
+//! iCalendar (RFC 5545) documents.
+//!
+//! A small reader and writer for what the calendar feed and import need:
+//! components, properties with parameters, TEXT escaping and line folding.
+//! Property values are kept as written; callers interpret dates and rules.
+
+use crate::error::AppError;
+
+/// Content lines are folded after this many octets, excluding the CRLF.
+const MAX_LINE_OCTETS: usize = 75;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Property {
+    /// Upper case, e.g. `DTSTART`
+    pub name: String,
+    /// Parameter names in upper case, values without quotes
+    pub params: Vec<(String, String)>,
+    pub value: String,
+}
+
+impl Property {
+    pub fn new(name: &str, value: impl Into<String>) -> Self {
+        Property {
+            name: name.to_ascii_uppercase(),
+            params: Vec::new(),
+            value: value.into(),
+        }
+    }
+
+    /// A TEXT property; `text` is escaped.
+    pub fn text(name: &str, text: &str) -> Self {
+        Property::new(name, escape_text(text))
+    }
+
+    pub fn param(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.params.push((name.to_ascii_uppercase(), value.into()));
+        self
+    }
+
+    pub fn get_param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The value of a TEXT property, unescaped.
+    pub fn text_value(&self) -> String {
+        unescape_text(&self.value)
+    }
+
+    fn write(&self, out: &mut String) {
+        let mut line = self.name.clone();
+        for (name, value) in &self.params {
+            line.push(';');
+            line.push_str(name);
+            line.push('=');
+            if value.contains([':', ';', ',']) {
+                line.push('"');
+                line.push_str(value);
+                line.push('"');
+            } else {
+                line.push_str(value);
+            }
+        }
+        line.push(':');
+        line.push_str(&self.value);
+        fold_into(&line, out);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Component {
+    /// Upper case, e.g. `VEVENT`
+    pub name: String,
+    pub properties: Vec<Property>,
+    pub components: Vec<Component>,
+}
+
+impl Component {
+    pub fn new(name: &str) -> Self {
+        Component {
+            name: name.to_ascii_uppercase(),
+            properties: Vec::new(),
+            components: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, property: Property) -> &mut Self {
+        self.properties.push(property);
+        self
+    }
+
+    pub fn add(&mut self, component: Component) -> &mut Self {
+        self.components.push(component);
+        self
+    }
+
+    /// The first property called `name`.
+    pub fn property(&self, name: &str) -> Option<&Property> {
+        self.properties
+            .iter()
+            .find(|p| p.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn properties<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Property> {
+        self.properties
+            .iter()
+            .filter(move |p| p.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn components<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Component> {
+        self.components
+            .iter()
+            .filter(move |c| c.name.eq_ignore_ascii_case(name))
+    }
+
+    /// The component as an iCalendar document, with CRLF line endings and
+    /// folded lines.
+    pub fn to_ics(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out);
+        out
+    }
+
+    fn write(&self, out: &mut String) {
+        fold_into(&format!("BEGIN:{}", self.name), out);
+        for property in &self.properties {
+            property.write(out);
+        }
+        for component in &self.components {
+            component.write(out);
+        }
+        fold_into(&format!("END:{}", self.name), out);
+    }
+
+    /// Parses a document holding exactly one top-level component, usually
+    /// `VCALENDAR`. Accepts LF as well as CRLF line endings.
+    pub fn parse(input: &str) -> Result<Self, AppError> {
+        let mut stack: Vec<Component> = Vec::new();
+        let mut root = None;
+
+        for (number, line) in unfold(input) {
+            let property =
+                parse_line(&line).map_err(|e| invalid(format!("line {}: {}", number, e)))?;
+            match property.name.as_str() {
+                "BEGIN" => {
+                    if root.is_some() {
+                        return Err(invalid(format!(
+                            "line {}: content after the end of the calendar",
+                            number
+                        )));
+                    }
+                    stack.push(Component::new(&property.value));
+                }
+                "END" => {
+                    let component = stack
+                        .pop()
+                        .ok_or_else(|| invalid(format!("line {}: END without BEGIN", number)))?;
+                    if !component.name.eq_ignore_ascii_case(&property.value) {
+                        return Err(invalid(format!(
+                            "line {}: END:{} does not close BEGIN:{}",
+                            number, property.value, component.name
+                        )));
+                    }
+                    match stack.last_mut() {
+                        Some(parent) => parent.components.push(component),
+                        None => root = Some(component),
+                    }
+                }
+                _ => {
+                    stack
+                        .last_mut()
+                        .ok_or_else(|| {
+                            invalid(format!("line {}: property outside a component", number))
+                        })?
+                        .properties
+                        .push(property);
+                }
+            }
+        }
+
+        if let Some(open) = stack.last() {
+            return Err(invalid(format!("BEGIN:{} is never closed", open.name)));
+        }
+        root.ok_or_else(|| invalid("the document is empty".to_string()))
+    }
+}
+
+/// Escapes `\`, `;`, `,` and line breaks in a TEXT value.
+pub fn escape_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            ';' => out.push_str("\\;"),
+            ',' => out.push_str("\\,"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+pub fn unescape_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push('\n'),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Appends `line` and a CRLF, continuing on indented lines so none is longer
+/// than 75 octets. Never splits a UTF-8 character.
+fn fold_into(line: &str, out: &mut String) {
+    let mut limit = MAX_LINE_OCTETS;
+    let mut rest = line;
+    while rest.len() > limit {
+        let mut split = limit;
+        while !rest.is_char_boundary(split) {
+            split -= 1;
+        }
+        out.push_str(&rest[..split]);
+        out.push_str("\r\n ");
+        rest = &rest[split..];
+        // The leading space counts towards the next line
+        limit = MAX_LINE_OCTETS - 1;
+    }
+    out.push_str(rest);
+    out.push_str("\r\n");
+}
+
+/// Joins folded lines, returning each content line with its starting line number.
+fn unfold(input: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for (index, raw) in input.split('\n').enumerate() {
+        let raw = raw.strip_suffix('\r').unwrap_or(raw);
+        match raw.strip_prefix([' ', '\t']) {
+            Some(continued) if !lines.is_empty() => {
+                lines.last_mut().unwrap().1.push_str(continued);
+            }
+            _ if raw.is_empty() => {}
+            _ => lines.push((index + 1, raw.to_string())),
+        }
+    }
+    lines
+}
+
+fn parse_line(line: &str) -> Result<Property, String> {
+    let name_end = line
+        .find([';', ':'])
+        .ok_or_else(|| format!("expected NAME:VALUE, got `{}`", line))?;
+    let name = &line[..name_end];
+    if name.is_empty() {
+        return Err("missing property name".to_string());
+    }
+
+    let mut property = Property::new(name, "");
+    let mut rest = &line[name_end..];
+    while let Some(params) = rest.strip_prefix(';') {
+        let eq = params
+            .find('=')
+            .ok_or_else(|| format!("parameter without a value in {}", name))?;
+        let param_name = &params[..eq];
+        let after = &params[eq + 1..];
+        let (value, remaining) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let close = quoted
+                    .find('"')
+                    .ok_or_else(|| format!("unterminated quote in {}", name))?;
+                (&quoted[..close], &quoted[close + 1..])
+            }
+            None => {
+                let end = after.find([';', ':']).unwrap_or(after.len());
+                (&after[..end], &after[end..])
+            }
+        };
+        property
+            .params
+            .push((param_name.to_ascii_uppercase(), value.to_string()));
+        rest = remaining;
+    }
+
+    property.value = rest
+        .strip_prefix(':')
+        .ok_or_else(|| format!("missing `:` after {}", name))?
+        .to_string();
+    Ok(property)
+}
+
+fn invalid(message: String) -> AppError {
+    AppError::Validation(format!("Invalid iCalendar data: {}", message))
+        .with_code("INVALID_ICALENDAR")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calendar(event: Component) -> Component {
+        let mut calendar = Component::new("VCALENDAR");
+        calendar
+            .push(Property::new("VERSION", "2.0"))
+            .push(Property::new("PRODID", "-//Test//EN"))
+            .add(event);
+        calendar
+    }
+
+    #[test]
+    fn text_round_trips_through_escaping() {
+        let text = "Review; bring laptops, chargers\nand the \\ key";
+        let mut event = Component::new("VEVENT");
+        event.push(Property::text("DESCRIPTION", text));
+
+        let ics = calendar(event).to_ics();
+        assert!(ics.contains("Review\\; bring laptops\\, chargers\\nand the \\\\ key"));
+
+        let parsed = Component::parse(&ics).unwrap();
+        let event = parsed.components("VEVENT").next().unwrap();
+        assert_eq!(event.property("DESCRIPTION").unwrap().text_value(), text);
+    }
+
+    #[test]
+    fn long_lines_fold_within_limit_and_unfold() {
+        // Multi-byte characters must not be split across lines
+        let text = "Équipement de mesure ".repeat(20);
+        let mut event = Component::new("VEVENT");
+        event.push(Property::text("SUMMARY", &text));
+
+        let ics = calendar(event).to_ics();
+        assert!(ics.split("\r\n").all(|line| line.len() <= MAX_LINE_OCTETS));
+        assert!(ics.lines().count() > 8);
+
+        let parsed = Component::parse(&ics).unwrap();
+        let summary = parsed
+            .components("VEVENT")
+            .next()
+            .unwrap()
+            .property("SUMMARY");
+        assert_eq!(summary.unwrap().text_value(), text);
+    }
+
+    #[test]
+    fn parameters_round_trip_with_quoting() {
+        let mut event = Component::new("VEVENT");
+        event.push(
+            Property::new("ATTENDEE", "mailto:sarah@company.com")
+                .param("CN", "Chen, Sarah")
+                .param("PARTSTAT", "ACCEPTED"),
+        );
+
+        let ics = calendar(event.clone()).to_ics();
+        assert!(ics.contains("ATTENDEE;CN=\"Chen, Sarah\";PARTSTAT=ACCEPTED:mailto:"));
+
+        let parsed = Component::parse(&ics).unwrap();
+        assert_eq!(parsed, calendar(event));
+        let attendee = parsed.components[0].property("ATTENDEE").unwrap();
+        assert_eq!(attendee.get_param("cn"), Some("Chen, Sarah"));
+    }
+
+    #[test]
+    fn parses_lf_line_endings_and_tab_folding() {
+        let ics = "BEGIN:VCALENDAR\nBEGIN:VTODO\nSUMMARY:Order\n\tparts\nDUE;VALUE=DATE:20260115\nEND:VTODO\nEND:VCALENDAR\n";
+        let parsed = Component::parse(ics).unwrap();
+        let todo = parsed.components("VTODO").next().unwrap();
+        assert_eq!(todo.property("SUMMARY").unwrap().value, "Orderparts");
+        assert_eq!(
+            todo.property("DUE").unwrap().get_param("VALUE"),
+            Some("DATE")
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_documents() {
+        for ics in [
+            "",
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nEND:VCALENDAR\r\n",
+            "BEGIN:VCALENDAR\r\nSUMMARY\r\nEND:VCALENDAR\r\n",
+            "BEGIN:VCALENDAR\r\n",
+            "SUMMARY:orphan\r\n",
+        ] {
+            let err = Component::parse(ics).unwrap_err();
+            assert_eq!(err.code(), "INVALID_ICALENDAR", "{:?}", ics);
+        }
+    }
+}
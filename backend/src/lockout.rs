@@ -0,0 +1,370 @@
+//! Login brute-force protection.
+//!
+//! Every wrong password for an existing account bumps
+//! `users.failed_login_count`. After the n-th failure the next attempt is
+//! refused for 2^(n-1) seconds (capped at [`MAX_BACKOFF_SECS`]), and the
+//! `login_max_failures`-th failure locks the account for
+//! `login_lockout_minutes`. Refused attempts are answered before bcrypt runs.
+//! A successful login or an admin unlock clears the counter.
+//!
+//! Failures, lockouts and unlocks are written to `auth_audit_log`, and the
+//! account owner is notified when their account is locked or unlocked.
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{Sqlite, SqliteExecutor, SqlitePool};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::routes::notifications::create_notification;
+use crate::tokens::{from_sql_datetime, to_sql_datetime};
+
+/// Longest delay between two attempts before the account is locked outright
+const MAX_BACKOFF_SECS: i64 = 60;
+
+#[derive(sqlx::FromRow)]
+struct LoginState {
+    failed_login_count: i64,
+    last_failed_login_at: Option<String>,
+    locked_until: Option<String>,
+}
+
+/// Refuses the attempt if the account is locked or still backing off.
+pub async fn check_login_allowed(pool: &SqlitePool, user_id: &str) -> Result<(), AppError> {
+    let state = sqlx::query_as::<_, LoginState>(
+        "SELECT failed_login_count, last_failed_login_at, locked_until FROM users WHERE id = ?",
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+
+    let now = Utc::now();
+
+    if let Some(until) = state.locked_until.as_deref().and_then(from_sql_datetime)
+        && until > now
+    {
+        return Err(account_locked(until));
+    }
+
+    if state.failed_login_count > 0
+        && let Some(last) = state
+            .last_failed_login_at
+            .as_deref()
+            .and_then(from_sql_datetime)
+    {
+        let retry_at = last + backoff(state.failed_login_count);
+        if retry_at > now {
+            return Err(AppError::TooManyRequests(
+                "Too many failed login attempts! Please wait before trying again.".to_string(),
+            )
+            .with_code("TOO_MANY_ATTEMPTS")
+            .with_retry_after(secs_until(retry_at)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Counts a wrong password and locks the account once the limit is reached.
+/// Returns the end of the lockout if this failure caused one.
+pub async fn record_failed_login(
+    pool: &SqlitePool,
+    config: &Config,
+    user_id: &str,
+    ip_address: Option<&str>,
+) -> Result<Option<DateTime<Utc>>, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let failures = sqlx::query_scalar::<_, i64>(
+        "UPDATE users SET failed_login_count = failed_login_count + 1, last_failed_login_at = datetime('now') WHERE id = ? RETURNING failed_login_count",
+    )
+    .bind(user_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    audit(&mut *tx, user_id, "login_failed", None, ip_address).await?;
+
+    let locked_until = if failures >= config.login_max_failures {
+        let until = Utc::now() + config.login_lockout();
+        sqlx::query(
+            "UPDATE users SET failed_login_count = 0, last_failed_login_at = NULL, locked_until = ? WHERE id = ?",
+        )
+        .bind(to_sql_datetime(&until))
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        audit(&mut *tx, user_id, "account_locked", None, ip_address).await?;
+        Some(until)
+    } else {
+        None
+    };
+
+    tx.commit().await?;
+
+    if let Some(until) = locked_until {
+        log::warn!(
+            "Locked account {} after {} failed logins (last from {})",
+            user_id,
+            failures,
+            ip_address.unwrap_or("unknown address")
+        );
+        create_notification(
+            pool,
+            user_id,
+            "urgent",
+            "Account locked",
+            &format!(
+                "Your account was locked after {} failed sign-in attempts. It unlocks at {} UTC, or an administrator can unlock it sooner.",
+                failures,
+                to_sql_datetime(&until)
+            ),
+            Some("user"),
+            Some(user_id),
+        )
+        .await?;
+    }
+
+    Ok(locked_until)
+}
+
+/// Clears failed attempts after a successful login.
+pub async fn clear_failed_logins(pool: &SqlitePool, user_id: &str) -> Result<(), AppError> {
+    sqlx::query(
+        "UPDATE users SET failed_login_count = 0, last_failed_login_at = NULL, locked_until = NULL WHERE id = ?",
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Lifts a lockout on behalf of `admin_id`.
+pub async fn unlock_account(
+    pool: &SqlitePool,
+    user_id: &str,
+    admin_id: &str,
+) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+
+    let rows = sqlx::query(
+        "UPDATE users SET failed_login_count = 0, last_failed_login_at = NULL, locked_until = NULL, updated_at = datetime('now') WHERE id = ?",
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    if rows.rows_affected() == 0 {
+        return Err(AppError::NotFound("User not found".to_string()));
+    }
+
+    audit(&mut *tx, user_id, "account_unlocked", Some(admin_id), None).await?;
+    tx.commit().await?;
+
+    create_notification(
+        pool,
+        user_id,
+        "info",
+        "Account unlocked",
+        "An administrator unlocked your account. You can sign in again.",
+        Some("user"),
+        Some(user_id),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// The error for a login attempt on a locked account.
+pub fn account_locked(until: DateTime<Utc>) -> AppError {
+    AppError::TooManyRequests("Account is locked after too many failed login attempts".to_string())
+        .with_code("ACCOUNT_LOCKED")
+        .with_retry_after(secs_until(until))
+}
+
+async fn audit(
+    executor: impl SqliteExecutor<'_>,
+    user_id: &str,
+    event: &str,
+    actor_id: Option<&str>,
+    ip_address: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query::<Sqlite>(
+        "INSERT INTO auth_audit_log (id, user_id, event, actor_id, ip_address) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(user_id)
+    .bind(event)
+    .bind(actor_id)
+    .bind(ip_address)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+fn backoff(failures: i64) -> Duration {
+    let exponent = (failures - 1).clamp(0, 30) as u32;
+    Duration::seconds(2_i64.pow(exponent).min(MAX_BACKOFF_SECS))
+}
+
+/// Whole seconds from now until `at`, rounded up and at least one.
+fn secs_until(at: DateTime<Utc>) -> u64 {
+    let millis = (at - Utc::now()).num_milliseconds().max(1);
+    (millis as u64).div_ceil(1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn test_pool() -> SqlitePool {
+        let pool = crate::migrations::test_pool().await;
+        for id in ["user", "admin"] {
+            sqlx::query(
+                "INSERT INTO users (id, email, password_hash, first_name, last_name, department, role) VALUES (?, ?, 'x', 'Test', 'User', 'IT', 'Member')",
+            )
+            .bind(id)
+            .bind(format!("{}@example.com", id))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        pool
+    }
+
+    fn config() -> Config {
+        Config {
+            login_max_failures: 4,
+            login_lockout_minutes: 15,
+            ..Config::default()
+        }
+    }
+
+    /// Moves the last failure into the past, as if the user waited.
+    async fn wait(pool: &SqlitePool, secs: i64) {
+        sqlx::query(
+            "UPDATE users SET last_failed_login_at = datetime(last_failed_login_at, ?) WHERE id = 'user'",
+        )
+        .bind(format!("-{} seconds", secs))
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn failed_login_count(pool: &SqlitePool) -> i64 {
+        sqlx::query_scalar("SELECT failed_login_count FROM users WHERE id = 'user'")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let secs: Vec<i64> = [1, 2, 3, 4, 7, 8, 100]
+            .into_iter()
+            .map(|failures| backoff(failures).num_seconds())
+            .collect();
+        assert_eq!(secs, [1, 2, 4, 8, 60, 60, 60]);
+    }
+
+    #[tokio::test]
+    async fn backs_off_then_locks_the_account() {
+        let pool = test_pool().await;
+        let config = config();
+        check_login_allowed(&pool, "user").await.unwrap();
+
+        for _ in 0..3 {
+            let locked = record_failed_login(&pool, &config, "user", Some("192.0.2.1"))
+                .await
+                .unwrap();
+            assert_eq!(locked, None);
+        }
+        // The third failure means a four-second wait; stored times have whole seconds
+        let err = check_login_allowed(&pool, "user").await.unwrap_err();
+        assert_eq!(err.code(), "TOO_MANY_ATTEMPTS");
+        assert!((3..=4).contains(&err.retry_after().unwrap()));
+
+        wait(&pool, 4).await;
+        check_login_allowed(&pool, "user").await.unwrap();
+        let locked_until = record_failed_login(&pool, &config, "user", None)
+            .await
+            .unwrap()
+            .expect("the fourth failure locks the account");
+        assert!(locked_until > Utc::now() + Duration::minutes(14));
+
+        let err = check_login_allowed(&pool, "user").await.unwrap_err();
+        assert_eq!(err.code(), "ACCOUNT_LOCKED");
+        assert!((899..=900).contains(&err.retry_after().unwrap()));
+        assert_eq!(failed_login_count(&pool).await, 0);
+
+        let events: Vec<String> =
+            sqlx::query_scalar("SELECT event FROM auth_audit_log ORDER BY rowid")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            events,
+            [
+                "login_failed",
+                "login_failed",
+                "login_failed",
+                "login_failed",
+                "account_locked"
+            ]
+        );
+        let notified: Vec<(String, String)> =
+            sqlx::query_as("SELECT user_id, type FROM notifications")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(notified, [("user".to_string(), "urgent".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn a_successful_login_resets_the_count() {
+        let pool = test_pool().await;
+        let config = config();
+        for _ in 0..3 {
+            record_failed_login(&pool, &config, "user", None)
+                .await
+                .unwrap();
+        }
+
+        clear_failed_logins(&pool, "user").await.unwrap();
+        assert_eq!(failed_login_count(&pool).await, 0);
+        check_login_allowed(&pool, "user").await.unwrap();
+
+        // Counting starts over, so three more failures do not lock
+        for _ in 0..3 {
+            let locked = record_failed_login(&pool, &config, "user", None)
+                .await
+                .unwrap();
+            assert_eq!(locked, None);
+        }
+    }
+
+    #[tokio::test]
+    async fn an_admin_can_unlock_the_account() {
+        let pool = test_pool().await;
+        let config = Config {
+            login_max_failures: 1,
+            ..config()
+        };
+        record_failed_login(&pool, &config, "user", None)
+            .await
+            .unwrap()
+            .unwrap();
+
+        unlock_account(&pool, "user", "admin").await.unwrap();
+        check_login_allowed(&pool, "user").await.unwrap();
+
+        let actor: Option<String> = sqlx::query_scalar(
+            "SELECT actor_id FROM auth_audit_log WHERE event = 'account_unlocked'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(actor.as_deref(), Some("admin"));
+
+        let err = unlock_account(&pool, "nobody", "admin").await.unwrap_err();
+        assert_eq!(err.code(), "NOT_FOUND");
+    }
+}
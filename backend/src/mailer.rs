@@ -0,0 +1,91 @@
+//! Outgoing email.
+//!
+//! Handlers send mail through the [`Mailer`] trait, shared as
+//! `web::Data<dyn Mailer>`. The backend is chosen by the `mailer` setting;
+//! neither built-in backend delivers anything, they exist so flows like
+//! password reset can be exercised locally. A real transport only needs to
+//! implement the trait and be returned from [`from_config`].
+
+use chrono::Utc;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::config::{Config, MailerBackend};
+use crate::error::AppError;
+
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+pub trait Mailer: Send + Sync {
+    fn send(&self, email: &Email) -> Result<(), AppError>;
+}
+
+pub fn from_config(config: &Config) -> Arc<dyn Mailer> {
+    match config.mailer {
+        MailerBackend::Log => Arc::new(LogMailer {
+            from: config.mail_from.clone(),
+        }),
+        MailerBackend::File => Arc::new(FileMailer {
+            from: config.mail_from.clone(),
+            dir: PathBuf::from(&config.mail_dir),
+        }),
+    }
+}
+
+/// Writes each message to the server log.
+pub struct LogMailer {
+    from: String,
+}
+
+impl Mailer for LogMailer {
+    fn send(&self, email: &Email) -> Result<(), AppError> {
+        log::info!(
+            "Email from {} to {}: {}\n{}",
+            self.from,
+            email.to,
+            email.subject,
+            email.body
+        );
+        Ok(())
+    }
+}
+
+/// Writes each message to its own `.eml` file in `dir`.
+pub struct FileMailer {
+    from: String,
+    dir: PathBuf,
+}
+
+impl Mailer for FileMailer {
+    fn send(&self, email: &Email) -> Result<(), AppError> {
+        let now = Utc::now();
+        let path = self.dir.join(format!(
+            "{}-{}.eml",
+            now.format("%Y%m%dT%H%M%S"),
+            Uuid::new_v4()
+        ));
+        let message = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\n\r\n{}\r\n",
+            self.from,
+            email.to,
+            email.subject,
+            now.to_rfc2822(),
+            email.body
+        );
+
+        fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&path, message))
+            .map_err(|e| {
+                AppError::Internal(format!(
+                    "Failed to write email to {}: {}",
+                    path.display(),
+                    e
+                ))
+            })
+    }
+}
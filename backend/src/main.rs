@@ -1,9 +1,24 @@
 mod middleware {
     pub mod auth;
+    pub mod guards;
     pub mod logging;
+    pub mod rate_limit;
 }
+mod clock;
+mod config;
 mod error;
+mod escalation;
+mod filters;
+mod ical;
+mod lockout;
+mod mailer;
+mod migrations;
+mod recurrence;
+mod reminders;
 mod seeder;
+mod task_generator;
+mod timezone;
+mod tokens;
 mod models {
     pub mod equipment;
     pub mod events;
@@ -20,15 +35,25 @@ mod models {
 
 mod routes {
     pub mod auth;
+    pub mod calendar_feed;
+    pub mod calendar_import;
+    pub mod calendar_range;
     pub mod dashboard;
     pub mod equipment;
+    pub mod escalation_rules;
+    pub mod event_templates;
     pub mod events;
     pub mod glossary;
     pub mod locations;
     pub mod notifications;
     pub mod projects;
     pub mod quick_links;
+    pub mod recurring_tasks;
+    pub mod scheduling;
     pub mod search;
+    pub mod task_checklists;
+    pub mod task_dependencies;
+    pub mod task_history;
     pub mod tasks;
     pub mod tracking;
     pub mod users;
@@ -37,13 +62,25 @@ mod routes {
 use actix_cors::Cors;
 use actix_web::{App, HttpServer, web};
 use log::info;
-use middleware::{auth::Auth, logging::Logger};
+use middleware::{
+    auth::Auth,
+    logging::Logger,
+    rate_limit::{RateLimit, RateLimiter},
+};
 use routes::{
-    auth, dashboard, equipment, events, glossary, locations, notifications, projects, quick_links,
-    search, tasks, tracking, users,
+    auth, calendar_feed, calendar_import, calendar_range, dashboard, equipment, escalation_rules,
+    event_templates, events, glossary, locations, notifications, projects, quick_links,
+    recurring_tasks, scheduling, search, task_checklists, task_dependencies, task_history, tasks,
+    tracking, users,
 };
 
+use config::Config;
+use error::AppError;
 use sqlx::SqlitePool;
+use sqlx::sqlite::SqliteConnectOptions;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokens::RevokedSessions;
 use tokio::time::{Duration, interval};
 
 #[actix_web::main]
@@ -51,18 +88,46 @@ async fn main() -> std::io::Result<()> {
     env_logger::init();
     info!("Starting the IT-Engineering Collaboration Dashboard server...");
 
-    let db_pool = SqlitePool::connect("sqlite://database.db")
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Failed to load configuration: {}", e);
+            return Err(std::io::Error::other(e));
+        }
+    };
+    if config.dev_mode {
+        log::warn!("Running in dev mode - insecure defaults are allowed");
+    }
+
+    let connect_options = SqliteConnectOptions::from_str(&config.database_url)
+        .map_err(std::io::Error::other)?
+        .create_if_missing(true);
+    let db_pool = SqlitePool::connect_with(connect_options)
         .await
         .expect("Failed to connect to SQLite database");
     let db_pool = web::Data::new(db_pool);
 
-    // Run database seeder on startup
-    info!("Running database seeder...");
-    if let Err(e) = seeder::run_seeder(db_pool.get_ref()).await {
-        eprintln!("Failed to seed database: {}", e);
-        // Continue anyway - seeding is optional
-    } else {
-        info!("Database seeding completed");
+    // Bring the schema up to date before serving any requests
+    info!("Running database migrations...");
+    match migrations::run_migrations(db_pool.get_ref()).await {
+        Ok(0) => info!("Database schema is up to date"),
+        Ok(applied) => info!("Applied {} database migration(s)", applied),
+        Err(e) => {
+            eprintln!("Failed to run database migrations: {}", e);
+            return Err(std::io::Error::other(e));
+        }
+    }
+
+    // Seeding is destructive and strictly opt-in
+    let seed_flag = std::env::args().skip(1).any(|arg| arg == "--seed");
+    if config.seed_database || seed_flag {
+        info!("Running database seeder...");
+        if let Err(e) = seeder::run_seeder(db_pool.get_ref()).await {
+            eprintln!("Failed to seed database: {}", e);
+            // Continue anyway - seeding is optional
+        } else {
+            info!("Database seeding completed");
+        }
     }
 
     // Perform initial cleanup of expired sessions
@@ -82,8 +147,9 @@ async fn main() -> std::io::Result<()> {
 
     // Start background task for periodic session cleanup
     let db_pool_for_cleanup = db_pool.clone();
+    let cleanup_interval_secs = config.session_cleanup_interval_secs;
     tokio::spawn(async move {
-        let mut interval = interval(Duration::from_secs(3600)); // Run every hour
+        let mut interval = interval(Duration::from_secs(cleanup_interval_secs));
         loop {
             interval.tick().await;
             match middleware::auth::cleanup_expired_sessions(db_pool_for_cleanup.get_ref()).await {
@@ -102,7 +168,96 @@ async fn main() -> std::io::Result<()> {
         }
     });
 
-    info!("Session cleanup background task started (runs every hour)");
+    info!(
+        "Session cleanup background task started (runs every {}s)",
+        cleanup_interval_secs
+    );
+
+    // Start background task for event reminders
+    let db_pool_for_reminders = db_pool.clone();
+    let reminder_interval_secs = config.reminder_interval_secs;
+    tokio::spawn(async move {
+        let clock = clock::SystemClock;
+        let mut interval = interval(Duration::from_secs(reminder_interval_secs));
+        loop {
+            interval.tick().await;
+            match reminders::send_due_reminders(db_pool_for_reminders.get_ref(), &clock).await {
+                Ok(sent) => {
+                    if sent > 0 {
+                        info!("Sent {} event reminder(s)", sent);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Sending event reminders failed: {}", e);
+                }
+            }
+        }
+    });
+
+    info!(
+        "Event reminder background task started (runs every {}s)",
+        reminder_interval_secs
+    );
+
+    // Start background task for recurring task instances
+    let db_pool_for_generator = db_pool.clone();
+    let task_generation_interval_secs = config.task_generation_interval_secs;
+    tokio::spawn(async move {
+        let clock = clock::SystemClock;
+        let mut interval = interval(Duration::from_secs(task_generation_interval_secs));
+        loop {
+            interval.tick().await;
+            match task_generator::generate_due_tasks(db_pool_for_generator.get_ref(), &clock).await
+            {
+                Ok(created) => {
+                    if created > 0 {
+                        info!("Created {} recurring task instance(s)", created);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Creating recurring task instances failed: {}", e);
+                }
+            }
+        }
+    });
+
+    info!(
+        "Recurring task generator background task started (runs every {}s)",
+        task_generation_interval_secs
+    );
+
+    // Start background task for urgency escalation
+    let db_pool_for_escalation = db_pool.clone();
+    let escalation_interval_secs = config.escalation_interval_secs;
+    tokio::spawn(async move {
+        let clock = clock::SystemClock;
+        let mut interval = interval(Duration::from_secs(escalation_interval_secs));
+        loop {
+            interval.tick().await;
+            match escalation::escalate_tasks(db_pool_for_escalation.get_ref(), &clock).await {
+                Ok(escalated) => {
+                    if escalated > 0 {
+                        info!("Raised the urgency of {} task(s)", escalated);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Escalating task urgency failed: {}", e);
+                }
+            }
+        }
+    });
+
+    info!(
+        "Urgency escalation background task started (runs every {}s)",
+        escalation_interval_secs
+    );
+
+    let bind_address = config.bind_address();
+    let mailer: web::Data<dyn mailer::Mailer> = web::Data::from(mailer::from_config(&config));
+    let config = web::Data::new(config);
+    let revoked_sessions = web::Data::new(RevokedSessions::default());
+    // Shared by all workers so the limit applies per IP, not per worker
+    let rate_limiter = Arc::new(RateLimiter::per_minute(config.rate_limit_per_minute));
 
     HttpServer::new(move || {
         // Use permissive CORS for development
@@ -110,15 +265,42 @@ async fn main() -> std::io::Result<()> {
 
         App::new()
             .app_data(db_pool.clone())
+            .app_data(config.clone())
+            .app_data(revoked_sessions.clone())
+            .app_data(mailer.clone())
+            // Malformed bodies, query strings and path segments use the standard error envelope
+            .app_data(
+                web::JsonConfig::default()
+                    .error_handler(|err, _req| AppError::Validation(err.to_string()).into()),
+            )
+            .app_data(
+                web::QueryConfig::default()
+                    .error_handler(|err, _req| AppError::Validation(err.to_string()).into()),
+            )
+            .app_data(
+                web::PathConfig::default()
+                    .error_handler(|err, _req| AppError::Validation(err.to_string()).into()),
+            )
             .wrap(cors)
             .wrap(Auth)
+            .wrap(RateLimit::new(rate_limiter.clone()))
             .wrap(Logger)
             .service(
                 web::scope("/api")
                     .configure(auth::configure_routes)
                     .configure(dashboard::configure_routes)
                     .configure(users::configure_routes)
+                    .configure(calendar_feed::configure_routes)
+                    .configure(calendar_import::configure_routes)
+                    .configure(calendar_range::configure_routes)
+                    .configure(scheduling::configure_routes)
+                    .configure(event_templates::configure_routes)
                     .configure(events::configure_routes)
+                    .configure(escalation_rules::configure_routes)
+                    .configure(recurring_tasks::configure_routes)
+                    .configure(task_checklists::configure_routes)
+                    .configure(task_dependencies::configure_routes)
+                    .configure(task_history::configure_routes)
                     .configure(tasks::configure_routes)
                     .configure(equipment::configure_routes)
                     .configure(locations::configure_routes)
@@ -130,7 +312,7 @@ async fn main() -> std::io::Result<()> {
                     .configure(tracking::configure_routes),
             )
     })
-    .bind(("127.0.0.1", 8080))?
+    .bind(bind_address)?
     .run()
     .await
 }
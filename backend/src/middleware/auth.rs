@@ -9,6 +9,15 @@ use std::{
     rc::Rc,
 };
 
+use crate::config::Config;
+use crate::error::AppError;
+use crate::middleware::guards::AuthUser;
+use crate::routes::calendar_feed;
+use crate::tokens::{self, RevokedSessions};
+
+/// Cookie carrying the access token for browser clients
+pub const ACCESS_TOKEN_COOKIE: &str = "access_token";
+
 type LocalBoxFuture<T> = Pin<Box<dyn Future<Output = T> + 'static>>;
 
 /// Utility function to clean up expired sessions
@@ -45,11 +54,13 @@ pub struct AuthMiddleware<S> {
     service: Rc<S>,
 }
 
-const WHITELISTED_PATHS: [&str; 4] = [
+const WHITELISTED_PATHS: [&str; 6] = [
     "/",
     "/api/auth/login",
     "/api/auth/register",
     "/api/auth/refresh",
+    "/api/auth/forgot-password",
+    "/api/auth/reset-password",
 ];
 
 impl<S, B> Service<ServiceRequest> for AuthMiddleware<S>
@@ -75,23 +86,22 @@ where
                 return Ok(res);
             }
 
-            // Skip authentication for login, register, and root paths
+            // Skip authentication for login, register, and root paths, and for
+            // calendar feeds, which carry their own token
             let path = req.path();
-            if WHITELISTED_PATHS.contains(&path) {
+            if WHITELISTED_PATHS.contains(&path) || calendar_feed::is_feed_path(path) {
                 println!("Skipping auth for path: {}", path);
                 let res = service.call(req).await?;
                 return Ok(res);
             }
 
-            // Get session token from Authorization header (Bearer format) or cookies
-            // Priority: Authorization header > session_token cookie
+            // Get the access token from the Authorization header (Bearer format) or cookies
+            // Priority: Authorization header > access_token cookie
             // This supports both API clients (Bearer token) and web browsers (cookies)
-            let session_token = match req.headers().get("authorization") {
+            let access_token = match req.headers().get("authorization") {
                 Some(auth_header) => match auth_header.to_str() {
                     Ok(header_value) => {
-                        if header_value.starts_with("Bearer ") {
-                            let token = header_value.strip_prefix("Bearer ").unwrap();
-                            println!("Authorization header found: Bearer {}", token);
+                        if let Some(token) = header_value.strip_prefix("Bearer ") {
                             Some(token.to_string())
                         } else {
                             println!("Invalid Authorization header format (missing Bearer prefix)");
@@ -103,124 +113,57 @@ where
                         None
                     }
                 },
-                None => {
-                    // If no Authorization header, check for session token in cookies
-                    match req.headers().get("cookie") {
-                        Some(cookie_header) => {
-                            if let Ok(cookie_str) = cookie_header.to_str() {
-                                // Parse cookies to find session_token
-                                let token = cookie_str
-                                    .split(';')
-                                    .map(|cookie| cookie.trim())
-                                    .find(|cookie| cookie.starts_with("session_token="))
-                                    .and_then(|cookie: &str| cookie.strip_prefix("session_token="))
-                                    .map(|token| token.to_string());
-
-                                if let Some(ref token) = token {
-                                    println!("Session token found in cookie: {}", token);
-                                } else {
-                                    println!("No session_token cookie found");
-                                }
-                                token
-                            } else {
-                                println!("Invalid cookie header format");
-                                None
-                            }
-                        }
-                        None => {
-                            println!("No Authorization header or cookies found!");
-                            None
-                        }
-                    }
-                }
+                None => req
+                    .cookie(ACCESS_TOKEN_COOKIE)
+                    .map(|cookie| cookie.value().to_string()),
             };
 
-            let session_token = match session_token {
+            let access_token = match access_token {
                 Some(token) => token,
                 None => {
-                    println!(
-                        "Session token missing from both Authorization header and cookies. Please log in."
-                    );
-                    return Err(Error::from(actix_web::error::ErrorUnauthorized(
-                        "Session token missing! Please log in with Authorization header or cookie.",
-                    )));
+                    println!("Access token missing from both Authorization header and cookies.");
+                    return Err(AppError::Unauthorized(
+                        "Access token missing! Please log in with Authorization header or cookie."
+                            .to_string(),
+                    )
+                    .into());
                 }
             };
 
-            println!("Session token: {}", session_token);
+            // Access tokens are self-contained: the signature and expiry are
+            // checked with the secret alone, without a database round trip.
+            let config = req.app_data::<web::Data<Config>>().unwrap();
+            let claims = match tokens::verify_access_token(config, &access_token) {
+                Ok(claims) => claims,
+                Err(e) => {
+                    println!("Unauthorized! {}", e.message());
+                    return Err(e.into());
+                }
+            };
 
-            // Get database pool
-            let pool = req.app_data::<web::Data<sqlx::SqlitePool>>().unwrap();
+            // Logging out or revoking a session takes effect before its
+            // access tokens expire
+            if let Some(revoked) = req.app_data::<web::Data<RevokedSessions>>()
+                && revoked.is_revoked(&claims.sid)
+            {
+                println!("Unauthorized! Session {} was revoked.", claims.sid);
+                return Err(AppError::Unauthorized(
+                    "Session revoked! Please log in again.".to_string(),
+                )
+                .with_code("SESSION_REVOKED")
+                .into());
+            }
 
-            // First, clean up any expired sessions (optional cleanup)
-            let cleanup_query = "DELETE FROM sessions WHERE expires_at <= datetime('now')";
-            let cleanup_result = sqlx::query(cleanup_query).execute(pool.get_ref()).await;
+            println!(
+                "Authorized! User ID: {}, Role: {}, Session: {}",
+                claims.sub, claims.role, claims.sid
+            );
 
-            if let Ok(cleanup_rows) = cleanup_result {
-                if cleanup_rows.rows_affected() > 0 {
-                    println!(
-                        "Cleaned up {} expired sessions",
-                        cleanup_rows.rows_affected()
-                    );
-                }
-            }
+            // Add user info to request extensions for use in handlers
+            req.extensions_mut().insert(AuthUser::from(claims));
 
-            // Check if session is valid and not expired
-            let query = "SELECT s.token, s.user_id, s.expires_at, u.role 
-                         FROM sessions s 
-                         JOIN users u ON s.user_id = u.id
-                         WHERE s.token = ? AND s.expires_at > datetime('now') AND u.is_active = 1";
-
-            let res = sqlx::query_as::<_, (String, String, String, String)>(query)
-                .bind(&session_token)
-                .fetch_optional(pool.get_ref())
-                .await;
-
-            match res {
-                Ok(Some((_, user_id, expires_at, role))) => {
-                    let is_admin = role == "Admin";
-                    println!(
-                        "Authorized! User ID: {}, Role: {}, Session expires: {}",
-                        user_id, role, expires_at
-                    );
-
-                    // Add user info to request extensions for use in handlers
-                    req.extensions_mut().insert(user_id);
-                    req.extensions_mut().insert(is_admin);
-
-                    let res = service.call(req).await?;
-                    Ok(res)
-                }
-                Ok(None) => {
-                    // Check if the session exists but is expired (for better error messaging)
-                    let expired_check_query = "SELECT expires_at FROM sessions WHERE token = ?";
-                    let expired_check = sqlx::query_as::<_, (String,)>(expired_check_query)
-                        .bind(&session_token)
-                        .fetch_optional(pool.get_ref())
-                        .await;
-
-                    match expired_check {
-                        Ok(Some((expires_at,))) => {
-                            println!("Session found but expired at: {}", expires_at);
-                            return Err(Error::from(actix_web::error::ErrorUnauthorized(
-                                "Session expired! Please log in again.",
-                            )));
-                        }
-                        _ => {
-                            println!("Unauthorized! Invalid session token.");
-                            return Err(Error::from(actix_web::error::ErrorUnauthorized(
-                                "Invalid session! Please log in again.",
-                            )));
-                        }
-                    }
-                }
-                Err(e) => {
-                    println!("Database Error: {}", e);
-                    return Err(Error::from(actix_web::error::ErrorInternalServerError(
-                        "Database Error",
-                    )));
-                }
-            }
+            let res = service.call(req).await?;
+            Ok(res)
         })
     }
 }
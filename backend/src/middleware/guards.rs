@@ -0,0 +1,385 @@
+//! Request extractors for the user authenticated by the `Auth` middleware.
+//!
+//! Handlers take an [`AuthUser`] when any signed-in user may call them, or a
+//! [`RequireRole`] when the route needs a minimum role:
+//!
+//! ```ignore
+//! async fn create_equipment(auth: RequireRole<Admin>, ...) -> Result<HttpResponse, AppError>
+//! ```
+//!
+//! Roles are ranked `Viewer < Member < Admin`; a guard admits its own role and
+//! every role above it.
+
+use actix_web::{FromRequest, HttpMessage, HttpRequest, dev::Payload};
+use std::future::{Ready, ready};
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use crate::error::AppError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    Viewer,
+    Member,
+    Admin,
+}
+
+impl Role {
+    /// Parses `users.role`. Unknown values get the least privilege.
+    pub fn from_db(role: &str) -> Self {
+        match role {
+            "Admin" => Role::Admin,
+            "Member" => Role::Member,
+            _ => Role::Viewer,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "Viewer",
+            Role::Member => "Member",
+            Role::Admin => "Admin",
+        }
+    }
+}
+
+/// The signed-in user, taken from the access token by the `Auth` middleware.
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub id: String,
+    pub role: Role,
+    pub department: String,
+    /// Session the access token was issued for
+    pub session_id: String,
+}
+
+impl AuthUser {
+    pub fn is_admin(&self) -> bool {
+        self.role == Role::Admin
+    }
+
+    /// Passes for `owner_id` and for Admins; anyone else gets a 403 with `code`.
+    pub fn ensure_owner_or_admin(
+        &self,
+        owner_id: &str,
+        code: &str,
+        message: &str,
+    ) -> Result<(), AppError> {
+        if self.is_admin() || self.id == owner_id {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden(message.to_string()).with_code(code))
+        }
+    }
+}
+
+impl FromRequest for AuthUser {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<AuthUser>()
+                .cloned()
+                .ok_or_else(|| AppError::Unauthorized("Not authenticated".to_string())),
+        )
+    }
+}
+
+/// Minimum role admitted by a [`RequireRole`] guard.
+pub trait RoleRequirement {
+    const MIN: Role;
+}
+
+pub struct Member;
+
+impl RoleRequirement for Member {
+    const MIN: Role = Role::Member;
+}
+
+pub struct Admin;
+
+impl RoleRequirement for Admin {
+    const MIN: Role = Role::Admin;
+}
+
+/// An [`AuthUser`] whose role is at least `R::MIN`; rejects with 403 otherwise.
+pub struct RequireRole<R> {
+    user: AuthUser,
+    _role: PhantomData<R>,
+}
+
+impl<R> Deref for RequireRole<R> {
+    type Target = AuthUser;
+
+    fn deref(&self) -> &AuthUser {
+        &self.user
+    }
+}
+
+impl<R: RoleRequirement> FromRequest for RequireRole<R> {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let user = match AuthUser::from_request(req, payload).into_inner() {
+            Ok(user) => user,
+            Err(e) => return ready(Err(e)),
+        };
+
+        if user.role < R::MIN {
+            return ready(Err(AppError::Forbidden(format!(
+                "Requires {} role",
+                R::MIN.as_str()
+            ))));
+        }
+
+        ready(Ok(RequireRole {
+            user,
+            _role: PhantomData,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::dev::Service;
+    use actix_web::http::{Method, StatusCode};
+    use actix_web::test::{TestRequest, call_service, init_service};
+    use actix_web::{App, HttpResponse, web};
+    use sqlx::SqlitePool;
+
+    use crate::config::Config;
+    use crate::routes;
+    use crate::tokens::RevokedSessions;
+
+    const ROLE_HEADER: &str = "x-test-role";
+
+    fn user(role: Role) -> AuthUser {
+        AuthUser {
+            id: format!("{}-user", role.as_str().to_lowercase()),
+            role,
+            department: "IT".to_string(),
+            session_id: "test-session".to_string(),
+        }
+    }
+
+    /// Builds a request that the test app authenticates as `role`.
+    fn request(method: Method, uri: &str, role: Option<Role>) -> TestRequest {
+        let req = TestRequest::default().method(method).uri(uri);
+        match role {
+            Some(role) => req.insert_header((ROLE_HEADER, role.as_str())),
+            None => req,
+        }
+    }
+
+    /// Stands in for the `Auth` middleware: turns `x-test-role` into an `AuthUser`.
+    macro_rules! test_app {
+        ($app:expr) => {
+            init_service($app.wrap_fn(|req, srv| {
+                let role = req
+                    .headers()
+                    .get(ROLE_HEADER)
+                    .and_then(|v| v.to_str().ok())
+                    .map(Role::from_db);
+                if let Some(role) = role {
+                    req.extensions_mut().insert(user(role));
+                }
+                srv.call(req)
+            }))
+            .await
+        };
+    }
+
+    #[actix_web::test]
+    async fn extractors_rank_roles() {
+        let app = test_app!(
+            App::new()
+                .route(
+                    "/any",
+                    web::get().to(|_: AuthUser| async { HttpResponse::Ok().finish() }),
+                )
+                .route(
+                    "/member",
+                    web::get().to(|_: RequireRole<Member>| async { HttpResponse::Ok().finish() }),
+                )
+                .route(
+                    "/admin",
+                    web::get().to(|_: RequireRole<Admin>| async { HttpResponse::Ok().finish() }),
+                )
+        );
+
+        let cases = [
+            ("/any", None, StatusCode::UNAUTHORIZED),
+            ("/any", Some(Role::Viewer), StatusCode::OK),
+            ("/any", Some(Role::Member), StatusCode::OK),
+            ("/any", Some(Role::Admin), StatusCode::OK),
+            ("/member", None, StatusCode::UNAUTHORIZED),
+            ("/member", Some(Role::Viewer), StatusCode::FORBIDDEN),
+            ("/member", Some(Role::Member), StatusCode::OK),
+            ("/member", Some(Role::Admin), StatusCode::OK),
+            ("/admin", None, StatusCode::UNAUTHORIZED),
+            ("/admin", Some(Role::Viewer), StatusCode::FORBIDDEN),
+            ("/admin", Some(Role::Member), StatusCode::FORBIDDEN),
+            ("/admin", Some(Role::Admin), StatusCode::OK),
+        ];
+        for (uri, role, expected) in cases {
+            let res = call_service(&app, request(Method::GET, uri, role).to_request()).await;
+            assert_eq!(res.status(), expected, "{} as {:?}", uri, role);
+        }
+    }
+
+    #[test]
+    fn unknown_db_role_is_viewer() {
+        assert_eq!(Role::from_db("Admin"), Role::Admin);
+        assert_eq!(Role::from_db("Member"), Role::Member);
+        assert_eq!(Role::from_db("Viewer"), Role::Viewer);
+        assert_eq!(Role::from_db("admin"), Role::Viewer);
+    }
+
+    async fn pool() -> SqlitePool {
+        let pool = crate::migrations::test_pool().await;
+        for role in [Role::Viewer, Role::Member, Role::Admin] {
+            let u = user(role);
+            sqlx::query(
+                "INSERT INTO users (id, email, password_hash, first_name, last_name, department, role) VALUES (?, ?, 'x', 'Test', ?, 'IT', ?)",
+            )
+            .bind(&u.id)
+            .bind(format!("{}@example.com", u.id))
+            .bind(role.as_str())
+            .bind(role.as_str())
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        pool
+    }
+
+    /// Checks the policy on real routes: a rejected role never reaches the
+    /// handler, a permitted one does.
+    #[actix_web::test]
+    async fn route_policies() {
+        let app = test_app!(
+            App::new()
+                .app_data(web::Data::new(pool().await))
+                .app_data(web::Data::new(Config::default()))
+                .app_data(web::Data::new(RevokedSessions::default()))
+                .service(
+                    web::scope("/api")
+                        .configure(routes::equipment::configure_routes)
+                        .configure(routes::glossary::configure_routes)
+                        .configure(routes::tasks::configure_routes)
+                        .configure(routes::users::configure_routes),
+                )
+        );
+
+        let equipment = serde_json::json!({ "name": "Scope", "category": "Test" });
+        let term = serde_json::json!({ "term": "RRULE", "definition": "Recurrence rule" });
+        let task = serde_json::json!({
+            "title": "Write tests",
+            "urgency": "low",
+            "department": "IT",
+            "deadline": "2030-01-01"
+        });
+
+        let cases = [
+            // Admin only
+            (
+                Method::POST,
+                "/api/equipment",
+                Role::Viewer,
+                Some(&equipment),
+                StatusCode::FORBIDDEN,
+            ),
+            (
+                Method::POST,
+                "/api/equipment",
+                Role::Member,
+                Some(&equipment),
+                StatusCode::FORBIDDEN,
+            ),
+            (
+                Method::POST,
+                "/api/equipment",
+                Role::Admin,
+                Some(&equipment),
+                StatusCode::CREATED,
+            ),
+            (
+                Method::DELETE,
+                "/api/users/viewer-user",
+                Role::Member,
+                None,
+                StatusCode::FORBIDDEN,
+            ),
+            (
+                Method::DELETE,
+                "/api/users/viewer-user",
+                Role::Admin,
+                None,
+                StatusCode::OK,
+            ),
+            // Member and above
+            (
+                Method::POST,
+                "/api/glossary/terms",
+                Role::Viewer,
+                Some(&term),
+                StatusCode::FORBIDDEN,
+            ),
+            (
+                Method::POST,
+                "/api/glossary/terms",
+                Role::Member,
+                Some(&term),
+                StatusCode::CREATED,
+            ),
+            (
+                Method::POST,
+                "/api/tasks",
+                Role::Viewer,
+                Some(&task),
+                StatusCode::FORBIDDEN,
+            ),
+            (
+                Method::POST,
+                "/api/tasks",
+                Role::Member,
+                Some(&task),
+                StatusCode::CREATED,
+            ),
+            (
+                Method::POST,
+                "/api/tasks",
+                Role::Admin,
+                Some(&task),
+                StatusCode::CREATED,
+            ),
+            // Any signed-in user
+            (
+                Method::GET,
+                "/api/tasks",
+                Role::Viewer,
+                None,
+                StatusCode::OK,
+            ),
+            (
+                Method::GET,
+                "/api/users/me",
+                Role::Viewer,
+                None,
+                StatusCode::OK,
+            ),
+        ];
+        for (method, uri, role, body, expected) in cases {
+            let mut req = request(method.clone(), uri, Some(role));
+            if let Some(body) = body {
+                req = req.set_json(body);
+            }
+            let res = call_service(&app, req.to_request()).await;
+            assert_eq!(res.status(), expected, "{} {} as {:?}", method, uri, role);
+        }
+    }
+}
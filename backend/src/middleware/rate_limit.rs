@@ -0,0 +1,224 @@
+//! Per-IP request rate limiting.
+//!
+//! Each client address gets a fixed one-minute window. Requests over the limit
+//! are rejected with `429 RATE_LIMIT_EXCEEDED` and a `Retry-After` header.
+//!
+//! The client address is the TCP peer, not `X-Forwarded-For`, so a client
+//! cannot dodge the limit by sending that header.
+
+use actix_web::{
+    Error,
+    dev::{Service, ServiceRequest, ServiceResponse, Transform, forward_ready},
+};
+use std::{
+    collections::HashMap,
+    future::{Future, Ready, ready},
+    net::IpAddr,
+    pin::Pin,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::error::AppError;
+
+type LocalBoxFuture<T> = Pin<Box<dyn Future<Output = T> + 'static>>;
+
+struct Window {
+    started: Instant,
+    requests: u32,
+}
+
+struct Clients {
+    windows: HashMap<IpAddr, Window>,
+    last_pruned: Instant,
+}
+
+/// Request counts per client address, shared by all workers.
+pub struct RateLimiter {
+    limit: u32,
+    window: Duration,
+    clients: Mutex<Clients>,
+}
+
+impl RateLimiter {
+    pub fn per_minute(limit: u32) -> Self {
+        Self::new(limit, Duration::from_secs(60))
+    }
+
+    fn new(limit: u32, window: Duration) -> Self {
+        RateLimiter {
+            limit,
+            window,
+            clients: Mutex::new(Clients {
+                windows: HashMap::new(),
+                last_pruned: Instant::now(),
+            }),
+        }
+    }
+
+    /// Counts a request from `ip`. Over the limit, returns how long until the
+    /// client's window resets.
+    pub fn check(&self, ip: IpAddr) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Forget clients whose window has ended, at most once per window
+        if now.duration_since(clients.last_pruned) >= self.window {
+            clients
+                .windows
+                .retain(|_, w| now.duration_since(w.started) < self.window);
+            clients.last_pruned = now;
+        }
+
+        let window = clients.windows.entry(ip).or_insert(Window {
+            started: now,
+            requests: 0,
+        });
+        if now.duration_since(window.started) >= self.window {
+            window.started = now;
+            window.requests = 0;
+        }
+
+        window.requests = window.requests.saturating_add(1);
+        if window.requests > self.limit {
+            Err(self.window - now.duration_since(window.started))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimit {
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimit {
+    pub fn new(limiter: Arc<RateLimiter>) -> Self {
+        RateLimit { limiter }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            limiter: self.limiter.clone(),
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let limited = req
+            .peer_addr()
+            .and_then(|addr| self.limiter.check(addr.ip()).err().map(|wait| (addr, wait)));
+
+        Box::pin(async move {
+            if let Some((addr, wait)) = limited {
+                println!("Rate limit exceeded for {}", addr.ip());
+                let retry_after = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+                return Err(AppError::TooManyRequests(
+                    "Rate limit exceeded! Please slow down.".to_string(),
+                )
+                .with_code("RATE_LIMIT_EXCEEDED")
+                .with_retry_after(retry_after)
+                .into());
+            }
+
+            service.call(req).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::{StatusCode, header};
+    use actix_web::test::{TestRequest, init_service, try_call_service};
+    use actix_web::{App, HttpResponse, web};
+
+    fn ip(last: u8) -> IpAddr {
+        IpAddr::from([192, 0, 2, last])
+    }
+
+    #[test]
+    fn limits_each_client_within_its_window() {
+        let limiter = RateLimiter::per_minute(2);
+        assert!(limiter.check(ip(1)).is_ok());
+        assert!(limiter.check(ip(1)).is_ok());
+
+        let wait = limiter.check(ip(1)).unwrap_err();
+        assert!(wait <= Duration::from_secs(60) && wait > Duration::from_secs(59));
+
+        // Other clients have their own count
+        assert!(limiter.check(ip(2)).is_ok());
+    }
+
+    #[test]
+    fn a_new_window_resets_the_count() {
+        let limiter = RateLimiter::new(1, Duration::from_millis(50));
+        assert!(limiter.check(ip(1)).is_ok());
+        assert!(limiter.check(ip(1)).is_err());
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(limiter.check(ip(1)).is_ok());
+        assert!(limiter.check(ip(1)).is_err());
+    }
+
+    #[actix_web::test]
+    async fn rejects_with_retry_after() {
+        let app = init_service(
+            App::new()
+                .wrap(RateLimit::new(Arc::new(RateLimiter::per_minute(1))))
+                .route("/", web::get().to(|| async { HttpResponse::Ok().finish() })),
+        )
+        .await;
+        let request = || {
+            TestRequest::get()
+                .uri("/")
+                .peer_addr("192.0.2.1:40000".parse().unwrap())
+                .to_request()
+        };
+
+        let res = try_call_service(&app, request()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let err = try_call_service(&app, request()).await.unwrap_err();
+        let res = err.error_response();
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers().get(header::RETRY_AFTER).unwrap(), "60");
+        let body = actix_web::body::to_bytes(res.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["code"], "RATE_LIMIT_EXCEEDED");
+        assert_eq!(body["error"]["details"]["retryAfter"], 60);
+    }
+}
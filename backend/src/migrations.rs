@@ -0,0 +1,182 @@
+//! Database Migrations Module
+//!
+//! Versioned, forward-only schema migrations. Each migration is a numbered SQL
+//! file under `backend/migrations/` that is embedded into the binary at compile
+//! time. Applied versions are recorded in the `schema_migrations` table so every
+//! migration runs exactly once per database.
+//!
+//! To change the schema, add a new file with the next version number and append
+//! it to `MIGRATIONS`. Never edit a migration that has already been released.
+
+use sqlx::sqlite::SqlitePool;
+
+struct Migration {
+    version: i64,
+    name: &'static str,
+    sql: &'static str,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        sql: include_str!("../migrations/0001_initial_schema.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "refresh_tokens",
+        sql: include_str!("../migrations/0002_refresh_tokens.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "session_last_seen",
+        sql: include_str!("../migrations/0003_session_last_seen.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "login_lockout",
+        sql: include_str!("../migrations/0004_login_lockout.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "password_reset_tokens",
+        sql: include_str!("../migrations/0005_password_reset_tokens.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "event_recurrence",
+        sql: include_str!("../migrations/0006_event_recurrence.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "calendar_feed_tokens",
+        sql: include_str!("../migrations/0007_calendar_feed_tokens.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "event_ical_uid",
+        sql: include_str!("../migrations/0008_event_ical_uid.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "event_rsvp",
+        sql: include_str!("../migrations/0009_event_rsvp.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "event_reminders",
+        sql: include_str!("../migrations/0010_event_reminders.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "time_zones",
+        sql: include_str!("../migrations/0011_time_zones.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "event_templates",
+        sql: include_str!("../migrations/0012_event_templates.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "task_dependencies",
+        sql: include_str!("../migrations/0013_task_dependencies.sql"),
+    },
+    Migration {
+        version: 14,
+        name: "task_checklists",
+        sql: include_str!("../migrations/0014_task_checklists.sql"),
+    },
+    Migration {
+        version: 15,
+        name: "recurring_tasks",
+        sql: include_str!("../migrations/0015_recurring_tasks.sql"),
+    },
+    Migration {
+        version: 16,
+        name: "urgency_escalation",
+        sql: include_str!("../migrations/0016_urgency_escalation.sql"),
+    },
+    Migration {
+        version: 17,
+        name: "task_history_source",
+        sql: include_str!("../migrations/0017_task_history_source.sql"),
+    },
+    Migration {
+        version: 18,
+        name: "task_deadlines_utc",
+        sql: include_str!("../migrations/0018_task_deadlines_utc.sql"),
+    },
+];
+
+async fn ensure_migrations_table(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL DEFAULT (datetime('now'))
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Returns the highest applied migration version, or 0 for a fresh database.
+pub async fn current_version(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    ensure_migrations_table(pool).await?;
+
+    let (version,): (i64,) =
+        sqlx::query_as("SELECT COALESCE(MAX(version), 0) FROM schema_migrations")
+            .fetch_one(pool)
+            .await?;
+
+    Ok(version)
+}
+
+/// Applies every pending migration in version order.
+///
+/// Each migration runs in its own transaction together with its
+/// `schema_migrations` row, so a failing migration leaves the database at the
+/// previous version. Returns the number of migrations applied.
+pub async fn run_migrations(pool: &SqlitePool) -> Result<usize, sqlx::Error> {
+    let current = current_version(pool).await?;
+    let mut applied = 0;
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        log::info!(
+            "Applying migration {:04}_{}...",
+            migration.version,
+            migration.name
+        );
+
+        let mut tx = pool.begin().await?;
+
+        sqlx::query(migration.sql).execute(&mut *tx).await?;
+
+        sqlx::query("INSERT INTO schema_migrations (version, name) VALUES (?, ?)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        applied += 1;
+    }
+
+    Ok(applied)
+}
+
+/// A migrated in-memory database for tests. It has one connection, so every
+/// query sees the same database.
+#[cfg(test)]
+pub async fn test_pool() -> SqlitePool {
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    run_migrations(&pool).await.unwrap();
+    pool
+}
@@ -3,6 +3,7 @@ use sqlx::FromRow;
 use uuid::Uuid;
 
 use super::users::UserSummary;
+use crate::reminders;
 
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
 pub struct Event {
@@ -22,6 +23,17 @@ pub struct Event {
     pub parent_event_id: Option<String>,
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
+    /// Dates left out of a recurring series, comma-separated `YYYY-MM-DD`
+    pub recurrence_exdates: Option<String>,
+    /// For an edited occurrence of a series: the date it replaces
+    pub original_date: Option<String>,
+    /// `UID` of the iCalendar event this was imported from
+    pub ical_uid: Option<String>,
+    /// Minutes before the start to remind attendees, comma-separated; `None`
+    /// leaves it to each attendee's settings
+    pub reminder_minutes: Option<String>,
+    /// IANA zone that `event_date`, `start_time` and `end_time` are in
+    pub timezone: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -50,6 +62,13 @@ pub struct EventAttendee {
     pub user_id: String,
     pub status: String,
     pub added_at: Option<String>,
+    pub response_comment: Option<String>,
+    /// When the invitee last answered; `None` while pending
+    pub responded_at: Option<String>,
+    /// Another time the invitee suggested, all three set together
+    pub proposed_date: Option<String>,
+    pub proposed_start_time: Option<String>,
+    pub proposed_end_time: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -64,14 +83,16 @@ pub struct AttendeeResponse {
     pub added_at: Option<String>,
 }
 
+/// Title, type and start time may come from `template_id` instead.
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateEventRequest {
-    pub title: String,
+    pub template_id: Option<String>,
+    pub title: Option<String>,
     pub description: Option<String>,
-    pub event_type: String,
+    pub event_type: Option<String>,
     pub event_date: String,
-    pub start_time: String,
+    pub start_time: Option<String>,
     pub end_time: Option<String>,
     pub location: Option<String>,
     pub meeting_url: Option<String>,
@@ -79,6 +100,12 @@ pub struct CreateEventRequest {
     pub attendee_ids: Option<Vec<String>>,
     pub is_recurring: Option<bool>,
     pub recurrence_rule: Option<String>,
+    /// Dates (`YYYY-MM-DD`) to leave out of the series
+    pub exdates: Option<Vec<String>>,
+    /// Minutes before the start to remind attendees, for everyone
+    pub reminder_minutes: Option<Vec<i64>>,
+    /// IANA zone of the date and times; defaults to the organizer's
+    pub time_zone: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -93,6 +120,177 @@ pub struct UpdateEventRequest {
     pub location: Option<String>,
     pub meeting_url: Option<String>,
     pub department: Option<String>,
+    /// Only for a whole series; an empty string stops the event recurring
+    pub recurrence_rule: Option<String>,
+    pub reminder_minutes: Option<Vec<i64>>,
+    pub time_zone: Option<String>,
+}
+
+impl UpdateEventRequest {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.description.is_none()
+            && self.event_type.is_none()
+            && self.event_date.is_none()
+            && self.start_time.is_none()
+            && self.end_time.is_none()
+            && self.location.is_none()
+            && self.meeting_url.is_none()
+            && self.department.is_none()
+            && self.recurrence_rule.is_none()
+            && self.reminder_minutes.is_none()
+            && self.time_zone.is_none()
+    }
+}
+
+/// Defaults for events of a kind that is created again and again.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct EventTemplate {
+    pub id: String,
+    pub name: String,
+    /// Title of the events; the name when `None`
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub event_type: String,
+    pub start_time: Option<String>,
+    pub duration_minutes: Option<i64>,
+    pub location: Option<String>,
+    pub meeting_url: Option<String>,
+    pub department: String,
+    pub recurrence_rule: Option<String>,
+    /// Comma-separated, like `Event::reminder_minutes`
+    pub reminder_minutes: Option<String>,
+    pub visibility: String,
+    pub created_by: String,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+/// Who can see and use a template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateVisibility {
+    /// Only its creator
+    Personal,
+    /// Everyone in the template's department
+    Department,
+}
+
+impl TemplateVisibility {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TemplateVisibility::Personal => "personal",
+            TemplateVisibility::Department => "department",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateEventTemplateRequest {
+    pub name: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub event_type: String,
+    pub start_time: Option<String>,
+    pub duration_minutes: Option<i64>,
+    pub location: Option<String>,
+    pub meeting_url: Option<String>,
+    /// Defaults to the creator's
+    pub department: Option<String>,
+    pub attendee_ids: Option<Vec<String>>,
+    pub recurrence_rule: Option<String>,
+    pub reminder_minutes: Option<Vec<i64>>,
+    /// Defaults to personal
+    pub visibility: Option<TemplateVisibility>,
+}
+
+/// An empty string clears an optional text field.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateEventTemplateRequest {
+    pub name: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub event_type: Option<String>,
+    pub start_time: Option<String>,
+    pub duration_minutes: Option<i64>,
+    pub location: Option<String>,
+    pub meeting_url: Option<String>,
+    pub department: Option<String>,
+    /// Replaces the default attendees
+    pub attendee_ids: Option<Vec<String>>,
+    pub recurrence_rule: Option<String>,
+    /// An empty list turns reminders off
+    pub reminder_minutes: Option<Vec<i64>>,
+    pub visibility: Option<TemplateVisibility>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetEventTemplatesQuery {
+    /// Only the caller's own templates
+    pub mine: Option<bool>,
+    pub search: Option<String>,
+}
+
+/// Which part of a recurring series an edit or delete applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditScope {
+    /// Only the occurrence on `occurrenceDate`
+    This,
+    /// The occurrence on `occurrenceDate` and every later one
+    Following,
+    /// The whole series
+    All,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditScopeQuery {
+    pub scope: Option<EditScope>,
+    pub occurrence_date: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConflictQuery {
+    /// Save the event even when attendees are already busy
+    pub force: Option<bool>,
+}
+
+/// An invitee's answer to an invitation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RsvpStatus {
+    Accepted,
+    Declined,
+}
+
+impl RsvpStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RsvpStatus::Accepted => "accepted",
+            RsvpStatus::Declined => "declined",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RsvpRequest {
+    pub status: RsvpStatus,
+    pub comment: Option<String>,
+    pub proposed_time: Option<ProposedTime>,
+}
+
+/// A time the invitee would prefer, sent to the organizer with the response.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProposedTime {
+    /// `YYYY-MM-DD`; defaults to the event's date
+    pub date: Option<String>,
+    pub start_time: String,
+    pub end_time: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -137,6 +335,58 @@ impl Event {
             parent_event_id: None,
             created_at: None,
             updated_at: None,
+            recurrence_exdates: None,
+            original_date: None,
+            ical_uid: None,
+            reminder_minutes: None,
+            timezone: crate::timezone::UTC.to_string(),
+        }
+    }
+
+    /// The first row of a recurring series, which holds its rule.
+    pub fn is_series(&self) -> bool {
+        self.is_recurring && self.recurrence_rule.is_some() && self.parent_event_id.is_none()
+    }
+
+    /// A row that replaces one occurrence of a series.
+    pub fn is_override(&self) -> bool {
+        self.parent_event_id.is_some() && self.original_date.is_some()
+    }
+
+    /// Copies every field set in `update`, except the recurrence rule.
+    pub fn apply_update(&mut self, update: &UpdateEventRequest) {
+        if let Some(ref title) = update.title {
+            self.title = title.clone();
+        }
+        if let Some(ref description) = update.description {
+            self.description = Some(description.clone());
+        }
+        if let Some(ref event_type) = update.event_type {
+            self.event_type = event_type.clone();
+        }
+        if let Some(ref event_date) = update.event_date {
+            self.event_date = event_date.clone();
+        }
+        if let Some(ref start_time) = update.start_time {
+            self.start_time = start_time.clone();
+        }
+        if let Some(ref end_time) = update.end_time {
+            self.end_time = Some(end_time.clone());
+        }
+        if let Some(ref location) = update.location {
+            self.location = Some(location.clone());
+        }
+        if let Some(ref meeting_url) = update.meeting_url {
+            self.meeting_url = Some(meeting_url.clone());
+        }
+        if let Some(ref department) = update.department {
+            self.department = Some(department.clone());
+        }
+        if let Some(ref minutes) = update.reminder_minutes {
+            self.reminder_minutes = Some(reminders::format_minutes(minutes));
+        }
+        if let Some(ref time_zone) = update.time_zone {
+            self.timezone = time_zone.clone();
         }
     }
 }
@@ -115,7 +115,7 @@ impl CheckInRecord {
             id: Uuid::new_v4().to_string(),
             user_id,
             location,
-            check_in_time: chrono::Utc::now().to_rfc3339(),
+            check_in_time: crate::timezone::format_sql_datetime(crate::timezone::now_utc()),
             check_out_time: None,
             notes,
             device_type,
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use std::collections::HashMap;
 use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
@@ -81,6 +82,12 @@ pub struct PreferenceUpdate {
     pub in_app_enabled: bool,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateReminderPreferencesRequest {
+    pub reminders: HashMap<String, Vec<i64>>,
+}
+
 impl Notification {
     pub fn new(
         user_id: String,
@@ -10,4 +10,33 @@ pub struct Session {
     pub created_at: Option<String>,
     pub ip_address: Option<String>,
     pub user_agent: Option<String>,
+    pub last_used_at: Option<String>,
+}
+
+/// A session as shown to its owner or an admin; never includes the token hash.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionResponse {
+    pub id: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: Option<String>,
+    pub last_seen_at: Option<String>,
+    pub expires_at: String,
+    /// Whether this is the session making the request
+    pub current: bool,
+}
+
+impl SessionResponse {
+    pub fn new(session: Session, current_session_id: &str) -> Self {
+        SessionResponse {
+            current: session.id == current_session_id,
+            id: session.id,
+            ip_address: session.ip_address,
+            user_agent: session.user_agent,
+            created_at: session.created_at,
+            last_seen_at: session.last_used_at,
+            expires_at: session.expires_at,
+        }
+    }
 }
@@ -57,7 +57,10 @@ pub struct TaskResponse {
 pub struct TaskHistory {
     pub id: String,
     pub task_id: String,
-    pub user_id: String,
+    /// `None` for changes made by the system
+    pub user_id: Option<String>,
+    /// `user`, or what made a system change, e.g. `escalation`
+    pub source: String,
     pub action: String,
     pub field_changed: Option<String>,
     pub old_value: Option<String>,
@@ -74,6 +77,7 @@ pub struct TaskHistoryResponse {
     pub old_value: Option<String>,
     pub new_value: Option<String>,
     pub changed_by: Option<UserSummary>,
+    pub source: String,
     pub created_at: Option<String>,
 }
 
@@ -89,6 +93,7 @@ pub struct CreateTaskRequest {
     pub assignee_ids: Option<Vec<String>>,
     pub deadline: String,
     pub is_completed: Option<bool>,
+    pub checklist_auto_complete: Option<bool>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -103,6 +108,7 @@ pub struct UpdateTaskRequest {
     pub assignee_ids: Option<Vec<String>>,
     pub deadline: Option<String>,
     pub is_completed: Option<bool>,
+    pub checklist_auto_complete: Option<bool>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -110,6 +116,134 @@ pub struct UpdateTaskStatusRequest {
     pub status: String,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddTaskDependencyRequest {
+    pub depends_on_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct ChecklistItem {
+    pub id: String,
+    pub task_id: String,
+    pub title: String,
+    pub position: i64,
+    pub is_completed: bool,
+    pub assignee_id: Option<String>,
+    pub completed_by: Option<String>,
+    pub completed_at: Option<String>,
+    pub created_by: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateChecklistItemRequest {
+    pub title: String,
+    pub assignee_id: Option<String>,
+    /// Zero-based; appended at the end when omitted
+    pub position: Option<i64>,
+}
+
+/// An empty `assigneeId` unassigns the item.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateChecklistItemRequest {
+    pub title: Option<String>,
+    pub assignee_id: Option<String>,
+    pub position: Option<i64>,
+    pub is_completed: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct RecurringTask {
+    pub id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub urgency: String,
+    pub department: String,
+    pub project_id: Option<String>,
+    /// Normalized RRULE; its dates are the instances' deadlines
+    pub recurrence_rule: String,
+    /// First deadline of the series, `YYYY-MM-DD`
+    pub start_date: String,
+    /// Days before its deadline that an instance is created
+    pub lead_days: i64,
+    /// Comma-separated, like `Event::recurrence_exdates`
+    pub skipped_dates: Option<String>,
+    pub last_generated_date: Option<String>,
+    pub is_active: bool,
+    pub created_by: String,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateRecurringTaskRequest {
+    pub title: String,
+    pub description: Option<String>,
+    pub urgency: String,
+    /// Defaults to the creator's
+    pub department: Option<String>,
+    pub project_id: Option<String>,
+    pub assignee_ids: Option<Vec<String>>,
+    pub recurrence_rule: String,
+    pub start_date: String,
+    pub lead_days: Option<i64>,
+    pub is_active: Option<bool>,
+}
+
+/// An empty string clears `description` and `projectId`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateRecurringTaskRequest {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub urgency: Option<String>,
+    pub department: Option<String>,
+    pub project_id: Option<String>,
+    pub assignee_ids: Option<Vec<String>>,
+    pub recurrence_rule: Option<String>,
+    pub start_date: Option<String>,
+    pub lead_days: Option<i64>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SkipOccurrenceRequest {
+    pub date: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct EscalationRule {
+    pub id: String,
+    pub department: String,
+    /// Negative once the deadline has passed
+    pub hours_before_deadline: i64,
+    /// Urgency the department's tasks are raised to
+    pub urgency: String,
+    pub created_by: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateEscalationRuleRequest {
+    pub department: String,
+    pub hours_before_deadline: i64,
+    pub urgency: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateEscalationRuleRequest {
+    pub hours_before_deadline: Option<i64>,
+    pub urgency: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GetTasksQuery {
@@ -119,6 +253,7 @@ pub struct GetTasksQuery {
     pub project_id: Option<String>,
     pub assignee_id: Option<String>,
     pub is_completed: Option<bool>,
+    pub recurring_task_id: Option<String>,
     pub limit: Option<i32>,
     pub offset: Option<i32>,
 }
@@ -16,6 +16,7 @@ pub struct User {
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
     pub last_login: Option<String>,
+    pub timezone: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -30,6 +31,7 @@ pub struct UserResponse {
     pub is_active: bool,
     pub last_login: Option<String>,
     pub created_at: Option<String>,
+    pub time_zone: String,
 }
 
 impl From<User> for UserResponse {
@@ -44,6 +46,7 @@ impl From<User> for UserResponse {
             is_active: user.is_active,
             last_login: user.last_login,
             created_at: user.created_at,
+            time_zone: user.timezone,
         }
     }
 }
@@ -72,8 +75,11 @@ pub struct LoginResponse {
 #[serde(rename_all = "camelCase")]
 pub struct LoginData {
     pub user: UserResponse,
+    /// Access token
     pub token: String,
     pub expires_at: String,
+    pub refresh_token: String,
+    pub refresh_expires_at: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -95,6 +101,7 @@ pub struct UpdateUserRequest {
     pub department: Option<String>,
     pub role: Option<String>,
     pub is_active: Option<bool>,
+    pub time_zone: Option<String>,
 }
 
 impl User {
@@ -118,6 +125,7 @@ impl User {
             created_at: None,
             updated_at: None,
             last_login: None,
+            timezone: crate::timezone::UTC.to_string(),
         }
     }
 }
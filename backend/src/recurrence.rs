@@ -0,0 +1,630 @@
+//! Recurrence rules (RFC 5545 `RRULE`) for calendar events.
+//!
+//! Supported rule parts are `FREQ` (`DAILY`, `WEEKLY`, `MONTHLY`, `YEARLY`),
+//! `INTERVAL`, `BYDAY`, `COUNT`, `UNTIL` and `WKST`. `BYDAY` takes ordinals
+//! such as `1MO` or `-1FR` with `MONTHLY`, and limits `DAILY` to the given
+//! weekdays. Any other rule part is rejected rather than ignored, so a series
+//! never expands differently here than in a calendar client.
+//!
+//! Recurrence is by date: every occurrence keeps the series' start and end
+//! time. As in RFC 5545, the series' first date always counts as its first
+//! occurrence, and `COUNT` is applied before excluded dates (`EXDATE`) are
+//! removed. Excluded dates are stored next to the rule, in
+//! `events.recurrence_exdates`.
+
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use std::fmt;
+
+use crate::error::AppError;
+
+/// Upper bound on generated periods, so a rule without an end cannot loop
+/// forever when the requested window is far away or never matches.
+const MAX_PERIODS: u32 = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// A `BYDAY` entry: a weekday, optionally the n-th of the month (`-1` is the last).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByDay {
+    pub ordinal: Option<i8>,
+    pub weekday: Weekday,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RRule {
+    pub freq: Frequency,
+    pub interval: u32,
+    pub by_day: Vec<ByDay>,
+    pub count: Option<u32>,
+    pub until: Option<NaiveDate>,
+    pub week_start: Weekday,
+}
+
+impl RRule {
+    /// Parses an `RRULE` value, with or without the `RRULE:` prefix.
+    pub fn parse(rule: &str) -> Result<Self, AppError> {
+        let rule = rule.trim();
+        let rule = rule
+            .strip_prefix("RRULE:")
+            .or_else(|| rule.strip_prefix("rrule:"))
+            .unwrap_or(rule);
+
+        let mut freq = None;
+        let mut interval = 1;
+        let mut by_day = Vec::new();
+        let mut count = None;
+        let mut until = None;
+        let mut week_start = Weekday::Mon;
+
+        for part in rule.split(';').filter(|p| !p.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("expected KEY=VALUE, got `{}`", part)))?;
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    freq = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(invalid(format!("unsupported FREQ `{}`", value))),
+                    })
+                }
+                "INTERVAL" => {
+                    interval = value
+                        .parse()
+                        .ok()
+                        .filter(|i| *i >= 1)
+                        .ok_or_else(|| invalid(format!("invalid INTERVAL `{}`", value)))?
+                }
+                "COUNT" => {
+                    count = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|c| *c >= 1)
+                            .ok_or_else(|| invalid(format!("invalid COUNT `{}`", value)))?,
+                    )
+                }
+                "UNTIL" => until = Some(parse_ical_date(value)?),
+                "BYDAY" => {
+                    by_day = value
+                        .split(',')
+                        .map(parse_by_day)
+                        .collect::<Result<Vec<_>, _>>()?
+                }
+                "WKST" => {
+                    week_start = parse_weekday(value)
+                        .ok_or_else(|| invalid(format!("invalid WKST `{}`", value)))?
+                }
+                other => return Err(invalid(format!("unsupported rule part `{}`", other))),
+            }
+        }
+
+        let freq = freq.ok_or_else(|| invalid("FREQ is required".to_string()))?;
+        if count.is_some() && until.is_some() {
+            return Err(invalid("COUNT and UNTIL cannot be combined".to_string()));
+        }
+        if by_day.iter().any(|d| d.ordinal.is_some()) && freq != Frequency::Monthly {
+            return Err(invalid(
+                "BYDAY ordinals such as 1MO are only supported with FREQ=MONTHLY".to_string(),
+            ));
+        }
+        if !by_day.is_empty() && freq == Frequency::Yearly {
+            return Err(invalid(
+                "BYDAY is not supported with FREQ=YEARLY".to_string(),
+            ));
+        }
+
+        Ok(RRule {
+            freq,
+            interval,
+            by_day,
+            count,
+            until,
+            week_start,
+        })
+    }
+
+    /// Occurrence dates of a series starting on `dtstart` that fall within
+    /// `from..=to`, in order, leaving out `exdates`.
+    pub fn occurrences(
+        &self,
+        dtstart: NaiveDate,
+        exdates: &[NaiveDate],
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Vec<NaiveDate> {
+        let mut dates = Vec::new();
+        self.for_each(dtstart, |date| {
+            if date > to {
+                return false;
+            }
+            if date >= from && !exdates.contains(&date) {
+                dates.push(date);
+            }
+            true
+        });
+        dates
+    }
+
+    /// Whether `date` is in the series, ignoring excluded dates.
+    pub fn includes(&self, dtstart: NaiveDate, date: NaiveDate) -> bool {
+        let mut found = false;
+        self.for_each(dtstart, |d| {
+            found = d == date;
+            d < date
+        });
+        found
+    }
+
+    /// Number of dates in the series before `date`, ignoring excluded dates.
+    pub fn count_before(&self, dtstart: NaiveDate, date: NaiveDate) -> u32 {
+        let mut count = 0;
+        self.for_each(dtstart, |d| {
+            if d < date {
+                count += 1;
+            }
+            d < date
+        });
+        count
+    }
+
+    /// Calls `f` with every date of the series in order until it returns `false`.
+    fn for_each(&self, dtstart: NaiveDate, mut f: impl FnMut(NaiveDate) -> bool) {
+        let mut emitted = 0u32;
+        let limit_reached = |emitted: u32| self.count.is_some_and(|c| emitted >= c);
+
+        // DTSTART is always the first occurrence
+        if !f(dtstart) {
+            return;
+        }
+        emitted += 1;
+
+        for period in 1..MAX_PERIODS {
+            if limit_reached(emitted) {
+                return;
+            }
+            let Some(candidates) = self.period_dates(dtstart, period - 1) else {
+                return;
+            };
+            for date in candidates {
+                if date <= dtstart {
+                    continue;
+                }
+                if self.until.is_some_and(|until| date > until) || !f(date) {
+                    return;
+                }
+                emitted += 1;
+                if limit_reached(emitted) {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Candidate dates of the `index`-th period, in order. `None` once the
+    /// dates leave chrono's range.
+    fn period_dates(&self, dtstart: NaiveDate, index: u32) -> Option<Vec<NaiveDate>> {
+        let step = index.checked_mul(self.interval)?;
+        match self.freq {
+            Frequency::Daily => {
+                let date = dtstart.checked_add_days(Days::new(step.into()))?;
+                let matches = self.by_day.is_empty()
+                    || self.by_day.iter().any(|d| d.weekday == date.weekday());
+                Some(if matches { vec![date] } else { Vec::new() })
+            }
+            Frequency::Weekly => {
+                let offset = days_from(self.week_start, dtstart.weekday());
+                let week = dtstart
+                    .checked_sub_days(Days::new(offset.into()))?
+                    .checked_add_days(Days::new(u64::from(step) * 7))?;
+                let mut offsets: Vec<u32> = if self.by_day.is_empty() {
+                    vec![offset]
+                } else {
+                    self.by_day
+                        .iter()
+                        .map(|d| days_from(self.week_start, d.weekday))
+                        .collect()
+                };
+                offsets.sort_unstable();
+                offsets.dedup();
+                offsets
+                    .into_iter()
+                    .map(|o| week.checked_add_days(Days::new(o.into())))
+                    .collect()
+            }
+            Frequency::Monthly => {
+                let month = dtstart.with_day(1)?.checked_add_months(Months::new(step))?;
+                if self.by_day.is_empty() {
+                    return Some(month.with_day(dtstart.day()).into_iter().collect());
+                }
+                let mut dates: Vec<NaiveDate> = self
+                    .by_day
+                    .iter()
+                    .flat_map(|d| weekdays_in_month(month, *d))
+                    .collect();
+                dates.sort_unstable();
+                dates.dedup();
+                Some(dates)
+            }
+            Frequency::Yearly => {
+                let year = dtstart.year().checked_add(i32::try_from(step).ok()?)?;
+                NaiveDate::from_ymd_opt(year, 1, 1)?;
+                // 29 February only recurs in leap years
+                Some(
+                    NaiveDate::from_ymd_opt(year, dtstart.month(), dtstart.day())
+                        .into_iter()
+                        .collect(),
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for RRule {
+    /// Formats the rule as an `RRULE` value without the prefix.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let freq = match self.freq {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        };
+        write!(f, "FREQ={}", freq)?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<String> = self
+                .by_day
+                .iter()
+                .map(|d| match d.ordinal {
+                    Some(n) => format!("{}{}", n, weekday_code(d.weekday)),
+                    None => weekday_code(d.weekday).to_string(),
+                })
+                .collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", until.format("%Y%m%d"))?;
+        }
+        if self.week_start != Weekday::Mon {
+            write!(f, ";WKST={}", weekday_code(self.week_start))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses an event date, `YYYY-MM-DD`. A trailing time is ignored.
+pub fn parse_date(value: &str) -> Result<NaiveDate, AppError> {
+    value
+        .get(..10)
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .ok_or_else(|| {
+            AppError::Validation(format!("Invalid date `{}`, expected YYYY-MM-DD", value))
+        })
+}
+
+/// Reads `events.recurrence_exdates`: comma-separated `YYYY-MM-DD` dates.
+pub fn parse_exdates(value: Option<&str>) -> Vec<NaiveDate> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|d| NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d").ok())
+        .collect()
+}
+
+/// Formats dates for `events.recurrence_exdates`, sorted and without duplicates.
+pub fn format_exdates(dates: &[NaiveDate]) -> Option<String> {
+    let mut dates = dates.to_vec();
+    dates.sort_unstable();
+    dates.dedup();
+    if dates.is_empty() {
+        return None;
+    }
+    Some(
+        dates
+            .iter()
+            .map(|d| d.format("%Y-%m-%d").to_string())
+            .collect::<Vec<_>>()
+            .join(","),
+    )
+}
+
+/// Parses an iCalendar DATE or DATE-TIME (`20250131`, `20250131T090000Z`),
+/// keeping only the date.
+pub fn parse_ical_date(value: &str) -> Result<NaiveDate, AppError> {
+    value
+        .get(..8)
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y%m%d").ok())
+        .ok_or_else(|| invalid(format!("invalid date `{}`", value)))
+}
+
+pub fn weekday_code(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+fn parse_weekday(code: &str) -> Option<Weekday> {
+    match code.to_ascii_uppercase().as_str() {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn parse_by_day(value: &str) -> Result<ByDay, AppError> {
+    let value = value.trim();
+    // The weekday code is the last two characters; split on a character
+    // boundary so non-ASCII input is rejected instead of panicking
+    let split = value
+        .char_indices()
+        .rev()
+        .nth(1)
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    let (ordinal, code) = value.split_at(split);
+    let weekday =
+        parse_weekday(code).ok_or_else(|| invalid(format!("invalid BYDAY `{}`", value)))?;
+    let ordinal = match ordinal {
+        "" => None,
+        n => Some(
+            n.trim_start_matches('+')
+                .parse::<i8>()
+                .ok()
+                .filter(|n| *n != 0 && (-5..=5).contains(n))
+                .ok_or_else(|| invalid(format!("invalid BYDAY `{}`", value)))?,
+        ),
+    };
+    Ok(ByDay { ordinal, weekday })
+}
+
+/// Days from `week_start` forward to `weekday` (0..=6).
+fn days_from(week_start: Weekday, weekday: Weekday) -> u32 {
+    (7 + weekday.num_days_from_monday() - week_start.num_days_from_monday()) % 7
+}
+
+/// Dates in the month of `month` (its first day) matching `by_day`.
+fn weekdays_in_month(month: NaiveDate, by_day: ByDay) -> Vec<NaiveDate> {
+    let all: Vec<NaiveDate> = month
+        .iter_days()
+        .take_while(|d| d.month() == month.month())
+        .filter(|d| d.weekday() == by_day.weekday)
+        .collect();
+    match by_day.ordinal {
+        None => all,
+        Some(n) if n > 0 => all.get(n as usize - 1).copied().into_iter().collect(),
+        Some(n) => all
+            .len()
+            .checked_sub(n.unsigned_abs() as usize)
+            .and_then(|i| all.get(i))
+            .copied()
+            .into_iter()
+            .collect(),
+    }
+}
+
+fn invalid(reason: String) -> AppError {
+    AppError::Validation(format!("Invalid recurrence rule: {}", reason))
+        .with_code("INVALID_RECURRENCE_RULE")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn dates(list: &[&str]) -> Vec<NaiveDate> {
+        list.iter().map(|d| date(d)).collect()
+    }
+
+    #[test]
+    fn daily_with_interval() {
+        let rule = RRule::parse("RRULE:FREQ=DAILY;INTERVAL=2").unwrap();
+        assert_eq!(rule.freq, Frequency::Daily);
+        assert_eq!(rule.interval, 2);
+        assert_eq!(
+            rule.occurrences(
+                date("2025-01-01"),
+                &[],
+                date("2025-01-01"),
+                date("2025-01-07")
+            ),
+            dates(&["2025-01-01", "2025-01-03", "2025-01-05", "2025-01-07"])
+        );
+    }
+
+    #[test]
+    fn weekly_by_day() {
+        // 2025-01-06 is a Monday
+        let rule = RRule::parse("FREQ=WEEKLY;BYDAY=MO,WE").unwrap();
+        assert_eq!(
+            rule.occurrences(
+                date("2025-01-06"),
+                &[],
+                date("2025-01-06"),
+                date("2025-01-15")
+            ),
+            dates(&["2025-01-06", "2025-01-08", "2025-01-13", "2025-01-15"])
+        );
+    }
+
+    #[test]
+    fn monthly_by_day_ordinals() {
+        let second_tuesday = RRule::parse("FREQ=MONTHLY;BYDAY=2TU").unwrap();
+        assert_eq!(
+            second_tuesday.by_day,
+            vec![ByDay {
+                ordinal: Some(2),
+                weekday: Weekday::Tue
+            }]
+        );
+        assert_eq!(
+            second_tuesday.occurrences(
+                date("2025-01-14"),
+                &[],
+                date("2025-01-01"),
+                date("2025-03-31")
+            ),
+            dates(&["2025-01-14", "2025-02-11", "2025-03-11"])
+        );
+
+        let last_friday = RRule::parse("FREQ=MONTHLY;BYDAY=-1FR").unwrap();
+        assert_eq!(
+            last_friday.occurrences(
+                date("2025-01-31"),
+                &[],
+                date("2025-01-01"),
+                date("2025-03-31")
+            ),
+            dates(&["2025-01-31", "2025-02-28", "2025-03-28"])
+        );
+    }
+
+    #[test]
+    fn count_is_applied_before_exdates() {
+        let rule = RRule::parse("FREQ=DAILY;COUNT=3").unwrap();
+        let start = date("2025-01-01");
+        assert_eq!(
+            rule.occurrences(start, &[], start, date("2025-12-31")),
+            dates(&["2025-01-01", "2025-01-02", "2025-01-03"])
+        );
+        assert_eq!(
+            rule.occurrences(start, &dates(&["2025-01-02"]), start, date("2025-12-31")),
+            dates(&["2025-01-01", "2025-01-03"])
+        );
+    }
+
+    #[test]
+    fn until_is_inclusive() {
+        let rule = RRule::parse("FREQ=WEEKLY;UNTIL=20250115T235959Z").unwrap();
+        assert_eq!(rule.until, Some(date("2025-01-15")));
+        assert_eq!(
+            rule.occurrences(
+                date("2025-01-01"),
+                &[],
+                date("2025-01-01"),
+                date("2025-12-31")
+            ),
+            dates(&["2025-01-01", "2025-01-08", "2025-01-15"])
+        );
+    }
+
+    #[test]
+    fn includes_and_count_before() {
+        let rule = RRule::parse("FREQ=WEEKLY;BYDAY=MO,WE").unwrap();
+        let start = date("2025-01-06");
+        assert!(rule.includes(start, date("2025-01-15")));
+        assert!(!rule.includes(start, date("2025-01-14")));
+        assert!(!rule.includes(start, date("2025-01-01")));
+        assert_eq!(rule.count_before(start, start), 0);
+        assert_eq!(rule.count_before(start, date("2025-01-13")), 2);
+        assert_eq!(rule.count_before(start, date("2025-01-14")), 3);
+
+        let limited = RRule::parse("FREQ=DAILY;COUNT=2").unwrap();
+        assert!(!limited.includes(date("2025-01-01"), date("2025-01-03")));
+    }
+
+    #[test]
+    fn count_split_keeps_total_occurrences() {
+        // As in `events::edit_following`: the original ends before the split
+        // date, the new series takes the remaining count from it
+        let rule = RRule::parse("FREQ=DAILY;COUNT=5").unwrap();
+        let start = date("2025-01-01");
+        let split = date("2025-01-03");
+        let following = RRule {
+            count: rule.count.map(|c| c - rule.count_before(start, split)),
+            ..rule.clone()
+        };
+        let earlier = RRule {
+            count: None,
+            until: split.pred_opt(),
+            ..rule.clone()
+        };
+
+        let far = date("2025-12-31");
+        let mut all = earlier.occurrences(start, &[], start, far);
+        all.extend(following.occurrences(split, &[], split, far));
+        assert_eq!(all, rule.occurrences(start, &[], start, far));
+        assert_eq!(following.to_string(), "FREQ=DAILY;COUNT=3");
+    }
+
+    #[test]
+    fn formats_back_to_a_parsable_rule() {
+        let rule = RRule::parse("freq=monthly;interval=2;byday=-1fr;count=4;wkst=su").unwrap();
+        assert_eq!(
+            rule.to_string(),
+            "FREQ=MONTHLY;INTERVAL=2;BYDAY=-1FR;COUNT=4;WKST=SU"
+        );
+        assert_eq!(RRule::parse(&rule.to_string()).unwrap(), rule);
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        for rule in [
+            "",
+            "INTERVAL=2",
+            "FREQ=HOURLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;COUNT=0",
+            "FREQ=DAILY;COUNT=2;UNTIL=20250101",
+            "FREQ=WEEKLY;BYDAY=1MO",
+            "FREQ=MONTHLY;BYDAY=6MO",
+            "FREQ=MONTHLY;BYDAY=0MO",
+            "FREQ=YEARLY;BYDAY=MO",
+            "FREQ=DAILY;BYMONTH=1",
+            "FREQ=WEEKLY;BYDAY=XX",
+        ] {
+            let err = RRule::parse(rule).unwrap_err();
+            assert_eq!(err.code(), "INVALID_RECURRENCE_RULE", "{}", rule);
+        }
+    }
+
+    #[test]
+    fn rejects_non_ascii_by_day_without_panicking() {
+        for rule in [
+            "FREQ=WEEKLY;BYDAY=1€",
+            "FREQ=MONTHLY;BYDAY=€",
+            "FREQ=MONTHLY;BYDAY=1MÖ",
+            "FREQ=MONTHLY;BYDAY=€MO",
+        ] {
+            assert!(RRule::parse(rule).is_err(), "{}", rule);
+        }
+    }
+
+    #[test]
+    fn exdates_are_sorted_and_deduplicated() {
+        let exdates = parse_exdates(Some("2025-01-03, 2025-01-01,bogus"));
+        assert_eq!(exdates, dates(&["2025-01-03", "2025-01-01"]));
+        assert_eq!(
+            format_exdates(&dates(&["2025-01-03", "2025-01-01", "2025-01-03"])).as_deref(),
+            Some("2025-01-01,2025-01-03")
+        );
+        assert_eq!(format_exdates(&[]), None);
+    }
+}
@@ -0,0 +1,470 @@
+//! Event reminders.
+//!
+//! A background task started in `main` calls [`send_due_reminders`] every
+//! `reminder_interval_secs`. Each organizer and attendee who has not declined
+//! gets an in-app notification a set number of minutes before an event
+//! starts: `meeting` for meetings, `shipping` for deliveries and `urgent` for
+//! deadlines.
+//!
+//! The minutes come from the event (`events.reminder_minutes`) when set,
+//! otherwise from the user's setting for the event type
+//! (`reminder_preferences`), otherwise from [`default_minutes`].
+//!
+//! Sent reminders are recorded in `sent_reminders`, in the same transaction
+//! as the notification, so each is sent exactly once even across restarts.
+//! A reminder whose time passed while the server was down is still sent if
+//! the event has not started; when several are due at once, the user gets a
+//! single notification.
+//!
+//! The clock and `sent_reminders.starts_at` are in UTC. Each event's start is
+//! converted from its own time zone, and messages give the time in the
+//! recipient's zone.
+
+use chrono::{Duration, NaiveDateTime};
+use sqlx::{Acquire, SqliteConnection, SqlitePool};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::clock::Clock;
+use crate::error::AppError;
+use crate::filters::Filters;
+use crate::models::events::Event;
+use crate::routes::events::events_in_range;
+use crate::routes::notifications::create_notification;
+use crate::routes::scheduling::event_interval;
+use crate::timezone::{self, TimeZone};
+
+pub const EVENT_TYPES: [&str; 3] = ["meeting", "deadline", "delivery"];
+
+/// Reminders can be set at most a week ahead, which is also how far ahead
+/// the scheduler looks for events
+pub const MAX_REMINDER_MINUTES: i64 = 7 * 24 * 60;
+
+const MAX_REMINDERS: usize = 5;
+
+/// Sent reminders are kept this long after the event started
+const SENT_RETENTION_DAYS: i64 = 7;
+
+/// Minutes before the start used when neither the event nor the user sets any.
+pub fn default_minutes(event_type: &str) -> Vec<i64> {
+    match event_type {
+        "meeting" => vec![15],
+        "delivery" | "deadline" => vec![24 * 60],
+        _ => Vec::new(),
+    }
+}
+
+/// Checks a list of reminder minutes sent by a client.
+pub fn validate_minutes(minutes: &[i64]) -> Result<(), AppError> {
+    if minutes.len() > MAX_REMINDERS {
+        return Err(AppError::Validation(format!(
+            "At most {} reminders can be set",
+            MAX_REMINDERS
+        ))
+        .with_code("INVALID_REMINDER"));
+    }
+    if let Some(m) = minutes
+        .iter()
+        .find(|m| !(0..=MAX_REMINDER_MINUTES).contains(*m))
+    {
+        return Err(AppError::Validation(format!(
+            "Reminder of {} minutes is out of range (0 to {})",
+            m, MAX_REMINDER_MINUTES
+        ))
+        .with_code("INVALID_REMINDER"));
+    }
+    Ok(())
+}
+
+/// Stored form of a list of minutes: distinct, largest first, comma-separated.
+pub fn format_minutes(minutes: &[i64]) -> String {
+    let mut minutes = minutes.to_vec();
+    minutes.sort_unstable_by(|a, b| b.cmp(a));
+    minutes.dedup();
+    minutes
+        .iter()
+        .map(i64::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+pub fn parse_minutes(value: &str) -> Vec<i64> {
+    value
+        .split(',')
+        .filter_map(|m| m.trim().parse().ok())
+        .collect()
+}
+
+fn notification_type(event_type: &str) -> &'static str {
+    match event_type {
+        "meeting" => "meeting",
+        "delivery" => "shipping",
+        "deadline" => "urgent",
+        _ => "info",
+    }
+}
+
+/// The reminders among `minutes` whose time has come for an event starting
+/// at `start`; none once it has started.
+fn due_minutes(minutes: &[i64], start: NaiveDateTime, now: NaiveDateTime) -> Vec<i64> {
+    if start <= now {
+        return Vec::new();
+    }
+    minutes
+        .iter()
+        .copied()
+        .filter(|m| start - Duration::minutes(*m) <= now)
+        .collect()
+}
+
+/// Sends every reminder due at `clock.now()` and returns how many
+/// notifications were created.
+pub async fn send_due_reminders(pool: &SqlitePool, clock: &dyn Clock) -> Result<usize, AppError> {
+    let now = clock.now();
+    let horizon = now + Duration::minutes(MAX_REMINDER_MINUTES);
+    let mut conn = pool.acquire().await?;
+
+    sqlx::query("DELETE FROM sent_reminders WHERE starts_at < ?")
+        .bind(format_starts_at(now - Duration::days(SENT_RETENTION_DAYS)))
+        .execute(&mut *conn)
+        .await?;
+
+    let preferences: HashMap<(String, String), Vec<i64>> =
+        sqlx::query_as::<_, (String, String, String)>(
+            "SELECT user_id, event_type, reminder_minutes FROM reminder_preferences",
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|(user_id, event_type, minutes)| ((user_id, event_type), parse_minutes(&minutes)))
+        .collect();
+
+    // Event dates are in each event's zone, at most a day either side of UTC
+    let occurrences = events_in_range(
+        &mut conn,
+        &Filters::new(),
+        now.date() - Duration::days(1),
+        horizon.date() + Duration::days(1),
+    )
+    .await?;
+    let mut recipients: HashMap<String, Vec<String>> = HashMap::new();
+    let mut zones: HashMap<String, TimeZone> = HashMap::new();
+    let mut sent = 0;
+
+    for occurrence in occurrences {
+        let event = &occurrence.event;
+        let Ok(date) = crate::recurrence::parse_date(&occurrence.date) else {
+            continue;
+        };
+        let Some((start, _)) = event_interval(event, date) else {
+            continue;
+        };
+        if start <= now || start > horizon {
+            continue;
+        }
+
+        if !recipients.contains_key(&event.id) {
+            let users = event_recipients(&mut conn, event).await?;
+            recipients.insert(event.id.clone(), users);
+        }
+        for user_id in &recipients[&event.id] {
+            let minutes = match event.reminder_minutes.as_deref() {
+                Some(minutes) => parse_minutes(minutes),
+                None => preferences
+                    .get(&(user_id.clone(), event.event_type.clone()))
+                    .cloned()
+                    .unwrap_or_else(|| default_minutes(&event.event_type)),
+            };
+            let due = due_minutes(&minutes, start, now);
+            if due.is_empty() {
+                continue;
+            }
+            if !zones.contains_key(user_id) {
+                let zone = timezone::user_time_zone(&mut *conn, user_id).await?;
+                zones.insert(user_id.clone(), zone);
+            }
+            let zone = &zones[user_id];
+            if send_reminder(&mut conn, event, user_id, zone, start, now, &due).await? {
+                sent += 1;
+            }
+        }
+    }
+    Ok(sent)
+}
+
+/// The organizer and every attendee who has not declined.
+async fn event_recipients(
+    conn: &mut SqliteConnection,
+    event: &Event,
+) -> Result<Vec<String>, AppError> {
+    let mut users: Vec<String> = sqlx::query_scalar(
+        "SELECT user_id FROM event_attendees WHERE event_id = ? AND status != 'declined' AND user_id != ?",
+    )
+    .bind(&event.id)
+    .bind(&event.created_by)
+    .fetch_all(&mut *conn)
+    .await?;
+    users.push(event.created_by.clone());
+    Ok(users)
+}
+
+/// Records every reminder in `due` as sent and, unless all of them already
+/// were, notifies the user once, giving the time in `zone`. Returns whether a
+/// notification was created.
+async fn send_reminder(
+    conn: &mut SqliteConnection,
+    event: &Event,
+    user_id: &str,
+    zone: &TimeZone,
+    start: NaiveDateTime,
+    now: NaiveDateTime,
+    due: &[i64],
+) -> Result<bool, AppError> {
+    let starts_at = format_starts_at(start);
+    let mut tx = conn.begin().await?;
+
+    let mut claimed = 0;
+    for minutes in due {
+        claimed += sqlx::query(
+            "INSERT OR IGNORE INTO sent_reminders (id, event_id, user_id, starts_at, minutes_before) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(&event.id)
+        .bind(user_id)
+        .bind(&starts_at)
+        .bind(minutes)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    }
+    if claimed == 0 {
+        return Ok(false);
+    }
+
+    let verb = match event.event_type.as_str() {
+        "deadline" => "is due",
+        "delivery" => "is expected",
+        _ => "starts",
+    };
+    create_notification(
+        &mut *tx,
+        user_id,
+        notification_type(&event.event_type),
+        &format!("Reminder: {}", event.title),
+        &format!(
+            "{} {} {} ({} at {})",
+            event.title,
+            verb,
+            format_lead_time(start - now),
+            zone.to_local(start).format("%Y-%m-%d"),
+            zone.to_local(start).format("%H:%M")
+        ),
+        Some("event"),
+        Some(&event.id),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+fn format_starts_at(start: NaiveDateTime) -> String {
+    start.format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// "in 15 minutes", "in 2 hours", "in 1 day", rounded down.
+fn format_lead_time(lead: Duration) -> String {
+    let plural = |n: i64, unit: &str| format!("in {} {}{}", n, unit, if n == 1 { "" } else { "s" });
+    match lead.num_minutes() {
+        m if m >= 24 * 60 => plural(m / (24 * 60), "day"),
+        m if m >= 60 => plural(m / 60, "hour"),
+        m if m >= 1 => plural(m, "minute"),
+        _ => "now".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    async fn test_pool() -> SqlitePool {
+        let pool = crate::migrations::test_pool().await;
+        for (id, email) in [
+            ("organizer", "org@company.com"),
+            ("guest", "guest@company.com"),
+        ] {
+            sqlx::query(
+                "INSERT INTO users (id, email, password_hash, first_name, last_name, department, role) VALUES (?, ?, 'x', 'Test', 'User', 'IT', 'Member')",
+            )
+            .bind(id)
+            .bind(email)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        pool
+    }
+
+    async fn add_event(pool: &SqlitePool, event_type: &str, date: &str, start: &str) -> Event {
+        let event = Event::new(
+            "Design review".to_string(),
+            None,
+            event_type.to_string(),
+            date.to_string(),
+            start.to_string(),
+            None,
+            None,
+            None,
+            "organizer".to_string(),
+            Some("IT".to_string()),
+            false,
+            None,
+        );
+        let mut conn = pool.acquire().await.unwrap();
+        crate::routes::events::insert_event(&mut conn, &event)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO event_attendees (id, event_id, user_id) VALUES (?, ?, 'guest')")
+            .bind(Uuid::new_v4().to_string())
+            .bind(&event.id)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        event
+    }
+
+    async fn notifications(pool: &SqlitePool) -> Vec<(String, String, String)> {
+        sqlx::query_as(
+            "SELECT user_id, type, message FROM notifications ORDER BY user_id, created_at",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    #[test]
+    fn minutes_are_normalised_and_validated() {
+        assert_eq!(format_minutes(&[15, 1440, 15, 60]), "1440,60,15");
+        assert_eq!(parse_minutes("1440,60,15"), [1440, 60, 15]);
+        assert_eq!(parse_minutes(""), Vec::<i64>::new());
+        assert!(validate_minutes(&[0, MAX_REMINDER_MINUTES]).is_ok());
+        assert_eq!(
+            validate_minutes(&[-5]).unwrap_err().code(),
+            "INVALID_REMINDER"
+        );
+        assert!(validate_minutes(&[1, 2, 3, 4, 5, 6]).is_err());
+    }
+
+    #[test]
+    fn due_reminders_stop_at_the_start() {
+        let start = at("2026-03-02 10:00");
+        assert!(due_minutes(&[15], start, at("2026-03-02 09:44")).is_empty());
+        assert_eq!(due_minutes(&[15], start, at("2026-03-02 09:45")), [15]);
+        assert_eq!(
+            due_minutes(&[1440, 15], start, at("2026-03-02 09:50")),
+            [1440, 15]
+        );
+        assert!(due_minutes(&[15], start, at("2026-03-02 10:00")).is_empty());
+        assert_eq!(format_lead_time(Duration::minutes(15)), "in 15 minutes");
+        assert_eq!(format_lead_time(Duration::minutes(61)), "in 1 hour");
+        assert_eq!(format_lead_time(Duration::minutes(2 * 1440)), "in 2 days");
+    }
+
+    #[tokio::test]
+    async fn sends_each_reminder_once() {
+        let pool = test_pool().await;
+        add_event(&pool, "meeting", "2026-03-02", "10:00").await;
+        let clock = FixedClock::new(at("2026-03-02 09:40"));
+
+        assert_eq!(send_due_reminders(&pool, &clock).await.unwrap(), 0);
+
+        clock.set(at("2026-03-02 09:45"));
+        assert_eq!(send_due_reminders(&pool, &clock).await.unwrap(), 2);
+        let sent = notifications(&pool).await;
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0].0, "guest");
+        assert_eq!(sent[0].1, "meeting");
+        assert!(sent[0].2.contains("starts in 15 minutes"));
+
+        // Later ticks, including after a restart, find nothing new
+        clock.set(at("2026-03-02 09:50"));
+        assert_eq!(send_due_reminders(&pool, &clock).await.unwrap(), 0);
+        assert_eq!(notifications(&pool).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn uses_event_then_user_then_default_minutes() {
+        let pool = test_pool().await;
+        let delivery = add_event(&pool, "delivery", "2026-03-03", "09:00").await;
+        sqlx::query("INSERT INTO reminder_preferences (user_id, event_type, reminder_minutes) VALUES ('guest', 'delivery', '')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let clock = FixedClock::new(at("2026-03-02 09:00"));
+
+        // Default of a day for the organizer; the guest turned them off
+        assert_eq!(send_due_reminders(&pool, &clock).await.unwrap(), 1);
+        let sent = notifications(&pool).await;
+        assert_eq!(sent[0].0, "organizer");
+        assert_eq!(sent[0].1, "shipping");
+
+        // The event's own minutes apply to everyone. Both of the guest's
+        // reminders passed while the server was down, but only one is sent.
+        sqlx::query("UPDATE events SET reminder_minutes = '1440,60' WHERE id = ?")
+            .bind(&delivery.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        clock.set(at("2026-03-03 08:30"));
+        assert_eq!(send_due_reminders(&pool, &clock).await.unwrap(), 2);
+        assert_eq!(send_due_reminders(&pool, &clock).await.unwrap(), 0);
+        let sent = notifications(&pool).await;
+        assert_eq!(sent.iter().filter(|n| n.0 == "guest").count(), 1);
+        assert_eq!(sent.iter().filter(|n| n.0 == "organizer").count(), 2);
+        assert!(sent[0].2.contains("is expected in 30 minutes"));
+    }
+
+    #[tokio::test]
+    async fn moving_an_event_sends_its_reminders_again() {
+        let pool = test_pool().await;
+        let event = add_event(&pool, "meeting", "2026-03-02", "10:00").await;
+        let clock = FixedClock::new(at("2026-03-02 09:50"));
+        assert_eq!(send_due_reminders(&pool, &clock).await.unwrap(), 2);
+
+        sqlx::query("UPDATE events SET start_time = '10:05' WHERE id = ?")
+            .bind(&event.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(send_due_reminders(&pool, &clock).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn event_and_recipient_time_zones_are_honoured() {
+        let pool = test_pool().await;
+        let event = add_event(&pool, "meeting", "2026-03-02", "10:00").await;
+        for (table, id) in [("events", event.id.as_str()), ("users", "organizer")] {
+            sqlx::query(&format!(
+                "UPDATE {} SET timezone = 'Asia/Singapore' WHERE id = ?",
+                table
+            ))
+            .bind(id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        // 10:00 in Singapore is 02:00 UTC
+        let clock = FixedClock::new(at("2026-03-02 01:44"));
+        assert_eq!(send_due_reminders(&pool, &clock).await.unwrap(), 0);
+        clock.set(at("2026-03-02 01:45"));
+        assert_eq!(send_due_reminders(&pool, &clock).await.unwrap(), 2);
+        let sent = notifications(&pool).await;
+        assert!(sent[0].2.ends_with("(2026-03-02 at 02:00)"));
+        assert!(sent[1].2.ends_with("(2026-03-02 at 10:00)"));
+    }
+}
@@ -1,12 +1,25 @@
+use actix_web::cookie::{Cookie, time::Duration as CookieDuration};
 use actix_web::{HttpRequest, HttpResponse, web};
-use bcrypt::{verify, hash, DEFAULT_COST};
-use chrono::{Duration, Utc};
-use jsonwebtoken::{EncodingKey, Header, encode};
-use serde::{Deserialize, Serialize};
-use sqlx::SqlitePool;
+use bcrypt::{DEFAULT_COST, hash, verify};
+use chrono::Utc;
+use serde::Deserialize;
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
 use uuid::Uuid;
 
+use crate::config::Config;
+use crate::error::AppError;
+use crate::lockout;
+use crate::mailer::{Email, Mailer};
+use crate::middleware::auth::ACCESS_TOKEN_COOKIE;
+use crate::middleware::guards::AuthUser;
+use crate::models::sessions::{Session, SessionResponse};
 use crate::models::users::{LoginData, LoginRequest, LoginResponse, User, UserResponse};
+use crate::tokens::{self, AccessToken, RevokedSessions};
+
+/// Cookie carrying the refresh token; only sent to the auth endpoints
+const REFRESH_TOKEN_COOKIE: &str = "refresh_token";
+const REFRESH_TOKEN_COOKIE_PATH: &str = "/api/auth";
+const MIN_PASSWORD_LEN: usize = 6;
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -18,13 +31,29 @@ pub struct RegisterRequest {
     pub department: String,
 }
 
-const JWT_SECRET: &str = "your-secret-key-change-in-production";
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshRequest {
+    pub refresh_token: Option<String>,
+}
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Claims {
-    pub sub: String,
-    pub exp: usize,
-    pub role: String,
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    pub new_password: String,
 }
 
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
@@ -33,188 +62,218 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
             .route("/login", web::post().to(login))
             .route("/register", web::post().to(register))
             .route("/logout", web::post().to(logout))
-            .route("/refresh", web::post().to(refresh_token)),
+            .route("/refresh", web::post().to(refresh_token))
+            .route("/change-password", web::post().to(change_password))
+            .route("/forgot-password", web::post().to(forgot_password))
+            .route("/reset-password", web::post().to(reset_password))
+            .route("/sessions", web::get().to(list_my_sessions))
+            .route(
+                "/sessions/revoke-others",
+                web::post().to(revoke_other_sessions),
+            )
+            .route(
+                "/sessions/{session_id}",
+                web::delete().to(revoke_my_session),
+            ),
     );
 }
 
-pub fn create_token(user_id: &str, role: &str) -> String {
-    let expiration = Utc::now()
-        .checked_add_signed(Duration::hours(24))
-        .expect("valid timestamp")
-        .timestamp() as usize;
+/// Tokens handed to the client when a session starts or is refreshed.
+struct IssuedTokens {
+    access: AccessToken,
+    refresh_token: String,
+    refresh_expires_at: String,
+}
 
-    let claims = Claims {
-        sub: user_id.to_string(),
-        exp: expiration,
-        role: role.to_string(),
-    };
+impl IssuedTokens {
+    fn cookies(&self, config: &Config) -> (Cookie<'static>, Cookie<'static>) {
+        let access = Cookie::build(ACCESS_TOKEN_COOKIE, self.access.token.clone())
+            .path("/")
+            .http_only(true)
+            .max_age(CookieDuration::minutes(config.access_token_ttl_minutes))
+            .finish();
+        let refresh = Cookie::build(REFRESH_TOKEN_COOKIE, self.refresh_token.clone())
+            .path(REFRESH_TOKEN_COOKIE_PATH)
+            .http_only(true)
+            .max_age(CookieDuration::hours(config.session_lifetime_hours))
+            .finish();
+        (access, refresh)
+    }
+}
 
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(JWT_SECRET.as_ref()),
+/// Client address and `User-Agent`, recorded on the session.
+fn client_info(req: &HttpRequest) -> (Option<String>, Option<String>) {
+    let ip_address = req
+        .connection_info()
+        .realip_remote_addr()
+        .map(|ip| ip.to_string());
+    let user_agent = req
+        .headers()
+        .get("user-agent")
+        .and_then(|ua| ua.to_str().ok())
+        .map(|ua| ua.to_string());
+    (ip_address, user_agent)
+}
+
+/// Creates a session for the user and issues its first token pair.
+async fn start_session(
+    pool: &SqlitePool,
+    config: &Config,
+    req: &HttpRequest,
+    user_id: &str,
+    role: &str,
+    department: &str,
+) -> Result<IssuedTokens, AppError> {
+    let session_id = Uuid::new_v4().to_string();
+    let refresh_token = tokens::new_opaque_token();
+    let refresh_expires_at = tokens::session_expires_at(config);
+    let (ip_address, user_agent) = client_info(req);
+
+    sqlx::query(
+        "INSERT INTO sessions (id, user_id, token, expires_at, ip_address, user_agent, last_used_at) VALUES (?, ?, ?, ?, ?, ?, datetime('now'))",
     )
-    .unwrap()
+    .bind(&session_id)
+    .bind(user_id)
+    .bind(tokens::hash_opaque_token(&refresh_token))
+    .bind(tokens::to_sql_datetime(&refresh_expires_at))
+    .bind(&ip_address)
+    .bind(&user_agent)
+    .execute(pool)
+    .await?;
+
+    Ok(IssuedTokens {
+        access: tokens::issue_access_token(config, &session_id, user_id, role, department)?,
+        refresh_token,
+        refresh_expires_at: refresh_expires_at.to_rfc3339(),
+    })
 }
 
-async fn login(pool: web::Data<SqlitePool>, body: web::Json<LoginRequest>) -> HttpResponse {
-    let result = sqlx::query_as::<_, User>(
-        "SELECT id, email, password_hash, first_name, last_name, department, role, is_active, created_at, updated_at, last_login FROM users WHERE email = ?"
+async fn login(
+    pool: web::Data<SqlitePool>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    body: web::Json<LoginRequest>,
+) -> Result<HttpResponse, AppError> {
+    let user = sqlx::query_as::<_, User>(
+        "SELECT id, email, password_hash, first_name, last_name, department, role, is_active, created_at, updated_at, last_login, timezone FROM users WHERE email = ?"
     )
     .bind(&body.email)
     .fetch_optional(pool.get_ref())
-    .await;
+    .await?
+    .ok_or_else(invalid_credentials)?;
 
-    match result {
-        Ok(Some(user)) => {
-            if !user.is_active {
-                return HttpResponse::Unauthorized().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "ACCOUNT_DISABLED",
-                        "message": "Account is disabled"
-                    }
-                }));
-            }
+    if !user.is_active {
+        return Err(
+            AppError::Unauthorized("Account is disabled".to_string()).with_code("ACCOUNT_DISABLED")
+        );
+    }
 
-            let is_valid = verify(&body.password, &user.password_hash).unwrap_or(false);
-            if !is_valid {
-                return HttpResponse::Unauthorized().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "INVALID_CREDENTIALS",
-                        "message": "Invalid email or password"
-                    }
-                }));
-            }
+    lockout::check_login_allowed(pool.get_ref(), &user.id).await?;
 
-            // Create session
-            let session_id = Uuid::new_v4().to_string();
-            let token = create_token(&user.id, &user.role);
-            let expires_at = (Utc::now() + Duration::hours(24)).to_rfc3339();
+    let is_valid = verify(&body.password, &user.password_hash).unwrap_or(false);
+    if !is_valid {
+        let (ip_address, _) = client_info(&req);
+        let locked_until =
+            lockout::record_failed_login(pool.get_ref(), &config, &user.id, ip_address.as_deref())
+                .await?;
+        return Err(match locked_until {
+            Some(until) => lockout::account_locked(until),
+            None => invalid_credentials(),
+        });
+    }
 
-            let insert_result = sqlx::query(
-                "INSERT INTO sessions (id, user_id, token, expires_at) VALUES (?, ?, ?, ?)",
-            )
-            .bind(&session_id)
-            .bind(&user.id)
-            .bind(&token)
-            .bind(&expires_at)
-            .execute(pool.get_ref())
-            .await;
-
-            if let Err(e) = insert_result {
-                eprintln!("Failed to insert session: {:?}", e);
-                return HttpResponse::InternalServerError().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "INTERNAL_ERROR",
-                        "message": "Failed to create session"
-                    }
-                }));
-            }
+    lockout::clear_failed_logins(pool.get_ref(), &user.id).await?;
 
-            // Update last login
-            let _ = sqlx::query("UPDATE users SET last_login = datetime('now') WHERE id = ?")
-                .bind(&user.id)
-                .execute(pool.get_ref())
-                .await;
-
-            let cookie = actix_web::cookie::Cookie::build("session_token", token.clone())
-                .path("/")
-                .http_only(true)
-                .finish();
-
-            let response = LoginResponse {
-                success: true,
-                data: LoginData {
-                    user: UserResponse::from(user),
-                    token: token.clone(),
-                    expires_at,
-                },
-            };
+    let issued = start_session(
+        pool.get_ref(),
+        &config,
+        &req,
+        &user.id,
+        &user.role,
+        &user.department,
+    )
+    .await?;
 
-            HttpResponse::Ok().cookie(cookie).json(response)
-        }
-        Ok(None) => HttpResponse::Unauthorized().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "INVALID_CREDENTIALS",
-                "message": "Invalid email or password"
-            }
-        })),
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
-        }
+    // Update last login
+    let _ = sqlx::query("UPDATE users SET last_login = datetime('now') WHERE id = ?")
+        .bind(&user.id)
+        .execute(pool.get_ref())
+        .await;
+
+    let (access_cookie, refresh_cookie) = issued.cookies(&config);
+
+    let response = LoginResponse {
+        success: true,
+        data: LoginData {
+            user: UserResponse::from(user),
+            token: issued.access.token,
+            expires_at: issued.access.expires_at,
+            refresh_token: issued.refresh_token,
+            refresh_expires_at: issued.refresh_expires_at,
+        },
+    };
+
+    Ok(HttpResponse::Ok()
+        .cookie(access_cookie)
+        .cookie(refresh_cookie)
+        .json(response))
+}
+
+/// Checks the password policy and returns the bcrypt hash.
+fn hash_new_password(password: &str) -> Result<String, AppError> {
+    if password.len() < MIN_PASSWORD_LEN {
+        return Err(AppError::Validation(format!(
+            "Password must be at least {} characters",
+            MIN_PASSWORD_LEN
+        )));
     }
+
+    hash(password, DEFAULT_COST)
+        .map_err(|e| AppError::Internal(format!("Failed to hash password: {}", e)))
 }
 
-async fn register(pool: web::Data<SqlitePool>, body: web::Json<RegisterRequest>) -> HttpResponse {
+/// Unknown email and wrong password share one error so login cannot be used
+/// to probe which accounts exist.
+fn invalid_credentials() -> AppError {
+    AppError::Unauthorized("Invalid email or password".to_string()).with_code("INVALID_CREDENTIALS")
+}
+
+async fn register(
+    pool: web::Data<SqlitePool>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    body: web::Json<RegisterRequest>,
+) -> Result<HttpResponse, AppError> {
     // Validate required fields
-    if body.email.is_empty() || body.password.is_empty() || body.first_name.is_empty() || body.last_name.is_empty() {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "VALIDATION_ERROR",
-                "message": "All fields are required"
-            }
-        }));
+    if body.email.is_empty()
+        || body.password.is_empty()
+        || body.first_name.is_empty()
+        || body.last_name.is_empty()
+    {
+        return Err(AppError::Validation("All fields are required".to_string()));
     }
 
-    // Validate password length
-    if body.password.len() < 6 {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "VALIDATION_ERROR",
-                "message": "Password must be at least 6 characters"
-            }
-        }));
-    }
+    let password_hash = hash_new_password(&body.password)?;
 
     // Check if email already exists
     let existing = sqlx::query_as::<_, (String,)>("SELECT id FROM users WHERE email = ?")
         .bind(&body.email)
         .fetch_optional(pool.get_ref())
-        .await;
+        .await?;
 
-    if let Ok(Some(_)) = existing {
-        return HttpResponse::Conflict().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "CONFLICT",
-                "message": "Email already exists"
-            }
-        }));
+    if existing.is_some() {
+        return Err(AppError::Conflict("Email already exists".to_string()));
     }
 
-    // Hash password
-    let password_hash = match hash(&body.password, DEFAULT_COST) {
-        Ok(h) => h,
-        Err(e) => {
-            eprintln!("Hashing error: {:?}", e);
-            return HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to hash password"
-                }
-            }));
-        }
-    };
-
     let user_id = Uuid::new_v4().to_string();
-    let department = if body.department.is_empty() { "IT".to_string() } else { body.department.clone() };
+    let department = if body.department.is_empty() {
+        "IT".to_string()
+    } else {
+        body.department.clone()
+    };
 
     // Insert new user with Member role
-    let result = sqlx::query(
+    sqlx::query(
         "INSERT INTO users (id, email, password_hash, first_name, last_name, department, role, is_active) VALUES (?, ?, ?, ?, ?, ?, 'Member', 1)"
     )
     .bind(&user_id)
@@ -224,215 +283,891 @@ async fn register(pool: web::Data<SqlitePool>, body: web::Json<RegisterRequest>)
     .bind(&body.last_name)
     .bind(&department)
     .execute(pool.get_ref())
-    .await;
+    .await?;
 
-    match result {
-        Ok(_) => {
-            // Create session for the new user
-            let session_id = Uuid::new_v4().to_string();
-            let token = create_token(&user_id, "Member");
-            let expires_at = (Utc::now() + Duration::hours(24)).to_rfc3339();
+    // Create session for the new user
+    let issued = start_session(
+        pool.get_ref(),
+        &config,
+        &req,
+        &user_id,
+        "Member",
+        &department,
+    )
+    .await?;
+    let (access_cookie, refresh_cookie) = issued.cookies(&config);
 
-            let _ = sqlx::query(
-                "INSERT INTO sessions (id, user_id, token, expires_at) VALUES (?, ?, ?, ?)",
-            )
-            .bind(&session_id)
-            .bind(&user_id)
-            .bind(&token)
-            .bind(&expires_at)
-            .execute(pool.get_ref())
-            .await;
-
-            let cookie = actix_web::cookie::Cookie::build("session_token", token.clone())
-                .path("/")
-                .http_only(true)
-                .finish();
-
-            HttpResponse::Created().cookie(cookie).json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "user": {
-                        "id": user_id,
-                        "email": body.email,
-                        "firstName": body.first_name,
-                        "lastName": body.last_name,
-                        "department": department,
-                        "role": "Member",
-                        "isActive": true
-                    },
-                    "token": token,
-                    "expiresAt": expires_at
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to create user"
-                }
-            }))
-        }
+    Ok(HttpResponse::Created()
+        .cookie(access_cookie)
+        .cookie(refresh_cookie)
+        .json(serde_json::json!({
+            "success": true,
+            "data": {
+                "user": {
+                    "id": user_id,
+                    "email": body.email,
+                    "firstName": body.first_name,
+                    "lastName": body.last_name,
+                    "department": department,
+                    "role": "Member",
+                    "isActive": true
+                },
+                "token": issued.access.token,
+                "expiresAt": issued.access.expires_at,
+                "refreshToken": issued.refresh_token,
+                "refreshExpiresAt": issued.refresh_expires_at
+            }
+        })))
+}
+
+async fn logout(
+    pool: web::Data<SqlitePool>,
+    config: web::Data<Config>,
+    revoked: web::Data<RevokedSessions>,
+    auth: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let ended = revoke_sessions(
+        pool.get_ref(),
+        &config,
+        &revoked,
+        &auth.id,
+        Revoke::Only(&auth.session_id),
+    )
+    .await?;
+
+    if ended == 0 {
+        return Err(AppError::NotFound("Session not found".to_string()));
     }
+
+    let clear_access = Cookie::build(ACCESS_TOKEN_COOKIE, "")
+        .path("/")
+        .max_age(CookieDuration::seconds(0))
+        .finish();
+    let clear_refresh = Cookie::build(REFRESH_TOKEN_COOKIE, "")
+        .path(REFRESH_TOKEN_COOKIE_PATH)
+        .max_age(CookieDuration::seconds(0))
+        .finish();
+
+    Ok(HttpResponse::Ok()
+        .cookie(clear_access)
+        .cookie(clear_refresh)
+        .json(serde_json::json!({
+            "success": true,
+            "message": "Successfully logged out"
+        })))
 }
 
-async fn logout(pool: web::Data<SqlitePool>, req: HttpRequest) -> HttpResponse {
-    let token = extract_token(&req);
-
-    let token = match token {
-        Some(t) => t,
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "No session token provided"
-                }
-            }));
-        }
+/// Exchanges a refresh token for a new access token and a new refresh token.
+///
+/// The presented token is retired. If a retired token is presented again, the
+/// session it belonged to is revoked, logging out both the legitimate client
+/// and whoever copied the token.
+async fn refresh_token(
+    pool: web::Data<SqlitePool>,
+    config: web::Data<Config>,
+    revoked: web::Data<RevokedSessions>,
+    req: HttpRequest,
+    body: Option<web::Json<RefreshRequest>>,
+) -> Result<HttpResponse, AppError> {
+    let token = body
+        .and_then(|b| b.into_inner().refresh_token)
+        .or_else(|| {
+            req.cookie(REFRESH_TOKEN_COOKIE)
+                .map(|c| c.value().to_string())
+        })
+        .ok_or_else(|| AppError::Unauthorized("No refresh token provided".to_string()))?;
+    let token_hash = tokens::hash_opaque_token(&token);
+
+    // Find the session and associated user
+    let session = sqlx::query_as::<_, (String, String, String, String)>(
+        "SELECT s.id, s.user_id, u.role, u.department FROM sessions s JOIN users u ON s.user_id = u.id WHERE s.token = ? AND s.expires_at > datetime('now') AND u.is_active = 1"
+    )
+    .bind(&token_hash)
+    .fetch_optional(pool.get_ref())
+    .await?;
+
+    let Some((session_id, user_id, role, department)) = session else {
+        return Err(reject_refresh(pool.get_ref(), &config, &revoked, &token_hash).await);
     };
 
-    let result = sqlx::query("DELETE FROM sessions WHERE token = ?")
-        .bind(&token)
-        .execute(pool.get_ref())
-        .await;
+    let refresh_token = tokens::new_opaque_token();
+    let refresh_expires_at = tokens::session_expires_at(&config);
+    let (ip_address, user_agent) = client_info(&req);
+
+    let mut tx = pool.begin().await?;
+
+    // Matching on the old hash makes two concurrent refreshes with the same
+    // token race for one rotation; the loser is treated as reuse.
+    let rotated = sqlx::query(
+        "UPDATE sessions SET token = ?, expires_at = ?, ip_address = ?, user_agent = ?, last_used_at = datetime('now') WHERE id = ? AND token = ?",
+    )
+    .bind(tokens::hash_opaque_token(&refresh_token))
+    .bind(tokens::to_sql_datetime(&refresh_expires_at))
+    .bind(&ip_address)
+    .bind(&user_agent)
+    .bind(&session_id)
+    .bind(&token_hash)
+    .execute(&mut *tx)
+    .await?;
+
+    if rotated.rows_affected() == 0 {
+        tx.rollback().await?;
+        return Err(reject_refresh(pool.get_ref(), &config, &revoked, &token_hash).await);
+    }
+
+    sqlx::query("INSERT INTO rotated_refresh_tokens (token_hash, session_id) VALUES (?, ?)")
+        .bind(&token_hash)
+        .bind(&session_id)
+        .execute(&mut *tx)
+        .await?;
 
-    match result {
-        Ok(rows) => {
-            if rows.rows_affected() > 0 {
-                let clear_cookie = actix_web::cookie::Cookie::build("session_token", "")
-                    .path("/")
-                    .max_age(actix_web::cookie::time::Duration::seconds(0))
-                    .finish();
-
-                HttpResponse::Ok()
-                    .cookie(clear_cookie)
-                    .json(serde_json::json!({
-                        "success": true,
-                        "message": "Successfully logged out"
-                    }))
-            } else {
-                HttpResponse::NotFound().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "NOT_FOUND",
-                        "message": "Session not found"
-                    }
-                }))
+    tx.commit().await?;
+
+    let issued = IssuedTokens {
+        access: tokens::issue_access_token(&config, &session_id, &user_id, &role, &department)?,
+        refresh_token,
+        refresh_expires_at: refresh_expires_at.to_rfc3339(),
+    };
+    let (access_cookie, refresh_cookie) = issued.cookies(&config);
+
+    Ok(HttpResponse::Ok()
+        .cookie(access_cookie)
+        .cookie(refresh_cookie)
+        .json(serde_json::json!({
+            "success": true,
+            "data": {
+                "token": issued.access.token,
+                "expiresAt": issued.access.expires_at,
+                "refreshToken": issued.refresh_token,
+                "refreshExpiresAt": issued.refresh_expires_at
             }
+        })))
+}
+
+/// Builds the error for a refresh token that matches no live session. A token
+/// that was already rotated out revokes the session it came from.
+async fn reject_refresh(
+    pool: &SqlitePool,
+    config: &Config,
+    revoked: &RevokedSessions,
+    token_hash: &str,
+) -> AppError {
+    let reused = sqlx::query_scalar::<_, String>(
+        "SELECT session_id FROM rotated_refresh_tokens WHERE token_hash = ?",
+    )
+    .bind(token_hash)
+    .fetch_optional(pool)
+    .await;
+
+    match reused {
+        Ok(Some(session_id)) => {
+            log::warn!(
+                "Refresh token reuse detected, revoking session {}",
+                session_id
+            );
+            if let Err(e) = sqlx::query("DELETE FROM sessions WHERE id = ?")
+                .bind(&session_id)
+                .execute(pool)
+                .await
+            {
+                return AppError::Database(e);
+            }
+            revoked.revoke(config, [session_id]);
+            AppError::Unauthorized(
+                "Refresh token was already used! The session has been revoked.".to_string(),
+            )
+            .with_code("REFRESH_TOKEN_REUSED")
         }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to logout"
-                }
-            }))
-        }
+        Ok(None) => AppError::Unauthorized("Invalid or expired refresh token".to_string())
+            .with_code("INVALID_REFRESH_TOKEN"),
+        Err(e) => AppError::Database(e),
     }
 }
 
-async fn refresh_token(pool: web::Data<SqlitePool>, req: HttpRequest) -> HttpResponse {
-    let token = extract_token(&req);
-
-    let token = match token {
-        Some(t) => t,
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "No session token provided"
-                }
-            }));
+/// Which of a user's sessions [`revoke_sessions`] ends.
+pub enum Revoke<'a> {
+    All,
+    Only(&'a str),
+    AllExcept(&'a str),
+}
+
+/// Unexpired sessions of `user_id`, most recently used first.
+pub async fn active_sessions(
+    pool: &SqlitePool,
+    user_id: &str,
+    current_session_id: &str,
+) -> Result<Vec<SessionResponse>, AppError> {
+    let sessions = sqlx::query_as::<_, Session>(
+        "SELECT * FROM sessions WHERE user_id = ? AND expires_at > datetime('now') ORDER BY COALESCE(last_used_at, created_at) DESC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(sessions
+        .into_iter()
+        .map(|s| SessionResponse::new(s, current_session_id))
+        .collect())
+}
+
+/// Deletes the selected sessions of `user_id` and blocks their outstanding
+/// access tokens. Returns how many sessions were ended.
+pub async fn revoke_sessions(
+    pool: &SqlitePool,
+    config: &Config,
+    revoked: &RevokedSessions,
+    user_id: &str,
+    which: Revoke<'_>,
+) -> Result<u64, AppError> {
+    let mut qb = QueryBuilder::<Sqlite>::new("DELETE FROM sessions WHERE user_id = ");
+    qb.push_bind(user_id);
+    match which {
+        Revoke::All => {}
+        Revoke::Only(id) => {
+            qb.push(" AND id = ").push_bind(id);
         }
-    };
+        Revoke::AllExcept(id) => {
+            qb.push(" AND id != ").push_bind(id);
+        }
+    }
+    qb.push(" RETURNING id");
 
-    // Find the session and associated user
-    let result = sqlx::query_as::<_, (String, String)>(
-        "SELECT s.user_id, u.role FROM sessions s JOIN users u ON s.user_id = u.id WHERE s.token = ? AND s.expires_at > datetime('now')"
+    let ids: Vec<String> = qb.build_query_scalar().fetch_all(pool).await?;
+    let ended = ids.len() as u64;
+    revoked.revoke(config, ids);
+    Ok(ended)
+}
+
+async fn list_my_sessions(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let sessions = active_sessions(pool.get_ref(), &auth.id, &auth.session_id).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": sessions
+    })))
+}
+
+async fn revoke_my_session(
+    pool: web::Data<SqlitePool>,
+    config: web::Data<Config>,
+    revoked: web::Data<RevokedSessions>,
+    auth: AuthUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let session_id = path.into_inner();
+    let ended = revoke_sessions(
+        pool.get_ref(),
+        &config,
+        &revoked,
+        &auth.id,
+        Revoke::Only(&session_id),
+    )
+    .await?;
+
+    if ended == 0 {
+        return Err(AppError::NotFound("Session not found".to_string()));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Session revoked"
+    })))
+}
+
+/// Logs the caller out everywhere except the session making the request.
+async fn revoke_other_sessions(
+    pool: web::Data<SqlitePool>,
+    config: web::Data<Config>,
+    revoked: web::Data<RevokedSessions>,
+    auth: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let ended = revoke_sessions(
+        pool.get_ref(),
+        &config,
+        &revoked,
+        &auth.id,
+        Revoke::AllExcept(&auth.session_id),
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": { "revoked": ended }
+    })))
+}
+
+/// Changes the caller's password and logs out their other sessions.
+async fn change_password(
+    pool: web::Data<SqlitePool>,
+    config: web::Data<Config>,
+    revoked: web::Data<RevokedSessions>,
+    auth: AuthUser,
+    body: web::Json<ChangePasswordRequest>,
+) -> Result<HttpResponse, AppError> {
+    let current_hash =
+        sqlx::query_scalar::<_, String>("SELECT password_hash FROM users WHERE id = ?")
+            .bind(&auth.id)
+            .fetch_optional(pool.get_ref())
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    if !verify(&body.current_password, &current_hash).unwrap_or(false) {
+        return Err(
+            AppError::BadRequest("Current password is incorrect".to_string())
+                .with_code("INVALID_CURRENT_PASSWORD"),
+        );
+    }
+
+    let password_hash = hash_new_password(&body.new_password)?;
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE users SET password_hash = ?, updated_at = datetime('now') WHERE id = ?")
+        .bind(&password_hash)
+        .bind(&auth.id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM password_reset_tokens WHERE user_id = ? AND used_at IS NULL")
+        .bind(&auth.id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    let ended = revoke_sessions(
+        pool.get_ref(),
+        &config,
+        &revoked,
+        &auth.id,
+        Revoke::AllExcept(&auth.session_id),
     )
-    .bind(&token)
+    .await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Password changed successfully",
+        "data": { "revokedSessions": ended }
+    })))
+}
+
+/// Emails a single-use reset link. Answers the same whether or not the
+/// account exists, so it cannot be used to probe for accounts.
+async fn forgot_password(
+    pool: web::Data<SqlitePool>,
+    config: web::Data<Config>,
+    mailer: web::Data<dyn Mailer>,
+    body: web::Json<ForgotPasswordRequest>,
+) -> Result<HttpResponse, AppError> {
+    let user = sqlx::query_as::<_, (String, String)>(
+        "SELECT id, first_name FROM users WHERE email = ? AND is_active = 1",
+    )
+    .bind(&body.email)
     .fetch_optional(pool.get_ref())
-    .await;
+    .await?;
 
-    match result {
-        Ok(Some((user_id, role))) => {
-            // Create new token
-            let new_token = create_token(&user_id, &role);
-            let new_expires_at = (Utc::now() + Duration::hours(24)).to_rfc3339();
-
-            // Update session with new token
-            let update_result =
-                sqlx::query("UPDATE sessions SET token = ?, expires_at = ? WHERE token = ?")
-                    .bind(&new_token)
-                    .bind(&new_expires_at)
-                    .bind(&token)
-                    .execute(pool.get_ref())
-                    .await;
-
-            if let Err(e) = update_result {
-                eprintln!("Failed to update session: {:?}", e);
-                return HttpResponse::InternalServerError().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "INTERNAL_ERROR",
-                        "message": "Failed to refresh token"
-                    }
-                }));
-            }
+    if let Some((user_id, first_name)) = user {
+        let token = tokens::new_opaque_token();
+        let expires_at = Utc::now() + config.password_reset_ttl();
 
-            let cookie = actix_web::cookie::Cookie::build("session_token", new_token.clone())
-                .path("/")
-                .http_only(true)
-                .finish();
-
-            HttpResponse::Ok().cookie(cookie).json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "token": new_token,
-                    "expiresAt": new_expires_at
-                }
-            }))
-        }
-        Ok(None) => HttpResponse::Unauthorized().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "UNAUTHORIZED",
-                "message": "Invalid or expired session"
-            }
-        })),
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM password_reset_tokens WHERE user_id = ? AND used_at IS NULL")
+            .bind(&user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "INSERT INTO password_reset_tokens (token_hash, user_id, expires_at) VALUES (?, ?, ?)",
+        )
+        .bind(tokens::hash_opaque_token(&token))
+        .bind(&user_id)
+        .bind(tokens::to_sql_datetime(&expires_at))
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        let email = Email {
+            to: body.email.clone(),
+            subject: "Reset your password".to_string(),
+            body: format!(
+                "Hi {},\n\nUse this link to choose a new password:\n\n{}/reset-password?token={}\n\nThe link works once and expires in {} minutes. If you did not ask for a reset, you can ignore this email.",
+                first_name,
+                config.frontend_url.trim_end_matches('/'),
+                token,
+                config.password_reset_ttl_minutes
+            ),
+        };
+        if let Err(e) = mailer.send(&email) {
+            log::error!(
+                "Failed to send password reset email for user {}: {}",
+                user_id,
+                e
+            );
         }
     }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "If an account exists for that email, a reset link has been sent"
+    })))
+}
+
+/// Sets a new password with a reset token. The token is spent, every session
+/// of the user is revoked and any login lockout is lifted.
+async fn reset_password(
+    pool: web::Data<SqlitePool>,
+    config: web::Data<Config>,
+    revoked: web::Data<RevokedSessions>,
+    body: web::Json<ResetPasswordRequest>,
+) -> Result<HttpResponse, AppError> {
+    // Checked before the token is spent so a rejected password does not burn it
+    let password_hash = hash_new_password(&body.new_password)?;
+
+    let mut tx = pool.begin().await?;
+
+    let user_id = sqlx::query_scalar::<_, String>(
+        "UPDATE password_reset_tokens SET used_at = datetime('now') WHERE token_hash = ? AND used_at IS NULL AND expires_at > datetime('now') RETURNING user_id",
+    )
+    .bind(tokens::hash_opaque_token(&body.token))
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| {
+        AppError::BadRequest("Invalid or expired reset token".to_string())
+            .with_code("INVALID_RESET_TOKEN")
+    })?;
+
+    sqlx::query(
+        "UPDATE users SET password_hash = ?, failed_login_count = 0, last_failed_login_at = NULL, locked_until = NULL, updated_at = datetime('now') WHERE id = ?",
+    )
+    .bind(&password_hash)
+    .bind(&user_id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM password_reset_tokens WHERE user_id = ? AND used_at IS NULL")
+        .bind(&user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    revoke_sessions(pool.get_ref(), &config, &revoked, &user_id, Revoke::All).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Password has been reset. Please log in with your new password."
+    })))
 }
 
-fn extract_token(req: &HttpRequest) -> Option<String> {
-    // Try Authorization header first
-    if let Some(auth_header) = req.headers().get("authorization") {
-        if let Ok(header_value) = auth_header.to_str() {
-            if header_value.starts_with("Bearer ") {
-                return Some(header_value.strip_prefix("Bearer ").unwrap().to_string());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::App;
+    use actix_web::body::{MessageBody, to_bytes};
+    use actix_web::dev::{Service, ServiceResponse};
+    use actix_web::http::StatusCode;
+    use actix_web::test::{TestRequest, init_service, read_body, try_call_service};
+    use serde_json::Value;
+    use std::sync::{Arc, Mutex};
+
+    use crate::middleware::auth::Auth;
+
+    const PASSWORD: &str = "correct horse";
+
+    async fn test_pool() -> SqlitePool {
+        let pool = crate::migrations::test_pool().await;
+        // The lowest bcrypt cost keeps logins fast
+        sqlx::query(
+            "INSERT INTO users (id, email, password_hash, first_name, last_name, department, role) VALUES ('user', 'user@company.com', ?, 'Test', 'User', 'IT', 'Member')",
+        )
+        .bind(hash(PASSWORD, 4).unwrap())
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool
+    }
+
+    /// Calls the app and returns the status and JSON body, including for
+    /// errors raised by middleware.
+    async fn send<S, R, B>(app: &S, req: R) -> (StatusCode, Value)
+    where
+        S: Service<R, Response = ServiceResponse<B>, Error = actix_web::Error>,
+        B: MessageBody,
+    {
+        let (status, body) = match try_call_service(app, req).await {
+            Ok(res) => (res.status(), read_body(res).await),
+            Err(e) => {
+                let res = e.error_response();
+                (res.status(), to_bytes(res.into_body()).await.unwrap())
             }
+        };
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    fn login_request() -> TestRequest {
+        TestRequest::post()
+            .uri("/api/auth/login")
+            .set_json(serde_json::json!({ "email": "user@company.com", "password": PASSWORD }))
+    }
+
+    fn refresh_request(refresh_token: &Value) -> TestRequest {
+        TestRequest::post()
+            .uri("/api/auth/refresh")
+            .set_json(serde_json::json!({ "refreshToken": refresh_token }))
+    }
+
+    /// A request authenticated with `access_token`.
+    fn authorized(req: TestRequest, access_token: &Value) -> TestRequest {
+        req.insert_header((
+            "Authorization",
+            format!("Bearer {}", access_token.as_str().unwrap()),
+        ))
+    }
+
+    fn session_id(token: &Value) -> String {
+        tokens::verify_access_token(&Config::default(), token.as_str().unwrap())
+            .unwrap()
+            .sid
+    }
+
+    /// Keeps sent emails for the test to read.
+    #[derive(Default)]
+    struct Outbox(Mutex<Vec<Email>>);
+
+    impl Mailer for Outbox {
+        fn send(&self, email: &Email) -> Result<(), AppError> {
+            self.0.lock().unwrap().push(Email {
+                to: email.to.clone(),
+                subject: email.subject.clone(),
+                body: email.body.clone(),
+            });
+            Ok(())
         }
     }
 
-    // Try cookie
-    if let Some(cookie) = req.cookie("session_token") {
-        return Some(cookie.value().to_string());
+    impl Outbox {
+        /// The token in the last reset link sent.
+        fn reset_token(&self) -> String {
+            let sent = self.0.lock().unwrap();
+            let body = &sent.last().expect("no email was sent").body;
+            let (_, rest) = body.split_once("token=").unwrap();
+            rest.split_whitespace().next().unwrap().to_string()
+        }
+    }
+
+    macro_rules! test_app {
+        ($pool:expr, $revoked:expr) => {
+            test_app!($pool, $revoked, Arc::new(Outbox::default()))
+        };
+        ($pool:expr, $revoked:expr, $outbox:expr) => {
+            init_service(
+                App::new()
+                    .app_data(web::Data::new($pool.clone()))
+                    .app_data(web::Data::new(Config::default()))
+                    .app_data($revoked.clone())
+                    .app_data(web::Data::<dyn Mailer>::from(
+                        $outbox.clone() as Arc<dyn Mailer>
+                    ))
+                    .wrap(Auth)
+                    .service(web::scope("/api").configure(configure_routes)),
+            )
+            .await
+        };
+    }
+
+    #[actix_web::test]
+    async fn refresh_rotates_the_refresh_token() {
+        let pool = test_pool().await;
+        let revoked = web::Data::new(RevokedSessions::default());
+        let app = test_app!(pool, revoked);
+
+        let (status, login) = send(&app, login_request().to_request()).await;
+        assert_eq!(status, StatusCode::OK);
+        let first = &login["data"]["refreshToken"];
+
+        let (status, refreshed) = send(&app, refresh_request(first).to_request()).await;
+        assert_eq!(status, StatusCode::OK);
+        let second = &refreshed["data"]["refreshToken"];
+        assert_ne!(second, first);
+        assert_eq!(
+            session_id(&refreshed["data"]["token"]),
+            session_id(&login["data"]["token"])
+        );
+
+        // Only the hash of the current token is stored
+        let stored: String = sqlx::query_scalar("SELECT token FROM sessions")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored, tokens::hash_opaque_token(second.as_str().unwrap()));
+
+        let (status, _) = send(&app, refresh_request(second).to_request()).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) =
+            send(&app, refresh_request(&Value::from("unknown")).to_request()).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"]["code"], "INVALID_REFRESH_TOKEN");
+    }
+
+    #[actix_web::test]
+    async fn reusing_a_rotated_refresh_token_revokes_the_session() {
+        let pool = test_pool().await;
+        let revoked = web::Data::new(RevokedSessions::default());
+        let app = test_app!(pool, revoked);
+
+        let (_, login) = send(&app, login_request().to_request()).await;
+        let stolen = &login["data"]["refreshToken"];
+        let (_, refreshed) = send(&app, refresh_request(stolen).to_request()).await;
+        let access_token = &refreshed["data"]["token"];
+
+        let (status, body) = send(&app, refresh_request(stolen).to_request()).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"]["code"], "REFRESH_TOKEN_REUSED");
+        assert!(revoked.is_revoked(&session_id(&login["data"]["token"])));
+
+        // The legitimate client is logged out too
+        let (status, body) = send(
+            &app,
+            refresh_request(&refreshed["data"]["refreshToken"]).to_request(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"]["code"], "INVALID_REFRESH_TOKEN");
+
+        let (status, body) = send(
+            &app,
+            authorized(TestRequest::get().uri("/api/auth/sessions"), access_token).to_request(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"]["code"], "SESSION_REVOKED");
+    }
+
+    #[actix_web::test]
+    async fn lists_and_revokes_own_sessions() {
+        let pool = test_pool().await;
+        let revoked = web::Data::new(RevokedSessions::default());
+        let app = test_app!(pool, revoked);
+
+        let (_, laptop) = send(&app, login_request().to_request()).await;
+        let (_, phone) = send(&app, login_request().to_request()).await;
+        let (_, tablet) = send(&app, login_request().to_request()).await;
+        let laptop_token = &laptop["data"]["token"];
+        let phone_id = session_id(&phone["data"]["token"]);
+
+        let (status, body) = send(
+            &app,
+            authorized(TestRequest::get().uri("/api/auth/sessions"), laptop_token).to_request(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let sessions = body["data"].as_array().unwrap();
+        assert_eq!(sessions.len(), 3);
+        let current: Vec<&Value> = sessions.iter().filter(|s| s["current"] == true).collect();
+        assert_eq!(current.len(), 1);
+        assert_eq!(current[0]["id"], session_id(laptop_token));
+        assert!(sessions.iter().all(|s| s.get("token").is_none()));
+
+        let (status, _) = send(
+            &app,
+            authorized(
+                TestRequest::delete().uri(&format!("/api/auth/sessions/{}", phone_id)),
+                laptop_token,
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(revoked.is_revoked(&phone_id));
+
+        // Its access token stops working at once, its refresh token too
+        let (status, body) = send(
+            &app,
+            authorized(
+                TestRequest::get().uri("/api/auth/sessions"),
+                &phone["data"]["token"],
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"]["code"], "SESSION_REVOKED");
+        let (status, _) = send(
+            &app,
+            refresh_request(&phone["data"]["refreshToken"]).to_request(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _) = send(
+            &app,
+            authorized(
+                TestRequest::delete().uri(&format!("/api/auth/sessions/{}", phone_id)),
+                laptop_token,
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, body) = send(
+            &app,
+            authorized(
+                TestRequest::post().uri("/api/auth/sessions/revoke-others"),
+                laptop_token,
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["revoked"], 1);
+        assert!(revoked.is_revoked(&session_id(&tablet["data"]["token"])));
+        assert!(!revoked.is_revoked(&session_id(laptop_token)));
+    }
+
+    #[actix_web::test]
+    async fn logout_ends_only_the_current_session() {
+        let pool = test_pool().await;
+        let revoked = web::Data::new(RevokedSessions::default());
+        let app = test_app!(pool, revoked);
+
+        let (_, first) = send(&app, login_request().to_request()).await;
+        let (_, second) = send(&app, login_request().to_request()).await;
+
+        let (status, _) = send(
+            &app,
+            authorized(
+                TestRequest::post().uri("/api/auth/logout"),
+                &first["data"]["token"],
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(revoked.is_revoked(&session_id(&first["data"]["token"])));
+
+        let (status, body) = send(
+            &app,
+            authorized(
+                TestRequest::get().uri("/api/auth/sessions"),
+                &second["data"]["token"],
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    }
+
+    fn forgot_password_request(email: &str) -> TestRequest {
+        TestRequest::post()
+            .uri("/api/auth/forgot-password")
+            .set_json(serde_json::json!({ "email": email }))
     }
 
-    None
+    fn reset_password_request(token: &str, new_password: &str) -> TestRequest {
+        TestRequest::post()
+            .uri("/api/auth/reset-password")
+            .set_json(serde_json::json!({ "token": token, "newPassword": new_password }))
+    }
+
+    #[actix_web::test]
+    async fn a_reset_link_works_once() {
+        let pool = test_pool().await;
+        let revoked = web::Data::new(RevokedSessions::default());
+        let outbox = Arc::new(Outbox::default());
+        let app = test_app!(pool, revoked, outbox);
+        let (_, login) = send(&app, login_request().to_request()).await;
+
+        // Unknown accounts get the same answer and no email
+        let (status, unknown) = send(
+            &app,
+            forgot_password_request("nobody@company.com").to_request(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(outbox.0.lock().unwrap().is_empty());
+
+        let (status, known) = send(
+            &app,
+            forgot_password_request("user@company.com").to_request(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(known, unknown);
+        let token = outbox.reset_token();
+        assert_eq!(outbox.0.lock().unwrap()[0].to, "user@company.com");
+
+        // A rejected password does not spend the token
+        let (status, _) = send(&app, reset_password_request(&token, "short").to_request()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = send(
+            &app,
+            reset_password_request(&token, "a new password").to_request(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(revoked.is_revoked(&session_id(&login["data"]["token"])));
+        let password_hash: String =
+            sqlx::query_scalar("SELECT password_hash FROM users WHERE id = 'user'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert!(verify("a new password", &password_hash).unwrap());
+
+        let (status, body) = send(
+            &app,
+            reset_password_request(&token, "another password").to_request(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "INVALID_RESET_TOKEN");
+    }
+
+    #[actix_web::test]
+    async fn an_expired_reset_link_is_refused() {
+        let pool = test_pool().await;
+        let revoked = web::Data::new(RevokedSessions::default());
+        let outbox = Arc::new(Outbox::default());
+        let app = test_app!(pool, revoked, outbox);
+
+        send(
+            &app,
+            forgot_password_request("user@company.com").to_request(),
+        )
+        .await;
+        let expired = outbox.reset_token();
+        sqlx::query("UPDATE password_reset_tokens SET expires_at = datetime('now', '-1 minute')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let (status, body) = send(
+            &app,
+            reset_password_request(&expired, "a new password").to_request(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "INVALID_RESET_TOKEN");
+
+        // Asking again replaces the old link
+        send(
+            &app,
+            forgot_password_request("user@company.com").to_request(),
+        )
+        .await;
+        let fresh = outbox.reset_token();
+        assert_ne!(fresh, expired);
+        let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM password_reset_tokens")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored, 1);
+        let (status, _) = send(
+            &app,
+            reset_password_request(&fresh, "a new password").to_request(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
 }
@@ -0,0 +1,889 @@
+//! iCalendar feed for Outlook, Thunderbird and other calendar clients.
+//!
+//! Calendar clients subscribe to a URL and cannot send a bearer token, so the
+//! URL carries its own secret: `GET /api/calendar/{token}.ics`. Only the
+//! token's hash is stored, one per user; creating a new feed URL replaces the
+//! old one.
+//!
+//! The feed has the events the user created or attends, with recurring series
+//! as one `VEVENT` with its `RRULE` and edited occurrences as `RECURRENCE-ID`
+//! overrides; the user's active equipment bookings as all-day `VEVENT`s; and
+//! the deadlines of their tasks as `VTODO`s. Event times carry the event's
+//! time zone as a `TZID`, described by a `VTIMEZONE` with its current rules,
+//! or are in UTC.
+
+use actix_web::{HttpResponse, web};
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use serde::Deserialize;
+use sqlx::{FromRow, QueryBuilder, Sqlite, SqlitePool};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::error::AppError;
+use crate::escalation;
+use crate::filters::Filters;
+use crate::ical::{Component, Property};
+use crate::middleware::guards::AuthUser;
+use crate::models::events::Event;
+use crate::models::tasks::Task;
+use crate::recurrence::{self, RRule};
+use crate::routes::events::EVENT_COLUMNS;
+use crate::timezone::{self, TimeZone, YearlyChange};
+use crate::tokens;
+
+/// Domain part of every `UID` in the feed
+const UID_DOMAIN: &str = "it-engineering-dashboard";
+
+/// Single events, bookings and tasks older than this are left out
+const FEED_HISTORY_DAYS: i64 = 90;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FeedQuery {
+    department: Option<String>,
+    project_id: Option<String>,
+}
+
+/// An active booking with the booked equipment's name.
+#[derive(Debug, Clone, FromRow)]
+pub struct FeedBooking {
+    pub id: String,
+    pub equipment_name: String,
+    pub start_date: String,
+    pub end_date: String,
+    pub purpose: String,
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered ahead of the `/calendar` scope in `events`, which would
+    // otherwise claim these paths
+    cfg.service(
+        web::resource("/calendar/feed")
+            .route(web::get().to(get_feed_status))
+            .route(web::post().to(create_feed))
+            .route(web::delete().to(delete_feed)),
+    )
+    .service(web::resource("/calendar/{token}.ics").route(web::get().to(get_feed)));
+}
+
+/// Whether `path` is a feed URL, which authenticates with its own token.
+pub fn is_feed_path(path: &str) -> bool {
+    path.strip_prefix("/api/calendar/")
+        .and_then(|rest| rest.strip_suffix(".ics"))
+        .is_some_and(|token| !token.is_empty() && !token.contains('/'))
+}
+
+async fn get_feed_status(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let feed = sqlx::query_as::<_, (Option<String>, Option<String>)>(
+        "SELECT created_at, last_used_at FROM calendar_feed_tokens WHERE user_id = ?",
+    )
+    .bind(&auth.id)
+    .fetch_optional(pool.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "enabled": feed.is_some(),
+            "createdAt": feed.as_ref().and_then(|f| f.0.clone()),
+            "lastUsedAt": feed.as_ref().and_then(|f| f.1.clone())
+        }
+    })))
+}
+
+/// Creates the user's feed URL, replacing any previous one. The token is only
+/// ever returned here.
+async fn create_feed(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let token = tokens::new_opaque_token();
+
+    sqlx::query(
+        "INSERT INTO calendar_feed_tokens (user_id, token_hash) VALUES (?, ?)
+         ON CONFLICT (user_id) DO UPDATE SET token_hash = excluded.token_hash, created_at = datetime('now'), last_used_at = NULL",
+    )
+    .bind(&auth.id)
+    .bind(tokens::hash_opaque_token(&token))
+    .execute(pool.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "token": token,
+            "path": format!("/api/calendar/{}.ics", token)
+        }
+    })))
+}
+
+async fn delete_feed(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    sqlx::query("DELETE FROM calendar_feed_tokens WHERE user_id = ?")
+        .bind(&auth.id)
+        .execute(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Calendar feed disabled"
+    })))
+}
+
+async fn get_feed(
+    pool: web::Data<SqlitePool>,
+    path: web::Path<String>,
+    query: web::Query<FeedQuery>,
+) -> Result<HttpResponse, AppError> {
+    let token = path.into_inner();
+
+    // Unknown and disabled feeds look the same
+    let user_id = sqlx::query_scalar::<_, String>(
+        "SELECT u.id FROM calendar_feed_tokens f JOIN users u ON u.id = f.user_id
+         WHERE f.token_hash = ? AND u.is_active = 1",
+    )
+    .bind(tokens::hash_opaque_token(&token))
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFound("Calendar feed not found".to_string()))?;
+
+    sqlx::query("UPDATE calendar_feed_tokens SET last_used_at = datetime('now') WHERE user_id = ?")
+        .bind(&user_id)
+        .execute(pool.get_ref())
+        .await?;
+
+    let since = (Utc::now() - Duration::days(FEED_HISTORY_DAYS))
+        .format("%Y-%m-%d")
+        .to_string();
+
+    // Events and bookings belong to no project, so a project feed only has tasks
+    let (events, bookings) = if query.project_id.is_none() {
+        (
+            feed_events(pool.get_ref(), &user_id, &since, &query).await?,
+            feed_bookings(pool.get_ref(), &user_id, &since, &query).await?,
+        )
+    } else {
+        (Vec::new(), Vec::new())
+    };
+    let tasks = feed_tasks(pool.get_ref(), &user_id, &since, &query).await?;
+
+    let calendar = build_calendar(&events, &bookings, &tasks, Utc::now());
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/calendar; charset=utf-8")
+        .body(calendar.to_ics()))
+}
+
+async fn feed_events(
+    pool: &SqlitePool,
+    user_id: &str,
+    since: &str,
+    query: &FeedQuery,
+) -> Result<Vec<Event>, AppError> {
+    let mut filters = Filters::new();
+    filters
+        .condition(
+            "(e.created_by = ? OR EXISTS (SELECT 1 FROM event_attendees a WHERE a.event_id = e.id AND a.user_id = ?))",
+            vec![user_id.into(), user_id.into()],
+        )
+        // Series are kept whatever their start date
+        .condition(
+            "(e.event_date >= ? OR COALESCE(e.is_recurring, 0) = 1)",
+            vec![since.into()],
+        );
+    if let Some(ref department) = query.department {
+        filters.condition(
+            "(e.department = ? OR e.department = 'Both')",
+            vec![department.into()],
+        );
+    }
+
+    let mut sql = QueryBuilder::<Sqlite>::new(format!("SELECT {} FROM events e", EVENT_COLUMNS));
+    filters.push_where(&mut sql);
+    sql.push(" ORDER BY e.event_date, e.start_time");
+    Ok(sql.build_query_as::<Event>().fetch_all(pool).await?)
+}
+
+async fn feed_bookings(
+    pool: &SqlitePool,
+    user_id: &str,
+    since: &str,
+    query: &FeedQuery,
+) -> Result<Vec<FeedBooking>, AppError> {
+    let mut filters = Filters::new();
+    filters
+        .eq("b.user_id", user_id)
+        .raw("b.status = 'active'")
+        .condition("b.end_date >= ?", vec![since.into()]);
+    if let Some(ref department) = query.department {
+        filters.condition(
+            "(b.department = ? OR b.department = 'Both')",
+            vec![department.into()],
+        );
+    }
+
+    let mut sql = QueryBuilder::<Sqlite>::new(
+        "SELECT b.id, eq.name AS equipment_name, b.start_date, b.end_date, b.purpose
+         FROM bookings b JOIN equipment eq ON eq.id = b.equipment_id",
+    );
+    filters.push_where(&mut sql);
+    sql.push(" ORDER BY b.start_date");
+    Ok(sql.build_query_as::<FeedBooking>().fetch_all(pool).await?)
+}
+
+async fn feed_tasks(
+    pool: &SqlitePool,
+    user_id: &str,
+    since: &str,
+    query: &FeedQuery,
+) -> Result<Vec<Task>, AppError> {
+    let mut filters = Filters::new();
+    filters
+        .condition(
+            "(t.assignee_id = ? OR t.created_by = ? OR EXISTS (SELECT 1 FROM task_assignees ta WHERE ta.task_id = t.id AND ta.user_id = ?))",
+            vec![user_id.into(), user_id.into(), user_id.into()],
+        )
+        .condition("t.deadline >= ?", vec![since.into()])
+        .eq_opt("t.project_id", query.project_id.as_ref());
+    if let Some(ref department) = query.department {
+        filters.condition(
+            "(t.department = ? OR t.department = 'Both')",
+            vec![department.into()],
+        );
+    }
+
+    let mut sql = QueryBuilder::<Sqlite>::new("SELECT t.* FROM tasks t");
+    filters.push_where(&mut sql);
+    sql.push(" ORDER BY t.deadline");
+    Ok(sql.build_query_as::<Task>().fetch_all(pool).await?)
+}
+
+/// The feed as a `VCALENDAR`, stamped with `now`.
+pub fn build_calendar(
+    events: &[Event],
+    bookings: &[FeedBooking],
+    tasks: &[Task],
+    now: DateTime<Utc>,
+) -> Component {
+    let stamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let series: HashMap<&str, &Event> = events
+        .iter()
+        .filter(|e| e.is_series())
+        .map(|e| (e.id.as_str(), e))
+        .collect();
+
+    let mut calendar = Component::new("VCALENDAR");
+    calendar
+        .push(Property::new("VERSION", "2.0"))
+        .push(Property::new(
+            "PRODID",
+            "-//IT-Engineering Collaboration Dashboard//Calendar Feed//EN",
+        ))
+        .push(Property::new("CALSCALE", "GREGORIAN"))
+        .push(Property::new("METHOD", "PUBLISH"))
+        .push(Property::text("X-WR-CALNAME", "IT-Engineering Dashboard"));
+
+    let zones: BTreeMap<&str, TimeZone> = events
+        .iter()
+        .map(|e| (e.timezone.as_str(), event_zone(e)))
+        .collect();
+    for zone in zones.values() {
+        if zone.name() != timezone::UTC {
+            calendar.add(timezone_component(zone));
+        }
+    }
+
+    for event in events {
+        match event_component(event, &zones[event.timezone.as_str()], &series, &stamp) {
+            Some(component) => {
+                calendar.add(component);
+            }
+            None => log::warn!("Leaving event {} out of the calendar feed", event.id),
+        }
+    }
+    for booking in bookings {
+        if let Some(component) = booking_component(booking, &stamp) {
+            calendar.add(component);
+        }
+    }
+    for task in tasks {
+        if let Some(component) = task_component(task, &stamp) {
+            calendar.add(component);
+        }
+    }
+    calendar
+}
+
+/// Events in a zone this system no longer knows are given in UTC.
+fn event_zone(event: &Event) -> TimeZone {
+    TimeZone::named(&event.timezone).unwrap_or_else(|_| TimeZone::utc())
+}
+
+fn event_component(
+    event: &Event,
+    zone: &TimeZone,
+    series: &HashMap<&str, &Event>,
+    stamp: &str,
+) -> Option<Component> {
+    let date = recurrence::parse_date(&event.event_date).ok()?;
+    let start_time = parse_time(&event.start_time);
+
+    // An edited occurrence shares its series' UID and names the date it replaces
+    let parent = event
+        .parent_event_id
+        .as_deref()
+        .zip(event.original_date.as_deref())
+        .and_then(|(parent_id, original_date)| Some((*series.get(parent_id)?, original_date)));
+
+    let mut component = Component::new("VEVENT");
+    component
+        .push(Property::new(
+            "UID",
+            event_uid(parent.map_or(event, |(p, _)| p)),
+        ))
+        .push(Property::new("DTSTAMP", stamp));
+
+    if let Some((parent, original_date)) = parent {
+        let original_date = recurrence::parse_date(original_date).ok()?;
+        component.push(date_property(
+            "RECURRENCE-ID",
+            original_date,
+            parse_time(&parent.start_time),
+            zone,
+        ));
+    }
+
+    component.push(date_property("DTSTART", date, start_time, zone));
+    match (start_time, event.end_time.as_deref().and_then(parse_time)) {
+        (Some(start), Some(end)) => {
+            // An end before the start runs past midnight
+            let end_date = if end <= start { date.succ_opt()? } else { date };
+            component.push(date_property("DTEND", end_date, Some(end), zone));
+        }
+        (None, _) => {
+            component.push(date_property("DTEND", date.succ_opt()?, None, zone));
+        }
+        (Some(_), None) => {}
+    }
+
+    component.push(Property::text("SUMMARY", &event.title));
+    if let Some(ref description) = event.description {
+        component.push(Property::text("DESCRIPTION", description));
+    }
+    if let Some(ref location) = event.location {
+        component.push(Property::text("LOCATION", location));
+    }
+    if let Some(ref url) = event.meeting_url {
+        component.push(Property::new("URL", url.clone()));
+    }
+    component.push(Property::text("CATEGORIES", &event.event_type));
+
+    if event.is_series() {
+        let rule = RRule::parse(event.recurrence_rule.as_deref().unwrap_or_default()).ok()?;
+        component.push(Property::new("RRULE", ical_rrule(&rule, start_time, zone)));
+
+        let exdates = recurrence::parse_exdates(event.recurrence_exdates.as_deref());
+        if !exdates.is_empty() {
+            let values: Vec<String> = exdates
+                .iter()
+                .map(|d| date_property("EXDATE", *d, start_time, zone).value)
+                .collect();
+            let mut exdate = Property::new("EXDATE", values.join(","));
+            if start_time.is_none() {
+                exdate = exdate.param("VALUE", "DATE");
+            } else if zone.name() != timezone::UTC {
+                exdate = exdate.param("TZID", zone.name());
+            }
+            component.push(exdate);
+        }
+    }
+
+    Some(component)
+}
+
+fn booking_component(booking: &FeedBooking, stamp: &str) -> Option<Component> {
+    let start = recurrence::parse_date(&booking.start_date).ok()?;
+    // DTEND of an all-day event is exclusive
+    let end = recurrence::parse_date(&booking.end_date).ok()?.succ_opt()?;
+
+    let mut component = Component::new("VEVENT");
+    component
+        .push(Property::new(
+            "UID",
+            uid(&format!("booking-{}", booking.id)),
+        ))
+        .push(Property::new("DTSTAMP", stamp))
+        .push(all_day_property("DTSTART", start))
+        .push(all_day_property("DTEND", end))
+        .push(Property::text(
+            "SUMMARY",
+            &format!("Booking: {}", booking.equipment_name),
+        ))
+        .push(Property::text("DESCRIPTION", &booking.purpose))
+        .push(Property::text("CATEGORIES", "booking"))
+        .push(Property::new("TRANSP", "TRANSPARENT"));
+    Some(component)
+}
+
+fn task_component(task: &Task, stamp: &str) -> Option<Component> {
+    let due = due_property(&task.deadline)?;
+    let (status, priority) = (
+        match task.status.as_str() {
+            "completed" => "COMPLETED",
+            "in-progress" => "IN-PROCESS",
+            _ => "NEEDS-ACTION",
+        },
+        match task.urgency.as_str() {
+            "urgent" => "1",
+            "high" => "3",
+            "medium" => "5",
+            _ => "7",
+        },
+    );
+
+    let mut component = Component::new("VTODO");
+    component
+        .push(Property::new("UID", uid(&format!("task-{}", task.id))))
+        .push(Property::new("DTSTAMP", stamp))
+        .push(due)
+        .push(Property::text("SUMMARY", &task.title))
+        .push(Property::new("STATUS", status))
+        .push(Property::new("PRIORITY", priority));
+    if let Some(ref description) = task.description {
+        component.push(Property::text("DESCRIPTION", description));
+    }
+    Some(component)
+}
+
+/// `RRULE` value for a series starting at `start_time`. `UNTIL` must have the
+/// same value type as `DTSTART`, and be in UTC for timed series, so those end
+/// at the start of the last day's occurrence.
+fn ical_rrule(rule: &RRule, start_time: Option<NaiveTime>, zone: &TimeZone) -> String {
+    let mut value = RRule {
+        until: None,
+        ..rule.clone()
+    }
+    .to_string();
+    if let Some(until) = rule.until {
+        value.push_str(";UNTIL=");
+        match start_time {
+            Some(time) => value.push_str(
+                &zone
+                    .to_utc(until.and_time(time))
+                    .format("%Y%m%dT%H%M%SZ")
+                    .to_string(),
+            ),
+            None => value.push_str(&until.format("%Y%m%d").to_string()),
+        }
+    }
+    value
+}
+
+/// A `DATE-TIME` property in `zone`, or a `DATE` one without a time.
+fn date_property(
+    name: &str,
+    date: NaiveDate,
+    time: Option<NaiveTime>,
+    zone: &TimeZone,
+) -> Property {
+    let Some(time) = time else {
+        return all_day_property(name, date);
+    };
+    let local = date.and_time(time).format("%Y%m%dT%H%M%S").to_string();
+    if zone.name() == timezone::UTC {
+        Property::new(name, local + "Z")
+    } else {
+        Property::new(name, local).param("TZID", zone.name())
+    }
+}
+
+/// `DUE` of a task: a `DATE` for a date-only deadline, otherwise the UTC
+/// instant, as `escalation` reads it.
+fn due_property(deadline: &str) -> Option<Property> {
+    if let Ok(date) = NaiveDate::parse_from_str(deadline.trim(), "%Y-%m-%d") {
+        return Some(all_day_property("DUE", date));
+    }
+    match escalation::deadline_instant(deadline, &TimeZone::utc()) {
+        Some(at) => Some(Property::new(
+            "DUE",
+            at.format("%Y%m%dT%H%M%SZ").to_string(),
+        )),
+        None => Some(all_day_property(
+            "DUE",
+            recurrence::parse_date(deadline).ok()?,
+        )),
+    }
+}
+
+fn all_day_property(name: &str, date: NaiveDate) -> Property {
+    Property::new(name, date.format("%Y%m%d").to_string()).param("VALUE", "DATE")
+}
+
+/// A `VTIMEZONE` with the zone's current rules, in effect since 1970.
+fn timezone_component(zone: &TimeZone) -> Component {
+    let rules = zone.current_rules();
+    let mut component = Component::new("VTIMEZONE");
+    component.push(Property::new("TZID", zone.name()));
+
+    let Some(dst) = rules.daylight_saving else {
+        component.add(observance(
+            "STANDARD",
+            "19700101T000000".to_string(),
+            None,
+            rules.std_offset,
+            rules.std_offset,
+        ));
+        return component;
+    };
+    component
+        .add(observance(
+            "STANDARD",
+            dst.end.local_time(1970).format("%Y%m%dT%H%M%S").to_string(),
+            Some(&dst.end),
+            dst.offset,
+            rules.std_offset,
+        ))
+        .add(observance(
+            "DAYLIGHT",
+            dst.start
+                .local_time(1970)
+                .format("%Y%m%dT%H%M%S")
+                .to_string(),
+            Some(&dst.start),
+            rules.std_offset,
+            dst.offset,
+        ));
+    component
+}
+
+fn observance(
+    name: &str,
+    start: String,
+    change: Option<&YearlyChange>,
+    offset_from: i32,
+    offset_to: i32,
+) -> Component {
+    let mut component = Component::new(name);
+    component
+        .push(Property::new("DTSTART", start))
+        .push(Property::new("TZOFFSETFROM", utc_offset(offset_from)))
+        .push(Property::new("TZOFFSETTO", utc_offset(offset_to)));
+    if let Some(change) = change {
+        let week = if change.week == 5 {
+            -1
+        } else {
+            change.week as i32
+        };
+        let weekday = recurrence::weekday_code(
+            chrono::Weekday::try_from(((change.weekday + 6) % 7) as u8)
+                .unwrap_or(chrono::Weekday::Sun),
+        );
+        component.push(Property::new(
+            "RRULE",
+            format!(
+                "FREQ=YEARLY;BYMONTH={};BYDAY={}{}",
+                change.month, week, weekday
+            ),
+        ));
+    }
+    component
+}
+
+/// `+HHMM` or `-HHMM`, with seconds if the offset has any.
+fn utc_offset(seconds: i32) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.abs();
+    let mut value = format!("{}{:02}{:02}", sign, seconds / 3600, seconds / 60 % 60);
+    if seconds % 60 != 0 {
+        value.push_str(&format!("{:02}", seconds % 60));
+    }
+    value
+}
+
+/// Parses `HH:MM` or `HH:MM:SS`.
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M"))
+        .ok()
+}
+
+fn uid(id: &str) -> String {
+    format!("{}@{}", id, UID_DOMAIN)
+}
+
+/// Imported events keep the `UID` they were imported with.
+fn event_uid(event: &Event) -> String {
+    event.ical_uid.clone().unwrap_or_else(|| uid(&event.id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn event(id: &str, date: &str, start: &str, end: Option<&str>) -> Event {
+        let mut event = Event::new(
+            "Team standup".to_string(),
+            Some("Daily sync; bring blockers, not status".to_string()),
+            "meeting".to_string(),
+            date.to_string(),
+            start.to_string(),
+            end.map(str::to_string),
+            Some("Room 3".to_string()),
+            Some("https://teams.example.com/standup".to_string()),
+            "user-1".to_string(),
+            Some("IT".to_string()),
+            false,
+            None,
+        );
+        event.id = id.to_string();
+        event
+    }
+
+    fn series() -> Event {
+        let mut series = event("series-1", "2026-01-05", "09:00", Some("09:15"));
+        series.is_recurring = true;
+        series.recurrence_rule = Some("FREQ=WEEKLY;BYDAY=MO,WE;UNTIL=20260227".to_string());
+        series.recurrence_exdates = Some("2026-01-07,2026-01-19".to_string());
+        series
+    }
+
+    fn task(id: &str, deadline: &str, urgency: &str, status: &str) -> Task {
+        Task {
+            id: id.to_string(),
+            title: "Order sensors".to_string(),
+            description: Some("Lab 3, 12 units".to_string()),
+            urgency: urgency.to_string(),
+            status: status.to_string(),
+            department: "Engineering".to_string(),
+            project_id: None,
+            assignee_id: Some("user-1".to_string()),
+            created_by: "user-1".to_string(),
+            deadline: deadline.to_string(),
+            completed_at: None,
+            created_at: None,
+            updated_at: None,
+            is_completed: Some(status == "completed"),
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 2, 8, 30, 0).unwrap()
+    }
+
+    /// Builds the feed, serializes it and parses it back.
+    fn round_trip(events: &[Event], bookings: &[FeedBooking], tasks: &[Task]) -> Component {
+        let built = build_calendar(events, bookings, tasks, now());
+        let parsed = Component::parse(&built.to_ics()).unwrap();
+        assert_eq!(parsed, built);
+        parsed
+    }
+
+    fn value<'a>(component: &'a Component, name: &str) -> &'a str {
+        &component.property(name).unwrap().value
+    }
+
+    #[test]
+    fn single_event_round_trips() {
+        let calendar = round_trip(
+            &[event("event-1", "2026-01-08", "14:00", Some("15:30"))],
+            &[],
+            &[],
+        );
+        assert_eq!(value(&calendar, "VERSION"), "2.0");
+
+        let vevent = calendar.components("VEVENT").next().unwrap();
+        assert_eq!(value(vevent, "UID"), "event-1@it-engineering-dashboard");
+        assert_eq!(value(vevent, "DTSTAMP"), "20260102T083000Z");
+        assert_eq!(value(vevent, "DTSTART"), "20260108T140000Z");
+        assert_eq!(value(vevent, "DTEND"), "20260108T153000Z");
+        assert_eq!(
+            vevent.property("DESCRIPTION").unwrap().text_value(),
+            "Daily sync; bring blockers, not status"
+        );
+        assert_eq!(value(vevent, "URL"), "https://teams.example.com/standup");
+        assert!(vevent.property("RRULE").is_none());
+    }
+
+    #[test]
+    fn overnight_event_ends_next_day() {
+        let calendar = round_trip(
+            &[event("event-1", "2026-01-08", "22:00", Some("02:00"))],
+            &[],
+            &[],
+        );
+        let vevent = calendar.components("VEVENT").next().unwrap();
+        assert_eq!(value(vevent, "DTEND"), "20260109T020000Z");
+    }
+
+    #[test]
+    fn series_round_trips_with_rule_and_exdates() {
+        let series = series();
+        let calendar = round_trip(std::slice::from_ref(&series), &[], &[]);
+        let vevent = calendar.components("VEVENT").next().unwrap();
+
+        let rrule = value(vevent, "RRULE");
+        assert_eq!(rrule, "FREQ=WEEKLY;BYDAY=MO,WE;UNTIL=20260227T090000Z");
+        assert_eq!(
+            RRule::parse(rrule).unwrap(),
+            RRule::parse(series.recurrence_rule.as_deref().unwrap()).unwrap()
+        );
+        assert_eq!(value(vevent, "EXDATE"), "20260107T090000Z,20260119T090000Z");
+
+        // A client expanding the parsed rule sees the same dates as the dashboard
+        let start = recurrence::parse_date(&series.event_date).unwrap();
+        let exdates: Vec<NaiveDate> = value(vevent, "EXDATE")
+            .split(',')
+            .map(|d| recurrence::parse_ical_date(d).unwrap())
+            .collect();
+        let window = (start, NaiveDate::from_ymd_opt(2026, 12, 31).unwrap());
+        assert_eq!(
+            RRule::parse(rrule)
+                .unwrap()
+                .occurrences(start, &exdates, window.0, window.1),
+            RRule::parse(series.recurrence_rule.as_deref().unwrap())
+                .unwrap()
+                .occurrences(
+                    start,
+                    &recurrence::parse_exdates(series.recurrence_exdates.as_deref()),
+                    window.0,
+                    window.1
+                )
+        );
+    }
+
+    #[test]
+    fn edited_occurrence_overrides_its_series() {
+        let mut moved = event("override-1", "2026-01-12", "10:00", Some("10:30"));
+        moved.parent_event_id = Some("series-1".to_string());
+        moved.original_date = Some("2026-01-12".to_string());
+
+        let calendar = round_trip(&[series(), moved], &[], &[]);
+        let vevents: Vec<&Component> = calendar.components("VEVENT").collect();
+        assert_eq!(vevents.len(), 2);
+        assert_eq!(value(vevents[1], "UID"), value(vevents[0], "UID"));
+        // Identifies the occurrence by its original start, not the new one
+        assert_eq!(value(vevents[1], "RECURRENCE-ID"), "20260112T090000Z");
+        assert_eq!(value(vevents[1], "DTSTART"), "20260112T100000Z");
+        assert!(vevents[1].property("RRULE").is_none());
+    }
+
+    #[test]
+    fn zoned_series_names_its_time_zone() {
+        let mut series = series();
+        series.timezone = "Europe/Berlin".to_string();
+        let calendar = round_trip(&[series], &[], &[]);
+
+        let vtimezone = calendar.components("VTIMEZONE").next().unwrap();
+        assert_eq!(value(vtimezone, "TZID"), "Europe/Berlin");
+        let standard = vtimezone.components("STANDARD").next().unwrap();
+        assert_eq!(value(standard, "TZOFFSETFROM"), "+0200");
+        assert_eq!(value(standard, "TZOFFSETTO"), "+0100");
+        assert_eq!(
+            value(standard, "RRULE"),
+            "FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU"
+        );
+        let daylight = vtimezone.components("DAYLIGHT").next().unwrap();
+        assert_eq!(value(daylight, "DTSTART"), "19700329T020000");
+        assert_eq!(value(daylight, "RRULE"), "FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU");
+
+        let vevent = calendar.components("VEVENT").next().unwrap();
+        let start = vevent.property("DTSTART").unwrap();
+        assert_eq!(
+            (start.value.as_str(), start.get_param("TZID")),
+            ("20260105T090000", Some("Europe/Berlin"))
+        );
+        assert_eq!(
+            vevent.property("EXDATE").unwrap().get_param("TZID"),
+            Some("Europe/Berlin")
+        );
+        // 09:00 in Berlin winter time
+        assert_eq!(
+            value(vevent, "RRULE"),
+            "FREQ=WEEKLY;BYDAY=MO,WE;UNTIL=20260227T080000Z"
+        );
+    }
+
+    #[test]
+    fn bookings_are_all_day_events() {
+        let booking = FeedBooking {
+            id: "booking-1".to_string(),
+            equipment_name: "Thermal camera".to_string(),
+            start_date: "2026-01-10".to_string(),
+            end_date: "2026-01-12".to_string(),
+            purpose: "Site survey, Block B".to_string(),
+        };
+        let calendar = round_trip(&[], &[booking], &[]);
+        let vevent = calendar.components("VEVENT").next().unwrap();
+
+        assert_eq!(
+            value(vevent, "UID"),
+            "booking-booking-1@it-engineering-dashboard"
+        );
+        let start = vevent.property("DTSTART").unwrap();
+        assert_eq!(
+            (start.value.as_str(), start.get_param("VALUE")),
+            ("20260110", Some("DATE"))
+        );
+        assert_eq!(value(vevent, "DTEND"), "20260113");
+        assert_eq!(
+            vevent.property("SUMMARY").unwrap().text_value(),
+            "Booking: Thermal camera"
+        );
+    }
+
+    #[test]
+    fn tasks_are_todos_with_due_dates() {
+        let calendar = round_trip(
+            &[],
+            &[],
+            &[
+                task("task-1", "2026-01-15", "urgent", "in-progress"),
+                task("task-2", "2026-01-20 09:00:00", "low", "completed"),
+            ],
+        );
+        let todos: Vec<&Component> = calendar.components("VTODO").collect();
+        assert_eq!(todos.len(), 2);
+
+        let due = todos[0].property("DUE").unwrap();
+        assert_eq!(
+            (due.value.as_str(), due.get_param("VALUE")),
+            ("20260115", Some("DATE"))
+        );
+        assert_eq!(value(todos[0], "PRIORITY"), "1");
+        assert_eq!(value(todos[0], "STATUS"), "IN-PROCESS");
+        assert_eq!(value(todos[1], "STATUS"), "COMPLETED");
+    }
+
+    #[test]
+    fn timed_deadlines_are_due_in_utc() {
+        let calendar = round_trip(
+            &[],
+            &[],
+            &[
+                task("task-1", "2026-01-20 09:00:00", "low", "pending"),
+                task("task-2", "2026-01-20T17:30:00+08:00", "low", "pending"),
+            ],
+        );
+        let todos: Vec<&Component> = calendar.components("VTODO").collect();
+
+        let due = todos[0].property("DUE").unwrap();
+        assert_eq!(
+            (due.value.as_str(), due.get_param("VALUE")),
+            ("20260120T090000Z", None)
+        );
+        assert_eq!(value(todos[1], "DUE"), "20260120T093000Z");
+    }
+
+    #[test]
+    fn feed_paths() {
+        assert!(is_feed_path("/api/calendar/abc123.ics"));
+        assert!(!is_feed_path("/api/calendar/.ics"));
+        assert!(!is_feed_path("/api/calendar/events/x.ics"));
+        assert!(!is_feed_path("/api/calendar/feed"));
+    }
+}
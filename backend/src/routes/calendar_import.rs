@@ -0,0 +1,843 @@
+//! Bulk event creation from an iCalendar (.ics) file.
+//!
+//! Each `VEVENT` becomes an event, with `RRULE` and `EXDATE` kept as its
+//! recurrence and `ATTENDEE`s matched to users by email. A `VEVENT` with a
+//! `RECURRENCE-ID` edits (or, when cancelled, removes) one occurrence of the
+//! series with the same `UID`.
+//!
+//! Events remember their `UID`, so importing the same file again updates them
+//! rather than adding copies. A dry run goes through the same steps inside a
+//! transaction that is rolled back, so the preview matches the real import.
+//!
+//! Events keep the time zone of their `DTSTART`: its `TZID`, or UTC for a
+//! `Z` value. Other times are converted into that zone. Floating times and
+//! zones this system does not know are taken as written, in the importing
+//! user's zone.
+
+use actix_web::{HttpResponse, web};
+use chrono::{Duration, NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::ical::Component;
+use crate::middleware::guards::{AuthUser, Member, RequireRole};
+use crate::models::events::Event;
+use crate::recurrence::{self, RRule};
+use crate::routes::events::{EVENT_COLUMNS, copy_attendees, insert_event, load_event, save_event};
+use crate::timezone::{self, TimeZone};
+
+const DEPARTMENTS: [&str; 3] = ["IT", "Engineering", "Both"];
+const EVENT_TYPES: [&str; 3] = ["meeting", "deadline", "delivery"];
+
+/// Used when `SUMMARY` is missing or empty
+const UNTITLED: &str = "(No title)";
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportQuery {
+    dry_run: Option<bool>,
+    department: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum ImportAction {
+    Create,
+    Update,
+    Skip,
+}
+
+/// What happened to one `VEVENT`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportResult {
+    uid: Option<String>,
+    title: Option<String>,
+    action: ImportAction,
+    /// `None` for events a dry run would create
+    event_id: Option<String>,
+    event_date: Option<String>,
+    recurrence_rule: Option<String>,
+    /// For a `VEVENT` with `RECURRENCE-ID`: the occurrence it changes
+    occurrence_date: Option<String>,
+    cancelled: bool,
+    attendees: Vec<String>,
+    unmatched_attendees: Vec<String>,
+    /// Why the event was skipped
+    reason: Option<String>,
+}
+
+/// A `VEVENT` mapped onto event fields.
+#[derive(Debug, Clone, PartialEq)]
+struct ImportedEvent {
+    uid: Option<String>,
+    title: String,
+    description: Option<String>,
+    event_type: String,
+    date: NaiveDate,
+    start_time: String,
+    end_time: Option<String>,
+    location: Option<String>,
+    url: Option<String>,
+    /// `None` for floating times or an unknown `TZID`
+    time_zone: Option<String>,
+    recurrence_rule: Option<String>,
+    exdates: Vec<NaiveDate>,
+    recurrence_id: Option<NaiveDate>,
+    cancelled: bool,
+    /// Lower-case email and attendee status
+    attendees: Vec<(String, String)>,
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered ahead of the `/calendar` scope in `events`, like the feed
+    cfg.service(web::resource("/calendar/events/import").route(web::post().to(import_events)));
+}
+
+async fn import_events(
+    pool: web::Data<SqlitePool>,
+    auth: RequireRole<Member>,
+    query: web::Query<ImportQuery>,
+    body: String,
+) -> Result<HttpResponse, AppError> {
+    let dry_run = query.dry_run.unwrap_or(false);
+    let department = query
+        .department
+        .clone()
+        .unwrap_or_else(|| auth.department.clone());
+    if !DEPARTMENTS.contains(&department.as_str()) {
+        return Err(AppError::Validation(format!(
+            "department must be one of {}",
+            DEPARTMENTS.join(", ")
+        )));
+    }
+
+    let calendar = Component::parse(&body)?;
+    if calendar.name != "VCALENDAR" {
+        return Err(AppError::Validation(format!(
+            "Invalid iCalendar data: expected VCALENDAR, got {}",
+            calendar.name
+        ))
+        .with_code("INVALID_ICALENDAR"));
+    }
+    let vevents: Vec<&Component> = calendar.components("VEVENT").collect();
+    if vevents.is_empty() {
+        return Err(
+            AppError::Validation("Invalid iCalendar data: no VEVENT found".to_string())
+                .with_code("INVALID_ICALENDAR"),
+        );
+    }
+
+    let parsed: Vec<(&Component, Result<ImportedEvent, String>)> = vevents
+        .into_iter()
+        .map(|vevent| (vevent, ImportedEvent::from_vevent(vevent)))
+        .collect();
+    let emails: Vec<&str> = parsed
+        .iter()
+        .filter_map(|(_, item)| item.as_ref().ok())
+        .flat_map(|item| item.attendees.iter().map(|(email, _)| email.as_str()))
+        .collect();
+    let users = users_by_email(pool.get_ref(), &emails).await?;
+
+    let mut tx = pool.begin().await?;
+    let mut results = Vec::new();
+
+    // Series and single events first, so occurrences find their series
+    let (occurrences, series): (Vec<_>, Vec<_>) = parsed
+        .iter()
+        .partition(|(_, item)| item.as_ref().is_ok_and(|item| item.recurrence_id.is_some()));
+    for (vevent, item) in series.into_iter().chain(occurrences) {
+        let item = match item {
+            Ok(item) => item,
+            Err(reason) => {
+                results.push(ImportResult::skipped_invalid(vevent, reason));
+                continue;
+            }
+        };
+        let outcome = if item.recurrence_id.is_some() {
+            import_occurrence(&mut tx, &auth, item, &users).await?
+        } else {
+            import_event(&mut tx, &auth, &department, item, &users).await?
+        };
+        results.push(ImportResult::new(item, outcome, &users));
+    }
+
+    if dry_run {
+        tx.rollback().await?;
+        // Events created in the rolled back transaction do not exist
+        let created: HashSet<String> = results
+            .iter()
+            .filter(|r| r.action == ImportAction::Create)
+            .filter_map(|r| r.event_id.clone())
+            .collect();
+        for result in &mut results {
+            result.event_id.take_if(|id| created.contains(id));
+        }
+    } else {
+        tx.commit().await?;
+    }
+
+    let count = |action| results.iter().filter(|r| r.action == action).count();
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "dryRun": dry_run,
+            "summary": {
+                "created": count(ImportAction::Create),
+                "updated": count(ImportAction::Update),
+                "skipped": count(ImportAction::Skip)
+            },
+            "events": results
+        }
+    })))
+}
+
+/// The imported or updated row, or why the `VEVENT` was skipped.
+type Outcome = Result<(ImportAction, Event), String>;
+
+/// Creates or updates a single event or series by its `UID`.
+async fn import_event(
+    conn: &mut SqliteConnection,
+    auth: &AuthUser,
+    department: &str,
+    item: &ImportedEvent,
+    users: &HashMap<String, String>,
+) -> Result<Outcome, AppError> {
+    if item.cancelled {
+        return Ok(Err("The event is cancelled".to_string()));
+    }
+
+    let existing = match item.uid {
+        Some(ref uid) => find_by_uid(conn, uid).await?,
+        None => None,
+    };
+    let (action, mut event) = match existing {
+        Some(event) => {
+            if !auth.is_admin() && event.created_by != auth.id {
+                return Ok(Err(
+                    "An event with this UID was imported by another user".to_string()
+                ));
+            }
+            (ImportAction::Update, event)
+        }
+        None => {
+            let mut event = Event::new(
+                item.title.clone(),
+                None,
+                item.event_type.clone(),
+                String::new(),
+                String::new(),
+                None,
+                None,
+                None,
+                auth.id.clone(),
+                Some(department.to_string()),
+                false,
+                None,
+            );
+            event.ical_uid = item.uid.clone();
+            event.timezone = timezone::user_time_zone(&mut *conn, &auth.id)
+                .await?
+                .name()
+                .to_string();
+            (ImportAction::Create, event)
+        }
+    };
+
+    item.apply(&mut event);
+    event.is_recurring = item.recurrence_rule.is_some();
+    event.recurrence_rule = item.recurrence_rule.clone();
+    event.recurrence_exdates = item
+        .recurrence_rule
+        .as_ref()
+        .and_then(|_| recurrence::format_exdates(&item.exdates));
+
+    match action {
+        ImportAction::Create => insert_event(conn, &event).await?,
+        _ => save_event(conn, &event).await?,
+    }
+    add_attendees(conn, &event.id, item, users).await?;
+    Ok(Ok((action, event)))
+}
+
+/// Edits or cancels one occurrence of an imported series.
+async fn import_occurrence(
+    conn: &mut SqliteConnection,
+    auth: &AuthUser,
+    item: &ImportedEvent,
+    users: &HashMap<String, String>,
+) -> Result<Outcome, AppError> {
+    let (Some(uid), Some(date)) = (&item.uid, item.recurrence_id) else {
+        return Ok(Err("RECURRENCE-ID without a UID".to_string()));
+    };
+    let Some(mut series) = find_by_uid(conn, uid).await?.filter(|e| e.is_series()) else {
+        return Ok(Err("No recurring event with this UID".to_string()));
+    };
+    if !auth.is_admin() && series.created_by != auth.id {
+        return Ok(Err(
+            "An event with this UID was imported by another user".to_string()
+        ));
+    }
+
+    let date = date.format("%Y-%m-%d").to_string();
+    let existing = sqlx::query_as::<_, Event>(&format!(
+        "SELECT {} FROM events e WHERE e.parent_event_id = ? AND e.original_date = ?",
+        EVENT_COLUMNS
+    ))
+    .bind(&series.id)
+    .bind(&date)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if item.cancelled {
+        let mut exdates = recurrence::parse_exdates(series.recurrence_exdates.as_deref());
+        exdates.extend(item.recurrence_id);
+        series.recurrence_exdates = recurrence::format_exdates(&exdates);
+        save_event(conn, &series).await?;
+        if let Some(ref occurrence) = existing {
+            sqlx::query("DELETE FROM events WHERE id = ?")
+                .bind(&occurrence.id)
+                .execute(&mut *conn)
+                .await?;
+        }
+        return Ok(Ok((ImportAction::Update, series)));
+    }
+
+    let (action, mut occurrence) = match existing {
+        Some(occurrence) => (ImportAction::Update, occurrence),
+        None => {
+            let mut occurrence = series.clone();
+            occurrence.id = Uuid::new_v4().to_string();
+            occurrence.is_recurring = false;
+            occurrence.recurrence_rule = None;
+            occurrence.recurrence_exdates = None;
+            occurrence.parent_event_id = Some(series.id.clone());
+            occurrence.original_date = Some(date);
+            occurrence.ical_uid = None;
+            (ImportAction::Create, occurrence)
+        }
+    };
+    item.apply(&mut occurrence);
+
+    match action {
+        ImportAction::Create => {
+            insert_event(conn, &occurrence).await?;
+            copy_attendees(conn, &series.id, &occurrence.id).await?;
+        }
+        _ => save_event(conn, &occurrence).await?,
+    }
+    add_attendees(conn, &occurrence.id, item, users).await?;
+    Ok(Ok((action, occurrence)))
+}
+
+/// The series or single event imported with `uid`.
+async fn find_by_uid(conn: &mut SqliteConnection, uid: &str) -> Result<Option<Event>, AppError> {
+    let id = sqlx::query_scalar::<_, String>("SELECT id FROM events WHERE ical_uid = ?")
+        .bind(uid)
+        .fetch_optional(&mut *conn)
+        .await?;
+    match id {
+        Some(id) => Ok(Some(load_event(conn, &id).await?)),
+        None => Ok(None),
+    }
+}
+
+/// Adds the matched attendees, or updates their status if already invited.
+async fn add_attendees(
+    conn: &mut SqliteConnection,
+    event_id: &str,
+    item: &ImportedEvent,
+    users: &HashMap<String, String>,
+) -> Result<(), AppError> {
+    for (email, status) in &item.attendees {
+        let Some(user_id) = users.get(email) else {
+            continue;
+        };
+        sqlx::query(
+            "INSERT INTO event_attendees (id, event_id, user_id, status) VALUES (?, ?, ?, ?)
+             ON CONFLICT (event_id, user_id) DO UPDATE SET status = excluded.status",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(event_id)
+        .bind(user_id)
+        .bind(status)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Active users by lower-case email.
+async fn users_by_email(
+    pool: &SqlitePool,
+    emails: &[&str],
+) -> Result<HashMap<String, String>, AppError> {
+    if emails.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let mut sql = QueryBuilder::<Sqlite>::new(
+        "SELECT lower(email), id FROM users WHERE COALESCE(is_active, 1) = 1 AND lower(email) IN (",
+    );
+    let mut values = sql.separated(", ");
+    for email in emails {
+        values.push_bind(email.to_string());
+    }
+    sql.push(")");
+
+    Ok(sql
+        .build_query_as::<(String, String)>()
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect())
+}
+
+impl ImportResult {
+    fn new(item: &ImportedEvent, outcome: Outcome, users: &HashMap<String, String>) -> Self {
+        let (attendees, unmatched_attendees) = item
+            .attendees
+            .iter()
+            .map(|(email, _)| email.clone())
+            .partition(|email| users.contains_key(email));
+        let mut result = ImportResult {
+            uid: item.uid.clone(),
+            title: Some(item.title.clone()),
+            action: ImportAction::Skip,
+            event_id: None,
+            event_date: Some(item.date.format("%Y-%m-%d").to_string()),
+            recurrence_rule: item.recurrence_rule.clone(),
+            occurrence_date: item.recurrence_id.map(|d| d.format("%Y-%m-%d").to_string()),
+            cancelled: item.cancelled,
+            attendees,
+            unmatched_attendees,
+            reason: None,
+        };
+        match outcome {
+            Ok((action, event)) => {
+                result.action = action;
+                result.event_id = Some(event.id);
+            }
+            Err(reason) => result.reason = Some(reason),
+        }
+        result
+    }
+
+    fn skipped_invalid(vevent: &Component, reason: &str) -> Self {
+        ImportResult {
+            uid: vevent.property("UID").map(|p| p.value.clone()),
+            title: vevent.property("SUMMARY").map(|p| p.text_value()),
+            action: ImportAction::Skip,
+            event_id: None,
+            event_date: None,
+            recurrence_rule: None,
+            occurrence_date: None,
+            cancelled: false,
+            attendees: Vec::new(),
+            unmatched_attendees: Vec::new(),
+            reason: Some(reason.to_string()),
+        }
+    }
+}
+
+impl ImportedEvent {
+    fn from_vevent(vevent: &Component) -> Result<Self, String> {
+        let text = |name: &str| {
+            vevent
+                .property(name)
+                .map(|p| p.text_value().trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+
+        let start = vevent
+            .property("DTSTART")
+            .ok_or_else(|| "DTSTART is missing".to_string())?;
+        let (date, start_time) = parse_date_time(&start.value)
+            .ok_or_else(|| format!("Invalid DTSTART `{}`", start.value))?;
+        let zone = value_zone(&start.value, start.get_param("TZID"));
+        let zone = zone.as_ref();
+
+        let end_time = match (vevent.property("DTEND"), vevent.property("DURATION")) {
+            (Some(end), _) => {
+                parse_local(&end.value, end.get_param("TZID"), zone)
+                    .ok_or_else(|| format!("Invalid DTEND `{}`", end.value))?
+                    .1
+            }
+            (None, Some(duration)) => {
+                let duration = parse_duration(&duration.value)
+                    .ok_or_else(|| format!("Invalid DURATION `{}`", duration.value))?;
+                start_time
+                    .map(|t| date.and_time(t) + duration)
+                    .map(|end| end.time())
+            }
+            (None, None) => None,
+        };
+
+        let recurrence_rule = vevent
+            .property("RRULE")
+            .map(|rule| {
+                let mut parsed = RRule::parse(&rule.value)?;
+                // A UTC `UNTIL` may fall on another day in the event's zone
+                let until = rule.value.split(';').find_map(|part| {
+                    let (name, value) = part.split_once('=')?;
+                    name.trim().eq_ignore_ascii_case("UNTIL").then_some(value)
+                });
+                if let Some((until, _)) = until.and_then(|u| parse_local(u, None, zone)) {
+                    parsed.until = Some(until);
+                }
+                Ok::<_, AppError>(parsed.to_string())
+            })
+            .transpose()
+            .map_err(|e| e.message())?;
+
+        let exdates = vevent
+            .properties("EXDATE")
+            .flat_map(|p| p.value.split(',').map(|d| (d.trim(), p.get_param("TZID"))))
+            .map(|(d, tzid)| local_date(d, tzid, zone))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let recurrence_id = vevent
+            .property("RECURRENCE-ID")
+            .map(|p| local_date(&p.value, p.get_param("TZID"), zone))
+            .transpose()?;
+
+        let event_type = vevent
+            .properties("CATEGORIES")
+            .flat_map(|p| p.value.split(','))
+            .map(|c| c.trim().to_ascii_lowercase())
+            .find(|c| EVENT_TYPES.contains(&c.as_str()))
+            .unwrap_or_else(|| "meeting".to_string());
+
+        let attendees = vevent
+            .properties("ATTENDEE")
+            .filter_map(|p| {
+                let email = p
+                    .value
+                    .get(..7)?
+                    .eq_ignore_ascii_case("mailto:")
+                    .then(|| &p.value[7..])?;
+                let status = match p.get_param("PARTSTAT").map(str::to_ascii_uppercase) {
+                    Some(s) if s == "ACCEPTED" => "accepted",
+                    Some(s) if s == "DECLINED" => "declined",
+                    _ => "pending",
+                };
+                Some((email.trim().to_ascii_lowercase(), status.to_string()))
+            })
+            .collect();
+
+        Ok(ImportedEvent {
+            uid: vevent
+                .property("UID")
+                .map(|p| p.value.trim().to_string())
+                .filter(|uid| !uid.is_empty()),
+            title: text("SUMMARY").unwrap_or_else(|| UNTITLED.to_string()),
+            description: text("DESCRIPTION"),
+            event_type,
+            date,
+            start_time: format_time(start_time.unwrap_or(NaiveTime::MIN)),
+            end_time: end_time.map(format_time),
+            location: text("LOCATION"),
+            url: vevent
+                .property("URL")
+                .map(|p| p.value.trim().to_string())
+                .filter(|url| !url.is_empty()),
+            time_zone: zone.map(|z| z.name().to_string()),
+            recurrence_rule,
+            exdates,
+            recurrence_id,
+            cancelled: vevent
+                .property("STATUS")
+                .is_some_and(|s| s.value.eq_ignore_ascii_case("CANCELLED")),
+            attendees,
+        })
+    }
+
+    /// Copies the fields shared by series, single events and occurrences.
+    fn apply(&self, event: &mut Event) {
+        event.title = self.title.clone();
+        event.description = self.description.clone();
+        event.event_type = self.event_type.clone();
+        event.event_date = self.date.format("%Y-%m-%d").to_string();
+        event.start_time = self.start_time.clone();
+        event.end_time = self.end_time.clone();
+        event.location = self.location.clone();
+        event.meeting_url = self.url.clone();
+        if let Some(ref time_zone) = self.time_zone {
+            event.timezone = time_zone.clone();
+        }
+    }
+}
+
+/// The zone of a date-time value: UTC for a `Z` value, else its known `TZID`.
+fn value_zone(value: &str, tzid: Option<&str>) -> Option<TimeZone> {
+    if value.trim().ends_with('Z') {
+        return Some(TimeZone::utc());
+    }
+    tzid.and_then(|tzid| TimeZone::named(tzid).ok())
+}
+
+/// Parses a date or date-time value into `zone`'s local time. Values in
+/// another known zone are converted; others are taken as written.
+fn parse_local(
+    value: &str,
+    tzid: Option<&str>,
+    zone: Option<&TimeZone>,
+) -> Option<(NaiveDate, Option<NaiveTime>)> {
+    let (date, time) = parse_date_time(value)?;
+    let (Some(time), Some(zone), Some(source)) = (time, zone, value_zone(value, tzid)) else {
+        return Some((date, time));
+    };
+    if source.name() == zone.name() {
+        return Some((date, Some(time)));
+    }
+    let local = zone.to_local(source.to_utc(date.and_time(time)));
+    Some((local.date(), Some(local.time())))
+}
+
+/// The local date of an `EXDATE` or `RECURRENCE-ID` value.
+fn local_date(
+    value: &str,
+    tzid: Option<&str>,
+    zone: Option<&TimeZone>,
+) -> Result<NaiveDate, String> {
+    let date = recurrence::parse_ical_date(value).map_err(|e| e.message())?;
+    Ok(parse_local(value, tzid, zone).map_or(date, |(date, _)| date))
+}
+
+/// Parses `YYYYMMDD` or `YYYYMMDDTHHMMSS`, with an optional `Z`.
+fn parse_date_time(value: &str) -> Option<(NaiveDate, Option<NaiveTime>)> {
+    let value = value.trim();
+    let date = NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()?;
+    match value.get(8..) {
+        Some("") => Some((date, None)),
+        Some(time) => {
+            let time = time.strip_prefix('T')?;
+            let time = time.strip_suffix('Z').unwrap_or(time);
+            Some((date, Some(NaiveTime::parse_from_str(time, "%H%M%S").ok()?)))
+        }
+        None => None,
+    }
+}
+
+/// Parses a `DURATION` such as `PT1H30M`, `P1D` or `P2W`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let value = value.strip_prefix('+').unwrap_or(value);
+    let mut rest = value.strip_prefix('P')?.chars().peekable();
+
+    let mut total = Duration::zero();
+    let mut in_time = false;
+    let mut any = false;
+    while let Some(c) = rest.next() {
+        if c == 'T' {
+            in_time = true;
+            continue;
+        }
+        let mut digits = c.to_digit(10)? as i64;
+        while let Some(d) = rest.peek().and_then(|c| c.to_digit(10)) {
+            digits = digits.checked_mul(10)?.checked_add(d as i64)?;
+            rest.next();
+        }
+        total += match (rest.next()?, in_time) {
+            ('W', false) => Duration::try_weeks(digits)?,
+            ('D', false) => Duration::try_days(digits)?,
+            ('H', true) => Duration::try_hours(digits)?,
+            ('M', true) => Duration::try_minutes(digits)?,
+            ('S', true) => Duration::try_seconds(digits)?,
+            _ => return None,
+        };
+        any = true;
+    }
+    any.then_some(total)
+}
+
+fn format_time(time: NaiveTime) -> String {
+    time.format("%H:%M").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vevent(lines: &str) -> Component {
+        let ics = format!(
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\n{}\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+            lines.replace('\n', "\r\n")
+        );
+        Component::parse(&ics).unwrap().components.remove(0)
+    }
+
+    #[test]
+    fn maps_a_recurring_meeting() {
+        let item = ImportedEvent::from_vevent(&vevent(
+            "UID:abc-123@outlook.com
+SUMMARY:Weekly sync\\, IT + Eng
+DTSTART;TZID=Asia/Singapore:20260105T093000
+DTEND;TZID=Asia/Singapore:20260105T101500
+RRULE:FREQ=WEEKLY;BYDAY=MO;UNTIL=20260330T013000Z
+EXDATE;TZID=Asia/Singapore:20260119T093000,20260202T093000
+LOCATION:Meeting Room 2
+URL:https://teams.example.com/sync
+CATEGORIES:Work,Meeting
+ATTENDEE;CN=Sarah Chen;PARTSTAT=ACCEPTED:mailto:Sarah.Chen@company.com
+ATTENDEE;PARTSTAT=DECLINED:MAILTO:john.smith@company.com
+ATTENDEE:urn:uuid:not-an-email",
+        ))
+        .unwrap();
+
+        assert_eq!(item.uid.as_deref(), Some("abc-123@outlook.com"));
+        assert_eq!(item.title, "Weekly sync, IT + Eng");
+        assert_eq!(item.date, NaiveDate::from_ymd_opt(2026, 1, 5).unwrap());
+        assert_eq!(item.start_time, "09:30");
+        assert_eq!(item.end_time.as_deref(), Some("10:15"));
+        assert_eq!(
+            item.recurrence_rule.as_deref(),
+            Some("FREQ=WEEKLY;BYDAY=MO;UNTIL=20260330")
+        );
+        assert_eq!(
+            item.exdates,
+            vec![
+                NaiveDate::from_ymd_opt(2026, 1, 19).unwrap(),
+                NaiveDate::from_ymd_opt(2026, 2, 2).unwrap()
+            ]
+        );
+        assert_eq!(item.event_type, "meeting");
+        assert_eq!(item.url.as_deref(), Some("https://teams.example.com/sync"));
+        assert_eq!(
+            item.attendees,
+            vec![
+                ("sarah.chen@company.com".to_string(), "accepted".to_string()),
+                ("john.smith@company.com".to_string(), "declined".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn duration_and_all_day_events() {
+        let item = ImportedEvent::from_vevent(&vevent(
+            "UID:x\nSUMMARY:Review\nDTSTART:20260105T230000\nDURATION:PT1H30M",
+        ))
+        .unwrap();
+        assert_eq!(item.end_time.as_deref(), Some("00:30"));
+
+        let item = ImportedEvent::from_vevent(&vevent(
+            "DTSTART;VALUE=DATE:20260105\nDTEND;VALUE=DATE:20260106",
+        ))
+        .unwrap();
+        assert_eq!((item.start_time.as_str(), item.end_time), ("00:00", None));
+        assert_eq!(item.title, UNTITLED);
+        assert_eq!(item.uid, None);
+    }
+
+    #[test]
+    fn occurrences_and_cancellations() {
+        let item = ImportedEvent::from_vevent(&vevent(
+            "UID:abc\nRECURRENCE-ID:20260112T093000\nDTSTART:20260112T110000\nSTATUS:CANCELLED",
+        ))
+        .unwrap();
+        assert_eq!(item.recurrence_id, NaiveDate::from_ymd_opt(2026, 1, 12));
+        assert!(item.cancelled);
+    }
+
+    #[test]
+    fn rejects_unusable_events() {
+        for lines in [
+            "SUMMARY:No start",
+            "DTSTART:2026-01-05",
+            "DTSTART:20260105T0930",
+            "DTSTART:20260105T093000\nRRULE:FREQ=HOURLY",
+            "DTSTART:20260105T093000\nDURATION:1H",
+        ] {
+            assert!(
+                ImportedEvent::from_vevent(&vevent(lines)).is_err(),
+                "{}",
+                lines
+            );
+        }
+    }
+
+    #[test]
+    fn times_are_kept_in_the_start_time_zone() {
+        let item = ImportedEvent::from_vevent(&vevent(
+            "DTSTART;TZID=Europe/Berlin:20260105T090000
+DTEND:20260105T090000Z
+RRULE:FREQ=DAILY;UNTIL=20260131T230000Z
+EXDATE:20260107T080000Z,20260108T233000Z",
+        ))
+        .unwrap();
+        assert_eq!(item.time_zone.as_deref(), Some("Europe/Berlin"));
+        assert_eq!(item.end_time.as_deref(), Some("10:00"));
+        assert_eq!(
+            item.recurrence_rule.as_deref(),
+            Some("FREQ=DAILY;UNTIL=20260201")
+        );
+        assert_eq!(
+            item.exdates,
+            vec![
+                NaiveDate::from_ymd_opt(2026, 1, 7).unwrap(),
+                NaiveDate::from_ymd_opt(2026, 1, 9).unwrap()
+            ]
+        );
+
+        let item = ImportedEvent::from_vevent(&vevent("DTSTART:20260105T230000Z")).unwrap();
+        assert_eq!(
+            (item.time_zone.as_deref(), item.start_time.as_str()),
+            (Some("UTC"), "23:00")
+        );
+
+        for lines in [
+            "DTSTART:20260105T090000",
+            "DTSTART;TZID=W. Europe Standard Time:20260105T090000",
+        ] {
+            let item = ImportedEvent::from_vevent(&vevent(lines)).unwrap();
+            assert_eq!((item.time_zone, item.start_time.as_str()), (None, "09:00"));
+        }
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("PT1H30M"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("P1DT2H"), Some(Duration::hours(26)));
+        assert_eq!(parse_duration("+P2W"), Some(Duration::weeks(2)));
+        assert_eq!(parse_duration("P"), None);
+        assert_eq!(parse_duration("PT5D"), None);
+    }
+
+    #[tokio::test]
+    async fn attendees_match_active_users_by_email() {
+        let pool = crate::migrations::test_pool().await;
+        for (id, is_active) in [
+            ("active", Some(true)),
+            ("unset", None),
+            ("left", Some(false)),
+        ] {
+            sqlx::query(
+                "INSERT INTO users (id, email, password_hash, first_name, last_name, department, role, is_active) VALUES (?, ?, 'x', 'Test', 'User', 'IT', 'Member', ?)",
+            )
+            .bind(id)
+            .bind(format!("{}@Example.com", id))
+            .bind(is_active)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let users = users_by_email(
+            &pool,
+            &[
+                "active@example.com",
+                "unset@example.com",
+                "left@example.com",
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(users.len(), 2);
+        assert_eq!(users["active@example.com"], "active");
+        // Users from before `is_active` was set count as active
+        assert_eq!(users["unset@example.com"], "unset");
+    }
+}
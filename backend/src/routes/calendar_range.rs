@@ -0,0 +1,602 @@
+//! One timeline of events, equipment bookings and task deadlines.
+//!
+//! `GET /api/calendar/range` lists everything dated within `from..=to`, so a
+//! week or month grid needs a single request. Recurring events are expanded
+//! into their occurrences and every event is placed in the viewer's time zone.
+//! Bookings span whole days and are listed on their first day in the range;
+//! tasks are listed on their deadline, at its time if it has one.
+//!
+//! Items are ordered by day, all-day items first, then by start time, type and
+//! id. The cursor names the last item of a page, so the next page carries on
+//! after it even when items were added or removed in between.
+
+use actix_web::{HttpResponse, web};
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
+
+use crate::error::AppError;
+use crate::escalation;
+use crate::filters::Filters;
+use crate::middleware::guards::AuthUser;
+use crate::models::tasks::Task;
+use crate::recurrence;
+use crate::routes::events::{events_in_range, occurrence_json};
+use crate::routes::scheduling::event_interval;
+use crate::timezone;
+
+const MAX_RANGE_DAYS: i64 = 366;
+
+/// Agenda pages default to this many items
+const DEFAULT_AGENDA_LIMIT: usize = 50;
+
+/// Largest page, and the default for week and month views
+const MAX_LIMIT: usize = 500;
+
+/// An agenda without `to` covers this many days
+const DEFAULT_AGENDA_DAYS: i64 = 30;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RangeQuery {
+    from: Option<String>,
+    to: Option<String>,
+    view: Option<View>,
+    /// Comma-separated item types; all when missing
+    types: Option<String>,
+    department: Option<String>,
+    /// Only items of this user: events they created or attend, their
+    /// bookings and tasks assigned to them
+    user_id: Option<String>,
+    event_type: Option<String>,
+    equipment_id: Option<String>,
+    project_id: Option<String>,
+    task_status: Option<String>,
+    limit: Option<usize>,
+    cursor: Option<String>,
+}
+
+/// How the frontend shows the range, which sets its default length and page
+/// size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum View {
+    Month,
+    Week,
+    Agenda,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum ItemType {
+    Event,
+    Booking,
+    Task,
+}
+
+impl ItemType {
+    const ALL: [ItemType; 3] = [ItemType::Event, ItemType::Booking, ItemType::Task];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            ItemType::Event => "event",
+            ItemType::Booking => "booking",
+            ItemType::Task => "task",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        ItemType::ALL.into_iter().find(|t| t.as_str() == value)
+    }
+}
+
+/// One entry of the timeline. Dates and times are in the viewer's zone.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TimelineItem {
+    r#type: ItemType,
+    id: String,
+    title: String,
+    /// Day the item is listed on
+    date: String,
+    /// `None` for all-day items
+    start_time: Option<String>,
+    end_date: String,
+    end_time: Option<String>,
+    all_day: bool,
+    department: Option<String>,
+    /// The fields only this type has
+    details: serde_json::Value,
+}
+
+/// Where an item sorts: its day, start time (empty for all-day items, which
+/// come first), type and id.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct SortKey {
+    date: String,
+    time: String,
+    item_type: ItemType,
+    id: String,
+}
+
+impl SortKey {
+    fn of(item: &TimelineItem) -> Self {
+        SortKey {
+            date: item.date.clone(),
+            time: item.start_time.clone().unwrap_or_default(),
+            item_type: item.r#type,
+            id: item.id.clone(),
+        }
+    }
+
+    /// An opaque cursor pointing just after this item.
+    fn to_cursor(&self) -> String {
+        hex::encode(format!(
+            "{}|{}|{}|{}",
+            self.date,
+            self.time,
+            self.item_type.as_str(),
+            self.id
+        ))
+    }
+
+    fn from_cursor(cursor: &str) -> Result<Self, AppError> {
+        let invalid =
+            || AppError::Validation("Invalid cursor".to_string()).with_code("INVALID_CURSOR");
+        let decoded = hex::decode(cursor).map_err(|_| invalid())?;
+        let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+        let mut parts = decoded.splitn(4, '|');
+        let mut next = || parts.next().ok_or_else(invalid);
+        Ok(SortKey {
+            date: next()?.to_string(),
+            time: next()?.to_string(),
+            item_type: ItemType::parse(next()?).ok_or_else(invalid)?,
+            id: next()?.to_string(),
+        })
+    }
+}
+
+/// A booking with the equipment and booker's names.
+#[derive(Debug, FromRow)]
+struct RangeBooking {
+    id: String,
+    equipment_id: String,
+    equipment_name: String,
+    user_id: String,
+    booked_by: String,
+    department: String,
+    start_date: String,
+    end_date: String,
+    purpose: String,
+    status: String,
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered ahead of the `/calendar` scope in `events`, like the feed
+    cfg.service(web::resource("/calendar/range").route(web::get().to(get_range)));
+}
+
+async fn get_range(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+    query: web::Query<RangeQuery>,
+) -> Result<HttpResponse, AppError> {
+    let zone = timezone::user_time_zone(pool.get_ref(), &auth.id).await?;
+    let view = query.view.unwrap_or(View::Agenda);
+
+    let from = match query.from {
+        Some(ref from) => recurrence::parse_date(from)?,
+        None => {
+            let today = zone.today(timezone::now_utc());
+            match view {
+                View::Month => today.with_day(1).unwrap_or(today),
+                View::Week | View::Agenda => today,
+            }
+        }
+    };
+    let to = match query.to {
+        Some(ref to) => recurrence::parse_date(to)?,
+        None => match view {
+            View::Week => from + Duration::days(6),
+            View::Month => last_of_month(from),
+            View::Agenda => from + Duration::days(DEFAULT_AGENDA_DAYS - 1),
+        },
+    };
+    if to < from {
+        return Err(AppError::Validation(
+            "to must not be before from".to_string(),
+        ));
+    }
+    if (to - from).num_days() >= MAX_RANGE_DAYS {
+        return Err(AppError::Validation(format!(
+            "The range can span at most {} days",
+            MAX_RANGE_DAYS
+        )));
+    }
+
+    let types = match query.types {
+        Some(ref types) => types
+            .split(',')
+            .map(|t| {
+                ItemType::parse(t.trim()).ok_or_else(|| {
+                    AppError::Validation(format!(
+                        "Unknown type '{}'; expected event, booking or task",
+                        t.trim()
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        None => ItemType::ALL.to_vec(),
+    };
+
+    let default_limit = match view {
+        View::Agenda => DEFAULT_AGENDA_LIMIT,
+        View::Week | View::Month => MAX_LIMIT,
+    };
+    let limit = query.limit.unwrap_or(default_limit).clamp(1, MAX_LIMIT);
+    let after = query
+        .cursor
+        .as_deref()
+        .map(SortKey::from_cursor)
+        .transpose()?;
+
+    let mut conn = pool.acquire().await?;
+    let mut items = Vec::new();
+    if types.contains(&ItemType::Event) {
+        items.extend(range_events(&mut conn, &query, &zone, from, to).await?);
+    }
+    if types.contains(&ItemType::Booking) {
+        items.extend(range_bookings(&mut conn, &query, from, to).await?);
+    }
+    if types.contains(&ItemType::Task) {
+        items.extend(range_tasks(&mut conn, &query, &zone, from, to).await?);
+    }
+
+    let mut items: Vec<(SortKey, TimelineItem)> =
+        items.into_iter().map(|i| (SortKey::of(&i), i)).collect();
+    items.sort_by(|a, b| a.0.cmp(&b.0));
+    let total = items.len();
+
+    let mut page: Vec<(SortKey, TimelineItem)> = items
+        .into_iter()
+        .filter(|(key, _)| after.as_ref().is_none_or(|after| key > after))
+        .take(limit + 1)
+        .collect();
+    let has_more = page.len() > limit;
+    page.truncate(limit);
+    let next_cursor = has_more
+        .then(|| page.last().map(|(key, _)| key.to_cursor()))
+        .flatten();
+    let page: Vec<TimelineItem> = page.into_iter().map(|(_, item)| item).collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "view": view,
+            "from": from.format("%Y-%m-%d").to_string(),
+            "to": to.format("%Y-%m-%d").to_string(),
+            "timeZone": zone.name(),
+            "items": page,
+            "pagination": {
+                "limit": limit,
+                "count": page.len(),
+                "total": total,
+                "hasMore": has_more,
+                "nextCursor": next_cursor
+            }
+        }
+    })))
+}
+
+/// Occurrences starting within the range in `zone`.
+async fn range_events(
+    conn: &mut SqliteConnection,
+    query: &RangeQuery,
+    zone: &timezone::TimeZone,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<TimelineItem>, AppError> {
+    let mut filters = Filters::new();
+    filters.eq_opt("e.event_type", query.event_type.as_ref());
+    if let Some(ref department) = query.department {
+        filters.condition(
+            "(e.department = ? OR e.department = 'Both')",
+            vec![department.into()],
+        );
+    }
+    if let Some(ref user_id) = query.user_id {
+        filters.condition(
+            "(e.created_by = ? OR EXISTS (SELECT 1 FROM event_attendees a WHERE a.event_id = e.id AND a.user_id = ?))",
+            vec![user_id.into(), user_id.into()],
+        );
+    }
+
+    // Events in other zones may start on a neighbouring day
+    let occurrences = events_in_range(
+        conn,
+        &filters,
+        from - Duration::days(1),
+        to + Duration::days(1),
+    )
+    .await?;
+
+    let mut items = Vec::new();
+    for occurrence in occurrences {
+        let event = &occurrence.event;
+        let Some((start, end)) = recurrence::parse_date(&occurrence.date)
+            .ok()
+            .and_then(|date| event_interval(event, date))
+        else {
+            log::warn!("Leaving event {} out of the calendar range", event.id);
+            continue;
+        };
+        let start = zone.to_local(start);
+        if !(from..=to).contains(&start.date()) {
+            continue;
+        }
+        // Without an end time, event_interval assumes one
+        let end = event.end_time.as_ref().map(|_| zone.to_local(end));
+
+        items.push(TimelineItem {
+            r#type: ItemType::Event,
+            id: event.id.clone(),
+            title: event.title.clone(),
+            date: start.format("%Y-%m-%d").to_string(),
+            start_time: Some(start.format("%H:%M").to_string()),
+            end_date: end.unwrap_or(start).format("%Y-%m-%d").to_string(),
+            end_time: end.map(|end| end.format("%H:%M").to_string()),
+            all_day: false,
+            department: event.department.clone(),
+            details: occurrence_json(event, &occurrence.date),
+        });
+    }
+    Ok(items)
+}
+
+/// Bookings overlapping the range, other than cancelled ones.
+async fn range_bookings(
+    conn: &mut SqliteConnection,
+    query: &RangeQuery,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<TimelineItem>, AppError> {
+    let from_date = from.format("%Y-%m-%d").to_string();
+    let mut filters = Filters::new();
+    filters
+        .raw("b.status != 'cancelled'")
+        .condition(
+            "b.start_date <= ?",
+            vec![to.format("%Y-%m-%d").to_string().into()],
+        )
+        .condition("b.end_date >= ?", vec![from_date.clone().into()])
+        .eq_opt("b.equipment_id", query.equipment_id.as_ref())
+        .eq_opt("b.user_id", query.user_id.as_ref());
+    if let Some(ref department) = query.department {
+        filters.condition(
+            "(b.department = ? OR b.department = 'Both')",
+            vec![department.into()],
+        );
+    }
+
+    let mut sql = QueryBuilder::<Sqlite>::new(
+        "SELECT b.id, b.equipment_id, eq.name AS equipment_name, b.user_id,
+                u.first_name || ' ' || u.last_name AS booked_by, b.department,
+                b.start_date, b.end_date, b.purpose, b.status
+         FROM bookings b
+         JOIN equipment eq ON eq.id = b.equipment_id
+         JOIN users u ON u.id = b.user_id",
+    );
+    filters.push_where(&mut sql);
+    let bookings = sql
+        .build_query_as::<RangeBooking>()
+        .fetch_all(&mut *conn)
+        .await?;
+
+    Ok(bookings
+        .into_iter()
+        .map(|b| TimelineItem {
+            r#type: ItemType::Booking,
+            id: b.id,
+            title: format!("Booking: {}", b.equipment_name),
+            // Bookings that began earlier are listed on the range's first day
+            date: b.start_date.clone().max(from_date.clone()),
+            start_time: None,
+            end_date: b.end_date.clone(),
+            end_time: None,
+            all_day: true,
+            department: Some(b.department),
+            details: serde_json::json!({
+                "equipmentId": b.equipment_id,
+                "equipmentName": b.equipment_name,
+                "userId": b.user_id,
+                "bookedBy": b.booked_by,
+                "startDate": b.start_date,
+                "endDate": b.end_date,
+                "purpose": b.purpose,
+                "status": b.status
+            }),
+        })
+        .collect())
+}
+
+/// Tasks due within the range in `zone`. A deadline with a time is listed at
+/// that time; a date-only one is an all-day item.
+async fn range_tasks(
+    conn: &mut SqliteConnection,
+    query: &RangeQuery,
+    zone: &timezone::TimeZone,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<TimelineItem>, AppError> {
+    // Timed deadlines are in UTC and may fall on a neighbouring day
+    let mut filters = Filters::new();
+    filters
+        .condition(
+            "date(t.deadline) >= ?",
+            vec![
+                (from - Duration::days(1))
+                    .format("%Y-%m-%d")
+                    .to_string()
+                    .into(),
+            ],
+        )
+        .condition(
+            "date(t.deadline) <= ?",
+            vec![
+                (to + Duration::days(1))
+                    .format("%Y-%m-%d")
+                    .to_string()
+                    .into(),
+            ],
+        )
+        .eq_opt("t.project_id", query.project_id.as_ref())
+        .eq_opt("t.status", query.task_status.as_ref());
+    if let Some(ref department) = query.department {
+        filters.condition(
+            "(t.department = ? OR t.department = 'Both')",
+            vec![department.into()],
+        );
+    }
+    if let Some(ref user_id) = query.user_id {
+        filters.condition(
+            "(t.assignee_id = ? OR EXISTS (SELECT 1 FROM task_assignees ta WHERE ta.task_id = t.id AND ta.user_id = ?))",
+            vec![user_id.into(), user_id.into()],
+        );
+    }
+
+    let mut sql = QueryBuilder::<Sqlite>::new("SELECT t.* FROM tasks t");
+    filters.push_where(&mut sql);
+    let tasks = sql.build_query_as::<Task>().fetch_all(&mut *conn).await?;
+
+    Ok(tasks
+        .into_iter()
+        .filter_map(|t| {
+            let (date, time) = escalation::local_deadline(&t.deadline, zone)?;
+            if !(from..=to).contains(&date) {
+                return None;
+            }
+            let date = date.format("%Y-%m-%d").to_string();
+            let time = time.map(|time| time.format("%H:%M").to_string());
+            Some(TimelineItem {
+                r#type: ItemType::Task,
+                id: t.id,
+                title: t.title,
+                date: date.clone(),
+                start_time: time.clone(),
+                end_date: date,
+                end_time: time.clone(),
+                all_day: time.is_none(),
+                department: Some(t.department),
+                details: serde_json::json!({
+                    "description": t.description,
+                    "urgency": t.urgency,
+                    "status": t.status,
+                    "projectId": t.project_id,
+                    "assigneeId": t.assignee_id,
+                    "deadline": t.deadline
+                }),
+            })
+        })
+        .collect())
+}
+
+fn last_of_month(date: NaiveDate) -> NaiveDate {
+    let (year, month) = match date.month() {
+        12 => (date.year() + 1, 1),
+        month => (date.year(), month + 1),
+    };
+    NaiveDate::from_ymd_opt(year, month, 1).unwrap_or(date) - Duration::days(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(date: &str, time: &str, item_type: ItemType, id: &str) -> SortKey {
+        SortKey {
+            date: date.to_string(),
+            time: time.to_string(),
+            item_type,
+            id: id.to_string(),
+        }
+    }
+
+    #[test]
+    fn all_day_items_come_first_each_day() {
+        let mut keys = [
+            key("2026-01-06", "", ItemType::Task, "t1"),
+            key("2026-01-05", "09:00", ItemType::Event, "e2"),
+            key("2026-01-05", "", ItemType::Task, "t2"),
+            key("2026-01-05", "", ItemType::Booking, "b1"),
+            key("2026-01-05", "08:30", ItemType::Event, "e1"),
+        ];
+        keys.sort();
+        let ids: Vec<&str> = keys.iter().map(|k| k.id.as_str()).collect();
+        assert_eq!(ids, ["b1", "t2", "e1", "e2", "t1"]);
+    }
+
+    #[test]
+    fn cursors_round_trip() {
+        let key = key("2026-01-05", "09:00", ItemType::Event, "a|b");
+        assert_eq!(SortKey::from_cursor(&key.to_cursor()).unwrap(), key);
+
+        for cursor in [
+            "zz",
+            &hex::encode("2026-01-05|09:00|meeting|x"),
+            &hex::encode("2026-01-05"),
+        ] {
+            assert!(SortKey::from_cursor(cursor).is_err(), "{}", cursor);
+        }
+    }
+
+    #[test]
+    fn months_end_on_their_last_day() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        assert_eq!(last_of_month(date(2026, 2, 10)), date(2026, 2, 28));
+        assert_eq!(last_of_month(date(2026, 12, 1)), date(2026, 12, 31));
+    }
+
+    #[tokio::test]
+    async fn tasks_are_listed_on_the_viewers_day() {
+        let pool = crate::migrations::test_pool().await;
+        sqlx::query(
+            "INSERT INTO users (id, email, password_hash, first_name, last_name, department, role) VALUES ('lead', 'lead@example.com', 'x', 'Test', 'User', 'IT', 'Member');
+             INSERT INTO tasks (id, title, urgency, department, created_by, deadline) VALUES
+                ('timed', 'Patch firewall', 'high', 'IT', 'lead', '2026-03-10 17:00:00'),
+                ('all-day', 'Renew licences', 'low', 'IT', 'lead', '2026-03-11'),
+                ('earlier', 'Rotate keys', 'low', 'IT', 'lead', '2026-03-10 15:00:00');",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let query: RangeQuery = serde_json::from_value(serde_json::json!({})).unwrap();
+        let singapore = timezone::TimeZone::named("Asia/Singapore").unwrap();
+        let day = NaiveDate::from_ymd_opt(2026, 3, 11).unwrap();
+
+        let mut conn = pool.acquire().await.unwrap();
+        let mut items = range_tasks(&mut conn, &query, &singapore, day, day)
+            .await
+            .unwrap();
+        items.sort_by(|a, b| a.id.cmp(&b.id));
+        let listed: Vec<_> = items
+            .iter()
+            .map(|i| {
+                (
+                    i.id.as_str(),
+                    i.date.as_str(),
+                    i.start_time.as_deref(),
+                    i.all_day,
+                )
+            })
+            .collect();
+        // 17:00 UTC on the 10th is 01:00 on the 11th in Singapore; 15:00 UTC
+        // is still the 10th there
+        assert_eq!(
+            listed,
+            [
+                ("all-day", "2026-03-11", None, true),
+                ("timed", "2026-03-11", Some("01:00"), false)
+            ]
+        );
+    }
+}
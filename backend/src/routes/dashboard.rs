@@ -1,11 +1,29 @@
 use actix_web::{HttpResponse, web};
+use chrono::Duration;
 use sqlx::SqlitePool;
 
+use crate::clock::SystemClock;
+use crate::error::AppError;
+use crate::escalation;
+use crate::filters::Filters;
+use crate::middleware::guards::AuthUser;
+use crate::recurrence;
+use crate::routes::events::events_in_range;
+use crate::routes::scheduling::event_interval;
+use crate::timezone;
+
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(web::scope("/dashboard").route("/data", web::get().to(get_dashboard_data)));
 }
 
-async fn get_dashboard_data(pool: web::Data<SqlitePool>) -> HttpResponse {
+async fn get_dashboard_data(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    // "Today" and "this week" follow the user's time zone
+    let zone = timezone::user_time_zone(pool.get_ref(), &auth.id).await?;
+    let today = zone.today(timezone::now_utc());
+
     // Get active projects count from the projects table
     let active_projects =
         sqlx::query_as::<_, (i64,)>("SELECT COUNT(*) FROM projects WHERE status = 'active'")
@@ -34,21 +52,31 @@ async fn get_dashboard_data(pool: web::Data<SqlitePool>) -> HttpResponse {
     // Get completed tasks count (this week)
     let completed_tasks = sqlx::query_as::<_, (i64,)>(
         "SELECT COUNT(*) FROM tasks WHERE (status = 'completed' OR is_completed = 1) 
-         AND updated_at >= date('now', '-7 days')",
+         AND updated_at >= ?",
     )
+    .bind(timezone::format_sql_datetime(
+        zone.day_bounds(today - Duration::days(7)).0,
+    ))
     .fetch_one(pool.get_ref())
     .await
     .map(|(count,)| count)
     .unwrap_or(0);
 
-    // Get today's meetings count
-    let today_meetings = sqlx::query_as::<_, (i64,)>(
-        "SELECT COUNT(*) FROM events WHERE event_type = 'meeting' AND event_date = date('now')",
+    // Get today's meetings count, counting each occurrence of a series
+    let (day_start, day_end) = zone.day_bounds(today);
+    let mut meetings = Filters::new();
+    meetings.raw("e.event_type = 'meeting'");
+    let today_meetings = events_in_range(
+        &mut *pool.acquire().await?,
+        &meetings,
+        today - Duration::days(1),
+        today + Duration::days(1),
     )
-    .fetch_one(pool.get_ref())
-    .await
-    .map(|(count,)| count)
-    .unwrap_or(0);
+    .await?
+    .iter()
+    .filter_map(|o| event_interval(&o.event, recurrence::parse_date(&o.date).ok()?))
+    .filter(|(start, _)| (day_start..day_end).contains(start))
+    .count();
 
     // Get recent meetings with host name
     let recent_meetings = sqlx::query_as::<
@@ -81,7 +109,7 @@ async fn get_dashboard_data(pool: web::Data<SqlitePool>) -> HttpResponse {
             .iter()
             .map(
                 |(id, title, description, date, start, end, _created_by, first_name, last_name)| {
-                    let is_upcoming = date >= &chrono::Utc::now().format("%Y-%m-%d").to_string();
+                    let is_upcoming = date >= &today.format("%Y-%m-%d").to_string();
                     let host_name = match (first_name, last_name) {
                         (Some(first), Some(last)) => format!("{} {}", first, last),
                         (Some(first), None) => first.clone(),
@@ -140,7 +168,8 @@ async fn get_dashboard_data(pool: web::Data<SqlitePool>) -> HttpResponse {
             .map(|(count,)| count)
             .unwrap_or(0);
 
-    // Get urgent tasks count
+    // Get urgent tasks count, once escalation has caught up
+    escalation::escalate_tasks(pool.get_ref(), &SystemClock).await?;
     let urgent_tasks = sqlx::query_as::<_, (i64,)>(
         "SELECT COUNT(*) FROM tasks WHERE urgency = 'urgent' AND status != 'completed'",
     )
@@ -149,7 +178,7 @@ async fn get_dashboard_data(pool: web::Data<SqlitePool>) -> HttpResponse {
     .map(|(count,)| count)
     .unwrap_or(0);
 
-    HttpResponse::Ok().json(serde_json::json!({
+    Ok(HttpResponse::Ok().json(serde_json::json!({
         "success": true,
         "data": {
             "stats": {
@@ -164,5 +193,5 @@ async fn get_dashboard_data(pool: web::Data<SqlitePool>) -> HttpResponse {
             "recentMeetings": meetings_json,
             "quickLinks": links_json
         }
-    }))
+    })))
 }
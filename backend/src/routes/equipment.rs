@@ -1,11 +1,16 @@
-use actix_web::{HttpMessage, HttpRequest, HttpResponse, web};
-use sqlx::SqlitePool;
+use actix_web::{HttpResponse, web};
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
 use uuid::Uuid;
 
+use crate::error::AppError;
+use crate::filters::Filters;
+use crate::middleware::guards::{Admin, AuthUser, Member, RequireRole};
 use crate::models::equipment::{
     Booking, CheckAvailabilityRequest, CreateBookingRequest, CreateEquipmentRequest, Equipment,
     GetBookingsQuery, GetEquipmentQuery,
 };
+use crate::routes::notifications::create_notification;
+use crate::timezone;
 
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -27,93 +32,63 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
 async fn get_equipment(
     pool: web::Data<SqlitePool>,
     query: web::Query<GetEquipmentQuery>,
-) -> HttpResponse {
-    let mut sql = String::from(
-        "SELECT id, name, category, status, serial_number, purchase_date, last_maintenance, notes, created_at, updated_at FROM equipment WHERE 1=1",
-    );
-
-    if let Some(ref status) = query.status {
-        sql.push_str(&format!(" AND status = '{}'", status));
-    }
-    if let Some(ref category) = query.category {
-        sql.push_str(&format!(" AND category = '{}'", category));
-    }
+) -> Result<HttpResponse, AppError> {
+    let mut filters = Filters::new();
+    filters
+        .eq_opt("status", query.status.as_ref())
+        .eq_opt("category", query.category.as_ref());
     if let Some(ref search) = query.search {
-        sql.push_str(&format!(
-            " AND (name LIKE '%{}%' OR category LIKE '%{}%')",
-            search, search
-        ));
+        filters.search(&["name", "category"], search);
     }
 
-    sql.push_str(" ORDER BY name");
+    let mut sql = QueryBuilder::<Sqlite>::new(
+        "SELECT id, name, category, status, serial_number, purchase_date, last_maintenance, notes, created_at, updated_at FROM equipment",
+    );
+    filters.push_where(&mut sql);
+    sql.push(" ORDER BY name");
 
-    let result = sqlx::query_as::<_, Equipment>(&sql)
+    let equipment = sql
+        .build_query_as::<Equipment>()
         .fetch_all(pool.get_ref())
-        .await;
-
-    match result {
-        Ok(equipment) => {
-            let equipment_json: Vec<serde_json::Value> = equipment
-                .iter()
-                .map(|e| {
-                    serde_json::json!({
-                        "id": e.id,
-                        "name": e.name,
-                        "category": e.category,
-                        "status": e.status,
-                        "serialNumber": e.serial_number,
-                        "createdAt": e.created_at,
-                        "updatedAt": e.updated_at
-                    })
-                })
-                .collect();
-
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "equipment": equipment_json,
-                    "pagination": {
-                        "total": equipment_json.len(),
-                        "count": equipment_json.len()
-                    }
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
+        .await?;
+
+    let equipment_json: Vec<serde_json::Value> = equipment
+        .iter()
+        .map(|e| {
+            serde_json::json!({
+                "id": e.id,
+                "name": e.name,
+                "category": e.category,
+                "status": e.status,
+                "serialNumber": e.serial_number,
+                "createdAt": e.created_at,
+                "updatedAt": e.updated_at
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "equipment": equipment_json,
+            "pagination": {
+                "total": equipment_json.len(),
+                "count": equipment_json.len()
+            }
         }
-    }
+    })))
 }
 
 async fn create_equipment(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    _auth: RequireRole<Admin>,
     body: web::Json<CreateEquipmentRequest>,
-) -> HttpResponse {
+) -> Result<HttpResponse, AppError> {
     // Check if user is authenticated
-    let _user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
 
     let equipment_id = Uuid::new_v4().to_string();
 
-    let result = sqlx::query(
+    sqlx::query(
         "INSERT INTO equipment (id, name, category, status, serial_number, notes, created_at, updated_at) VALUES (?, ?, ?, 'available', ?, ?, datetime('now'), datetime('now'))"
     )
     .bind(&equipment_id)
@@ -122,51 +97,27 @@ async fn create_equipment(
     .bind(&body.serial_number)
     .bind(&body.notes)
     .execute(pool.get_ref())
-    .await;
-
-    match result {
-        Ok(_) => HttpResponse::Created().json(serde_json::json!({
-            "success": true,
-            "data": {
-                "equipment": {
-                    "id": equipment_id,
-                    "name": body.name,
-                    "category": body.category,
-                    "status": "available",
-                    "serialNumber": body.serial_number,
-                    "notes": body.notes
-                }
+    .await?;
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "equipment": {
+                "id": equipment_id,
+                "name": body.name,
+                "category": body.category,
+                "status": "available",
+                "serialNumber": body.serial_number,
+                "notes": body.notes
             }
-        })),
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to create equipment"
-                }
-            }))
         }
-    }
+    })))
 }
 
-async fn get_all_bookings(pool: web::Data<SqlitePool>, req: HttpRequest) -> HttpResponse {
+async fn get_all_bookings(pool: web::Data<SqlitePool>) -> Result<HttpResponse, AppError> {
     // Check if user is authenticated
-    let _user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
 
-    let result = sqlx::query_as::<
+    let bookings = sqlx::query_as::<
         _,
         (
             String,
@@ -182,172 +133,132 @@ async fn get_all_bookings(pool: web::Data<SqlitePool>, req: HttpRequest) -> Http
             Option<String>,
         ),
     >(
-        "SELECT b.id, b.equipment_id, b.user_id, b.department, b.start_date, b.end_date, b.purpose, b.status, b.created_at, b.updated_at, e.name as equipment_name 
-         FROM bookings b 
-         LEFT JOIN equipment e ON b.equipment_id = e.id 
-         WHERE b.status = 'active' 
+        "SELECT b.id, b.equipment_id, b.user_id, b.department, b.start_date, b.end_date, b.purpose, b.status, b.created_at, b.updated_at, e.name as equipment_name
+         FROM bookings b
+         LEFT JOIN equipment e ON b.equipment_id = e.id
+         WHERE b.status = 'active'
          ORDER BY b.start_date",
     )
     .fetch_all(pool.get_ref())
-    .await;
-
-    match result {
-        Ok(bookings) => {
-            // Get user names for each booking
-            let mut bookings_json: Vec<serde_json::Value> = Vec::new();
-
-            for (
-                id,
-                equipment_id,
-                user_id,
-                department,
-                start_date,
-                end_date,
-                purpose,
-                status,
-                created_at,
-                _updated_at,
-                equipment_name,
-            ) in bookings
-            {
-                // Get user name
-                let user_name = sqlx::query_as::<_, (String, String)>(
-                    "SELECT first_name, last_name FROM users WHERE id = ?",
-                )
-                .bind(&user_id)
-                .fetch_optional(pool.get_ref())
-                .await
-                .ok()
-                .flatten()
-                .map(|(f, l)| format!("{} {}", f, l))
-                .unwrap_or_else(|| "Unknown".to_string());
-
-                bookings_json.push(serde_json::json!({
-                    "id": id,
-                    "equipmentId": equipment_id,
-                    "equipmentName": equipment_name.unwrap_or_else(|| "Unknown".to_string()),
-                    "userId": user_id,
-                    "bookedBy": user_name,
-                    "department": department,
-                    "startDate": start_date,
-                    "endDate": end_date,
-                    "purpose": purpose,
-                    "status": status,
-                    "createdAt": created_at
-                }));
-            }
+    .await?;
+
+    // Get user names for each booking
+    let mut bookings_json: Vec<serde_json::Value> = Vec::new();
+
+    for (
+        id,
+        equipment_id,
+        user_id,
+        department,
+        start_date,
+        end_date,
+        purpose,
+        status,
+        created_at,
+        _updated_at,
+        equipment_name,
+    ) in bookings
+    {
+        // Get user name
+        let user_name = sqlx::query_as::<_, (String, String)>(
+            "SELECT first_name, last_name FROM users WHERE id = ?",
+        )
+        .bind(&user_id)
+        .fetch_optional(pool.get_ref())
+        .await
+        .ok()
+        .flatten()
+        .map(|(f, l)| format!("{} {}", f, l))
+        .unwrap_or_else(|| "Unknown".to_string());
+
+        bookings_json.push(serde_json::json!({
+            "id": id,
+            "equipmentId": equipment_id,
+            "equipmentName": equipment_name.unwrap_or_else(|| "Unknown".to_string()),
+            "userId": user_id,
+            "bookedBy": user_name,
+            "department": department,
+            "startDate": start_date,
+            "endDate": end_date,
+            "purpose": purpose,
+            "status": status,
+            "createdAt": created_at
+        }));
+    }
 
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "bookings": bookings_json
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "bookings": bookings_json
         }
-    }
+    })))
 }
 
 async fn get_equipment_details(
     pool: web::Data<SqlitePool>,
+    auth: AuthUser,
     path: web::Path<String>,
-) -> HttpResponse {
+) -> Result<HttpResponse, AppError> {
     let equipment_id = path.into_inner();
+    let today = timezone::user_time_zone(pool.get_ref(), &auth.id)
+        .await?
+        .today(timezone::now_utc());
 
-    let result = sqlx::query_as::<_, Equipment>(
+    let equipment = sqlx::query_as::<_, Equipment>(
         "SELECT id, name, category, status, serial_number, purchase_date, last_maintenance, notes, created_at, updated_at FROM equipment WHERE id = ?"
     )
     .bind(&equipment_id)
     .fetch_optional(pool.get_ref())
-    .await;
+    .await?
+    .ok_or_else(|| AppError::NotFound("Equipment not found".to_string()))?;
 
-    match result {
-        Ok(Some(equipment)) => {
-            // Get upcoming bookings
-            let bookings = sqlx::query_as::<_, Booking>(
-                "SELECT id, equipment_id, user_id, department, start_date, end_date, purpose, status, created_at, updated_at, cancelled_at FROM bookings WHERE equipment_id = ? AND status = 'active' AND start_date >= date('now') ORDER BY start_date"
-            )
-            .bind(&equipment_id)
-            .fetch_all(pool.get_ref())
-            .await
-            .unwrap_or_default();
-
-            let bookings_json: Vec<serde_json::Value> = bookings
-                .iter()
-                .map(|b| {
-                    serde_json::json!({
-                        "id": b.id,
-                        "startDate": b.start_date,
-                        "endDate": b.end_date,
-                        "status": b.status
-                    })
-                })
-                .collect();
-
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "equipment": {
-                        "id": equipment.id,
-                        "name": equipment.name,
-                        "category": equipment.category,
-                        "status": equipment.status,
-                        "serialNumber": equipment.serial_number,
-                        "purchaseDate": equipment.purchase_date,
-                        "lastMaintenance": equipment.last_maintenance,
-                        "notes": equipment.notes,
-                        "upcomingBookings": bookings_json
-                    }
-                }
-            }))
-        }
-        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "NOT_FOUND",
-                "message": "Equipment not found"
+    // Get upcoming bookings
+    let bookings = sqlx::query_as::<_, Booking>(
+        "SELECT id, equipment_id, user_id, department, start_date, end_date, purpose, status, created_at, updated_at, cancelled_at FROM bookings WHERE equipment_id = ? AND status = 'active' AND start_date >= ? ORDER BY start_date"
+    )
+    .bind(&equipment_id)
+    .bind(today.format("%Y-%m-%d").to_string())
+    .fetch_all(pool.get_ref())
+    .await
+    .unwrap_or_default();
+
+    let bookings_json: Vec<serde_json::Value> = bookings
+        .iter()
+        .map(|b| {
+            serde_json::json!({
+                "id": b.id,
+                "startDate": b.start_date,
+                "endDate": b.end_date,
+                "status": b.status
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "equipment": {
+                "id": equipment.id,
+                "name": equipment.name,
+                "category": equipment.category,
+                "status": equipment.status,
+                "serialNumber": equipment.serial_number,
+                "purchaseDate": equipment.purchase_date,
+                "lastMaintenance": equipment.last_maintenance,
+                "notes": equipment.notes,
+                "upcomingBookings": bookings_json
             }
-        })),
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
         }
-    }
+    })))
 }
 
 async fn create_booking(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: RequireRole<Member>,
     path: web::Path<String>,
     body: web::Json<CreateBookingRequest>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     let equipment_id = path.into_inner();
 
@@ -366,21 +277,18 @@ async fn create_booking(
     .await
     .unwrap_or_default();
 
-    if !conflicts.is_empty() {
-        return HttpResponse::Conflict().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "BOOKING_CONFLICT",
-                "message": "Equipment is already booked for this period",
-                "details": {
+    if let Some(conflict) = conflicts.first() {
+        return Err(
+            AppError::Conflict("Equipment is already booked for this period".to_string())
+                .with_code("BOOKING_CONFLICT")
+                .with_details(serde_json::json!({
                     "conflictingBooking": {
-                        "id": conflicts[0].id,
-                        "startDate": conflicts[0].start_date,
-                        "endDate": conflicts[0].end_date
+                        "id": conflict.id,
+                        "startDate": conflict.start_date,
+                        "endDate": conflict.end_date
                     }
-                }
-            }
-        }));
+                })),
+        );
     }
 
     // Get user's department
@@ -398,7 +306,7 @@ async fn create_booking(
 
     let booking_id = Uuid::new_v4().to_string();
 
-    let result = sqlx::query(
+    sqlx::query(
         "INSERT INTO bookings (id, equipment_id, user_id, department, start_date, end_date, purpose) VALUES (?, ?, ?, ?, ?, ?, ?)"
     )
     .bind(&booking_id)
@@ -409,55 +317,40 @@ async fn create_booking(
     .bind(&body.end_date)
     .bind(&body.purpose)
     .execute(pool.get_ref())
-    .await;
+    .await?;
 
-    match result {
-        Ok(_) => {
-            // Get equipment name
-            let equipment =
-                sqlx::query_as::<_, (String,)>("SELECT name FROM equipment WHERE id = ?")
-                    .bind(&equipment_id)
-                    .fetch_optional(pool.get_ref())
-                    .await;
-
-            let equipment_name = equipment.ok().flatten().map(|(n,)| n).unwrap_or_default();
-
-            HttpResponse::Created().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "booking": {
-                        "id": booking_id,
-                        "equipmentId": equipment_id,
-                        "equipmentName": equipment_name,
-                        "userId": user_id,
-                        "bookedBy": format!("{} {}", first_name, last_name),
-                        "department": department,
-                        "startDate": body.start_date,
-                        "endDate": body.end_date,
-                        "purpose": body.purpose,
-                        "status": "active"
-                    }
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to create booking"
-                }
-            }))
+    // Get equipment name
+    let equipment = sqlx::query_as::<_, (String,)>("SELECT name FROM equipment WHERE id = ?")
+        .bind(&equipment_id)
+        .fetch_optional(pool.get_ref())
+        .await;
+
+    let equipment_name = equipment.ok().flatten().map(|(n,)| n).unwrap_or_default();
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "booking": {
+                "id": booking_id,
+                "equipmentId": equipment_id,
+                "equipmentName": equipment_name,
+                "userId": user_id,
+                "bookedBy": format!("{} {}", first_name, last_name),
+                "department": department,
+                "startDate": body.start_date,
+                "endDate": body.end_date,
+                "purpose": body.purpose,
+                "status": "active"
+            }
         }
-    }
+    })))
 }
 
 async fn check_availability(
     pool: web::Data<SqlitePool>,
     path: web::Path<String>,
     body: web::Json<CheckAvailabilityRequest>,
-) -> HttpResponse {
+) -> Result<HttpResponse, AppError> {
     let equipment_id = path.into_inner();
 
     let conflicts = sqlx::query_as::<_, Booking>(
@@ -474,86 +367,69 @@ async fn check_availability(
     .await
     .unwrap_or_default();
 
-    if conflicts.is_empty() {
-        HttpResponse::Ok().json(serde_json::json!({
-            "success": true,
-            "data": {
-                "isAvailable": true,
-                "conflicts": []
-            }
-        }))
-    } else {
-        let conflicts_json: Vec<serde_json::Value> = conflicts
-            .iter()
-            .map(|c| {
-                serde_json::json!({
-                    "bookingId": c.id,
-                    "department": c.department,
-                    "startDate": c.start_date,
-                    "endDate": c.end_date
-                })
+    let conflicts_json: Vec<serde_json::Value> = conflicts
+        .iter()
+        .map(|c| {
+            serde_json::json!({
+                "bookingId": c.id,
+                "department": c.department,
+                "startDate": c.start_date,
+                "endDate": c.end_date
             })
-            .collect();
-
-        HttpResponse::Ok().json(serde_json::json!({
-            "success": true,
-            "data": {
-                "isAvailable": false,
-                "conflicts": conflicts_json
-            }
-        }))
-    }
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "isAvailable": conflicts_json.is_empty(),
+            "conflicts": conflicts_json
+        }
+    })))
 }
 
 async fn get_my_bookings(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: AuthUser,
     query: web::Query<GetBookingsQuery>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     // Get user info
-    let user = sqlx::query_as::<_, (String, String, String)>(
-        "SELECT first_name, last_name, department FROM users WHERE id = ?",
+    let user = sqlx::query_as::<_, (String, String)>(
+        "SELECT first_name, last_name FROM users WHERE id = ?",
     )
     .bind(&user_id)
     .fetch_optional(pool.get_ref())
     .await;
 
-    let (first_name, last_name, user_department) = user.ok().flatten().unwrap_or((
-        "Unknown".to_string(),
-        "User".to_string(),
-        "IT".to_string(),
-    ));
-
-    let mut sql = String::from(
-        "SELECT b.id, b.equipment_id, b.user_id, b.department, b.start_date, b.end_date, b.purpose, b.status, b.created_at, b.updated_at, b.cancelled_at, e.name as equipment_name FROM bookings b LEFT JOIN equipment e ON b.equipment_id = e.id WHERE b.user_id = ?",
-    );
-
-    if let Some(ref status) = query.status {
-        sql.push_str(&format!(" AND b.status = '{}'", status));
-    }
+    let (first_name, last_name) = user
+        .ok()
+        .flatten()
+        .unwrap_or(("Unknown".to_string(), "User".to_string()));
 
+    let mut filters = Filters::new();
+    filters
+        .eq("b.user_id", &user_id)
+        .eq_opt("b.status", query.status.as_ref());
     if query.upcoming.unwrap_or(false) {
-        sql.push_str(" AND b.start_date >= date('now')");
+        let today = timezone::user_time_zone(pool.get_ref(), &user_id)
+            .await?
+            .today(timezone::now_utc());
+        filters.condition(
+            "b.start_date >= ?",
+            vec![today.format("%Y-%m-%d").to_string().into()],
+        );
     }
 
-    sql.push_str(" ORDER BY b.start_date");
+    let mut sql = QueryBuilder::<Sqlite>::new(
+        "SELECT b.id, b.equipment_id, b.user_id, b.department, b.start_date, b.end_date, b.purpose, b.status, b.created_at, b.updated_at, b.cancelled_at, e.name as equipment_name FROM bookings b LEFT JOIN equipment e ON b.equipment_id = e.id",
+    );
+    filters.push_where(&mut sql);
+    sql.push(" ORDER BY b.start_date");
 
-    let result = sqlx::query_as::<
-        _,
-        (
+    let bookings = sql
+        .build_query_as::<(
             String,
             String,
             String,
@@ -566,94 +442,88 @@ async fn get_my_bookings(
             Option<String>,
             Option<String>,
             Option<String>,
-        ),
-    >(&sql)
-    .bind(&user_id)
-    .fetch_all(pool.get_ref())
-    .await;
+        )>()
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    let bookings_json: Vec<serde_json::Value> = bookings
+        .iter()
+        .map(|(id, equipment_id, _user_id, department, start_date, end_date, purpose, status, created_at, _updated_at, _cancelled_at, equipment_name)| {
+            serde_json::json!({
+                "id": id,
+                "equipmentId": equipment_id,
+                "equipmentName": equipment_name.clone().unwrap_or_else(|| "Unknown".to_string()),
+                "bookedBy": format!("{} {}", first_name, last_name),
+                "department": department,
+                "startDate": start_date,
+                "endDate": end_date,
+                "purpose": purpose,
+                "status": status,
+                "createdAt": created_at
+            })
+        })
+        .collect();
 
-    match result {
-        Ok(bookings) => {
-            let bookings_json: Vec<serde_json::Value> = bookings
-                .iter()
-                .map(|(id, equipment_id, _user_id, department, start_date, end_date, purpose, status, created_at, _updated_at, _cancelled_at, equipment_name)| {
-                    serde_json::json!({
-                        "id": id,
-                        "equipmentId": equipment_id,
-                        "equipmentName": equipment_name.clone().unwrap_or_else(|| "Unknown".to_string()),
-                        "bookedBy": format!("{} {}", first_name, last_name),
-                        "department": department,
-                        "startDate": start_date,
-                        "endDate": end_date,
-                        "purpose": purpose,
-                        "status": status,
-                        "createdAt": created_at
-                    })
-                })
-                .collect();
-
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "bookings": bookings_json
-                }
-            }))
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "bookings": bookings_json
         }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
-        }
-    }
+    })))
 }
 
-async fn cancel_booking(pool: web::Data<SqlitePool>, path: web::Path<String>) -> HttpResponse {
+async fn cancel_booking(
+    pool: web::Data<SqlitePool>,
+    auth: RequireRole<Member>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
     let booking_id = path.into_inner();
 
-    let result = sqlx::query(
+    let (owner_id, equipment_name, start_date) = sqlx::query_as::<_, (String, String, String)>(
+        "SELECT b.user_id, e.name, b.start_date FROM bookings b JOIN equipment e ON b.equipment_id = e.id WHERE b.id = ?",
+    )
+    .bind(&booking_id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFound("Booking not found".to_string()))?;
+
+    auth.ensure_owner_or_admin(
+        &owner_id,
+        "NOT_BOOKING_OWNER",
+        "Only the person who made this booking or an admin can cancel it",
+    )?;
+
+    sqlx::query(
         "UPDATE bookings SET status = 'cancelled', cancelled_at = datetime('now'), updated_at = datetime('now') WHERE id = ?"
     )
     .bind(&booking_id)
     .execute(pool.get_ref())
-    .await;
+    .await?;
+
+    if owner_id != auth.id {
+        create_notification(
+            pool.get_ref(),
+            &owner_id,
+            "info",
+            "Booking cancelled",
+            &format!(
+                "Your booking of {} starting {} was cancelled by an administrator",
+                equipment_name, start_date
+            ),
+            Some("booking"),
+            Some(&booking_id),
+        )
+        .await?;
+    }
 
-    match result {
-        Ok(rows) => {
-            if rows.rows_affected() > 0 {
-                HttpResponse::Ok().json(serde_json::json!({
-                    "success": true,
-                    "message": "Booking cancelled successfully",
-                    "data": {
-                        "booking": {
-                            "id": booking_id,
-                            "status": "cancelled"
-                        }
-                    }
-                }))
-            } else {
-                HttpResponse::NotFound().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "NOT_FOUND",
-                        "message": "Booking not found"
-                    }
-                }))
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Booking cancelled successfully",
+        "data": {
+            "booking": {
+                "id": booking_id,
+                "status": "cancelled"
             }
         }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to cancel booking"
-                }
-            }))
-        }
-    }
+    })))
 }
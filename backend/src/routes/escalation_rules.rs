@@ -0,0 +1,204 @@
+//! Urgency escalation rules, applied by `escalation`.
+//!
+//! Anyone can see the rules; only admins can change them. A department can
+//! have one rule per number of hours.
+
+use actix_web::{HttpResponse, web};
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::escalation::{MAX_RULE_HOURS, RULE_COLUMNS, RULE_URGENCIES};
+use crate::filters::Filters;
+use crate::middleware::guards::{Admin, AuthUser, RequireRole};
+use crate::models::tasks::{
+    CreateEscalationRuleRequest, EscalationRule, UpdateEscalationRuleRequest,
+};
+
+const DEPARTMENTS: [&str; 3] = ["IT", "Engineering", "Both"];
+
+#[derive(serde::Deserialize)]
+struct GetEscalationRulesQuery {
+    department: Option<String>,
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered ahead of the `/tasks` scope in `tasks`, like dependencies
+    cfg.service(
+        web::resource("/tasks/escalation-rules")
+            .route(web::get().to(get_escalation_rules))
+            .route(web::post().to(create_escalation_rule)),
+    )
+    .service(
+        web::resource("/tasks/escalation-rules/{rule_id}")
+            .route(web::put().to(update_escalation_rule))
+            .route(web::delete().to(delete_escalation_rule)),
+    );
+}
+
+async fn get_escalation_rules(
+    pool: web::Data<SqlitePool>,
+    _auth: AuthUser,
+    query: web::Query<GetEscalationRulesQuery>,
+) -> Result<HttpResponse, AppError> {
+    let mut filters = Filters::new();
+    filters.eq_opt("department", query.department.as_ref());
+
+    let mut sql = QueryBuilder::<Sqlite>::new(format!(
+        "SELECT {} FROM urgency_escalation_rules",
+        RULE_COLUMNS
+    ));
+    filters.push_where(&mut sql);
+    sql.push(" ORDER BY department, hours_before_deadline DESC");
+    let rules = sql
+        .build_query_as::<EscalationRule>()
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "rules": rules.iter().map(rule_json).collect::<Vec<_>>()
+        }
+    })))
+}
+
+async fn create_escalation_rule(
+    pool: web::Data<SqlitePool>,
+    auth: RequireRole<Admin>,
+    body: web::Json<CreateEscalationRuleRequest>,
+) -> Result<HttpResponse, AppError> {
+    if !DEPARTMENTS.contains(&body.department.as_str()) {
+        return Err(AppError::Validation(format!(
+            "department must be one of {}",
+            DEPARTMENTS.join(", ")
+        )));
+    }
+    validate(body.hours_before_deadline, &body.urgency)?;
+
+    let rule_id = Uuid::new_v4().to_string();
+    sqlx::query(
+        "INSERT INTO urgency_escalation_rules (id, department, hours_before_deadline, urgency, created_by) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(&rule_id)
+    .bind(&body.department)
+    .bind(body.hours_before_deadline)
+    .bind(&body.urgency)
+    .bind(&auth.id)
+    .execute(pool.get_ref())
+    .await
+    .map_err(duplicate_rule)?;
+
+    let rule = find_rule(pool.get_ref(), &rule_id).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "rule": rule_json(&rule)
+        }
+    })))
+}
+
+async fn update_escalation_rule(
+    pool: web::Data<SqlitePool>,
+    _auth: RequireRole<Admin>,
+    path: web::Path<String>,
+    body: web::Json<UpdateEscalationRuleRequest>,
+) -> Result<HttpResponse, AppError> {
+    let mut rule = find_rule(pool.get_ref(), &path.into_inner()).await?;
+    if let Some(hours) = body.hours_before_deadline {
+        rule.hours_before_deadline = hours;
+    }
+    if let Some(ref urgency) = body.urgency {
+        rule.urgency = urgency.clone();
+    }
+    validate(rule.hours_before_deadline, &rule.urgency)?;
+
+    sqlx::query(
+        "UPDATE urgency_escalation_rules SET hours_before_deadline = ?, urgency = ?, updated_at = datetime('now') WHERE id = ?",
+    )
+    .bind(rule.hours_before_deadline)
+    .bind(&rule.urgency)
+    .bind(&rule.id)
+    .execute(pool.get_ref())
+    .await
+    .map_err(duplicate_rule)?;
+
+    let rule = find_rule(pool.get_ref(), &rule.id).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "rule": rule_json(&rule)
+        }
+    })))
+}
+
+async fn delete_escalation_rule(
+    pool: web::Data<SqlitePool>,
+    _auth: RequireRole<Admin>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let rule = find_rule(pool.get_ref(), &path.into_inner()).await?;
+    sqlx::query("DELETE FROM urgency_escalation_rules WHERE id = ?")
+        .bind(&rule.id)
+        .execute(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Escalation rule deleted"
+    })))
+}
+
+async fn find_rule(pool: &SqlitePool, rule_id: &str) -> Result<EscalationRule, AppError> {
+    sqlx::query_as::<_, EscalationRule>(&format!(
+        "SELECT {} FROM urgency_escalation_rules WHERE id = ?",
+        RULE_COLUMNS
+    ))
+    .bind(rule_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| {
+        AppError::NotFound("Escalation rule not found".to_string()).with_code("RULE_NOT_FOUND")
+    })
+}
+
+fn validate(hours_before_deadline: i64, urgency: &str) -> Result<(), AppError> {
+    if !(-MAX_RULE_HOURS..=MAX_RULE_HOURS).contains(&hours_before_deadline) {
+        return Err(AppError::Validation(format!(
+            "hoursBeforeDeadline must be between {} and {}",
+            -MAX_RULE_HOURS, MAX_RULE_HOURS
+        )));
+    }
+    if !RULE_URGENCIES.contains(&urgency) {
+        return Err(AppError::Validation(format!(
+            "urgency must be one of {}",
+            RULE_URGENCIES.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+fn duplicate_rule(e: sqlx::Error) -> AppError {
+    let is_unique_violation = e
+        .as_database_error()
+        .is_some_and(|db_err| db_err.is_unique_violation());
+    if is_unique_violation {
+        return AppError::Conflict(
+            "The department already has a rule for this many hours".to_string(),
+        )
+        .with_code("RULE_EXISTS");
+    }
+    AppError::Database(e)
+}
+
+fn rule_json(rule: &EscalationRule) -> serde_json::Value {
+    serde_json::json!({
+        "id": rule.id,
+        "department": rule.department,
+        "hoursBeforeDeadline": rule.hours_before_deadline,
+        "urgency": rule.urgency,
+        "createdBy": rule.created_by,
+        "createdAt": rule.created_at,
+        "updatedAt": rule.updated_at
+    })
+}
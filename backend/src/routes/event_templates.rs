@@ -0,0 +1,624 @@
+//! Event templates: saved defaults for events that are created again and
+//! again, such as a weekly sync or a regular delivery.
+//!
+//! A template is personal, or shared with everyone in its department; a
+//! 'Both' template is shared with everyone. Only its creator or an admin can
+//! change or delete it.
+//!
+//! `POST /api/calendar/events` with a `templateId` starts from the template:
+//! its fields and attendees fill in whatever the request leaves out, and
+//! without an `endTime` the event lasts the template's duration.
+
+use actix_web::{HttpResponse, web};
+use chrono::Duration;
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::filters::Filters;
+use crate::middleware::guards::{AuthUser, Member, RequireRole};
+use crate::models::events::{
+    CreateEventRequest, CreateEventTemplateRequest, EventTemplate, GetEventTemplatesQuery,
+    TemplateVisibility, UpdateEventTemplateRequest,
+};
+use crate::recurrence::RRule;
+use crate::reminders;
+use crate::routes::events::parse_time;
+use crate::routes::scheduling;
+
+const TEMPLATE_COLUMNS: &str = "t.id, t.name, t.title, t.description, t.event_type, t.start_time, t.duration_minutes, t.location, t.meeting_url, t.department, t.recurrence_rule, t.reminder_minutes, t.visibility, t.created_by, t.created_at, t.updated_at";
+
+const DEPARTMENTS: [&str; 3] = ["IT", "Engineering", "Both"];
+const EVENT_TYPES: [&str; 3] = ["meeting", "deadline", "delivery"];
+
+/// Longest duration a template can set, one day
+const MAX_DURATION_MINUTES: i64 = 24 * 60;
+
+/// A default attendee with their name.
+#[derive(sqlx::FromRow)]
+struct TemplateAttendee {
+    template_id: String,
+    id: String,
+    first_name: String,
+    last_name: String,
+    email: String,
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered ahead of the `/calendar` scope in `events`, like the feed
+    cfg.service(
+        web::resource("/calendar/templates")
+            .route(web::get().to(get_templates))
+            .route(web::post().to(create_template)),
+    )
+    .service(
+        web::resource("/calendar/templates/{template_id}")
+            .route(web::get().to(get_template))
+            .route(web::put().to(update_template))
+            .route(web::delete().to(delete_template)),
+    );
+}
+
+async fn get_templates(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+    query: web::Query<GetEventTemplatesQuery>,
+) -> Result<HttpResponse, AppError> {
+    let mut filters = Filters::new();
+    visible_to(&mut filters, &auth);
+    if query.mine == Some(true) {
+        filters.eq("t.created_by", &auth.id);
+    }
+    if let Some(ref search) = query.search {
+        filters.search(&["t.name", "t.title", "t.description"], search);
+    }
+
+    let mut sql = QueryBuilder::<Sqlite>::new(format!(
+        "SELECT {} FROM event_templates t",
+        TEMPLATE_COLUMNS
+    ));
+    filters.push_where(&mut sql);
+    sql.push(" ORDER BY t.name");
+    let templates = sql
+        .build_query_as::<EventTemplate>()
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    let ids: Vec<String> = templates.iter().map(|t| t.id.clone()).collect();
+    let mut attendees = attendees_of(pool.get_ref(), &ids).await?;
+    let templates_json: Vec<serde_json::Value> = templates
+        .iter()
+        .map(|t| template_json(t, &auth, attendees.remove(&t.id).unwrap_or_default()))
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "templates": templates_json
+        }
+    })))
+}
+
+async fn get_template(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let template = find_visible(pool.get_ref(), &auth, &path.into_inner()).await?;
+    template_response(pool.get_ref(), &auth, &template, HttpResponse::Ok()).await
+}
+
+async fn create_template(
+    pool: web::Data<SqlitePool>,
+    auth: RequireRole<Member>,
+    body: web::Json<CreateEventTemplateRequest>,
+) -> Result<HttpResponse, AppError> {
+    if let Some(ref minutes) = body.reminder_minutes {
+        reminders::validate_minutes(minutes)?;
+    }
+    let mut template = EventTemplate {
+        id: Uuid::new_v4().to_string(),
+        name: body.name.trim().to_string(),
+        title: non_empty(body.title.as_deref()),
+        description: non_empty(body.description.as_deref()),
+        event_type: body.event_type.clone(),
+        start_time: non_empty(body.start_time.as_deref()),
+        duration_minutes: body.duration_minutes,
+        location: non_empty(body.location.as_deref()),
+        meeting_url: non_empty(body.meeting_url.as_deref()),
+        department: body
+            .department
+            .clone()
+            .unwrap_or_else(|| auth.department.clone()),
+        recurrence_rule: non_empty(body.recurrence_rule.as_deref()),
+        reminder_minutes: body
+            .reminder_minutes
+            .as_deref()
+            .map(reminders::format_minutes),
+        visibility: body
+            .visibility
+            .unwrap_or(TemplateVisibility::Personal)
+            .as_str()
+            .to_string(),
+        created_by: auth.id.clone(),
+        created_at: None,
+        updated_at: None,
+    };
+    validate(&mut template)?;
+    let attendee_ids = checked_attendees(pool.get_ref(), body.attendee_ids.as_deref()).await?;
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO event_templates (id, name, title, description, event_type, start_time, duration_minutes, location, meeting_url, department, recurrence_rule, reminder_minutes, visibility, created_by)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&template.id)
+    .bind(&template.name)
+    .bind(&template.title)
+    .bind(&template.description)
+    .bind(&template.event_type)
+    .bind(&template.start_time)
+    .bind(template.duration_minutes)
+    .bind(&template.location)
+    .bind(&template.meeting_url)
+    .bind(&template.department)
+    .bind(&template.recurrence_rule)
+    .bind(&template.reminder_minutes)
+    .bind(&template.visibility)
+    .bind(&template.created_by)
+    .execute(&mut *tx)
+    .await?;
+    if let Some(ref ids) = attendee_ids {
+        set_attendees(&mut tx, &template.id, ids).await?;
+    }
+    tx.commit().await?;
+
+    let template = find_visible(pool.get_ref(), &auth, &template.id).await?;
+    template_response(pool.get_ref(), &auth, &template, HttpResponse::Created()).await
+}
+
+async fn update_template(
+    pool: web::Data<SqlitePool>,
+    auth: RequireRole<Member>,
+    path: web::Path<String>,
+    body: web::Json<UpdateEventTemplateRequest>,
+) -> Result<HttpResponse, AppError> {
+    let mut template = find_visible(pool.get_ref(), &auth, &path.into_inner()).await?;
+    auth.ensure_owner_or_admin(
+        &template.created_by,
+        "NOT_TEMPLATE_OWNER",
+        "Only the template's creator or an admin can change it",
+    )?;
+
+    if let Some(ref name) = body.name {
+        template.name = name.trim().to_string();
+    }
+    if let Some(ref event_type) = body.event_type {
+        template.event_type = event_type.clone();
+    }
+    if let Some(ref department) = body.department {
+        template.department = department.clone();
+    }
+    if let Some(duration) = body.duration_minutes {
+        template.duration_minutes = Some(duration);
+    }
+    if let Some(visibility) = body.visibility {
+        template.visibility = visibility.as_str().to_string();
+    }
+    if let Some(ref minutes) = body.reminder_minutes {
+        reminders::validate_minutes(minutes)?;
+        template.reminder_minutes = Some(reminders::format_minutes(minutes));
+    }
+    for (field, value) in [
+        (&mut template.title, &body.title),
+        (&mut template.description, &body.description),
+        (&mut template.start_time, &body.start_time),
+        (&mut template.location, &body.location),
+        (&mut template.meeting_url, &body.meeting_url),
+        (&mut template.recurrence_rule, &body.recurrence_rule),
+    ] {
+        if let Some(value) = value {
+            *field = non_empty(Some(value));
+        }
+    }
+    validate(&mut template)?;
+    let attendee_ids = checked_attendees(pool.get_ref(), body.attendee_ids.as_deref()).await?;
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "UPDATE event_templates SET name = ?, title = ?, description = ?, event_type = ?, start_time = ?, duration_minutes = ?, location = ?, meeting_url = ?, department = ?, recurrence_rule = ?, reminder_minutes = ?, visibility = ?, updated_at = datetime('now')
+         WHERE id = ?",
+    )
+    .bind(&template.name)
+    .bind(&template.title)
+    .bind(&template.description)
+    .bind(&template.event_type)
+    .bind(&template.start_time)
+    .bind(template.duration_minutes)
+    .bind(&template.location)
+    .bind(&template.meeting_url)
+    .bind(&template.department)
+    .bind(&template.recurrence_rule)
+    .bind(&template.reminder_minutes)
+    .bind(&template.visibility)
+    .bind(&template.id)
+    .execute(&mut *tx)
+    .await?;
+    if let Some(ref ids) = attendee_ids {
+        set_attendees(&mut tx, &template.id, ids).await?;
+    }
+    tx.commit().await?;
+
+    let template = find_visible(pool.get_ref(), &auth, &template.id).await?;
+    template_response(pool.get_ref(), &auth, &template, HttpResponse::Ok()).await
+}
+
+async fn delete_template(
+    pool: web::Data<SqlitePool>,
+    auth: RequireRole<Member>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let template = find_visible(pool.get_ref(), &auth, &path.into_inner()).await?;
+    auth.ensure_owner_or_admin(
+        &template.created_by,
+        "NOT_TEMPLATE_OWNER",
+        "Only the template's creator or an admin can change it",
+    )?;
+
+    sqlx::query("DELETE FROM event_templates WHERE id = ?")
+        .bind(&template.id)
+        .execute(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Event template deleted"
+    })))
+}
+
+/// Limits `filters` to templates `user` can see (table alias `t`).
+fn visible_to(filters: &mut Filters, user: &AuthUser) {
+    filters.condition(
+        "(t.created_by = ? OR (t.visibility = 'department' AND (t.department = 'Both' OR t.department = ? OR ? = 'Both')))",
+        vec![
+            user.id.as_str().into(),
+            user.department.as_str().into(),
+            user.department.as_str().into(),
+        ],
+    );
+}
+
+/// The template, if `user` can see it.
+pub async fn find_visible(
+    pool: &SqlitePool,
+    user: &AuthUser,
+    template_id: &str,
+) -> Result<EventTemplate, AppError> {
+    let mut filters = Filters::new();
+    filters.eq("t.id", template_id);
+    visible_to(&mut filters, user);
+
+    let mut sql = QueryBuilder::<Sqlite>::new(format!(
+        "SELECT {} FROM event_templates t",
+        TEMPLATE_COLUMNS
+    ));
+    filters.push_where(&mut sql);
+    sql.build_query_as::<EventTemplate>()
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| {
+            AppError::NotFound("Event template not found".to_string())
+                .with_code("TEMPLATE_NOT_FOUND")
+        })
+}
+
+/// Checks the fields and normalizes the recurrence rule.
+fn validate(template: &mut EventTemplate) -> Result<(), AppError> {
+    if template.name.is_empty() {
+        return Err(AppError::Validation("name is required".to_string()));
+    }
+    if !EVENT_TYPES.contains(&template.event_type.as_str()) {
+        return Err(AppError::Validation(format!(
+            "eventType must be one of {}",
+            EVENT_TYPES.join(", ")
+        )));
+    }
+    if !DEPARTMENTS.contains(&template.department.as_str()) {
+        return Err(AppError::Validation(format!(
+            "department must be one of {}",
+            DEPARTMENTS.join(", ")
+        )));
+    }
+    if let Some(ref start_time) = template.start_time {
+        parse_time(start_time)?;
+    }
+    if template
+        .duration_minutes
+        .is_some_and(|m| !(1..=MAX_DURATION_MINUTES).contains(&m))
+    {
+        return Err(AppError::Validation(format!(
+            "durationMinutes must be between 1 and {}",
+            MAX_DURATION_MINUTES
+        )));
+    }
+    if let Some(ref rule) = template.recurrence_rule {
+        template.recurrence_rule = Some(RRule::parse(rule)?.to_string());
+    }
+    Ok(())
+}
+
+/// `ids` without repeats, once every one is known to be an active user.
+async fn checked_attendees(
+    pool: &SqlitePool,
+    ids: Option<&[String]>,
+) -> Result<Option<Vec<String>>, AppError> {
+    let Some(ids) = ids else {
+        return Ok(None);
+    };
+    let ids = scheduling::unique(ids);
+    let missing = scheduling::missing_ids(
+        pool,
+        "SELECT id FROM users WHERE COALESCE(is_active, 1) = 1 AND id IN (",
+        &ids,
+    )
+    .await?;
+    if !missing.is_empty() {
+        return Err(
+            AppError::NotFound("Some attendees do not exist".to_string())
+                .with_code("USER_NOT_FOUND")
+                .with_details(serde_json::json!({ "userIds": missing })),
+        );
+    }
+    Ok(Some(ids))
+}
+
+async fn set_attendees(
+    conn: &mut sqlx::SqliteConnection,
+    template_id: &str,
+    user_ids: &[String],
+) -> Result<(), AppError> {
+    sqlx::query("DELETE FROM event_template_attendees WHERE template_id = ?")
+        .bind(template_id)
+        .execute(&mut *conn)
+        .await?;
+    for user_id in user_ids {
+        sqlx::query("INSERT INTO event_template_attendees (template_id, user_id) VALUES (?, ?)")
+            .bind(template_id)
+            .bind(user_id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// The active default attendees of each template, by template id.
+async fn attendees_of(
+    pool: &SqlitePool,
+    template_ids: &[String],
+) -> Result<HashMap<String, Vec<TemplateAttendee>>, AppError> {
+    if template_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let mut sql = QueryBuilder::<Sqlite>::new(
+        "SELECT ta.template_id, u.id, u.first_name, u.last_name, u.email
+         FROM event_template_attendees ta JOIN users u ON u.id = ta.user_id
+         WHERE COALESCE(u.is_active, 1) = 1 AND ta.template_id IN (",
+    );
+    let mut ids = sql.separated(", ");
+    for id in template_ids {
+        ids.push_bind(id.clone());
+    }
+    sql.push(") ORDER BY u.first_name, u.last_name");
+
+    let mut attendees: HashMap<String, Vec<TemplateAttendee>> = HashMap::new();
+    for attendee in sql
+        .build_query_as::<TemplateAttendee>()
+        .fetch_all(pool)
+        .await?
+    {
+        attendees
+            .entry(attendee.template_id.clone())
+            .or_default()
+            .push(attendee);
+    }
+    Ok(attendees)
+}
+
+/// The ids of the template's active default attendees.
+pub async fn attendee_ids(pool: &SqlitePool, template_id: &str) -> Result<Vec<String>, AppError> {
+    Ok(attendees_of(pool, &[template_id.to_string()])
+        .await?
+        .remove(template_id)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|a| a.id)
+        .collect())
+}
+
+async fn template_response(
+    pool: &SqlitePool,
+    user: &AuthUser,
+    template: &EventTemplate,
+    mut response: actix_web::HttpResponseBuilder,
+) -> Result<HttpResponse, AppError> {
+    let attendees = attendees_of(pool, std::slice::from_ref(&template.id))
+        .await?
+        .remove(&template.id)
+        .unwrap_or_default();
+    Ok(response.json(serde_json::json!({
+        "success": true,
+        "data": {
+            "template": template_json(template, user, attendees)
+        }
+    })))
+}
+
+fn template_json(
+    template: &EventTemplate,
+    user: &AuthUser,
+    attendees: Vec<TemplateAttendee>,
+) -> serde_json::Value {
+    let attendees: Vec<serde_json::Value> = attendees
+        .into_iter()
+        .map(|a| {
+            serde_json::json!({
+                "id": a.id,
+                "firstName": a.first_name,
+                "lastName": a.last_name,
+                "email": a.email
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "id": template.id,
+        "name": template.name,
+        "title": template.title,
+        "description": template.description,
+        "eventType": template.event_type,
+        "startTime": template.start_time,
+        "durationMinutes": template.duration_minutes,
+        "location": template.location,
+        "meetingUrl": template.meeting_url,
+        "department": template.department,
+        "recurrenceRule": template.recurrence_rule,
+        "reminderMinutes": template.reminder_minutes.as_deref().map(reminders::parse_minutes),
+        "visibility": template.visibility,
+        "attendees": attendees,
+        "createdBy": template.created_by,
+        "canEdit": template.created_by == user.id || user.is_admin(),
+        "createdAt": template.created_at,
+        "updatedAt": template.updated_at
+    })
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
+/// Fills in from `template` whatever `body` leaves out. Without an end time,
+/// the event lasts the template's duration from whichever start it ends up
+/// with.
+pub fn apply(template: &EventTemplate, attendee_ids: Vec<String>, body: &mut CreateEventRequest) {
+    fn fill(field: &mut Option<String>, default: &Option<String>) {
+        if field.is_none() {
+            field.clone_from(default);
+        }
+    }
+
+    body.title.get_or_insert_with(|| {
+        template
+            .title
+            .clone()
+            .unwrap_or_else(|| template.name.clone())
+    });
+    body.event_type
+        .get_or_insert_with(|| template.event_type.clone());
+    body.department
+        .get_or_insert_with(|| template.department.clone());
+    fill(&mut body.description, &template.description);
+    fill(&mut body.start_time, &template.start_time);
+    fill(&mut body.location, &template.location);
+    fill(&mut body.meeting_url, &template.meeting_url);
+    // isRecurring: false asks for a single event
+    if body.is_recurring != Some(false) {
+        fill(&mut body.recurrence_rule, &template.recurrence_rule);
+    }
+    if body.reminder_minutes.is_none() {
+        body.reminder_minutes = template
+            .reminder_minutes
+            .as_deref()
+            .map(reminders::parse_minutes);
+    }
+    if body.end_time.is_none() {
+        body.end_time = template
+            .duration_minutes
+            .zip(body.start_time.as_deref())
+            .and_then(|(minutes, start)| {
+                let end = parse_time(start).ok()? + Duration::minutes(minutes);
+                Some(end.format("%H:%M").to_string())
+            });
+    }
+    body.attendee_ids.get_or_insert(attendee_ids);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template() -> EventTemplate {
+        EventTemplate {
+            id: "template-1".to_string(),
+            name: "Weekly BMS sync".to_string(),
+            title: None,
+            description: Some("Status of the building management system".to_string()),
+            event_type: "meeting".to_string(),
+            start_time: Some("10:00".to_string()),
+            duration_minutes: Some(45),
+            location: None,
+            meeting_url: Some("https://teams.example.com/bms".to_string()),
+            department: "Engineering".to_string(),
+            recurrence_rule: Some("FREQ=WEEKLY;BYDAY=TU".to_string()),
+            reminder_minutes: Some("15".to_string()),
+            visibility: "department".to_string(),
+            created_by: "user-1".to_string(),
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    fn request(json: serde_json::Value) -> CreateEventRequest {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn template_fills_in_what_the_request_leaves_out() {
+        let mut body = request(serde_json::json!({
+            "templateId": "template-1",
+            "eventDate": "2026-01-13"
+        }));
+        apply(&template(), vec!["user-2".to_string()], &mut body);
+
+        assert_eq!(body.title.as_deref(), Some("Weekly BMS sync"));
+        assert_eq!(body.event_type.as_deref(), Some("meeting"));
+        assert_eq!(body.department.as_deref(), Some("Engineering"));
+        assert_eq!(
+            (body.start_time.as_deref(), body.end_time.as_deref()),
+            (Some("10:00"), Some("10:45"))
+        );
+        assert_eq!(
+            body.meeting_url.as_deref(),
+            Some("https://teams.example.com/bms")
+        );
+        assert_eq!(
+            body.recurrence_rule.as_deref(),
+            Some("FREQ=WEEKLY;BYDAY=TU")
+        );
+        assert_eq!(body.reminder_minutes, Some(vec![15]));
+        assert_eq!(body.attendee_ids, Some(vec!["user-2".to_string()]));
+    }
+
+    #[test]
+    fn request_fields_override_the_template() {
+        let mut body = request(serde_json::json!({
+            "templateId": "template-1",
+            "title": "BMS sync (vendor)",
+            "eventDate": "2026-01-13",
+            "startTime": "23:30",
+            "isRecurring": false,
+            "attendeeIds": []
+        }));
+        apply(&template(), vec!["user-2".to_string()], &mut body);
+
+        assert_eq!(body.title.as_deref(), Some("BMS sync (vendor)"));
+        // The duration runs from the new start, past midnight
+        assert_eq!(body.end_time.as_deref(), Some("00:15"));
+        assert_eq!(body.recurrence_rule, None);
+        assert_eq!(body.attendee_ids, Some(Vec::new()));
+    }
+}
@@ -1,14 +1,78 @@
-use actix_web::{HttpMessage, HttpRequest, HttpResponse, web};
-use sqlx::SqlitePool;
+use actix_web::{HttpResponse, web};
+use chrono::{NaiveDate, NaiveTime};
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
+use std::collections::HashSet;
 use uuid::Uuid;
 
-use crate::models::events::{CreateEventRequest, Event, GetEventsQuery, UpdateEventRequest};
+use crate::error::AppError;
+use crate::filters::Filters;
+use crate::middleware::guards::{AuthUser, Member, RequireRole};
+use crate::models::events::{
+    ConflictQuery, CreateEventRequest, EditScope, EditScopeQuery, Event, EventAttendee,
+    GetEventsQuery, ProposedTime, RsvpRequest, UpdateEventRequest,
+};
+use crate::recurrence::{self, RRule};
+use crate::reminders;
+use crate::routes::event_templates;
+use crate::routes::notifications::create_notification;
+use crate::routes::scheduling;
+use crate::timezone::{self, TimeZone};
+
+pub const EVENT_COLUMNS: &str = "e.id, e.title, e.description, e.event_type, e.event_date, e.start_time, e.end_time, e.location, e.meeting_url, e.created_by, e.department, e.is_recurring, e.recurrence_rule, e.parent_event_id, e.created_at, e.updated_at, e.recurrence_exdates, e.original_date, e.ical_uid, e.reminder_minutes, e.timezone";
+
+/// Rows holding the rule of a recurring series
+const IS_SERIES: &str = "COALESCE(e.is_recurring, 0) = 1 AND e.recurrence_rule IS NOT NULL AND e.parent_event_id IS NULL";
+const IS_NOT_SERIES: &str = "NOT (COALESCE(e.is_recurring, 0) = 1 AND e.recurrence_rule IS NOT NULL AND e.parent_event_id IS NULL)";
 
 #[derive(serde::Deserialize)]
 struct AddAttendeesRequest {
     user_ids: Vec<String>,
 }
 
+/// An attendee with the invitee's name and response, as listed for an event.
+#[derive(sqlx::FromRow)]
+struct AttendeeRow {
+    id: String,
+    first_name: String,
+    last_name: String,
+    email: String,
+    status: String,
+    added_at: Option<String>,
+    response_comment: Option<String>,
+    responded_at: Option<String>,
+    proposed_date: Option<String>,
+    proposed_start_time: Option<String>,
+    proposed_end_time: Option<String>,
+}
+
+/// One dated entry of a calendar listing: a single event, an edited
+/// occurrence, or an occurrence expanded from a series.
+pub struct EventOccurrence {
+    pub event: Event,
+    /// `YYYY-MM-DD`
+    pub date: String,
+}
+
+/// What an edit or delete applies to once its scope is resolved.
+enum Target {
+    /// The row itself: a single event, a whole series or an edited occurrence
+    Row(Event),
+    /// One occurrence of a series
+    Occurrence(Event, NaiveDate),
+    /// An occurrence of a series and every later one
+    Following(Event, NaiveDate),
+}
+
+impl Target {
+    fn event(&self) -> &Event {
+        match self {
+            Target::Row(event) | Target::Occurrence(event, _) | Target::Following(event, _) => {
+                event
+            }
+        }
+    }
+}
+
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/calendar")
@@ -27,457 +91,1058 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
             .route(
                 "/events/{event_id}/attendees/{user_id}",
                 web::delete().to(remove_event_attendee),
-            ),
+            )
+            .route("/events/{event_id}/rsvp", web::post().to(respond_to_event)),
     );
 }
 
-async fn get_events(
-    pool: web::Data<SqlitePool>,
-    query: web::Query<GetEventsQuery>,
-) -> HttpResponse {
-    let mut sql = String::from(
-        "SELECT e.*, u.first_name, u.last_name FROM events e 
-         LEFT JOIN users u ON e.created_by = u.id 
-         WHERE e.event_date >= ? AND e.event_date <= ?",
-    );
+/// Events dated within `from..=to`, ordered by date and start time, with
+/// every recurring series expanded into one entry per occurrence. `filters`
+/// narrows the events by their own columns (table alias `e`).
+pub async fn events_in_range(
+    conn: &mut SqliteConnection,
+    filters: &Filters,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<EventOccurrence>, AppError> {
+    let from_date = from.format("%Y-%m-%d").to_string();
+    let to_date = to.format("%Y-%m-%d").to_string();
+
+    let mut singles = filters.clone();
+    singles
+        .condition("e.event_date >= ?", vec![from_date.into()])
+        .condition("e.event_date <= ?", vec![to_date.clone().into()])
+        .raw(IS_NOT_SERIES);
+    let mut sql = QueryBuilder::<Sqlite>::new(format!("SELECT {} FROM events e", EVENT_COLUMNS));
+    singles.push_where(&mut sql);
+
+    let mut entries: Vec<EventOccurrence> = sql
+        .build_query_as::<Event>()
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|event| EventOccurrence {
+            date: event.event_date.clone(),
+            event,
+        })
+        .collect();
+
+    let mut series = filters.clone();
+    series
+        .raw(IS_SERIES)
+        .condition("e.event_date <= ?", vec![to_date.into()]);
+    let mut sql = QueryBuilder::<Sqlite>::new(format!("SELECT {} FROM events e", EVENT_COLUMNS));
+    series.push_where(&mut sql);
+    let series = sql.build_query_as::<Event>().fetch_all(&mut *conn).await?;
 
-    let mut params: Vec<String> = vec![query.start_date.clone(), query.end_date.clone()];
+    let overridden = overridden_dates(conn, &series).await?;
 
-    if let Some(ref event_type) = query.r#type {
-        sql.push_str(" AND e.event_type = ?");
-        params.push(event_type.clone());
+    for event in series {
+        let expanded = recurrence::parse_date(&event.event_date).and_then(|start| {
+            let rule = RRule::parse(event.recurrence_rule.as_deref().unwrap_or_default())?;
+            let exdates = recurrence::parse_exdates(event.recurrence_exdates.as_deref());
+            Ok(rule.occurrences(start, &exdates, from, to))
+        });
+        let dates = match expanded {
+            Ok(dates) => dates,
+            Err(e) => {
+                log::warn!("Not expanding event {}: {}", event.id, e.message());
+                recurrence::parse_date(&event.event_date)
+                    .ok()
+                    .filter(|d| (from..=to).contains(d))
+                    .into_iter()
+                    .collect()
+            }
+        };
+
+        for date in dates {
+            let date = date.format("%Y-%m-%d").to_string();
+            if !overridden.contains(&(event.id.clone(), date.clone())) {
+                entries.push(EventOccurrence {
+                    event: event.clone(),
+                    date,
+                });
+            }
+        }
     }
 
-    if let Some(ref department) = query.department {
-        sql.push_str(" AND (e.department = ? OR e.department = 'Both')");
-        params.push(department.clone());
+    entries.sort_by(|a, b| {
+        (a.date.as_str(), a.event.start_time.as_str())
+            .cmp(&(b.date.as_str(), b.event.start_time.as_str()))
+    });
+    Ok(entries)
+}
+
+/// `(series id, date)` of every occurrence of `series` that was edited on its own.
+async fn overridden_dates(
+    conn: &mut SqliteConnection,
+    series: &[Event],
+) -> Result<HashSet<(String, String)>, AppError> {
+    if series.is_empty() {
+        return Ok(HashSet::new());
     }
 
-    sql.push_str(" ORDER BY e.event_date, e.start_time");
+    let mut sql = QueryBuilder::<Sqlite>::new(
+        "SELECT parent_event_id, original_date FROM events WHERE original_date IS NOT NULL AND parent_event_id IN (",
+    );
+    let mut ids = sql.separated(", ");
+    for event in series {
+        ids.push_bind(event.id.clone());
+    }
+    sql.push(")");
 
-    let result = sqlx::query_as::<_, Event>(&sql.replace("e.*, u.first_name, u.last_name", "e.id, e.title, e.description, e.event_type, e.event_date, e.start_time, e.end_time, e.location, e.meeting_url, e.created_by, e.department, e.is_recurring, e.recurrence_rule, e.parent_event_id, e.created_at, e.updated_at"))
-        .bind(&params[0])
-        .bind(&params[1])
-        .fetch_all(pool.get_ref())
-        .await;
+    Ok(sql
+        .build_query_as::<(String, String)>()
+        .fetch_all(conn)
+        .await?
+        .into_iter()
+        .collect())
+}
 
-    match result {
-        Ok(events) => {
-            let events_json: Vec<serde_json::Value> = events
-                .iter()
-                .map(|e| {
-                    serde_json::json!({
-                        "id": e.id,
-                        "title": e.title,
-                        "description": e.description,
-                        "eventType": e.event_type,
-                        "eventDate": e.event_date,
-                        "startTime": e.start_time,
-                        "endTime": e.end_time,
-                        "location": e.location,
-                        "meetingUrl": e.meeting_url,
-                        "department": e.department,
-                        "createdAt": e.created_at,
-                        "updatedAt": e.updated_at
-                    })
-                })
-                .collect();
-
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "events": events_json,
-                    "pagination": {
-                        "total": events_json.len(),
-                        "count": events_json.len()
-                    }
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
-        }
+pub fn occurrence_json(event: &Event, date: &str) -> serde_json::Value {
+    let (series_id, occurrence_date) = if event.is_series() {
+        (Some(event.id.clone()), Some(date.to_string()))
+    } else if event.is_override() {
+        (event.parent_event_id.clone(), event.original_date.clone())
+    } else {
+        (None, None)
+    };
+
+    serde_json::json!({
+        "id": event.id,
+        "title": event.title,
+        "description": event.description,
+        "eventType": event.event_type,
+        "eventDate": date,
+        "startTime": event.start_time,
+        "endTime": event.end_time,
+        "location": event.location,
+        "meetingUrl": event.meeting_url,
+        "department": event.department,
+        "isRecurring": event.is_recurring,
+        "recurrenceRule": event.recurrence_rule,
+        "seriesId": series_id,
+        "occurrenceDate": occurrence_date,
+        "reminderMinutes": event.reminder_minutes.as_deref().map(reminders::parse_minutes),
+        "timeZone": event.timezone,
+        "createdAt": event.created_at,
+        "updatedAt": event.updated_at
+    })
+}
+
+async fn get_events(
+    pool: web::Data<SqlitePool>,
+    query: web::Query<GetEventsQuery>,
+) -> Result<HttpResponse, AppError> {
+    let from = recurrence::parse_date(&query.start_date)?;
+    let to = recurrence::parse_date(&query.end_date)?;
+
+    let mut filters = Filters::new();
+    filters.eq_opt("e.event_type", query.r#type.as_ref());
+    if let Some(ref department) = query.department {
+        filters.condition(
+            "(e.department = ? OR e.department = 'Both')",
+            vec![department.into()],
+        );
     }
+
+    let events = events_in_range(&mut *pool.acquire().await?, &filters, from, to).await?;
+    let events_json: Vec<serde_json::Value> = events
+        .iter()
+        .map(|o| occurrence_json(&o.event, &o.date))
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "events": events_json,
+            "pagination": {
+                "total": events_json.len(),
+                "count": events_json.len()
+            }
+        }
+    })))
 }
 
 async fn create_event(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: RequireRole<Member>,
+    query: web::Query<ConflictQuery>,
     body: web::Json<CreateEventRequest>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
+    let mut body = body.into_inner();
+    if let Some(ref template_id) = body.template_id {
+        let template = event_templates::find_visible(pool.get_ref(), &auth, template_id).await?;
+        let attendee_ids = event_templates::attendee_ids(pool.get_ref(), &template.id).await?;
+        event_templates::apply(&template, attendee_ids, &mut body);
+    }
+    let (Some(title), Some(event_type), Some(start_time)) = (
+        body.title.clone(),
+        body.event_type.clone(),
+        body.start_time.clone(),
+    ) else {
+        return Err(AppError::Validation(
+            "title, eventType and startTime are required unless the template sets them".to_string(),
+        ));
+    };
+    // Events without an explicit department belong to the creator's
+    let department = body
+        .department
+        .clone()
+        .unwrap_or_else(|| auth.department.clone());
+
+    let recurrence_rule = match body.recurrence_rule.as_deref().map(str::trim) {
+        Some(rule) if !rule.is_empty() => {
+            recurrence::parse_date(&body.event_date)?;
+            Some(RRule::parse(rule)?.to_string())
+        }
+        _ if body.is_recurring == Some(true) => {
+            return Err(AppError::Validation(
+                "recurrenceRule is required for a recurring event".to_string(),
+            )
+            .with_code("INVALID_RECURRENCE_RULE"));
         }
+        _ => None,
+    };
+    let exdates = body
+        .exdates
+        .iter()
+        .flatten()
+        .map(|d| recurrence::parse_date(d))
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Some(ref minutes) = body.reminder_minutes {
+        reminders::validate_minutes(minutes)?;
+    }
+    // Times are in the organizer's zone unless the event names its own
+    let time_zone = match body.time_zone {
+        Some(ref name) => TimeZone::named(name)?,
+        None => timezone::user_time_zone(pool.get_ref(), &user_id).await?,
     };
 
-    let event_id = Uuid::new_v4().to_string();
-    let is_recurring = body.is_recurring.unwrap_or(false);
+    let is_recurring = recurrence_rule.is_some();
+    let mut event = Event::new(
+        title,
+        body.description.clone(),
+        event_type,
+        body.event_date.clone(),
+        start_time,
+        body.end_time.clone(),
+        body.location.clone(),
+        body.meeting_url.clone(),
+        user_id.clone(),
+        Some(department),
+        is_recurring,
+        recurrence_rule.clone(),
+    );
+    event.recurrence_exdates = recurrence_rule
+        .as_ref()
+        .and_then(|_| recurrence::format_exdates(&exdates));
+    event.reminder_minutes = body
+        .reminder_minutes
+        .as_deref()
+        .map(reminders::format_minutes);
+    event.timezone = time_zone.name().to_string();
+    let event_id = event.id.clone();
 
-    let result = sqlx::query(
-        "INSERT INTO events (id, title, description, event_type, event_date, start_time, end_time, location, meeting_url, created_by, department, is_recurring, recurrence_rule) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
-    )
-    .bind(&event_id)
-    .bind(&body.title)
-    .bind(&body.description)
-    .bind(&body.event_type)
-    .bind(&body.event_date)
-    .bind(&body.start_time)
-    .bind(&body.end_time)
-    .bind(&body.location)
-    .bind(&body.meeting_url)
-    .bind(&user_id)
-    .bind(&body.department)
-    .bind(is_recurring)
-    .bind(&body.recurrence_rule)
-    .execute(pool.get_ref())
-    .await;
-
-    match result {
-        Ok(_) => {
-            // Add attendees if provided
-            if let Some(ref attendee_ids) = body.attendee_ids {
-                for attendee_id in attendee_ids {
-                    let attendee_record_id = Uuid::new_v4().to_string();
-                    let _ = sqlx::query(
-                        "INSERT INTO event_attendees (id, event_id, user_id) VALUES (?, ?, ?)",
-                    )
-                    .bind(&attendee_record_id)
-                    .bind(&event_id)
-                    .bind(attendee_id)
-                    .execute(pool.get_ref())
-                    .await;
-                }
-            }
+    let mut tx = pool.begin().await?;
+    insert_event(&mut tx, &event).await?;
+    let mut participants = vec![user_id.clone()];
+    participants.extend(body.attendee_ids.iter().flatten().cloned());
+    scheduling::ensure_no_conflicts(&mut tx, &event, &participants, query.force.unwrap_or(false))
+        .await?;
+    tx.commit().await?;
 
-            HttpResponse::Created().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "event": {
-                        "id": event_id,
-                        "title": body.title,
-                        "description": body.description,
-                        "eventType": body.event_type,
-                        "eventDate": body.event_date,
-                        "startTime": body.start_time,
-                        "endTime": body.end_time,
-                        "location": body.location
-                    }
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to create event"
-                }
-            }))
-        }
+    // Add attendees if provided
+    if let Some(ref attendee_ids) = body.attendee_ids {
+        invite_attendees(pool.get_ref(), &event, &user_id, attendee_ids).await?;
     }
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "event": {
+                "id": event_id,
+                "title": event.title,
+                "description": event.description,
+                "eventType": event.event_type,
+                "eventDate": event.event_date,
+                "startTime": event.start_time,
+                "endTime": event.end_time,
+                "location": event.location,
+                "meetingUrl": event.meeting_url,
+                "department": event.department,
+                "templateId": body.template_id,
+                "isRecurring": is_recurring,
+                "recurrenceRule": recurrence_rule,
+                "reminderMinutes": body.reminder_minutes,
+                "timeZone": event.timezone
+            }
+        }
+    })))
 }
 
+/// Updates an event. For a recurring series, `?scope=this|following|all`
+/// with `occurrenceDate` picks what to change; see `Target`.
 async fn update_event(
     pool: web::Data<SqlitePool>,
+    auth: RequireRole<Member>,
     path: web::Path<String>,
+    query: web::Query<EditScopeQuery>,
+    conflicts: web::Query<ConflictQuery>,
     body: web::Json<UpdateEventRequest>,
-) -> HttpResponse {
+) -> Result<HttpResponse, AppError> {
     let event_id = path.into_inner();
-    let mut updates = Vec::new();
-    let mut params: Vec<String> = Vec::new();
-
-    if let Some(ref title) = body.title {
-        updates.push("title = ?");
-        params.push(title.clone());
-    }
-    if let Some(ref description) = body.description {
-        updates.push("description = ?");
-        params.push(description.clone());
-    }
-    if let Some(ref event_type) = body.event_type {
-        updates.push("event_type = ?");
-        params.push(event_type.clone());
+    if body.is_empty() {
+        return Err(AppError::Validation("No fields to update".to_string()));
     }
-    if let Some(ref event_date) = body.event_date {
-        updates.push("event_date = ?");
-        params.push(event_date.clone());
+    if let Some(ref minutes) = body.reminder_minutes {
+        reminders::validate_minutes(minutes)?;
     }
-    if let Some(ref start_time) = body.start_time {
-        updates.push("start_time = ?");
-        params.push(start_time.clone());
+    if let Some(ref time_zone) = body.time_zone {
+        timezone::validate(time_zone)?;
     }
-    if let Some(ref end_time) = body.end_time {
-        updates.push("end_time = ?");
-        params.push(end_time.clone());
+
+    let mut tx = pool.begin().await?;
+    let event = load_event(&mut tx, &event_id).await?;
+    let target = resolve_target(&mut tx, event, &query).await?;
+    auth.ensure_owner_or_admin(
+        &target.event().created_by,
+        "NOT_EVENT_OWNER",
+        "Only the event organizer or an admin can edit this event",
+    )?;
+
+    let updated = match target {
+        Target::Row(mut event) => {
+            event.apply_update(&body);
+            if let Some(ref rule) = body.recurrence_rule {
+                if event.is_override() {
+                    return Err(single_occurrence_rule_error());
+                }
+                set_recurrence_rule(&mut event, rule)?;
+            }
+            save_event(&mut tx, &event).await?;
+            event
+        }
+        Target::Occurrence(series, date) => {
+            if body.recurrence_rule.is_some() {
+                return Err(single_occurrence_rule_error());
+            }
+            edit_occurrence(&mut tx, &series, date, &body).await?
+        }
+        Target::Following(series, date) => edit_following(&mut tx, series, date, &body).await?,
+    };
+
+    // Only a change of time can create a conflict
+    if body.event_date.is_some()
+        || body.start_time.is_some()
+        || body.end_time.is_some()
+        || body.recurrence_rule.is_some()
+        || body.time_zone.is_some()
+    {
+        let mut participants: Vec<String> = sqlx::query_scalar(
+            "SELECT user_id FROM event_attendees WHERE event_id = ? AND status != 'declined'",
+        )
+        .bind(&updated.id)
+        .fetch_all(&mut *tx)
+        .await?;
+        participants.push(updated.created_by.clone());
+        scheduling::ensure_no_conflicts(
+            &mut tx,
+            &updated,
+            &participants,
+            conflicts.force.unwrap_or(false),
+        )
+        .await?;
     }
-    if let Some(ref location) = body.location {
-        updates.push("location = ?");
-        params.push(location.clone());
+
+    tx.commit().await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "event": occurrence_json(&updated, &updated.event_date)
+        }
+    })))
+}
+
+/// Deletes an event. For a recurring series, `?scope=this|following|all`
+/// with `occurrenceDate` picks what to remove; see `Target`.
+async fn delete_event(
+    pool: web::Data<SqlitePool>,
+    auth: RequireRole<Member>,
+    path: web::Path<String>,
+    query: web::Query<EditScopeQuery>,
+) -> Result<HttpResponse, AppError> {
+    let event_id = path.into_inner();
+
+    let mut tx = pool.begin().await?;
+    let event = load_event(&mut tx, &event_id).await?;
+    let target = resolve_target(&mut tx, event, &query).await?;
+    auth.ensure_owner_or_admin(
+        &target.event().created_by,
+        "NOT_EVENT_OWNER",
+        "Only the event organizer or an admin can delete this event",
+    )?;
+
+    match target {
+        Target::Row(event) => {
+            if let (Some(parent_id), Some(original_date)) =
+                (&event.parent_event_id, &event.original_date)
+            {
+                // Without the exclusion the series would show the date again
+                let mut series = load_event(&mut tx, parent_id).await?;
+                exclude_date(&mut series, recurrence::parse_date(original_date)?);
+                save_event(&mut tx, &series).await?;
+            }
+            sqlx::query(
+                "DELETE FROM events WHERE parent_event_id = ? AND original_date IS NOT NULL",
+            )
+            .bind(&event.id)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query("DELETE FROM events WHERE id = ?")
+                .bind(&event.id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        Target::Occurrence(mut series, date) => {
+            exclude_date(&mut series, date);
+            save_event(&mut tx, &series).await?;
+            sqlx::query("DELETE FROM events WHERE parent_event_id = ? AND original_date = ?")
+                .bind(&series.id)
+                .bind(date.format("%Y-%m-%d").to_string())
+                .execute(&mut *tx)
+                .await?;
+        }
+        Target::Following(mut series, date) => {
+            end_series_before(&mut series, date)?;
+            save_event(&mut tx, &series).await?;
+            sqlx::query("DELETE FROM events WHERE parent_event_id = ? AND original_date >= ?")
+                .bind(&series.id)
+                .bind(date.format("%Y-%m-%d").to_string())
+                .execute(&mut *tx)
+                .await?;
+        }
     }
-    if let Some(ref meeting_url) = body.meeting_url {
-        updates.push("meeting_url = ?");
-        params.push(meeting_url.clone());
+
+    tx.commit().await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Event deleted successfully"
+    })))
+}
+
+/// Works out what `scope` means for `event`:
+///
+/// - a single event is always edited as a whole;
+/// - a series is edited as a whole unless `scope` is `this` or `following`,
+///   which need an `occurrenceDate`; `following` from the first date is the
+///   whole series;
+/// - an edited occurrence is edited on its own, or stands for its date in the
+///   series with `following` or `all`.
+async fn resolve_target(
+    conn: &mut SqliteConnection,
+    event: Event,
+    query: &EditScopeQuery,
+) -> Result<Target, AppError> {
+    if let (Some(parent_id), Some(original_date)) = (&event.parent_event_id, &event.original_date) {
+        return match query.scope {
+            None | Some(EditScope::This) => Ok(Target::Row(event)),
+            Some(scope) => {
+                let date = recurrence::parse_date(original_date)?;
+                let series = load_event(conn, parent_id).await?;
+                series_target(series, scope, date)
+            }
+        };
     }
-    if let Some(ref department) = body.department {
-        updates.push("department = ?");
-        params.push(department.clone());
+
+    if !event.is_series() {
+        return Ok(Target::Row(event));
     }
 
-    if updates.is_empty() {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "INVALID_REQUEST",
-                "message": "No fields to update"
+    match query.scope {
+        None | Some(EditScope::All) => Ok(Target::Row(event)),
+        Some(scope) => {
+            let date = query.occurrence_date.as_deref().ok_or_else(|| {
+                AppError::Validation(
+                    "occurrenceDate is required with scope=this or scope=following".to_string(),
+                )
+            })?;
+            let date = recurrence::parse_date(date)?;
+            let start = recurrence::parse_date(&event.event_date)?;
+            let rule = RRule::parse(event.recurrence_rule.as_deref().unwrap_or_default())?;
+            let excluded =
+                recurrence::parse_exdates(event.recurrence_exdates.as_deref()).contains(&date);
+            if excluded || !rule.includes(start, date) {
+                return Err(AppError::NotFound(format!(
+                    "The event does not occur on {}",
+                    date.format("%Y-%m-%d")
+                ))
+                .with_code("OCCURRENCE_NOT_FOUND"));
             }
-        }));
+            series_target(event, scope, date)
+        }
     }
+}
+
+fn series_target(series: Event, scope: EditScope, date: NaiveDate) -> Result<Target, AppError> {
+    let start = recurrence::parse_date(&series.event_date)?;
+    Ok(match scope {
+        EditScope::This => Target::Occurrence(series, date),
+        EditScope::Following if date > start => Target::Following(series, date),
+        EditScope::Following | EditScope::All => Target::Row(series),
+    })
+}
+
+/// Edits one occurrence by creating (or updating) the row that replaces it.
+async fn edit_occurrence(
+    conn: &mut SqliteConnection,
+    series: &Event,
+    date: NaiveDate,
+    update: &UpdateEventRequest,
+) -> Result<Event, AppError> {
+    let date = date.format("%Y-%m-%d").to_string();
 
-    updates.push("updated_at = datetime('now')");
-    let query = format!("UPDATE events SET {} WHERE id = ?", updates.join(", "));
+    let existing = sqlx::query_as::<_, Event>(&format!(
+        "SELECT {} FROM events e WHERE e.parent_event_id = ? AND e.original_date = ?",
+        EVENT_COLUMNS
+    ))
+    .bind(&series.id)
+    .bind(&date)
+    .fetch_optional(&mut *conn)
+    .await?;
 
-    let mut query_builder = sqlx::query(&query);
-    for param in params {
-        query_builder = query_builder.bind(param);
+    if let Some(mut occurrence) = existing {
+        occurrence.apply_update(update);
+        save_event(conn, &occurrence).await?;
+        return Ok(occurrence);
     }
-    query_builder = query_builder.bind(&event_id);
-
-    match query_builder.execute(pool.get_ref()).await {
-        Ok(result) => {
-            if result.rows_affected() == 0 {
-                return HttpResponse::NotFound().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "NOT_FOUND",
-                        "message": "Event not found"
-                    }
-                }));
-            }
 
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "event": {
-                        "id": event_id,
-                        "title": body.title,
-                        "startTime": body.start_time
-                    }
-                }
-            }))
+    let mut occurrence = series.clone();
+    occurrence.id = Uuid::new_v4().to_string();
+    occurrence.event_date = date.clone();
+    occurrence.is_recurring = false;
+    occurrence.recurrence_rule = None;
+    occurrence.recurrence_exdates = None;
+    occurrence.parent_event_id = Some(series.id.clone());
+    occurrence.original_date = Some(date);
+    occurrence.ical_uid = None;
+    occurrence.apply_update(update);
+
+    insert_event(conn, &occurrence).await?;
+    copy_attendees(conn, &series.id, &occurrence.id).await?;
+    Ok(occurrence)
+}
+
+/// Splits the series at `date`: the original ends the day before, and a new
+/// series carrying the changes starts on `date`. Edited occurrences and
+/// excluded dates from `date` on move to the new series.
+async fn edit_following(
+    conn: &mut SqliteConnection,
+    series: Event,
+    date: NaiveDate,
+    update: &UpdateEventRequest,
+) -> Result<Event, AppError> {
+    let start = recurrence::parse_date(&series.event_date)?;
+    let rule = RRule::parse(series.recurrence_rule.as_deref().unwrap_or_default())?;
+    let (_, later_exdates): (Vec<NaiveDate>, Vec<NaiveDate>) =
+        recurrence::parse_exdates(series.recurrence_exdates.as_deref())
+            .into_iter()
+            .partition(|d| *d < date);
+
+    let mut following = series.clone();
+    following.id = Uuid::new_v4().to_string();
+    following.event_date = date.format("%Y-%m-%d").to_string();
+    following.recurrence_rule = Some(
+        RRule {
+            count: rule.count.map(|c| c - rule.count_before(start, date)),
+            ..rule
         }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to update event"
-                }
-            }))
+        .to_string(),
+    );
+    following.recurrence_exdates = recurrence::format_exdates(&later_exdates);
+    following.ical_uid = None;
+    following.apply_update(update);
+    if let Some(ref new_rule) = update.recurrence_rule {
+        set_recurrence_rule(&mut following, new_rule)?;
+    }
+
+    let mut earlier = series;
+    end_series_before(&mut earlier, date)?;
+    save_event(conn, &earlier).await?;
+
+    insert_event(conn, &following).await?;
+    copy_attendees(conn, &earlier.id, &following.id).await?;
+    sqlx::query(
+        "UPDATE events SET parent_event_id = ? WHERE parent_event_id = ? AND original_date >= ?",
+    )
+    .bind(&following.id)
+    .bind(&earlier.id)
+    .bind(date.format("%Y-%m-%d").to_string())
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(following)
+}
+
+/// Makes the series' last occurrence fall before `date`.
+fn end_series_before(series: &mut Event, date: NaiveDate) -> Result<(), AppError> {
+    let rule = RRule::parse(series.recurrence_rule.as_deref().unwrap_or_default())?;
+    let until = date
+        .pred_opt()
+        .ok_or_else(|| AppError::Validation("Date out of range".to_string()))?;
+    series.recurrence_rule = Some(
+        RRule {
+            count: None,
+            until: Some(until),
+            ..rule
         }
+        .to_string(),
+    );
+    let earlier: Vec<NaiveDate> = recurrence::parse_exdates(series.recurrence_exdates.as_deref())
+        .into_iter()
+        .filter(|d| *d < date)
+        .collect();
+    series.recurrence_exdates = recurrence::format_exdates(&earlier);
+    Ok(())
+}
+
+fn exclude_date(series: &mut Event, date: NaiveDate) {
+    let mut exdates = recurrence::parse_exdates(series.recurrence_exdates.as_deref());
+    exdates.push(date);
+    series.recurrence_exdates = recurrence::format_exdates(&exdates);
+}
+
+/// Sets or, with an empty `rule`, removes the recurrence of a series.
+fn set_recurrence_rule(event: &mut Event, rule: &str) -> Result<(), AppError> {
+    let rule = rule.trim();
+    if rule.is_empty() {
+        event.is_recurring = false;
+        event.recurrence_rule = None;
+        event.recurrence_exdates = None;
+    } else {
+        recurrence::parse_date(&event.event_date)?;
+        event.is_recurring = true;
+        event.recurrence_rule = Some(RRule::parse(rule)?.to_string());
     }
+    Ok(())
 }
 
-async fn delete_event(pool: web::Data<SqlitePool>, path: web::Path<String>) -> HttpResponse {
-    let event_id = path.into_inner();
+fn single_occurrence_rule_error() -> AppError {
+    AppError::Validation(
+        "recurrenceRule can only be changed for a whole series or scope=following".to_string(),
+    )
+    .with_code("INVALID_RECURRENCE_RULE")
+}
 
-    let result = sqlx::query("DELETE FROM events WHERE id = ?")
-        .bind(&event_id)
-        .execute(pool.get_ref())
-        .await;
+pub async fn load_event(conn: &mut SqliteConnection, event_id: &str) -> Result<Event, AppError> {
+    sqlx::query_as::<_, Event>(&format!(
+        "SELECT {} FROM events e WHERE e.id = ?",
+        EVENT_COLUMNS
+    ))
+    .bind(event_id)
+    .fetch_optional(conn)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Event not found".to_string()))
+}
 
-    match result {
-        Ok(rows) => {
-            if rows.rows_affected() > 0 {
-                HttpResponse::Ok().json(serde_json::json!({
-                    "success": true,
-                    "message": "Event deleted successfully"
-                }))
-            } else {
-                HttpResponse::NotFound().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "NOT_FOUND",
-                        "message": "Event not found"
-                    }
-                }))
-            }
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to delete event"
-                }
-            }))
-        }
+pub async fn insert_event(conn: &mut SqliteConnection, event: &Event) -> Result<(), AppError> {
+    sqlx::query(
+        "INSERT INTO events (id, title, description, event_type, event_date, start_time, end_time, location, meeting_url, created_by, department, is_recurring, recurrence_rule, parent_event_id, recurrence_exdates, original_date, ical_uid, reminder_minutes, timezone) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&event.id)
+    .bind(&event.title)
+    .bind(&event.description)
+    .bind(&event.event_type)
+    .bind(&event.event_date)
+    .bind(&event.start_time)
+    .bind(&event.end_time)
+    .bind(&event.location)
+    .bind(&event.meeting_url)
+    .bind(&event.created_by)
+    .bind(&event.department)
+    .bind(event.is_recurring)
+    .bind(&event.recurrence_rule)
+    .bind(&event.parent_event_id)
+    .bind(&event.recurrence_exdates)
+    .bind(&event.original_date)
+    .bind(&event.ical_uid)
+    .bind(&event.reminder_minutes)
+    .bind(&event.timezone)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn save_event(conn: &mut SqliteConnection, event: &Event) -> Result<(), AppError> {
+    sqlx::query(
+        "UPDATE events SET title = ?, description = ?, event_type = ?, event_date = ?, start_time = ?, end_time = ?, location = ?, meeting_url = ?, department = ?, is_recurring = ?, recurrence_rule = ?, parent_event_id = ?, recurrence_exdates = ?, original_date = ?, ical_uid = ?, reminder_minutes = ?, timezone = ?, updated_at = datetime('now') WHERE id = ?",
+    )
+    .bind(&event.title)
+    .bind(&event.description)
+    .bind(&event.event_type)
+    .bind(&event.event_date)
+    .bind(&event.start_time)
+    .bind(&event.end_time)
+    .bind(&event.location)
+    .bind(&event.meeting_url)
+    .bind(&event.department)
+    .bind(event.is_recurring)
+    .bind(&event.recurrence_rule)
+    .bind(&event.parent_event_id)
+    .bind(&event.recurrence_exdates)
+    .bind(&event.original_date)
+    .bind(&event.ical_uid)
+    .bind(&event.reminder_minutes)
+    .bind(&event.timezone)
+    .bind(&event.id)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn copy_attendees(
+    conn: &mut SqliteConnection,
+    from_event_id: &str,
+    to_event_id: &str,
+) -> Result<(), AppError> {
+    let attendees = sqlx::query_as::<_, (String, String)>(
+        "SELECT user_id, status FROM event_attendees WHERE event_id = ?",
+    )
+    .bind(from_event_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for (user_id, status) in attendees {
+        sqlx::query(
+            "INSERT OR IGNORE INTO event_attendees (id, event_id, user_id, status) VALUES (?, ?, ?, ?)",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(to_event_id)
+        .bind(&user_id)
+        .bind(&status)
+        .execute(&mut *conn)
+        .await?;
     }
+    Ok(())
 }
 
-async fn get_event_attendees(pool: web::Data<SqlitePool>, path: web::Path<String>) -> HttpResponse {
+async fn get_event_attendees(
+    pool: web::Data<SqlitePool>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
     let event_id = path.into_inner();
 
-    let result = sqlx::query_as::<_, (String, String, String, String, String, Option<String>)>(
-        "SELECT u.id, u.first_name, u.last_name, u.email, ea.status, ea.added_at 
-         FROM event_attendees ea 
-         JOIN users u ON ea.user_id = u.id 
+    let attendees = sqlx::query_as::<_, AttendeeRow>(
+        "SELECT u.id, u.first_name, u.last_name, u.email, ea.status, ea.added_at,
+                ea.response_comment, ea.responded_at,
+                ea.proposed_date, ea.proposed_start_time, ea.proposed_end_time
+         FROM event_attendees ea
+         JOIN users u ON ea.user_id = u.id
          WHERE ea.event_id = ?",
     )
     .bind(&event_id)
     .fetch_all(pool.get_ref())
-    .await;
-
-    match result {
-        Ok(attendees) => {
-            let attendees_json: Vec<serde_json::Value> = attendees
-                .iter()
-                .map(|(id, first_name, last_name, email, status, added_at)| {
-                    serde_json::json!({
-                        "id": id,
-                        "firstName": first_name,
-                        "lastName": last_name,
-                        "email": email,
-                        "status": status,
-                        "addedAt": added_at
-                    })
-                })
-                .collect();
-
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "attendees": attendees_json
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
+    .await?;
+
+    let count = |status: &str| attendees.iter().filter(|a| a.status == status).count();
+    let counts = serde_json::json!({
+        "pending": count("pending"),
+        "accepted": count("accepted"),
+        "declined": count("declined"),
+        "total": attendees.len()
+    });
+
+    let attendees_json: Vec<serde_json::Value> = attendees
+        .iter()
+        .map(|a| {
+            serde_json::json!({
+                "id": a.id,
+                "firstName": a.first_name,
+                "lastName": a.last_name,
+                "email": a.email,
+                "status": a.status,
+                "addedAt": a.added_at,
+                "responseComment": a.response_comment,
+                "respondedAt": a.responded_at,
+                "proposedTime": proposed_time_json(
+                    &a.proposed_date,
+                    &a.proposed_start_time,
+                    &a.proposed_end_time
+                )
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "attendees": attendees_json,
+            "counts": counts
         }
-    }
+    })))
 }
 
 async fn add_event_attendees(
     pool: web::Data<SqlitePool>,
+    auth: RequireRole<Member>,
     path: web::Path<String>,
     body: web::Json<AddAttendeesRequest>,
-) -> HttpResponse {
+) -> Result<HttpResponse, AppError> {
     let event_id = path.into_inner();
+    let event = load_event(&mut *pool.acquire().await?, &event_id).await?;
+    auth.ensure_owner_or_admin(
+        &event.created_by,
+        "NOT_EVENT_OWNER",
+        "Only the event organizer or an admin can invite attendees",
+    )?;
 
-    // Verify event exists
-    let event_exists = sqlx::query_scalar::<_, i32>("SELECT 1 FROM events WHERE id = ?")
-        .bind(&event_id)
-        .fetch_optional(pool.get_ref())
-        .await;
+    let added = invite_attendees(pool.get_ref(), &event, &auth.id, &body.user_ids).await?;
 
-    match event_exists {
-        Ok(Some(_)) => {}
-        Ok(None) => {
-            return HttpResponse::NotFound().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "NOT_FOUND",
-                    "message": "Event not found"
-                }
-            }));
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            return HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }));
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Attendees added successfully",
+        "data": {
+            "added": added
         }
-    }
+    })))
+}
 
-    // Add each user as an attendee
-    for user_id in &body.user_ids {
-        let attendee_record_id = Uuid::new_v4().to_string();
+/// Adds `user_ids` to the event's attendees and sends each new invitee an
+/// invitation, except the inviter. Users already invited are left as they
+/// are. Returns how many attendees were added.
+async fn invite_attendees(
+    pool: &SqlitePool,
+    event: &Event,
+    inviter_id: &str,
+    user_ids: &[String],
+) -> Result<usize, AppError> {
+    let inviter = user_name(pool, inviter_id).await?;
+    let mut added = 0;
+
+    for user_id in user_ids {
         let result = sqlx::query(
             "INSERT OR IGNORE INTO event_attendees (id, event_id, user_id) VALUES (?, ?, ?)",
         )
-        .bind(&attendee_record_id)
-        .bind(&event_id)
+        .bind(Uuid::new_v4().to_string())
+        .bind(&event.id)
         .bind(user_id)
-        .execute(pool.get_ref())
+        .execute(pool)
         .await;
 
-        if let Err(e) = result {
-            eprintln!("Failed to add attendee: {:?}", e);
+        match result {
+            Ok(result) if result.rows_affected() > 0 => {
+                added += 1;
+                if user_id != inviter_id {
+                    create_notification(
+                        pool,
+                        user_id,
+                        "meeting",
+                        &format!("Invitation: {}", event.title),
+                        &format!(
+                            "{} invited you to {} on {} at {}",
+                            inviter, event.title, event.event_date, event.start_time
+                        ),
+                        Some("event"),
+                        Some(&event.id),
+                    )
+                    .await?;
+                }
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Failed to add attendee: {:?}", e),
+        }
+    }
+    Ok(added)
+}
+
+/// Records the caller's response to an invitation and notifies the organizer.
+/// For a recurring series the response covers every occurrence that has not
+/// been edited on its own.
+async fn respond_to_event(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+    path: web::Path<String>,
+    body: web::Json<RsvpRequest>,
+) -> Result<HttpResponse, AppError> {
+    let event_id = path.into_inner();
+    let event = load_event(&mut *pool.acquire().await?, &event_id).await?;
+
+    let comment = body
+        .comment
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty());
+    let proposed = body
+        .proposed_time
+        .as_ref()
+        .map(|p| validate_proposed_time(p, &event))
+        .transpose()?;
+    let (proposed_date, proposed_start, proposed_end) = match &proposed {
+        Some((date, start, end)) => (
+            Some(date.as_str()),
+            Some(start.as_str()),
+            Some(end.as_str()),
+        ),
+        None => (None, None, None),
+    };
+
+    let result = sqlx::query(
+        "UPDATE event_attendees SET status = ?, response_comment = ?, responded_at = datetime('now'), proposed_date = ?, proposed_start_time = ?, proposed_end_time = ? WHERE event_id = ? AND user_id = ?",
+    )
+    .bind(body.status.as_str())
+    .bind(comment)
+    .bind(proposed_date)
+    .bind(proposed_start)
+    .bind(proposed_end)
+    .bind(&event_id)
+    .bind(&auth.id)
+    .execute(pool.get_ref())
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(
+            AppError::Forbidden("You are not invited to this event".to_string())
+                .with_code("NOT_INVITED"),
+        );
+    }
+
+    if event.created_by != auth.id {
+        let responder = user_name(pool.get_ref(), &auth.id).await?;
+        let mut message = format!(
+            "{} {} {} on {} at {}",
+            responder,
+            body.status.as_str(),
+            event.title,
+            event.event_date,
+            event.start_time
+        );
+        if let Some(comment) = comment {
+            message.push_str(&format!(": \"{}\"", comment));
         }
+        if let Some((date, start, end)) = &proposed {
+            message.push_str(&format!(". Proposed time: {} {}-{}", date, start, end));
+        }
+        create_notification(
+            pool.get_ref(),
+            &event.created_by,
+            "meeting",
+            &format!("{} {} {}", responder, body.status.as_str(), event.title),
+            &message,
+            Some("event"),
+            Some(&event.id),
+        )
+        .await?;
     }
 
-    HttpResponse::Ok().json(serde_json::json!({
+    let attendee = sqlx::query_as::<_, EventAttendee>(
+        "SELECT id, event_id, user_id, status, added_at, response_comment, responded_at, proposed_date, proposed_start_time, proposed_end_time FROM event_attendees WHERE event_id = ? AND user_id = ?",
+    )
+    .bind(&event_id)
+    .bind(&auth.id)
+    .fetch_one(pool.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
         "success": true,
-        "message": "Attendees added successfully"
-    }))
+        "data": {
+            "attendee": {
+                "eventId": attendee.event_id,
+                "userId": attendee.user_id,
+                "status": attendee.status,
+                "responseComment": attendee.response_comment,
+                "respondedAt": attendee.responded_at,
+                "proposedTime": proposed_time_json(
+                    &attendee.proposed_date,
+                    &attendee.proposed_start_time,
+                    &attendee.proposed_end_time
+                )
+            }
+        }
+    })))
+}
+
+/// Normalises a proposed time to `YYYY-MM-DD`, `HH:MM` and `HH:MM`.
+fn validate_proposed_time(
+    proposed: &ProposedTime,
+    event: &Event,
+) -> Result<(String, String, String), AppError> {
+    let date = recurrence::parse_date(proposed.date.as_deref().unwrap_or(&event.event_date))?;
+    let start = parse_time(&proposed.start_time)?;
+    let end = parse_time(&proposed.end_time)?;
+    if end <= start {
+        return Err(AppError::Validation(
+            "proposedTime.endTime must be after proposedTime.startTime".to_string(),
+        ));
+    }
+    Ok((
+        date.format("%Y-%m-%d").to_string(),
+        start.format("%H:%M").to_string(),
+        end.format("%H:%M").to_string(),
+    ))
+}
+
+pub fn parse_time(value: &str) -> Result<NaiveTime, AppError> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M:%S"))
+        .map_err(|_| AppError::Validation(format!("Invalid time `{}`, expected HH:MM", value)))
+}
+
+fn proposed_time_json(
+    date: &Option<String>,
+    start_time: &Option<String>,
+    end_time: &Option<String>,
+) -> serde_json::Value {
+    match (date, start_time, end_time) {
+        (Some(date), Some(start_time), Some(end_time)) => serde_json::json!({
+            "date": date,
+            "startTime": start_time,
+            "endTime": end_time
+        }),
+        _ => serde_json::Value::Null,
+    }
+}
+
+async fn user_name(pool: &SqlitePool, user_id: &str) -> Result<String, AppError> {
+    let (first_name, last_name) = sqlx::query_as::<_, (String, String)>(
+        "SELECT first_name, last_name FROM users WHERE id = ?",
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(format!("{} {}", first_name, last_name))
 }
 
 async fn remove_event_attendee(
     pool: web::Data<SqlitePool>,
+    auth: RequireRole<Member>,
     path: web::Path<(String, String)>,
-) -> HttpResponse {
+) -> Result<HttpResponse, AppError> {
     let (event_id, user_id) = path.into_inner();
+    let event = load_event(&mut *pool.acquire().await?, &event_id).await?;
+    // Attendees can always take themselves off the list
+    if user_id != auth.id {
+        auth.ensure_owner_or_admin(
+            &event.created_by,
+            "NOT_EVENT_OWNER",
+            "Only the event organizer or an admin can remove attendees",
+        )?;
+    }
 
     let result = sqlx::query("DELETE FROM event_attendees WHERE event_id = ? AND user_id = ?")
         .bind(&event_id)
         .bind(&user_id)
         .execute(pool.get_ref())
-        .await;
+        .await?;
 
-    match result {
-        Ok(r) => {
-            if r.rows_affected() == 0 {
-                HttpResponse::NotFound().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "NOT_FOUND",
-                        "message": "Attendee not found"
-                    }
-                }))
-            } else {
-                HttpResponse::Ok().json(serde_json::json!({
-                    "success": true,
-                    "message": "Attendee removed successfully"
-                }))
-            }
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to remove attendee"
-                }
-            }))
-        }
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Attendee not found".to_string()));
     }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Attendee removed successfully"
+    })))
 }
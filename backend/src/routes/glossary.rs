@@ -1,11 +1,11 @@
-use actix_web::{HttpMessage, HttpRequest, HttpResponse, web};
-use sqlx::SqlitePool;
+use actix_web::{HttpResponse, web};
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
 use uuid::Uuid;
 
-use crate::models::glossary::{
-    CreateTermRequest, GetTermsQuery, GlossaryCategory, GlossaryTerm, SearchQuery,
-    UpdateTermRequest,
-};
+use crate::error::AppError;
+use crate::filters::Filters;
+use crate::middleware::guards::{Admin, Member, RequireRole};
+use crate::models::glossary::{CreateTermRequest, GetTermsQuery, SearchQuery, UpdateTermRequest};
 
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -21,26 +21,26 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     );
 }
 
-async fn get_terms(pool: web::Data<SqlitePool>, query: web::Query<GetTermsQuery>) -> HttpResponse {
-    let mut sql = String::from(
-        "SELECT t.id, t.acronym, t.full_name, t.definition, t.category_id, c.name as category_name, t.is_approved, t.created_by, t.created_at, t.updated_at
-         FROM glossary_terms t
-         LEFT JOIN glossary_categories c ON t.category_id = c.id
-         WHERE 1=1"
-    );
-
-    if let Some(ref category_id) = query.category_id {
-        sql.push_str(&format!(" AND t.category_id = '{}'", category_id));
-    }
+async fn get_terms(
+    pool: web::Data<SqlitePool>,
+    query: web::Query<GetTermsQuery>,
+) -> Result<HttpResponse, AppError> {
+    let mut filters = Filters::new();
+    filters.eq_opt("t.category_id", query.category_id.as_ref());
     if query.approved_only.unwrap_or(false) {
-        sql.push_str(" AND t.is_approved = 1");
+        filters.raw("t.is_approved = 1");
     }
 
-    sql.push_str(" ORDER BY t.acronym");
+    let mut sql = QueryBuilder::<Sqlite>::new(
+        "SELECT t.id, t.acronym, t.full_name, t.definition, t.category_id, c.name as category_name, t.is_approved, t.created_by, t.created_at, t.updated_at
+         FROM glossary_terms t
+         LEFT JOIN glossary_categories c ON t.category_id = c.id"
+    );
+    filters.push_where(&mut sql);
+    sql.push(" ORDER BY t.acronym");
 
-    let result = sqlx::query_as::<
-        _,
-        (
+    let terms = sql
+        .build_query_as::<(
             String,
             String,
             String,
@@ -51,151 +51,108 @@ async fn get_terms(pool: web::Data<SqlitePool>, query: web::Query<GetTermsQuery>
             String,
             Option<String>,
             Option<String>,
-        ),
-    >(&sql)
-    .fetch_all(pool.get_ref())
-    .await;
-
-    match result {
-        Ok(terms) => {
-            let terms_json: Vec<serde_json::Value> = terms
-                .iter()
-                .map(
-                    |(
-                        id,
-                        acronym,
-                        full_name,
-                        definition,
-                        category_id,
-                        category_name,
-                        is_approved,
-                        created_by,
-                        created_at,
-                        updated_at,
-                    )| {
-                        serde_json::json!({
-                            "id": id,
-                            "acronym": acronym,
-                            "fullName": full_name,
-                            "definition": definition,
-                            "categoryId": category_id,
-                            "categoryName": category_name,
-                            "isApproved": is_approved,
-                            "createdBy": created_by,
-                            "createdAt": created_at,
-                            "updatedAt": updated_at
-                        })
-                    },
-                )
-                .collect();
-
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "terms": terms_json,
-                    "total": terms_json.len()
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
+        )>()
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    let terms_json: Vec<serde_json::Value> = terms
+        .iter()
+        .map(
+            |(
+                id,
+                acronym,
+                full_name,
+                definition,
+                category_id,
+                category_name,
+                is_approved,
+                created_by,
+                created_at,
+                updated_at,
+            )| {
+                serde_json::json!({
+                    "id": id,
+                    "acronym": acronym,
+                    "fullName": full_name,
+                    "definition": definition,
+                    "categoryId": category_id,
+                    "categoryName": category_name,
+                    "isApproved": is_approved,
+                    "createdBy": created_by,
+                    "createdAt": created_at,
+                    "updatedAt": updated_at
+                })
+            },
+        )
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "terms": terms_json,
+            "total": terms_json.len()
         }
-    }
+    })))
 }
 
-async fn get_term(pool: web::Data<SqlitePool>, path: web::Path<String>) -> HttpResponse {
+async fn get_term(
+    pool: web::Data<SqlitePool>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
     let term_id = path.into_inner();
 
-    let result = sqlx::query_as::<_, (String, String, String, Option<String>, Option<String>, bool, String, Option<String>, Option<String>)>(
+    let (
+        id,
+        acronym,
+        full_name,
+        definition,
+        category_id,
+        is_approved,
+        created_by,
+        created_at,
+        updated_at,
+    ) = sqlx::query_as::<_, (String, String, String, Option<String>, Option<String>, bool, String, Option<String>, Option<String>)>(
         "SELECT id, acronym, full_name, definition, category_id, is_approved, created_by, created_at, updated_at FROM glossary_terms WHERE id = ?"
     )
     .bind(&term_id)
     .fetch_optional(pool.get_ref())
-    .await;
-
-    match result {
-        Ok(Some((
-            id,
-            acronym,
-            full_name,
-            definition,
-            category_id,
-            is_approved,
-            created_by,
-            created_at,
-            updated_at,
-        ))) => {
-            // Get category name
-            let category =
-                sqlx::query_as::<_, (String,)>("SELECT name FROM glossary_categories WHERE id = ?")
-                    .bind(&category_id)
-                    .fetch_optional(pool.get_ref())
-                    .await
-                    .ok()
-                    .flatten();
-
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "term": {
-                        "id": id,
-                        "acronym": acronym,
-                        "fullName": full_name,
-                        "definition": definition,
-                        "categoryId": category_id,
-                        "categoryName": category.map(|(n,)| n),
-                        "isApproved": is_approved,
-                        "createdBy": created_by,
-                        "createdAt": created_at,
-                        "updatedAt": updated_at
-                    }
-                }
-            }))
-        }
-        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "NOT_FOUND",
-                "message": "Term not found"
+    .await?
+    .ok_or_else(|| AppError::NotFound("Term not found".to_string()))?;
+
+    // Get category name
+    let category =
+        sqlx::query_as::<_, (String,)>("SELECT name FROM glossary_categories WHERE id = ?")
+            .bind(&category_id)
+            .fetch_optional(pool.get_ref())
+            .await
+            .ok()
+            .flatten();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "term": {
+                "id": id,
+                "acronym": acronym,
+                "fullName": full_name,
+                "definition": definition,
+                "categoryId": category_id,
+                "categoryName": category.map(|(n,)| n),
+                "isApproved": is_approved,
+                "createdBy": created_by,
+                "createdAt": created_at,
+                "updatedAt": updated_at
             }
-        })),
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
         }
-    }
+    })))
 }
 
 async fn create_term(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: RequireRole<Member>,
     body: web::Json<CreateTermRequest>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     let term_id = Uuid::new_v4().to_string();
 
@@ -209,7 +166,7 @@ async fn create_term(
         }
     });
 
-    let result = sqlx::query(
+    sqlx::query(
         "INSERT INTO glossary_terms (id, acronym, full_name, definition, category_id, created_by, is_approved) VALUES (?, ?, ?, ?, ?, ?, 0)"
     )
     .bind(&term_id)
@@ -219,203 +176,142 @@ async fn create_term(
     .bind(&category_id)
     .bind(&user_id)
     .execute(pool.get_ref())
+    .await?;
+
+    // Log to glossary_history
+    let _ = sqlx::query(
+        "INSERT INTO glossary_history (id, term_id, user_id, action, new_value) VALUES (?, ?, ?, 'created', ?)"
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(&term_id)
+    .bind(&user_id)
+    .bind(format!("Term '{}' created", body.term))
+    .execute(pool.get_ref())
     .await;
 
-    match result {
-        Ok(_) => {
-            // Log to glossary_history
-            let _ = sqlx::query(
-                "INSERT INTO glossary_history (id, term_id, user_id, action, new_value) VALUES (?, ?, ?, 'created', ?)"
-            )
-            .bind(Uuid::new_v4().to_string())
-            .bind(&term_id)
-            .bind(&user_id)
-            .bind(format!("Term '{}' created", body.term))
-            .execute(pool.get_ref())
-            .await;
-
-            HttpResponse::Created().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "term": {
-                        "id": term_id,
-                        "acronym": body.term,
-                        "fullName": body.term,
-                        "definition": body.definition,
-                        "categoryId": body.category_id,
-                        "isApproved": false,
-                        "createdBy": user_id
-                    }
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to create term"
-                }
-            }))
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "term": {
+                "id": term_id,
+                "acronym": body.term,
+                "fullName": body.term,
+                "definition": body.definition,
+                "categoryId": body.category_id,
+                "isApproved": false,
+                "createdBy": user_id
+            }
         }
-    }
+    })))
 }
 
 async fn update_term(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: RequireRole<Member>,
     path: web::Path<String>,
     body: web::Json<UpdateTermRequest>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     let term_id = path.into_inner();
 
-    let mut updates = vec![];
+    let mut updates = Vec::new();
+    let mut params: Vec<String> = Vec::new();
+
     if let Some(ref term) = body.term {
-        updates.push(format!("acronym = '{}'", term));
-        updates.push(format!("full_name = '{}'", term));
+        updates.push("acronym = ?");
+        params.push(term.clone());
+        updates.push("full_name = ?");
+        params.push(term.clone());
     }
     if let Some(ref definition) = body.definition {
-        updates.push(format!("definition = '{}'", definition));
+        updates.push("definition = ?");
+        params.push(definition.clone());
     }
     if let Some(ref category_id) = body.category_id {
         // Only update if it's a valid UUID, otherwise set to NULL
         if uuid::Uuid::parse_str(category_id).is_ok() {
-            updates.push(format!("category_id = '{}'", category_id));
+            updates.push("category_id = ?");
+            params.push(category_id.clone());
         }
     }
 
     if updates.is_empty() {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "BAD_REQUEST",
-                "message": "No fields to update"
-            }
-        }));
+        return Err(AppError::Validation("No fields to update".to_string()));
     }
 
-    updates.push("updated_at = datetime('now')".to_string());
-    let sql = format!(
+    updates.push("updated_at = datetime('now')");
+    let query = format!(
         "UPDATE glossary_terms SET {} WHERE id = ?",
         updates.join(", ")
     );
 
-    let result = sqlx::query(&sql)
-        .bind(&term_id)
-        .execute(pool.get_ref())
-        .await;
-
-    match result {
-        Ok(rows) => {
-            if rows.rows_affected() > 0 {
-                // Log to glossary_history
-                let _ = sqlx::query(
-                    "INSERT INTO glossary_history (id, term_id, action, user_id, new_value) VALUES (?, ?, 'updated', ?, ?)"
-                )
-                .bind(Uuid::new_v4().to_string())
-                .bind(&term_id)
-                .bind(&user_id)
-                .bind("Term updated")
-                .execute(pool.get_ref())
-                .await;
-
-                // Fetch updated term
-                let term = sqlx::query_as::<_, (String, String, String, Option<String>, Option<String>, bool, String, Option<String>, Option<String>)>(
-                    "SELECT id, acronym, full_name, definition, category_id, is_approved, created_by, created_at, updated_at FROM glossary_terms WHERE id = ?"
-                )
-                .bind(&term_id)
-                .fetch_optional(pool.get_ref())
-                .await;
-
-                match term {
-                    Ok(Some((
-                        id,
-                        acronym,
-                        full_name,
-                        definition,
-                        category_id,
-                        is_approved,
-                        created_by,
-                        created_at,
-                        updated_at,
-                    ))) => HttpResponse::Ok().json(serde_json::json!({
-                        "success": true,
-                        "data": {
-                            "term": {
-                                "id": id,
-                                "acronym": acronym,
-                                "fullName": full_name,
-                                "definition": definition,
-                                "categoryId": category_id,
-                                "isApproved": is_approved,
-                                "createdBy": created_by,
-                                "createdAt": created_at,
-                                "updatedAt": updated_at
-                            }
-                        }
-                    })),
-                    _ => HttpResponse::NotFound().json(serde_json::json!({
-                        "success": false,
-                        "error": {
-                            "code": "NOT_FOUND",
-                            "message": "Term not found"
-                        }
-                    })),
-                }
-            } else {
-                HttpResponse::NotFound().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "NOT_FOUND",
-                        "message": "Term not found"
-                    }
-                }))
+    let mut query_builder = sqlx::query(&query);
+    for param in params {
+        query_builder = query_builder.bind(param);
+    }
+    query_builder = query_builder.bind(&term_id);
+
+    let rows = query_builder.execute(pool.get_ref()).await?;
+
+    if rows.rows_affected() == 0 {
+        return Err(AppError::NotFound("Term not found".to_string()));
+    }
+
+    // Log to glossary_history
+    let _ = sqlx::query(
+        "INSERT INTO glossary_history (id, term_id, action, user_id, new_value) VALUES (?, ?, 'updated', ?, ?)"
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(&term_id)
+    .bind(&user_id)
+    .bind("Term updated")
+    .execute(pool.get_ref())
+    .await;
+
+    // Fetch updated term
+    let (
+        id,
+        acronym,
+        full_name,
+        definition,
+        category_id,
+        is_approved,
+        created_by,
+        created_at,
+        updated_at,
+    ) = sqlx::query_as::<_, (String, String, String, Option<String>, Option<String>, bool, String, Option<String>, Option<String>)>(
+        "SELECT id, acronym, full_name, definition, category_id, is_approved, created_by, created_at, updated_at FROM glossary_terms WHERE id = ?"
+    )
+    .bind(&term_id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFound("Term not found".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "term": {
+                "id": id,
+                "acronym": acronym,
+                "fullName": full_name,
+                "definition": definition,
+                "categoryId": category_id,
+                "isApproved": is_approved,
+                "createdBy": created_by,
+                "createdAt": created_at,
+                "updatedAt": updated_at
             }
         }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to update term"
-                }
-            }))
-        }
-    }
+    })))
 }
 
 async fn delete_term(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: RequireRole<Admin>,
     path: web::Path<String>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     let term_id = path.into_inner();
 
@@ -429,167 +325,99 @@ async fn delete_term(
     .execute(pool.get_ref())
     .await;
 
-    let result = sqlx::query("DELETE FROM glossary_terms WHERE id = ?")
+    let rows = sqlx::query("DELETE FROM glossary_terms WHERE id = ?")
         .bind(&term_id)
         .execute(pool.get_ref())
-        .await;
-
-    match result {
-        Ok(rows) => {
-            if rows.rows_affected() > 0 {
-                HttpResponse::Ok().json(serde_json::json!({
-                    "success": true,
-                    "message": "Term deleted successfully"
-                }))
-            } else {
-                HttpResponse::NotFound().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "NOT_FOUND",
-                        "message": "Term not found"
-                    }
-                }))
-            }
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to delete term"
-                }
-            }))
-        }
+        .await?;
+
+    if rows.rows_affected() == 0 {
+        return Err(AppError::NotFound("Term not found".to_string()));
     }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Term deleted successfully"
+    })))
 }
 
 async fn approve_term(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: RequireRole<Admin>,
     path: web::Path<String>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     let term_id = path.into_inner();
 
-    let result = sqlx::query(
+    let rows = sqlx::query(
         "UPDATE glossary_terms SET is_approved = 1, updated_at = datetime('now') WHERE id = ?",
     )
     .bind(&term_id)
     .execute(pool.get_ref())
+    .await?;
+
+    if rows.rows_affected() == 0 {
+        return Err(AppError::NotFound("Term not found".to_string()));
+    }
+
+    // Log to glossary_history
+    let _ = sqlx::query(
+        "INSERT INTO glossary_history (id, term_id, action, changed_by, changes) VALUES (?, ?, 'approved', ?, 'Term approved')"
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(&term_id)
+    .bind(&user_id)
+    .execute(pool.get_ref())
     .await;
 
-    match result {
-        Ok(rows) => {
-            if rows.rows_affected() > 0 {
-                // Log to glossary_history
-                let _ = sqlx::query(
-                    "INSERT INTO glossary_history (id, term_id, action, changed_by, changes) VALUES (?, ?, 'approved', ?, 'Term approved')"
-                )
-                .bind(Uuid::new_v4().to_string())
-                .bind(&term_id)
-                .bind(&user_id)
-                .execute(pool.get_ref())
-                .await;
-
-                HttpResponse::Ok().json(serde_json::json!({
-                    "success": true,
-                    "message": "Term approved successfully",
-                    "data": {
-                        "term": {
-                            "id": term_id,
-                            "isApproved": true
-                        }
-                    }
-                }))
-            } else {
-                HttpResponse::NotFound().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "NOT_FOUND",
-                        "message": "Term not found"
-                    }
-                }))
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Term approved successfully",
+        "data": {
+            "term": {
+                "id": term_id,
+                "isApproved": true
             }
         }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to approve term"
-                }
-            }))
-        }
-    }
+    })))
 }
 
-async fn get_categories(pool: web::Data<SqlitePool>) -> HttpResponse {
-    let result = sqlx::query_as::<_, (String, String, i32, Option<String>, Option<String>)>(
+async fn get_categories(pool: web::Data<SqlitePool>) -> Result<HttpResponse, AppError> {
+    let categories = sqlx::query_as::<_, (String, String, i32, Option<String>, Option<String>)>(
         "SELECT id, name, display_order, created_at, updated_at FROM glossary_categories ORDER BY display_order, name"
     )
     .fetch_all(pool.get_ref())
-    .await;
-
-    match result {
-        Ok(categories) => {
-            let categories_json: Vec<serde_json::Value> = categories
-                .iter()
-                .map(|(id, name, display_order, created_at, updated_at)| {
-                    serde_json::json!({
-                        "id": id,
-                        "name": name,
-                        "displayOrder": display_order,
-                        "createdAt": created_at,
-                        "updatedAt": updated_at
-                    })
-                })
-                .collect();
-
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "categories": categories_json
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
+    .await?;
+
+    let categories_json: Vec<serde_json::Value> = categories
+        .iter()
+        .map(|(id, name, display_order, created_at, updated_at)| {
+            serde_json::json!({
+                "id": id,
+                "name": name,
+                "displayOrder": display_order,
+                "createdAt": created_at,
+                "updatedAt": updated_at
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "categories": categories_json
         }
-    }
+    })))
 }
 
-async fn search_terms(pool: web::Data<SqlitePool>, query: web::Query<SearchQuery>) -> HttpResponse {
+async fn search_terms(
+    pool: web::Data<SqlitePool>,
+    query: web::Query<SearchQuery>,
+) -> Result<HttpResponse, AppError> {
     let search = query.q.as_deref().unwrap_or("");
 
     if search.is_empty() {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "BAD_REQUEST",
-                "message": "Search query is required"
-            }
-        }));
+        return Err(AppError::Validation("Search query is required".to_string()));
     }
 
     let sql = String::from(
@@ -605,7 +433,7 @@ async fn search_terms(pool: web::Data<SqlitePool>, query: web::Query<SearchQuery
     let starts_with = format!("{}%", search);
     let limit = query.limit.unwrap_or(20);
 
-    let result = sqlx::query_as::<
+    let terms = sqlx::query_as::<
         _,
         (
             String,
@@ -622,43 +450,29 @@ async fn search_terms(pool: web::Data<SqlitePool>, query: web::Query<SearchQuery
     .bind(&starts_with)
     .bind(limit)
     .fetch_all(pool.get_ref())
-    .await;
-
-    match result {
-        Ok(terms) => {
-            let terms_json: Vec<serde_json::Value> = terms
-                .iter()
-                .map(
-                    |(id, acronym, full_name, definition, category_id, category_name)| {
-                        serde_json::json!({
-                            "id": id,
-                            "acronym": acronym,
-                            "fullName": full_name,
-                            "definition": definition,
-                            "categoryId": category_id,
-                            "categoryName": category_name
-                        })
-                    },
-                )
-                .collect();
-
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "results": terms_json,
-                    "total": terms_json.len()
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
+    .await?;
+
+    let terms_json: Vec<serde_json::Value> = terms
+        .iter()
+        .map(
+            |(id, acronym, full_name, definition, category_id, category_name)| {
+                serde_json::json!({
+                    "id": id,
+                    "acronym": acronym,
+                    "fullName": full_name,
+                    "definition": definition,
+                    "categoryId": category_id,
+                    "categoryName": category_name
+                })
+            },
+        )
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "results": terms_json,
+            "total": terms_json.len()
         }
-    }
+    })))
 }
@@ -1,13 +1,23 @@
 pub mod auth;
+pub mod calendar_feed;
+pub mod calendar_import;
+pub mod calendar_range;
 pub mod dashboard;
 pub mod equipment;
+pub mod escalation_rules;
+pub mod event_templates;
 pub mod events;
 pub mod glossary;
 pub mod locations;
 pub mod notifications;
 pub mod projects;
 pub mod quick_links;
+pub mod recurring_tasks;
+pub mod scheduling;
 pub mod search;
+pub mod task_checklists;
+pub mod task_dependencies;
+pub mod task_history;
 pub mod tasks;
 pub mod tracking;
 pub mod users;
@@ -1,10 +1,15 @@
-use actix_web::{HttpMessage, HttpRequest, HttpResponse, web};
-use sqlx::SqlitePool;
+use actix_web::{HttpResponse, web};
+use sqlx::{QueryBuilder, Sqlite, SqliteExecutor, SqlitePool};
 use uuid::Uuid;
 
+use crate::error::AppError;
+use crate::filters::{Filters, push_limit};
+use crate::middleware::guards::AuthUser;
 use crate::models::notifications::{
     GetNotificationsQuery, Notification, NotificationPreference, UpdatePreferencesRequest,
+    UpdateReminderPreferencesRequest,
 };
+use crate::reminders;
 
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -16,294 +21,158 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
             .route("/clear-all", web::delete().to(clear_all_notifications))
             .route("/stats", web::get().to(get_stats))
             .route("/preferences", web::get().to(get_preferences))
-            .route("/preferences", web::put().to(update_preferences)),
+            .route("/preferences", web::put().to(update_preferences))
+            .route("/reminders", web::get().to(get_reminder_preferences))
+            .route("/reminders", web::put().to(update_reminder_preferences)),
     );
 }
 
 async fn get_notifications(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: AuthUser,
     query: web::Query<GetNotificationsQuery>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
-
-    let mut sql = String::from(
-        "SELECT id, user_id, type, title, message, related_entity_type, related_entity_id, is_read, created_at FROM notifications WHERE user_id = ?",
-    );
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
+    let mut filters = Filters::new();
+    filters.eq("user_id", &user_id);
     if query.unread_only.unwrap_or(false) {
-        sql.push_str(" AND is_read = 0");
-    }
-    if let Some(ref notification_type) = query.notification_type {
-        sql.push_str(&format!(" AND type = '{}'", notification_type));
+        filters.raw("is_read = 0");
     }
+    filters.eq_opt("type", query.notification_type.as_ref());
 
-    sql.push_str(" ORDER BY created_at DESC");
+    let mut sql = QueryBuilder::<Sqlite>::new(
+        "SELECT id, user_id, type, title, message, related_entity_type, related_entity_id, is_read, created_at FROM notifications",
+    );
+    filters.push_where(&mut sql);
+    sql.push(" ORDER BY created_at DESC");
 
     let limit = query.limit.unwrap_or(50);
-    sql.push_str(&format!(" LIMIT {}", limit));
+    push_limit(&mut sql, limit.into(), 0);
 
-    let result = sqlx::query_as::<_, Notification>(&sql)
-        .bind(&user_id)
+    let notifications = sql
+        .build_query_as::<Notification>()
         .fetch_all(pool.get_ref())
-        .await;
-
-    match result {
-        Ok(notifications) => {
-            let notifications_json: Vec<serde_json::Value> = notifications
-                .iter()
-                .map(|n| {
-                    serde_json::json!({
-                        "id": n.id,
-                        "type": n.notification_type,
-                        "title": n.title,
-                        "message": n.message,
-                        "relatedEntityType": n.related_entity_type,
-                        "relatedEntityId": n.related_entity_id,
-                        "isRead": n.is_read,
-                        "createdAt": n.created_at
-                    })
-                })
-                .collect();
-
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "notifications": notifications_json,
-                    "total": notifications_json.len()
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
+        .await?;
+
+    let notifications_json: Vec<serde_json::Value> = notifications
+        .iter()
+        .map(|n| {
+            serde_json::json!({
+                "id": n.id,
+                "type": n.notification_type,
+                "title": n.title,
+                "message": n.message,
+                "relatedEntityType": n.related_entity_type,
+                "relatedEntityId": n.related_entity_id,
+                "isRead": n.is_read,
+                "createdAt": n.created_at
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "notifications": notifications_json,
+            "total": notifications_json.len()
         }
-    }
+    })))
 }
 
 async fn mark_as_read(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: AuthUser,
     path: web::Path<String>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     let notification_id = path.into_inner();
 
-    let result = sqlx::query("UPDATE notifications SET is_read = 1 WHERE id = ? AND user_id = ?")
+    let rows = sqlx::query("UPDATE notifications SET is_read = 1 WHERE id = ? AND user_id = ?")
         .bind(&notification_id)
         .bind(&user_id)
         .execute(pool.get_ref())
-        .await;
+        .await?;
 
-    match result {
-        Ok(rows) => {
-            if rows.rows_affected() > 0 {
-                HttpResponse::Ok().json(serde_json::json!({
-                    "success": true,
-                    "message": "Notification marked as read"
-                }))
-            } else {
-                HttpResponse::NotFound().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "NOT_FOUND",
-                        "message": "Notification not found"
-                    }
-                }))
-            }
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to mark notification as read"
-                }
-            }))
-        }
+    if rows.rows_affected() == 0 {
+        return Err(AppError::NotFound("Notification not found".to_string()));
     }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Notification marked as read"
+    })))
 }
 
-async fn mark_all_as_read(pool: web::Data<SqlitePool>, req: HttpRequest) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+async fn mark_all_as_read(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
-    let result =
+    let rows =
         sqlx::query("UPDATE notifications SET is_read = 1 WHERE user_id = ? AND is_read = 0")
             .bind(&user_id)
             .execute(pool.get_ref())
-            .await;
+            .await?;
 
-    match result {
-        Ok(rows) => HttpResponse::Ok().json(serde_json::json!({
-            "success": true,
-            "message": "All notifications marked as read",
-            "data": {
-                "markedCount": rows.rows_affected()
-            }
-        })),
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to mark notifications as read"
-                }
-            }))
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "All notifications marked as read",
+        "data": {
+            "markedCount": rows.rows_affected()
         }
-    }
+    })))
 }
 
 async fn delete_notification(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: AuthUser,
     path: web::Path<String>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     let notification_id = path.into_inner();
 
-    let result = sqlx::query("DELETE FROM notifications WHERE id = ? AND user_id = ?")
+    let rows = sqlx::query("DELETE FROM notifications WHERE id = ? AND user_id = ?")
         .bind(&notification_id)
         .bind(&user_id)
         .execute(pool.get_ref())
-        .await;
+        .await?;
 
-    match result {
-        Ok(rows) => {
-            if rows.rows_affected() > 0 {
-                HttpResponse::Ok().json(serde_json::json!({
-                    "success": true,
-                    "message": "Notification deleted successfully"
-                }))
-            } else {
-                HttpResponse::NotFound().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "NOT_FOUND",
-                        "message": "Notification not found"
-                    }
-                }))
-            }
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to delete notification"
-                }
-            }))
-        }
+    if rows.rows_affected() == 0 {
+        return Err(AppError::NotFound("Notification not found".to_string()));
     }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Notification deleted successfully"
+    })))
 }
 
-async fn clear_all_notifications(pool: web::Data<SqlitePool>, req: HttpRequest) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+async fn clear_all_notifications(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
-    let result = sqlx::query("DELETE FROM notifications WHERE user_id = ?")
+    let rows = sqlx::query("DELETE FROM notifications WHERE user_id = ?")
         .bind(&user_id)
         .execute(pool.get_ref())
-        .await;
+        .await?;
 
-    match result {
-        Ok(rows) => HttpResponse::Ok().json(serde_json::json!({
-            "success": true,
-            "message": "All notifications cleared",
-            "data": {
-                "deletedCount": rows.rows_affected()
-            }
-        })),
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to clear notifications"
-                }
-            }))
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "All notifications cleared",
+        "data": {
+            "deletedCount": rows.rows_affected()
         }
-    }
+    })))
 }
 
-async fn get_stats(pool: web::Data<SqlitePool>, req: HttpRequest) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+async fn get_stats(pool: web::Data<SqlitePool>, auth: AuthUser) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     let total = sqlx::query_as::<_, (i64,)>("SELECT COUNT(*) FROM notifications WHERE user_id = ?")
         .bind(&user_id)
@@ -338,7 +207,7 @@ async fn get_stats(pool: web::Data<SqlitePool>, req: HttpRequest) -> HttpRespons
     )
     .unwrap_or(serde_json::json!({}));
 
-    HttpResponse::Ok().json(serde_json::json!({
+    Ok(HttpResponse::Ok().json(serde_json::json!({
         "success": true,
         "data": {
             "stats": {
@@ -348,102 +217,69 @@ async fn get_stats(pool: web::Data<SqlitePool>, req: HttpRequest) -> HttpRespons
                 "byType": by_type_json
             }
         }
-    }))
+    })))
 }
 
-async fn get_preferences(pool: web::Data<SqlitePool>, req: HttpRequest) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+async fn get_preferences(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
-    let result = sqlx::query_as::<_, NotificationPreference>(
+    let preferences = sqlx::query_as::<_, NotificationPreference>(
         "SELECT id, user_id, notification_type, email_enabled, push_enabled, in_app_enabled, created_at, updated_at FROM notification_preferences WHERE user_id = ?"
     )
     .bind(&user_id)
     .fetch_all(pool.get_ref())
-    .await;
-
-    match result {
-        Ok(preferences) => {
-            if preferences.is_empty() {
-                // Return default preferences
-                HttpResponse::Ok().json(serde_json::json!({
-                    "success": true,
-                    "data": {
-                        "preferences": {
-                            "task_assigned": { "emailEnabled": true, "pushEnabled": true, "inAppEnabled": true },
-                            "task_due": { "emailEnabled": true, "pushEnabled": true, "inAppEnabled": true },
-                            "event_reminder": { "emailEnabled": true, "pushEnabled": true, "inAppEnabled": true },
-                            "booking_confirmed": { "emailEnabled": true, "pushEnabled": false, "inAppEnabled": true },
-                            "booking_cancelled": { "emailEnabled": true, "pushEnabled": false, "inAppEnabled": true }
-                        }
-                    }
-                }))
-            } else {
-                let prefs_json: serde_json::Value = serde_json::to_value(
-                    preferences
-                        .iter()
-                        .map(|p| {
-                            (
-                                p.notification_type.clone(),
-                                serde_json::json!({
-                                    "emailEnabled": p.email_enabled,
-                                    "pushEnabled": p.push_enabled,
-                                    "inAppEnabled": p.in_app_enabled
-                                }),
-                            )
-                        })
-                        .collect::<std::collections::HashMap<_, _>>(),
-                )
-                .unwrap_or(serde_json::json!({}));
-
-                HttpResponse::Ok().json(serde_json::json!({
-                    "success": true,
-                    "data": {
-                        "preferences": prefs_json
-                    }
-                }))
-            }
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
+    .await?;
+
+    if preferences.is_empty() {
+        // Return default preferences
+        return Ok(HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "data": {
+                "preferences": {
+                    "task_assigned": { "emailEnabled": true, "pushEnabled": true, "inAppEnabled": true },
+                    "task_due": { "emailEnabled": true, "pushEnabled": true, "inAppEnabled": true },
+                    "event_reminder": { "emailEnabled": true, "pushEnabled": true, "inAppEnabled": true },
+                    "booking_confirmed": { "emailEnabled": true, "pushEnabled": false, "inAppEnabled": true },
+                    "booking_cancelled": { "emailEnabled": true, "pushEnabled": false, "inAppEnabled": true }
                 }
-            }))
-        }
+            }
+        })));
     }
+
+    let prefs_json: serde_json::Value = serde_json::to_value(
+        preferences
+            .iter()
+            .map(|p| {
+                (
+                    p.notification_type.clone(),
+                    serde_json::json!({
+                        "emailEnabled": p.email_enabled,
+                        "pushEnabled": p.push_enabled,
+                        "inAppEnabled": p.in_app_enabled
+                    }),
+                )
+            })
+            .collect::<std::collections::HashMap<_, _>>(),
+    )
+    .unwrap_or(serde_json::json!({}));
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "preferences": prefs_json
+        }
+    })))
 }
 
 async fn update_preferences(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: AuthUser,
     body: web::Json<UpdatePreferencesRequest>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     for pref in &body.preferences {
         let pref_id = Uuid::new_v4().to_string();
@@ -460,15 +296,98 @@ async fn update_preferences(
         .await;
     }
 
-    HttpResponse::Ok().json(serde_json::json!({
+    Ok(HttpResponse::Ok().json(serde_json::json!({
         "success": true,
         "message": "Preferences updated successfully"
-    }))
+    })))
+}
+
+async fn get_reminder_preferences(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "reminders": reminder_preferences_json(pool.get_ref(), &auth.id).await?
+        }
+    })))
+}
+
+async fn update_reminder_preferences(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+    body: web::Json<UpdateReminderPreferencesRequest>,
+) -> Result<HttpResponse, AppError> {
+    if body.reminders.is_empty() {
+        return Err(AppError::Validation(
+            "No reminder preferences provided".to_string(),
+        ));
+    }
+    for (event_type, minutes) in &body.reminders {
+        if !reminders::EVENT_TYPES.contains(&event_type.as_str()) {
+            return Err(AppError::Validation(format!(
+                "Unknown event type '{}'; expected one of: {}",
+                event_type,
+                reminders::EVENT_TYPES.join(", ")
+            ))
+            .with_code("INVALID_REMINDER"));
+        }
+        reminders::validate_minutes(minutes)?;
+    }
+
+    let mut tx = pool.begin().await?;
+    for (event_type, minutes) in &body.reminders {
+        sqlx::query(
+            "INSERT INTO reminder_preferences (user_id, event_type, reminder_minutes, updated_at) VALUES (?, ?, ?, datetime('now'))
+             ON CONFLICT(user_id, event_type) DO UPDATE SET reminder_minutes = excluded.reminder_minutes, updated_at = excluded.updated_at",
+        )
+        .bind(&auth.id)
+        .bind(event_type)
+        .bind(reminders::format_minutes(minutes))
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "reminders": reminder_preferences_json(pool.get_ref(), &auth.id).await?
+        }
+    })))
+}
+
+/// Reminder offsets per event type, falling back to the defaults for types
+/// the user has not configured.
+async fn reminder_preferences_json(
+    pool: &SqlitePool,
+    user_id: &str,
+) -> Result<serde_json::Value, AppError> {
+    let saved: Vec<(String, String)> = sqlx::query_as(
+        "SELECT event_type, reminder_minutes FROM reminder_preferences WHERE user_id = ?",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut prefs = serde_json::Map::new();
+    for event_type in reminders::EVENT_TYPES {
+        let (minutes, is_default) = match saved.iter().find(|(t, _)| t == event_type) {
+            Some((_, minutes)) => (reminders::parse_minutes(minutes), false),
+            None => (reminders::default_minutes(event_type), true),
+        };
+        prefs.insert(
+            event_type.to_string(),
+            serde_json::json!({ "minutes": minutes, "isDefault": is_default }),
+        );
+    }
+    Ok(serde_json::Value::Object(prefs))
 }
 
 // Helper function to create a notification (can be called from other routes)
 pub async fn create_notification(
-    pool: &SqlitePool,
+    executor: impl SqliteExecutor<'_>,
     user_id: &str,
     notification_type: &str,
     title: &str,
@@ -488,7 +407,7 @@ pub async fn create_notification(
     .bind(message)
     .bind(related_entity_type)
     .bind(related_entity_id)
-    .execute(pool)
+    .execute(executor)
     .await?;
 
     Ok(notification_id)
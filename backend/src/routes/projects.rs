@@ -1,10 +1,15 @@
-use actix_web::{HttpMessage, HttpRequest, HttpResponse, web};
-use sqlx::SqlitePool;
+use actix_web::{HttpResponse, web};
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
 use uuid::Uuid;
 
+use crate::error::AppError;
+use crate::filters::Filters;
+use crate::middleware::guards::{AuthUser, Member, RequireRole};
 use crate::models::projects::{
     AddMemberRequest, CreateProjectRequest, GetProjectsQuery, Project, UpdateProjectRequest,
 };
+use crate::routes::task_checklists;
+use crate::routes::task_dependencies::{self, LinkedTask};
 
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -20,121 +25,91 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
                 "/{project_id}/members/{user_id}",
                 web::delete().to(remove_member),
             )
-            .route("/{project_id}/tasks", web::get().to(get_project_tasks)),
+            .route("/{project_id}/tasks", web::get().to(get_project_tasks))
+            .route(
+                "/{project_id}/dependencies",
+                web::get().to(get_project_dependencies),
+            ),
     );
 }
 
 async fn get_projects(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
     query: web::Query<GetProjectsQuery>,
-) -> HttpResponse {
-    let _user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": { "code": "UNAUTHORIZED", "message": "Not authenticated" }
-            }));
-        }
-    };
-
+) -> Result<HttpResponse, AppError> {
     // Get all projects (visible to all authenticated users)
-    let mut sql = String::from(
-        "SELECT p.id, p.name, p.description, p.status, p.created_by, p.created_at, p.updated_at
-         FROM projects p
-         WHERE 1=1",
-    );
-
-    if let Some(ref status) = query.status {
-        sql.push_str(&format!(" AND p.status = '{}'", status));
-    }
+    let mut filters = Filters::new();
+    filters.eq_opt("p.status", query.status.as_ref());
     if let Some(ref search) = query.search {
-        sql.push_str(&format!(
-            " AND (p.name LIKE '%{}%' OR p.description LIKE '%{}%')",
-            search, search
-        ));
+        filters.search(&["p.name", "p.description"], search);
     }
 
-    sql.push_str(" ORDER BY p.updated_at DESC");
+    let mut sql = QueryBuilder::<Sqlite>::new(
+        "SELECT p.id, p.name, p.description, p.status, p.created_by, p.created_at, p.updated_at
+         FROM projects p",
+    );
+    filters.push_where(&mut sql);
+    sql.push(" ORDER BY p.updated_at DESC");
 
-    let result = sqlx::query_as::<_, Project>(&sql)
+    let projects = sql
+        .build_query_as::<Project>()
         .fetch_all(pool.get_ref())
-        .await;
+        .await?;
 
-    match result {
-        Ok(projects) => {
-            let mut projects_json: Vec<serde_json::Value> = Vec::new();
+    let mut projects_json: Vec<serde_json::Value> = Vec::new();
 
-            for p in projects.iter() {
-                // Get member count for each project
-                let member_count = sqlx::query_scalar::<_, i64>(
-                    "SELECT COUNT(*) FROM project_members WHERE project_id = ?",
-                )
+    for p in projects.iter() {
+        // Get member count for each project
+        let member_count = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM project_members WHERE project_id = ?",
+        )
+        .bind(&p.id)
+        .fetch_one(pool.get_ref())
+        .await
+        .unwrap_or(0);
+
+        // Get task count for each project
+        let task_count =
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM tasks WHERE project_id = ?")
                 .bind(&p.id)
                 .fetch_one(pool.get_ref())
                 .await
                 .unwrap_or(0);
 
-                // Get task count for each project
-                let task_count =
-                    sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM tasks WHERE project_id = ?")
-                        .bind(&p.id)
-                        .fetch_one(pool.get_ref())
-                        .await
-                        .unwrap_or(0);
-
-                projects_json.push(serde_json::json!({
-                    "id": p.id,
-                    "name": p.name,
-                    "description": p.description,
-                    "status": p.status,
-                    "createdBy": p.created_by,
-                    "createdAt": p.created_at,
-                    "updatedAt": p.updated_at,
-                    "memberCount": member_count,
-                    "taskCount": task_count
-                }));
-            }
+        projects_json.push(serde_json::json!({
+            "id": p.id,
+            "name": p.name,
+            "description": p.description,
+            "status": p.status,
+            "createdBy": p.created_by,
+            "createdAt": p.created_at,
+            "updatedAt": p.updated_at,
+            "memberCount": member_count,
+            "taskCount": task_count
+        }));
+    }
 
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "projects": projects_json,
-                    "total": projects_json.len()
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": { "code": "INTERNAL_ERROR", "message": "Database error" }
-            }))
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "projects": projects_json,
+            "total": projects_json.len()
         }
-    }
+    })))
 }
 
 async fn create_project(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: RequireRole<Member>,
     body: web::Json<CreateProjectRequest>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": { "code": "UNAUTHORIZED", "message": "Not authenticated" }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     let project_id = Uuid::new_v4().to_string();
     let status = body.status.clone().unwrap_or_else(|| "active".to_string());
 
     // Create project
-    let result = sqlx::query(
+    sqlx::query(
         "INSERT INTO projects (id, name, description, status, created_by, created_at, updated_at) 
          VALUES (?, ?, ?, ?, ?, datetime('now'), datetime('now'))",
     )
@@ -144,14 +119,7 @@ async fn create_project(
     .bind(&status)
     .bind(&user_id)
     .execute(pool.get_ref())
-    .await;
-
-    if result.is_err() {
-        return HttpResponse::InternalServerError().json(serde_json::json!({
-            "success": false,
-            "error": { "code": "INTERNAL_ERROR", "message": "Failed to create project" }
-        }));
-    }
+    .await?;
 
     // Add creator as owner
     let member_id = Uuid::new_v4().to_string();
@@ -183,7 +151,7 @@ async fn create_project(
         }
     }
 
-    HttpResponse::Created().json(serde_json::json!({
+    Ok(HttpResponse::Created().json(serde_json::json!({
         "success": true,
         "data": {
             "project": {
@@ -194,112 +162,81 @@ async fn create_project(
                 "createdBy": user_id
             }
         }
-    }))
+    })))
 }
 
 async fn get_project(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: AuthUser,
     path: web::Path<String>,
-) -> HttpResponse {
+) -> Result<HttpResponse, AppError> {
     // Get user_id to check their role in the project
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": { "code": "UNAUTHORIZED", "message": "Not authenticated" }
-            }));
-        }
-    };
+    let user_id = auth.id.clone();
 
     let project_id = path.into_inner();
 
     // All authenticated users can view project details
-    let result = sqlx::query_as::<_, Project>(
+    let project = sqlx::query_as::<_, Project>(
         "SELECT id, name, description, status, created_by, created_at, updated_at 
          FROM projects WHERE id = ?",
     )
     .bind(&project_id)
     .fetch_optional(pool.get_ref())
-    .await;
+    .await?
+    .ok_or_else(|| AppError::NotFound("Project not found".to_string()))?;
 
-    match result {
-        Ok(Some(project)) => {
-            // Get member count
-            let member_count = sqlx::query_scalar::<_, i64>(
-                "SELECT COUNT(*) FROM project_members WHERE project_id = ?",
-            )
+    // Get member count
+    let member_count =
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM project_members WHERE project_id = ?")
             .bind(&project_id)
             .fetch_one(pool.get_ref())
             .await
             .unwrap_or(0);
 
-            // Get task count
-            let task_count =
-                sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM tasks WHERE project_id = ?")
-                    .bind(&project_id)
-                    .fetch_one(pool.get_ref())
-                    .await
-                    .unwrap_or(0);
-
-            // Get the current user's role in the project (null if not a member)
-            let current_user_role = sqlx::query_scalar::<_, String>(
-                "SELECT role FROM project_members WHERE project_id = ? AND user_id = ?",
-            )
+    // Get task count
+    let task_count =
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM tasks WHERE project_id = ?")
             .bind(&project_id)
-            .bind(&user_id)
-            .fetch_optional(pool.get_ref())
+            .fetch_one(pool.get_ref())
             .await
-            .unwrap_or(None);
-
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "project": {
-                        "id": project.id,
-                        "name": project.name,
-                        "description": project.description,
-                        "status": project.status,
-                        "createdBy": project.created_by,
-                        "createdAt": project.created_at,
-                        "updatedAt": project.updated_at,
-                        "memberCount": member_count,
-                        "taskCount": task_count,
-                        "currentUserRole": current_user_role
-                    }
-                }
-            }))
-        }
-        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
-            "success": false,
-            "error": { "code": "NOT_FOUND", "message": "Project not found" }
-        })),
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": { "code": "INTERNAL_ERROR", "message": "Database error" }
-            }))
+            .unwrap_or(0);
+
+    // Get the current user's role in the project (null if not a member)
+    let current_user_role = sqlx::query_scalar::<_, String>(
+        "SELECT role FROM project_members WHERE project_id = ? AND user_id = ?",
+    )
+    .bind(&project_id)
+    .bind(&user_id)
+    .fetch_optional(pool.get_ref())
+    .await
+    .unwrap_or(None);
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "project": {
+                "id": project.id,
+                "name": project.name,
+                "description": project.description,
+                "status": project.status,
+                "createdBy": project.created_by,
+                "createdAt": project.created_at,
+                "updatedAt": project.updated_at,
+                "memberCount": member_count,
+                "taskCount": task_count,
+                "currentUserRole": current_user_role
+            }
         }
-    }
+    })))
 }
 
 async fn update_project(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: RequireRole<Member>,
     path: web::Path<String>,
     body: web::Json<UpdateProjectRequest>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": { "code": "UNAUTHORIZED", "message": "Not authenticated" }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     let project_id = path.into_inner();
 
@@ -310,16 +247,12 @@ async fn update_project(
     .bind(&project_id)
     .bind(&user_id)
     .fetch_optional(pool.get_ref())
-    .await;
+    .await?;
 
-    match role {
-        Ok(Some(r)) if r == "owner" || r == "admin" => {}
-        _ => {
-            return HttpResponse::Forbidden().json(serde_json::json!({
-                "success": false,
-                "error": { "code": "FORBIDDEN", "message": "Only project owner or admin can update" }
-            }));
-        }
+    if !matches!(role.as_deref(), Some("owner" | "admin")) {
+        return Err(AppError::Forbidden(
+            "Only project owner or admin can update".to_string(),
+        ));
     }
 
     let mut updates = Vec::new();
@@ -339,10 +272,7 @@ async fn update_project(
     }
 
     if updates.is_empty() {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false,
-            "error": { "code": "INVALID_REQUEST", "message": "No fields to update" }
-        }));
+        return Err(AppError::Validation("No fields to update".to_string()));
     }
 
     updates.push("updated_at = datetime('now')");
@@ -354,42 +284,27 @@ async fn update_project(
     }
     query = query.bind(&project_id);
 
-    match query.execute(pool.get_ref()).await {
-        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
-            "success": true,
-            "data": {
-                "project": {
-                    "id": project_id,
-                    "name": body.name,
-                    "description": body.description,
-                    "status": body.status
-                }
+    query.execute(pool.get_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "project": {
+                "id": project_id,
+                "name": body.name,
+                "description": body.description,
+                "status": body.status
             }
-        })),
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": { "code": "INTERNAL_ERROR", "message": "Failed to update project" }
-            }))
         }
-    }
+    })))
 }
 
 async fn delete_project(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: RequireRole<Member>,
     path: web::Path<String>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": { "code": "UNAUTHORIZED", "message": "Not authenticated" }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     let project_id = path.into_inner();
 
@@ -400,16 +315,12 @@ async fn delete_project(
     .bind(&project_id)
     .bind(&user_id)
     .fetch_optional(pool.get_ref())
-    .await;
+    .await?;
 
-    match role {
-        Ok(Some(r)) if r == "owner" => {}
-        _ => {
-            return HttpResponse::Forbidden().json(serde_json::json!({
-                "success": false,
-                "error": { "code": "FORBIDDEN", "message": "Only project owner can delete" }
-            }));
-        }
+    if role.as_deref() != Some("owner") {
+        return Err(AppError::Forbidden(
+            "Only project owner can delete".to_string(),
+        ));
     }
 
     // Delete project members first
@@ -425,42 +336,27 @@ async fn delete_project(
         .await;
 
     // Delete project
-    match sqlx::query("DELETE FROM projects WHERE id = ?")
+    sqlx::query("DELETE FROM projects WHERE id = ?")
         .bind(&project_id)
         .execute(pool.get_ref())
-        .await
-    {
-        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
-            "success": true,
-            "message": "Project deleted successfully"
-        })),
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": { "code": "INTERNAL_ERROR", "message": "Failed to delete project" }
-            }))
-        }
-    }
+        .await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Project deleted successfully"
+    })))
 }
 
 async fn get_project_members(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
     path: web::Path<String>,
-) -> HttpResponse {
+) -> Result<HttpResponse, AppError> {
     // Just check if user is authenticated (any user can view project members)
-    if req.extensions().get::<String>().is_none() {
-        return HttpResponse::Unauthorized().json(serde_json::json!({
-            "success": false,
-            "error": { "code": "UNAUTHORIZED", "message": "Not authenticated" }
-        }));
-    }
 
     let project_id = path.into_inner();
 
     // All authenticated users can view project members
-    let result = sqlx::query_as::<_, (String, String, String, String, String, String)>(
+    let members = sqlx::query_as::<_, (String, String, String, String, String, String)>(
         "SELECT pm.id, pm.user_id, u.first_name, u.last_name, pm.role, pm.added_at
          FROM project_members pm
          JOIN users u ON pm.user_id = u.id
@@ -474,56 +370,37 @@ async fn get_project_members(
     )
     .bind(&project_id)
     .fetch_all(pool.get_ref())
-    .await;
-
-    match result {
-        Ok(members) => {
-            let members_json: Vec<serde_json::Value> = members
-                .iter()
-                .map(|(id, uid, first_name, last_name, role, added_at)| {
-                    serde_json::json!({
-                        "id": id,
-                        "userId": uid,
-                        "name": format!("{} {}", first_name, last_name),
-                        "role": role,
-                        "addedAt": added_at
-                    })
-                })
-                .collect();
-
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "members": members_json,
-                    "total": members_json.len()
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": { "code": "INTERNAL_ERROR", "message": "Database error" }
-            }))
+    .await?;
+
+    let members_json: Vec<serde_json::Value> = members
+        .iter()
+        .map(|(id, uid, first_name, last_name, role, added_at)| {
+            serde_json::json!({
+                "id": id,
+                "userId": uid,
+                "name": format!("{} {}", first_name, last_name),
+                "role": role,
+                "addedAt": added_at
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "members": members_json,
+            "total": members_json.len()
         }
-    }
+    })))
 }
 
 async fn add_member(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: RequireRole<Member>,
     path: web::Path<String>,
     body: web::Json<AddMemberRequest>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": { "code": "UNAUTHORIZED", "message": "Not authenticated" }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     let project_id = path.into_inner();
 
@@ -534,16 +411,12 @@ async fn add_member(
     .bind(&project_id)
     .bind(&user_id)
     .fetch_optional(pool.get_ref())
-    .await;
+    .await?;
 
-    match role {
-        Ok(Some(r)) if r == "owner" || r == "admin" => {}
-        _ => {
-            return HttpResponse::Forbidden().json(serde_json::json!({
-                "success": false,
-                "error": { "code": "FORBIDDEN", "message": "Only project owner or admin can add members" }
-            }));
-        }
+    if !matches!(role.as_deref(), Some("owner" | "admin")) {
+        return Err(AppError::Forbidden(
+            "Only project owner or admin can add members".to_string(),
+        ));
     }
 
     // Check if user is already a member
@@ -557,16 +430,14 @@ async fn add_member(
     .unwrap_or(0);
 
     if existing > 0 {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false,
-            "error": { "code": "ALREADY_MEMBER", "message": "User is already a member" }
-        }));
+        return Err(AppError::BadRequest("User is already a member".to_string())
+            .with_code("ALREADY_MEMBER"));
     }
 
     let member_id = Uuid::new_v4().to_string();
     let member_role = body.role.clone().unwrap_or_else(|| "member".to_string());
 
-    match sqlx::query(
+    sqlx::query(
         "INSERT INTO project_members (id, project_id, user_id, role, added_at) 
          VALUES (?, ?, ?, ?, datetime('now'))",
     )
@@ -575,42 +446,26 @@ async fn add_member(
     .bind(&body.user_id)
     .bind(&member_role)
     .execute(pool.get_ref())
-    .await
-    {
-        Ok(_) => HttpResponse::Created().json(serde_json::json!({
-            "success": true,
-            "data": {
-                "member": {
-                    "id": member_id,
-                    "userId": body.user_id,
-                    "role": member_role
-                }
+    .await?;
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "member": {
+                "id": member_id,
+                "userId": body.user_id,
+                "role": member_role
             }
-        })),
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": { "code": "INTERNAL_ERROR", "message": "Failed to add member" }
-            }))
         }
-    }
+    })))
 }
 
 async fn remove_member(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: RequireRole<Member>,
     path: web::Path<(String, String)>,
-) -> HttpResponse {
-    let current_user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": { "code": "UNAUTHORIZED", "message": "Not authenticated" }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     let (project_id, target_user_id) = path.into_inner();
 
@@ -619,20 +474,19 @@ async fn remove_member(
         "SELECT role FROM project_members WHERE project_id = ? AND user_id = ?",
     )
     .bind(&project_id)
-    .bind(&current_user_id)
+    .bind(&user_id)
     .fetch_optional(pool.get_ref())
-    .await;
+    .await?;
 
     let can_remove = match role {
-        Ok(Some(r)) => r == "owner" || r == "admin" || current_user_id == target_user_id,
-        _ => false,
+        Some(r) => r == "owner" || r == "admin" || user_id == target_user_id,
+        None => false,
     };
 
     if !can_remove {
-        return HttpResponse::Forbidden().json(serde_json::json!({
-            "success": false,
-            "error": { "code": "FORBIDDEN", "message": "Not authorized to remove this member" }
-        }));
+        return Err(AppError::Forbidden(
+            "Not authorized to remove this member".to_string(),
+        ));
     }
 
     // Check if trying to remove the owner
@@ -642,63 +496,41 @@ async fn remove_member(
     .bind(&project_id)
     .bind(&target_user_id)
     .fetch_optional(pool.get_ref())
-    .await;
+    .await?;
 
-    if let Ok(Some(r)) = target_role {
-        if r == "owner" {
-            return HttpResponse::BadRequest().json(serde_json::json!({
-                "success": false,
-                "error": { "code": "CANNOT_REMOVE_OWNER", "message": "Cannot remove the project owner" }
-            }));
-        }
+    if target_role.as_deref() == Some("owner") {
+        return Err(
+            AppError::BadRequest("Cannot remove the project owner".to_string())
+                .with_code("CANNOT_REMOVE_OWNER"),
+        );
     }
 
-    match sqlx::query("DELETE FROM project_members WHERE project_id = ? AND user_id = ?")
+    let result = sqlx::query("DELETE FROM project_members WHERE project_id = ? AND user_id = ?")
         .bind(&project_id)
         .bind(&target_user_id)
         .execute(pool.get_ref())
-        .await
-    {
-        Ok(result) => {
-            if result.rows_affected() > 0 {
-                HttpResponse::Ok().json(serde_json::json!({
-                    "success": true,
-                    "message": "Member removed successfully"
-                }))
-            } else {
-                HttpResponse::NotFound().json(serde_json::json!({
-                    "success": false,
-                    "error": { "code": "NOT_FOUND", "message": "Member not found" }
-                }))
-            }
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": { "code": "INTERNAL_ERROR", "message": "Failed to remove member" }
-            }))
-        }
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Member not found".to_string()));
     }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Member removed successfully"
+    })))
 }
 
 async fn get_project_tasks(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
     path: web::Path<String>,
-) -> HttpResponse {
+) -> Result<HttpResponse, AppError> {
     // Just check if user is authenticated (any user can view project tasks)
-    if req.extensions().get::<String>().is_none() {
-        return HttpResponse::Unauthorized().json(serde_json::json!({
-            "success": false,
-            "error": { "code": "UNAUTHORIZED", "message": "Not authenticated" }
-        }));
-    }
 
     let project_id = path.into_inner();
 
     // All authenticated users can view project tasks
-    let result = sqlx::query_as::<_, (String, String, Option<String>, String, String, Option<String>, String, bool, String, String)>(
+    let tasks = sqlx::query_as::<_, (String, String, Option<String>, String, String, Option<String>, String, bool, String, String)>(
         "SELECT t.id, t.title, t.description, t.urgency, t.department, t.assignee_id, t.deadline, t.is_completed, t.created_at, t.updated_at
          FROM tasks t
          WHERE t.project_id = ?
@@ -706,55 +538,118 @@ async fn get_project_tasks(
     )
     .bind(&project_id)
     .fetch_all(pool.get_ref())
-    .await;
+    .await?;
+
+    let mut tasks_json: Vec<serde_json::Value> = Vec::new();
+    for (
+        id,
+        title,
+        description,
+        urgency,
+        department,
+        assignee_id,
+        deadline,
+        is_completed,
+        created_at,
+        updated_at,
+    ) in tasks
+    {
+        let checklist = task_checklists::progress(pool.get_ref(), &id).await?;
+        tasks_json.push(serde_json::json!({
+            "id": id,
+            "title": title,
+            "description": description,
+            "urgency": urgency,
+            "department": department,
+            "assigneeId": assignee_id,
+            "deadline": deadline,
+            "isCompleted": is_completed,
+            "createdAt": created_at,
+            "updatedAt": updated_at,
+            "checklist": checklist.to_json()
+        }));
+    }
 
-    match result {
-        Ok(tasks) => {
-            let tasks_json: Vec<serde_json::Value> = tasks
-                .iter()
-                .map(
-                    |(
-                        id,
-                        title,
-                        description,
-                        urgency,
-                        department,
-                        assignee_id,
-                        deadline,
-                        is_completed,
-                        created_at,
-                        updated_at,
-                    )| {
-                        serde_json::json!({
-                            "id": id,
-                            "title": title,
-                            "description": description,
-                            "urgency": urgency,
-                            "department": department,
-                            "assigneeId": assignee_id,
-                            "deadline": deadline,
-                            "isCompleted": is_completed,
-                            "createdAt": created_at,
-                            "updatedAt": updated_at
-                        })
-                    },
-                )
-                .collect();
-
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "tasks": tasks_json,
-                    "total": tasks_json.len()
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": { "code": "INTERNAL_ERROR", "message": "Database error" }
-            }))
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "tasks": tasks_json,
+            "total": tasks_json.len()
         }
+    })))
+}
+
+/// The project's tasks as a dependency graph, with the critical path: the
+/// longest chain of open tasks, each of which holds up the next.
+async fn get_project_dependencies(
+    pool: web::Data<SqlitePool>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let project_id = path.into_inner();
+
+    sqlx::query_scalar::<_, String>("SELECT id FROM projects WHERE id = ?")
+        .bind(&project_id)
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| AppError::NotFound("Project not found".to_string()))?;
+
+    let tasks = sqlx::query_as::<_, LinkedTask>(
+        "SELECT id, title, status, urgency, deadline, project_id FROM tasks WHERE project_id = ? ORDER BY deadline ASC",
+    )
+    .bind(&project_id)
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    // Only edges between the project's own tasks are drawn; prerequisites in
+    // other projects still show up in each task's `isBlocked`
+    let edges = sqlx::query_as::<_, (String, String)>(
+        r#"
+        SELECT d.task_id, d.depends_on_id
+        FROM task_dependencies d
+        JOIN tasks t ON t.id = d.task_id
+        JOIN tasks p ON p.id = d.depends_on_id
+        WHERE t.project_id = ? AND p.project_id = ?
+        "#,
+    )
+    .bind(&project_id)
+    .bind(&project_id)
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    let open_ids: Vec<String> = tasks
+        .iter()
+        .filter(|task| task.status != "completed")
+        .map(|task| task.id.clone())
+        .collect();
+    let critical_path = task_dependencies::critical_path(&open_ids, &edges);
+
+    let mut nodes: Vec<serde_json::Value> = Vec::new();
+    for task in &tasks {
+        let (is_blocked, is_blocking) = task_dependencies::flags(pool.get_ref(), &task.id).await?;
+        let mut node = task.to_json();
+        node["isBlocked"] = serde_json::json!(is_blocked);
+        node["isBlocking"] = serde_json::json!(is_blocking);
+        node["onCriticalPath"] = serde_json::json!(critical_path.contains(&task.id));
+        nodes.push(node);
     }
+
+    let edges_json: Vec<serde_json::Value> = edges
+        .iter()
+        .map(|(task_id, depends_on_id)| {
+            serde_json::json!({
+                "taskId": task_id,
+                "dependsOnId": depends_on_id
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "projectId": project_id,
+            "nodes": nodes,
+            "edges": edges_json,
+            "criticalPath": critical_path
+        }
+    })))
 }
@@ -1,7 +1,10 @@
-use actix_web::{HttpMessage, HttpRequest, HttpResponse, web};
-use sqlx::SqlitePool;
+use actix_web::{HttpResponse, web};
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
 use uuid::Uuid;
 
+use crate::error::AppError;
+use crate::filters::Filters;
+use crate::middleware::guards::{Admin, AuthUser, Member, RequireRole};
 use crate::models::quick_links::{
     CreateQuickLinkRequest, GetQuickLinksQuery, QuickLink, UpdateQuickLinkRequest,
 };
@@ -22,86 +25,61 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
 async fn get_quick_links(
     pool: web::Data<SqlitePool>,
     query: web::Query<GetQuickLinksQuery>,
-) -> HttpResponse {
-    let mut sql = String::from(
-        "SELECT id, title, url, description, category, department, icon, display_order, created_by, created_at, updated_at FROM quick_links WHERE 1=1",
-    );
-
-    if let Some(ref category) = query.category {
-        sql.push_str(&format!(" AND category = '{}'", category));
-    }
+) -> Result<HttpResponse, AppError> {
+    let mut filters = Filters::new();
+    filters.eq_opt("category", query.category.as_ref());
     if let Some(ref department) = query.department {
-        sql.push_str(&format!(
-            " AND (department = '{}' OR department = 'Both')",
-            department
-        ));
+        filters.condition(
+            "(department = ? OR department = 'Both')",
+            vec![department.into()],
+        );
     }
     if let Some(ref search) = query.search {
-        sql.push_str(&format!(
-            " AND (title LIKE '%{}%' OR description LIKE '%{}%' OR url LIKE '%{}%')",
-            search, search, search
-        ));
+        filters.search(&["title", "description", "url"], search);
     }
 
-    sql.push_str(" ORDER BY display_order, title");
+    let mut sql = QueryBuilder::<Sqlite>::new(
+        "SELECT id, title, url, description, category, department, icon, display_order, created_by, created_at, updated_at FROM quick_links",
+    );
+    filters.push_where(&mut sql);
+    sql.push(" ORDER BY display_order, title");
 
-    let result = sqlx::query_as::<_, QuickLink>(&sql)
+    let links = sql
+        .build_query_as::<QuickLink>()
         .fetch_all(pool.get_ref())
-        .await;
-
-    match result {
-        Ok(links) => {
-            let links_json: Vec<serde_json::Value> = links
-                .iter()
-                .map(|l| {
-                    serde_json::json!({
-                        "id": l.id,
-                        "title": l.title,
-                        "url": l.url,
-                        "description": l.description,
-                        "category": l.category,
-                        "department": l.department,
-                        "icon": l.icon,
-                        "displayOrder": l.display_order
-                    })
-                })
-                .collect();
-
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "links": links_json
-                }
-            }))
+        .await?;
+
+    let links_json: Vec<serde_json::Value> = links
+        .iter()
+        .map(|l| {
+            serde_json::json!({
+                "id": l.id,
+                "title": l.title,
+                "url": l.url,
+                "description": l.description,
+                "category": l.category,
+                "department": l.department,
+                "icon": l.icon,
+                "displayOrder": l.display_order
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "links": links_json
         }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
-        }
-    }
+    })))
 }
 
-async fn get_my_quick_links(pool: web::Data<SqlitePool>, req: HttpRequest) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+async fn get_my_quick_links(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
-    let result = sqlx::query_as::<_, QuickLink>(
+    let links = sqlx::query_as::<_, QuickLink>(
         "SELECT ql.id, ql.title, ql.url, ql.description, ql.category, ql.department, ql.icon, uql.display_order, ql.created_by, ql.created_at, ql.updated_at
          FROM quick_links ql
          JOIN user_quick_links uql ON ql.id = uql.link_id
@@ -110,69 +88,44 @@ async fn get_my_quick_links(pool: web::Data<SqlitePool>, req: HttpRequest) -> Ht
     )
     .bind(&user_id)
     .fetch_all(pool.get_ref())
-    .await;
-
-    match result {
-        Ok(links) => {
-            let links_json: Vec<serde_json::Value> = links
-                .iter()
-                .map(|l| {
-                    serde_json::json!({
-                        "id": l.id,
-                        "title": l.title,
-                        "url": l.url,
-                        "description": l.description,
-                        "category": l.category,
-                        "department": l.department,
-                        "icon": l.icon,
-                        "displayOrder": l.display_order,
-                        "isPinned": true
-                    })
-                })
-                .collect();
-
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "pinnedLinks": links_json
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
+    .await?;
+
+    let links_json: Vec<serde_json::Value> = links
+        .iter()
+        .map(|l| {
+            serde_json::json!({
+                "id": l.id,
+                "title": l.title,
+                "url": l.url,
+                "description": l.description,
+                "category": l.category,
+                "department": l.department,
+                "icon": l.icon,
+                "displayOrder": l.display_order,
+                "isPinned": true
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "pinnedLinks": links_json
         }
-    }
+    })))
 }
 
 async fn create_quick_link(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: RequireRole<Member>,
     body: web::Json<CreateQuickLinkRequest>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     let link_id = Uuid::new_v4().to_string();
     let display_order = body.display_order.unwrap_or(0);
 
-    let result = sqlx::query(
+    sqlx::query(
         "INSERT INTO quick_links (id, title, url, description, category, department, icon, display_order, created_by) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
     )
     .bind(&link_id)
@@ -185,204 +138,156 @@ async fn create_quick_link(
     .bind(display_order)
     .bind(&user_id)
     .execute(pool.get_ref())
-    .await;
-
-    match result {
-        Ok(_) => HttpResponse::Created().json(serde_json::json!({
-            "success": true,
-            "data": {
-                "link": {
-                    "id": link_id,
-                    "title": body.title,
-                    "url": body.url,
-                    "description": body.description,
-                    "category": body.category,
-                    "department": body.department.as_deref().unwrap_or("Both"),
-                    "icon": body.icon,
-                    "displayOrder": display_order
-                }
+    .await?;
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "link": {
+                "id": link_id,
+                "title": body.title,
+                "url": body.url,
+                "description": body.description,
+                "category": body.category,
+                "department": body.department.as_deref().unwrap_or("Both"),
+                "icon": body.icon,
+                "displayOrder": display_order
             }
-        })),
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to create quick link"
-                }
-            }))
         }
-    }
+    })))
 }
 
 async fn update_quick_link(
     pool: web::Data<SqlitePool>,
+    _auth: RequireRole<Admin>,
     path: web::Path<String>,
     body: web::Json<UpdateQuickLinkRequest>,
-) -> HttpResponse {
+) -> Result<HttpResponse, AppError> {
     let link_id = path.into_inner();
 
-    let mut updates = vec![];
+    let mut updates = Vec::new();
+    let mut params: Vec<String> = Vec::new();
+
     if let Some(ref title) = body.title {
-        updates.push(format!("title = '{}'", title));
+        updates.push("title = ?");
+        params.push(title.clone());
     }
     if let Some(ref url) = body.url {
-        updates.push(format!("url = '{}'", url));
+        updates.push("url = ?");
+        params.push(url.clone());
     }
     if let Some(ref description) = body.description {
-        updates.push(format!("description = '{}'", description));
+        updates.push("description = ?");
+        params.push(description.clone());
     }
     if let Some(ref category) = body.category {
-        updates.push(format!("category = '{}'", category));
+        updates.push("category = ?");
+        params.push(category.clone());
     }
     if let Some(ref department) = body.department {
-        updates.push(format!("department = '{}'", department));
+        updates.push("department = ?");
+        params.push(department.clone());
     }
     if let Some(ref icon) = body.icon {
-        updates.push(format!("icon = '{}'", icon));
+        updates.push("icon = ?");
+        params.push(icon.clone());
     }
     if let Some(order) = body.display_order {
-        updates.push(format!("display_order = {}", order));
+        updates.push("display_order = ?");
+        params.push(order.to_string());
     }
 
     if updates.is_empty() {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "BAD_REQUEST",
-                "message": "No fields to update"
-            }
-        }));
+        return Err(AppError::Validation("No fields to update".to_string()));
     }
 
-    updates.push("updated_at = datetime('now')".to_string());
-    let sql = format!("UPDATE quick_links SET {} WHERE id = ?", updates.join(", "));
+    updates.push("updated_at = datetime('now')");
+    let query = format!("UPDATE quick_links SET {} WHERE id = ?", updates.join(", "));
 
-    let result = sqlx::query(&sql)
-        .bind(&link_id)
-        .execute(pool.get_ref())
-        .await;
+    let mut query_builder = sqlx::query(&query);
+    for param in params {
+        query_builder = query_builder.bind(param);
+    }
+    query_builder = query_builder.bind(&link_id);
+
+    let rows = query_builder.execute(pool.get_ref()).await?;
+
+    if rows.rows_affected() == 0 {
+        return Err(AppError::NotFound("Quick link not found".to_string()));
+    }
 
-    match result {
-        Ok(rows) => {
-            if rows.rows_affected() > 0 {
-                // Fetch updated link
-                let link = sqlx::query_as::<_, QuickLink>(
-                    "SELECT id, title, url, description, category, department, icon, display_order, created_by, created_at, updated_at FROM quick_links WHERE id = ?"
-                )
-                .bind(&link_id)
-                .fetch_optional(pool.get_ref())
-                .await;
-
-                match link {
-                    Ok(Some(l)) => HttpResponse::Ok().json(serde_json::json!({
-                        "success": true,
-                        "data": {
-                            "link": {
-                                "id": l.id,
-                                "title": l.title,
-                                "url": l.url,
-                                "description": l.description,
-                                "category": l.category,
-                                "department": l.department,
-                                "icon": l.icon,
-                                "displayOrder": l.display_order
-                            }
-                        }
-                    })),
-                    _ => HttpResponse::NotFound().json(serde_json::json!({
-                        "success": false,
-                        "error": {
-                            "code": "NOT_FOUND",
-                            "message": "Quick link not found"
-                        }
-                    })),
-                }
-            } else {
-                HttpResponse::NotFound().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "NOT_FOUND",
-                        "message": "Quick link not found"
-                    }
-                }))
+    // Fetch updated link
+    let link = sqlx::query_as::<_, QuickLink>(
+        "SELECT id, title, url, description, category, department, icon, display_order, created_by, created_at, updated_at FROM quick_links WHERE id = ?"
+    )
+    .bind(&link_id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFound("Quick link not found".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "link": {
+                "id": link.id,
+                "title": link.title,
+                "url": link.url,
+                "description": link.description,
+                "category": link.category,
+                "department": link.department,
+                "icon": link.icon,
+                "displayOrder": link.display_order
             }
         }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to update quick link"
-                }
-            }))
-        }
-    }
+    })))
 }
 
-async fn delete_quick_link(pool: web::Data<SqlitePool>, path: web::Path<String>) -> HttpResponse {
+async fn delete_quick_link(
+    pool: web::Data<SqlitePool>,
+    auth: RequireRole<Member>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
     let link_id = path.into_inner();
 
+    let created_by =
+        sqlx::query_scalar::<_, String>("SELECT created_by FROM quick_links WHERE id = ?")
+            .bind(&link_id)
+            .fetch_optional(pool.get_ref())
+            .await?
+            .ok_or_else(|| AppError::NotFound("Quick link not found".to_string()))?;
+    auth.ensure_owner_or_admin(
+        &created_by,
+        "NOT_LINK_OWNER",
+        "Only the person who added this link or an admin can delete it",
+    )?;
+
     // Delete from user_quick_links first
     let _ = sqlx::query("DELETE FROM user_quick_links WHERE link_id = ?")
         .bind(&link_id)
         .execute(pool.get_ref())
         .await;
 
-    let result = sqlx::query("DELETE FROM quick_links WHERE id = ?")
+    let rows = sqlx::query("DELETE FROM quick_links WHERE id = ?")
         .bind(&link_id)
         .execute(pool.get_ref())
-        .await;
+        .await?;
 
-    match result {
-        Ok(rows) => {
-            if rows.rows_affected() > 0 {
-                HttpResponse::Ok().json(serde_json::json!({
-                    "success": true,
-                    "message": "Quick link deleted successfully"
-                }))
-            } else {
-                HttpResponse::NotFound().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "NOT_FOUND",
-                        "message": "Quick link not found"
-                    }
-                }))
-            }
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to delete quick link"
-                }
-            }))
-        }
+    if rows.rows_affected() == 0 {
+        return Err(AppError::NotFound("Quick link not found".to_string()));
     }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Quick link deleted successfully"
+    })))
 }
 
 async fn pin_quick_link(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: AuthUser,
     path: web::Path<String>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     let link_id = path.into_inner();
 
@@ -398,73 +303,38 @@ async fn pin_quick_link(
     .map(|(o,)| o)
     .unwrap_or(0);
 
-    let result = sqlx::query(
+    sqlx::query(
         "INSERT OR REPLACE INTO user_quick_links (user_id, link_id, display_order) VALUES (?, ?, ?)"
     )
     .bind(&user_id)
     .bind(&link_id)
     .bind(max_order + 1)
     .execute(pool.get_ref())
-    .await;
-
-    match result {
-        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
-            "success": true,
-            "message": "Quick link pinned successfully"
-        })),
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to pin quick link"
-                }
-            }))
-        }
-    }
+    .await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Quick link pinned successfully"
+    })))
 }
 
 async fn unpin_quick_link(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: AuthUser,
     path: web::Path<String>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     let link_id = path.into_inner();
 
-    let result = sqlx::query("DELETE FROM user_quick_links WHERE user_id = ? AND link_id = ?")
+    sqlx::query("DELETE FROM user_quick_links WHERE user_id = ? AND link_id = ?")
         .bind(&user_id)
         .bind(&link_id)
         .execute(pool.get_ref())
-        .await;
+        .await?;
 
-    match result {
-        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
-            "success": true,
-            "message": "Quick link unpinned successfully"
-        })),
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to unpin quick link"
-                }
-            }))
-        }
-    }
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Quick link unpinned successfully"
+    })))
 }
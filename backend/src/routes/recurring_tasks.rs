@@ -0,0 +1,577 @@
+//! Recurring tasks: routine work such as monthly filter checks or quarterly
+//! UPS tests, created ahead of each deadline by `task_generator`.
+//!
+//! A recurring task's rule yields deadlines from `startDate` on; its other
+//! fields and assignees are copied into each instance. Anyone can see them;
+//! only the creator or an admin can change, pause, skip or delete one.
+//! Deleting a recurring task keeps the instances created so far.
+
+use actix_web::{HttpResponse, web};
+use chrono::Duration;
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::filters::Filters;
+use crate::middleware::guards::{AuthUser, Member, RequireRole};
+use crate::models::tasks::{
+    CreateRecurringTaskRequest, RecurringTask, SkipOccurrenceRequest, UpdateRecurringTaskRequest,
+};
+use crate::recurrence::{self, RRule};
+use crate::routes::scheduling;
+use crate::routes::tasks::ensure_can_manage_project_tasks;
+use crate::task_generator::RECURRING_TASK_COLUMNS;
+use crate::timezone;
+
+const URGENCIES: [&str; 4] = ["urgent", "high", "medium", "low"];
+const DEPARTMENTS: [&str; 3] = ["IT", "Engineering", "Both"];
+
+/// Instances can be created at most a year ahead
+const MAX_LEAD_DAYS: i64 = 365;
+
+/// Upcoming deadlines listed with each recurring task
+const UPCOMING_COUNT: usize = 5;
+
+/// Recent instances listed by `GET /tasks/recurring/{id}`
+const RECENT_INSTANCES: i64 = 10;
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GetRecurringTasksQuery {
+    is_active: Option<bool>,
+    department: Option<String>,
+    project_id: Option<String>,
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered ahead of the `/tasks` scope in `tasks`, like dependencies
+    cfg.service(
+        web::resource("/tasks/recurring")
+            .route(web::get().to(get_recurring_tasks))
+            .route(web::post().to(create_recurring_task)),
+    )
+    .service(
+        web::resource("/tasks/recurring/{recurring_id}")
+            .route(web::get().to(get_recurring_task))
+            .route(web::put().to(update_recurring_task))
+            .route(web::delete().to(delete_recurring_task)),
+    )
+    .service(
+        web::resource("/tasks/recurring/{recurring_id}/skip")
+            .route(web::post().to(skip_occurrence)),
+    );
+}
+
+async fn get_recurring_tasks(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+    query: web::Query<GetRecurringTasksQuery>,
+) -> Result<HttpResponse, AppError> {
+    let mut filters = Filters::new();
+    filters
+        .eq_opt("r.is_active", query.is_active)
+        .eq_opt("r.department", query.department.as_ref())
+        .eq_opt("r.project_id", query.project_id.as_ref());
+
+    let mut sql = QueryBuilder::<Sqlite>::new(format!(
+        "SELECT {} FROM recurring_tasks r",
+        RECURRING_TASK_COLUMNS
+    ));
+    filters.push_where(&mut sql);
+    sql.push(" ORDER BY r.title");
+    let series = sql
+        .build_query_as::<RecurringTask>()
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    let mut series_json = Vec::new();
+    for recurring in &series {
+        series_json.push(recurring_json(pool.get_ref(), &auth, recurring).await?);
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "recurringTasks": series_json
+        }
+    })))
+}
+
+async fn get_recurring_task(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let recurring = find_recurring(pool.get_ref(), &path.into_inner()).await?;
+    let mut recurring_json = recurring_json(pool.get_ref(), &auth, &recurring).await?;
+
+    let instances = sqlx::query_as::<_, (String, String, String, String)>(
+        "SELECT id, status, deadline, occurrence_date FROM tasks WHERE recurring_task_id = ? ORDER BY occurrence_date DESC LIMIT ?",
+    )
+    .bind(&recurring.id)
+    .bind(RECENT_INSTANCES)
+    .fetch_all(pool.get_ref())
+    .await?;
+    recurring_json["recentInstances"] = instances
+        .into_iter()
+        .map(|(id, status, deadline, occurrence_date)| {
+            serde_json::json!({
+                "id": id,
+                "status": status,
+                "deadline": deadline,
+                "occurrenceDate": occurrence_date
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "recurringTask": recurring_json
+        }
+    })))
+}
+
+async fn create_recurring_task(
+    pool: web::Data<SqlitePool>,
+    auth: RequireRole<Member>,
+    body: web::Json<CreateRecurringTaskRequest>,
+) -> Result<HttpResponse, AppError> {
+    let mut recurring = RecurringTask {
+        id: Uuid::new_v4().to_string(),
+        title: body.title.trim().to_string(),
+        description: non_empty(body.description.as_deref()),
+        urgency: body.urgency.clone(),
+        department: body
+            .department
+            .clone()
+            .unwrap_or_else(|| auth.department.clone()),
+        project_id: non_empty(body.project_id.as_deref()),
+        recurrence_rule: body.recurrence_rule.clone(),
+        start_date: body.start_date.clone(),
+        lead_days: body.lead_days.unwrap_or(7),
+        skipped_dates: None,
+        last_generated_date: None,
+        is_active: body.is_active.unwrap_or(true),
+        created_by: auth.id.clone(),
+        created_at: None,
+        updated_at: None,
+    };
+    validate(pool.get_ref(), &mut recurring).await?;
+    if let Some(ref project_id) = recurring.project_id {
+        ensure_can_manage_project_tasks(pool.get_ref(), &auth, project_id).await?;
+    }
+    let assignee_ids = checked_assignees(pool.get_ref(), body.assignee_ids.as_deref()).await?;
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO recurring_tasks (id, title, description, urgency, department, project_id, recurrence_rule, start_date, lead_days, is_active, created_by)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&recurring.id)
+    .bind(&recurring.title)
+    .bind(&recurring.description)
+    .bind(&recurring.urgency)
+    .bind(&recurring.department)
+    .bind(&recurring.project_id)
+    .bind(&recurring.recurrence_rule)
+    .bind(&recurring.start_date)
+    .bind(recurring.lead_days)
+    .bind(recurring.is_active)
+    .bind(&recurring.created_by)
+    .execute(&mut *tx)
+    .await?;
+    if let Some(ref ids) = assignee_ids {
+        set_assignees(&mut tx, &recurring.id, ids).await?;
+    }
+    tx.commit().await?;
+
+    let recurring = find_recurring(pool.get_ref(), &recurring.id).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "recurringTask": recurring_json(pool.get_ref(), &auth, &recurring).await?
+        }
+    })))
+}
+
+async fn update_recurring_task(
+    pool: web::Data<SqlitePool>,
+    auth: RequireRole<Member>,
+    path: web::Path<String>,
+    body: web::Json<UpdateRecurringTaskRequest>,
+) -> Result<HttpResponse, AppError> {
+    let mut recurring = find_recurring(pool.get_ref(), &path.into_inner()).await?;
+    auth.ensure_owner_or_admin(
+        &recurring.created_by,
+        "NOT_RECURRING_TASK_OWNER",
+        "Only the recurring task's creator or an admin can change it",
+    )?;
+
+    if let Some(ref title) = body.title {
+        recurring.title = title.trim().to_string();
+    }
+    if let Some(ref description) = body.description {
+        recurring.description = non_empty(Some(description));
+    }
+    if let Some(ref urgency) = body.urgency {
+        recurring.urgency = urgency.clone();
+    }
+    if let Some(ref department) = body.department {
+        recurring.department = department.clone();
+    }
+    if let Some(ref project_id) = body.project_id {
+        recurring.project_id = non_empty(Some(project_id));
+    }
+    if let Some(ref rule) = body.recurrence_rule {
+        recurring.recurrence_rule = rule.clone();
+    }
+    if let Some(ref start_date) = body.start_date {
+        recurring.start_date = start_date.clone();
+    }
+    if let Some(lead_days) = body.lead_days {
+        recurring.lead_days = lead_days;
+    }
+    if let Some(is_active) = body.is_active {
+        recurring.is_active = is_active;
+    }
+    validate(pool.get_ref(), &mut recurring).await?;
+    if let Some(ref project_id) = recurring.project_id {
+        ensure_can_manage_project_tasks(pool.get_ref(), &auth, project_id).await?;
+    }
+    let assignee_ids = checked_assignees(pool.get_ref(), body.assignee_ids.as_deref()).await?;
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "UPDATE recurring_tasks SET title = ?, description = ?, urgency = ?, department = ?, project_id = ?, recurrence_rule = ?, start_date = ?, lead_days = ?, is_active = ?, updated_at = datetime('now')
+         WHERE id = ?",
+    )
+    .bind(&recurring.title)
+    .bind(&recurring.description)
+    .bind(&recurring.urgency)
+    .bind(&recurring.department)
+    .bind(&recurring.project_id)
+    .bind(&recurring.recurrence_rule)
+    .bind(&recurring.start_date)
+    .bind(recurring.lead_days)
+    .bind(recurring.is_active)
+    .bind(&recurring.id)
+    .execute(&mut *tx)
+    .await?;
+    if let Some(ref ids) = assignee_ids {
+        set_assignees(&mut tx, &recurring.id, ids).await?;
+    }
+    tx.commit().await?;
+
+    let recurring = find_recurring(pool.get_ref(), &recurring.id).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "recurringTask": recurring_json(pool.get_ref(), &auth, &recurring).await?
+        }
+    })))
+}
+
+async fn delete_recurring_task(
+    pool: web::Data<SqlitePool>,
+    auth: RequireRole<Member>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let recurring = find_recurring(pool.get_ref(), &path.into_inner()).await?;
+    auth.ensure_owner_or_admin(
+        &recurring.created_by,
+        "NOT_RECURRING_TASK_OWNER",
+        "Only the recurring task's creator or an admin can change it",
+    )?;
+
+    // Instances stay, without their link
+    sqlx::query("DELETE FROM recurring_tasks WHERE id = ?")
+        .bind(&recurring.id)
+        .execute(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Recurring task deleted"
+    })))
+}
+
+/// Leaves one deadline out of the series. An open instance already created
+/// for it is deleted; a completed one is kept.
+async fn skip_occurrence(
+    pool: web::Data<SqlitePool>,
+    auth: RequireRole<Member>,
+    path: web::Path<String>,
+    body: web::Json<SkipOccurrenceRequest>,
+) -> Result<HttpResponse, AppError> {
+    let recurring = find_recurring(pool.get_ref(), &path.into_inner()).await?;
+    auth.ensure_owner_or_admin(
+        &recurring.created_by,
+        "NOT_RECURRING_TASK_OWNER",
+        "Only the recurring task's creator or an admin can change it",
+    )?;
+    if let Some(ref project_id) = recurring.project_id {
+        // The open instance it deletes belongs to the project
+        ensure_can_manage_project_tasks(pool.get_ref(), &auth, project_id).await?;
+    }
+
+    let date = recurrence::parse_date(&body.date)?;
+    let rule = RRule::parse(&recurring.recurrence_rule)?;
+    let start_date = recurrence::parse_date(&recurring.start_date)?;
+    if !rule.includes(start_date, date) {
+        return Err(AppError::Validation(format!(
+            "{} is not a deadline of this recurring task",
+            date
+        ))
+        .with_code("NOT_AN_OCCURRENCE"));
+    }
+
+    let mut skipped = recurrence::parse_exdates(recurring.skipped_dates.as_deref());
+    skipped.push(date);
+    let date = date.format("%Y-%m-%d").to_string();
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "UPDATE recurring_tasks SET skipped_dates = ?, updated_at = datetime('now') WHERE id = ?",
+    )
+    .bind(recurrence::format_exdates(&skipped))
+    .bind(&recurring.id)
+    .execute(&mut *tx)
+    .await?;
+
+    let removed_task_id = sqlx::query_scalar::<_, String>(
+        "SELECT id FROM tasks WHERE recurring_task_id = ? AND occurrence_date = ? AND status != 'completed'",
+    )
+    .bind(&recurring.id)
+    .bind(&date)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some(ref task_id) = removed_task_id {
+        // Add to task history before delete
+        sqlx::query(
+            "INSERT INTO task_history (id, task_id, user_id, action) VALUES (?, ?, ?, 'deleted')",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(task_id)
+        .bind(&auth.id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM tasks WHERE id = ?")
+            .bind(task_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    let recurring = find_recurring(pool.get_ref(), &recurring.id).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "recurringTask": recurring_json(pool.get_ref(), &auth, &recurring).await?,
+            "skippedDate": date,
+            "removedTaskId": removed_task_id
+        }
+    })))
+}
+
+async fn find_recurring(pool: &SqlitePool, recurring_id: &str) -> Result<RecurringTask, AppError> {
+    sqlx::query_as::<_, RecurringTask>(&format!(
+        "SELECT {} FROM recurring_tasks r WHERE r.id = ?",
+        RECURRING_TASK_COLUMNS
+    ))
+    .bind(recurring_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| {
+        AppError::NotFound("Recurring task not found".to_string())
+            .with_code("RECURRING_TASK_NOT_FOUND")
+    })
+}
+
+/// Checks the fields and normalizes the rule and start date.
+async fn validate(pool: &SqlitePool, recurring: &mut RecurringTask) -> Result<(), AppError> {
+    if recurring.title.is_empty() {
+        return Err(AppError::Validation("title is required".to_string()));
+    }
+    if !URGENCIES.contains(&recurring.urgency.as_str()) {
+        return Err(AppError::Validation(format!(
+            "urgency must be one of {}",
+            URGENCIES.join(", ")
+        )));
+    }
+    if !DEPARTMENTS.contains(&recurring.department.as_str()) {
+        return Err(AppError::Validation(format!(
+            "department must be one of {}",
+            DEPARTMENTS.join(", ")
+        )));
+    }
+    if !(0..=MAX_LEAD_DAYS).contains(&recurring.lead_days) {
+        return Err(AppError::Validation(format!(
+            "leadDays must be between 0 and {}",
+            MAX_LEAD_DAYS
+        )));
+    }
+    recurring.recurrence_rule = RRule::parse(&recurring.recurrence_rule)?.to_string();
+    recurring.start_date = recurrence::parse_date(&recurring.start_date)?
+        .format("%Y-%m-%d")
+        .to_string();
+
+    if let Some(ref project_id) = recurring.project_id {
+        sqlx::query_scalar::<_, String>("SELECT id FROM projects WHERE id = ?")
+            .bind(project_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| {
+                AppError::NotFound("Project not found".to_string()).with_code("PROJECT_NOT_FOUND")
+            })?;
+    }
+    Ok(())
+}
+
+/// `ids` without repeats, once every one is known to be an active user.
+async fn checked_assignees(
+    pool: &SqlitePool,
+    ids: Option<&[String]>,
+) -> Result<Option<Vec<String>>, AppError> {
+    let Some(ids) = ids else {
+        return Ok(None);
+    };
+    let ids = scheduling::unique(ids);
+    let missing = scheduling::missing_ids(
+        pool,
+        "SELECT id FROM users WHERE COALESCE(is_active, 1) = 1 AND id IN (",
+        &ids,
+    )
+    .await?;
+    if !missing.is_empty() {
+        return Err(
+            AppError::NotFound("Some assignees do not exist".to_string())
+                .with_code("USER_NOT_FOUND")
+                .with_details(serde_json::json!({ "userIds": missing })),
+        );
+    }
+    Ok(Some(ids))
+}
+
+async fn set_assignees(
+    conn: &mut SqliteConnection,
+    recurring_id: &str,
+    user_ids: &[String],
+) -> Result<(), AppError> {
+    sqlx::query("DELETE FROM recurring_task_assignees WHERE recurring_task_id = ?")
+        .bind(recurring_id)
+        .execute(&mut *conn)
+        .await?;
+    for user_id in user_ids {
+        sqlx::query(
+            "INSERT INTO recurring_task_assignees (recurring_task_id, user_id) VALUES (?, ?)",
+        )
+        .bind(recurring_id)
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// The next deadlines that have not been created yet, as the generator will
+/// see them.
+async fn upcoming(pool: &SqlitePool, recurring: &RecurringTask) -> Result<Vec<String>, AppError> {
+    let (Ok(rule), Ok(start_date)) = (
+        RRule::parse(&recurring.recurrence_rule),
+        recurrence::parse_date(&recurring.start_date),
+    ) else {
+        return Ok(Vec::new());
+    };
+    let today = timezone::user_time_zone(pool, &recurring.created_by)
+        .await?
+        .today(timezone::now_utc());
+    let from = match recurring
+        .last_generated_date
+        .as_deref()
+        .and_then(|date| recurrence::parse_date(date).ok())
+    {
+        Some(last) => today.max(last + Duration::days(1)),
+        None => today,
+    };
+
+    // Far enough for yearly series to show a few
+    let dates = rule.occurrences(
+        start_date,
+        &recurrence::parse_exdates(recurring.skipped_dates.as_deref()),
+        from,
+        from + Duration::days(366 * UPCOMING_COUNT as i64),
+    );
+    Ok(dates
+        .into_iter()
+        .take(UPCOMING_COUNT)
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .collect())
+}
+
+async fn recurring_json(
+    pool: &SqlitePool,
+    user: &AuthUser,
+    recurring: &RecurringTask,
+) -> Result<serde_json::Value, AppError> {
+    let assignees = sqlx::query_as::<_, (String, String, String, String)>(
+        r#"
+        SELECT u.id, u.first_name, u.last_name, u.email
+        FROM recurring_task_assignees ra
+        JOIN users u ON ra.user_id = u.id
+        WHERE ra.recurring_task_id = ?
+        ORDER BY u.first_name, u.last_name
+        "#,
+    )
+    .bind(&recurring.id)
+    .fetch_all(pool)
+    .await?;
+    let assignees: Vec<serde_json::Value> = assignees
+        .iter()
+        .map(|(id, first, last, email)| {
+            serde_json::json!({
+                "id": id,
+                "firstName": first,
+                "lastName": last,
+                "email": email,
+                "name": format!("{} {}", first, last)
+            })
+        })
+        .collect();
+    let upcoming = if recurring.is_active {
+        upcoming(pool, recurring).await?
+    } else {
+        Vec::new()
+    };
+
+    Ok(serde_json::json!({
+        "id": recurring.id,
+        "title": recurring.title,
+        "description": recurring.description,
+        "urgency": recurring.urgency,
+        "department": recurring.department,
+        "projectId": recurring.project_id,
+        "assignees": assignees,
+        "recurrenceRule": recurring.recurrence_rule,
+        "startDate": recurring.start_date,
+        "leadDays": recurring.lead_days,
+        "skippedDates": recurrence::parse_exdates(recurring.skipped_dates.as_deref())
+            .iter()
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .collect::<Vec<_>>(),
+        "lastGeneratedDate": recurring.last_generated_date,
+        "upcomingDeadlines": upcoming,
+        "isActive": recurring.is_active,
+        "createdBy": recurring.created_by,
+        "canEdit": recurring.created_by == user.id || user.is_admin(),
+        "createdAt": recurring.created_at,
+        "updatedAt": recurring.updated_at
+    }))
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
@@ -0,0 +1,810 @@
+//! Scheduling assistant: free meeting slots for a group of people.
+//!
+//! `POST /api/calendar/find-slots` steps through the working hours of each
+//! day in the range and keeps the slots where no attendee has an event and no
+//! requested equipment is booked. Bookings cover whole days, so one booking
+//! rules out the day. Declined invitations and deadlines do not make anyone
+//! busy.
+//!
+//! With `requireOnSite`, each attendee's check-ins over the last eight weeks
+//! show how often they were on-site at that time on that weekday; slots where
+//! anyone was there less than half the time are dropped.
+//!
+//! Slots are ranked by on-site likelihood when requested, then by the free
+//! time around them, then by start. Overlapping slots are not both returned.
+//! Dates, working hours and slots are in the requester's time zone; events
+//! are compared with them in UTC, each converted from its own zone.
+//!
+//! The same notion of busy time backs conflict detection when events are
+//! created or moved; see `find_conflicts`.
+
+use actix_web::{HttpResponse, web};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
+use std::collections::{HashMap, HashSet};
+
+use crate::error::AppError;
+use crate::filters::Filters;
+use crate::middleware::guards::AuthUser;
+use crate::models::events::Event;
+use crate::recurrence;
+use crate::routes::events::{EventOccurrence, events_in_range, parse_time};
+use crate::timezone::{self, TimeZone};
+
+/// Length assumed for events without an end time
+const DEFAULT_EVENT_MINUTES: i64 = 60;
+
+/// Candidate slots start this far apart
+const SLOT_STEP_MINUTES: i64 = 30;
+
+/// Free time beyond this on either side of a slot does not rank it higher
+const MAX_BUFFER_MINUTES: i64 = 60;
+
+const MAX_RANGE_DAYS: i64 = 31;
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 50;
+
+/// Check-ins this far back make up an attendee's on-site pattern
+const ON_SITE_LOOKBACK_DAYS: i64 = 56;
+
+/// Share of past weekdays an attendee must have been on-site for the slot
+const ON_SITE_THRESHOLD: f64 = 0.5;
+
+/// Recurring events are checked for conflicts this far ahead
+const CONFLICT_HORIZON_DAYS: i64 = 365;
+
+/// At most this many conflicts are listed in an `EVENT_CONFLICT` error
+const MAX_REPORTED_CONFLICTS: usize = 50;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FindSlotsRequest {
+    attendee_ids: Vec<String>,
+    duration_minutes: i64,
+    start_date: String,
+    end_date: String,
+    working_hours: Option<WorkingHours>,
+    include_weekends: Option<bool>,
+    equipment_ids: Option<Vec<String>>,
+    require_on_site: Option<bool>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkingHours {
+    start: String,
+    end: String,
+}
+
+/// A free period long enough for the meeting.
+#[derive(Debug, Clone, PartialEq)]
+struct Slot {
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    /// Free time before and after the slot, whichever is shorter, capped at
+    /// `MAX_BUFFER_MINUTES`
+    buffer_minutes: i64,
+    /// Lowest on-site likelihood among the attendees, when requested
+    on_site: Option<f64>,
+}
+
+/// Time taken up by one occurrence of an event, in UTC.
+pub struct BusyTime {
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub occurrence: EventOccurrence,
+}
+
+/// An attendee already busy when an event being saved takes place.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conflict {
+    pub user_id: String,
+    /// The occurrence of the saved event that overlaps
+    pub occurrence_date: String,
+    pub event_id: String,
+    pub title: String,
+    pub date: String,
+    pub start_time: String,
+    pub end_time: Option<String>,
+    /// Zone of the other event's date and times
+    pub time_zone: String,
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered ahead of the `/calendar` scope in `events`, like the feed
+    cfg.service(web::resource("/calendar/find-slots").route(web::post().to(find_slots)));
+}
+
+async fn find_slots(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+    body: web::Json<FindSlotsRequest>,
+) -> Result<HttpResponse, AppError> {
+    let from = recurrence::parse_date(&body.start_date)?;
+    let to = recurrence::parse_date(&body.end_date)?;
+    if to < from {
+        return Err(AppError::Validation(
+            "endDate must not be before startDate".to_string(),
+        ));
+    }
+    if (to - from).num_days() >= MAX_RANGE_DAYS {
+        return Err(AppError::Validation(format!(
+            "The date range can cover at most {} days",
+            MAX_RANGE_DAYS
+        )));
+    }
+
+    let (work_start, work_end) = match &body.working_hours {
+        Some(hours) => (parse_time(&hours.start)?, parse_time(&hours.end)?),
+        None => (
+            NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+        ),
+    };
+    if work_end <= work_start {
+        return Err(AppError::Validation(
+            "workingHours.end must be after workingHours.start".to_string(),
+        ));
+    }
+    if body.duration_minutes <= 0 {
+        return Err(AppError::Validation(
+            "durationMinutes must be positive".to_string(),
+        ));
+    }
+    let duration = Duration::minutes(body.duration_minutes);
+    if duration > work_end - work_start {
+        return Err(AppError::Validation(
+            "durationMinutes is longer than the working hours".to_string(),
+        ));
+    }
+
+    let attendee_ids = unique(&body.attendee_ids);
+    if attendee_ids.is_empty() {
+        return Err(AppError::Validation(
+            "attendeeIds must name at least one user".to_string(),
+        ));
+    }
+    let equipment_ids = unique(body.equipment_ids.as_deref().unwrap_or_default());
+    let missing = missing_ids(
+        pool.get_ref(),
+        "SELECT id FROM users WHERE COALESCE(is_active, 1) = 1 AND id IN (",
+        &attendee_ids,
+    )
+    .await?;
+    if !missing.is_empty() {
+        return Err(
+            AppError::NotFound("Some attendees do not exist".to_string())
+                .with_code("USER_NOT_FOUND")
+                .with_details(serde_json::json!({ "userIds": missing })),
+        );
+    }
+    let missing = missing_ids(
+        pool.get_ref(),
+        "SELECT id FROM equipment WHERE id IN (",
+        &equipment_ids,
+    )
+    .await?;
+    if !missing.is_empty() {
+        return Err(
+            AppError::NotFound("Some equipment does not exist".to_string())
+                .with_code("EQUIPMENT_NOT_FOUND")
+                .with_details(serde_json::json!({ "equipmentIds": missing })),
+        );
+    }
+
+    let zone = timezone::user_time_zone(pool.get_ref(), &auth.id).await?;
+    let mut conn = pool.acquire().await?;
+    let mut busy = Vec::new();
+    for user_id in &attendee_ids {
+        let times = busy_intervals(
+            &mut conn,
+            user_id,
+            zone.day_bounds(from).0,
+            zone.day_bounds(to).1,
+        )
+        .await?;
+        busy.extend(
+            times
+                .into_iter()
+                .map(|b| (zone.to_local(b.start), zone.to_local(b.end))),
+        );
+    }
+    let booked = booked_days(pool.get_ref(), &equipment_ids, from, to).await?;
+
+    let require_on_site = body.require_on_site.unwrap_or(false);
+    let today = zone.today(timezone::now_utc());
+    let lookback = (
+        today - Duration::days(ON_SITE_LOOKBACK_DAYS),
+        today - Duration::days(1),
+    );
+    let check_ins = if require_on_site {
+        check_ins(pool.get_ref(), &attendee_ids, lookback.0, &zone).await?
+    } else {
+        HashMap::new()
+    };
+
+    let include_weekends = body.include_weekends.unwrap_or(false);
+    let mut candidates = Vec::new();
+    for date in from.iter_days().take_while(|d| *d <= to) {
+        if booked.contains(&date)
+            || (!include_weekends && matches!(date.weekday(), Weekday::Sat | Weekday::Sun))
+        {
+            continue;
+        }
+        for mut slot in day_slots(date, work_start, work_end, duration, &busy) {
+            if require_on_site {
+                let likelihood = attendee_ids
+                    .iter()
+                    .map(|id| {
+                        let records = check_ins.get(id).map(Vec::as_slice).unwrap_or_default();
+                        on_site_likelihood(records, &slot, lookback)
+                    })
+                    .fold(1.0, f64::min);
+                if likelihood < ON_SITE_THRESHOLD {
+                    continue;
+                }
+                slot.on_site = Some(likelihood);
+            }
+            candidates.push(slot);
+        }
+    }
+
+    let limit = body.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let slots_json: Vec<serde_json::Value> = rank_slots(candidates, limit)
+        .iter()
+        .map(|slot| {
+            serde_json::json!({
+                "date": slot.start.format("%Y-%m-%d").to_string(),
+                "startTime": slot.start.format("%H:%M").to_string(),
+                "endTime": slot.end.format("%H:%M").to_string(),
+                "bufferMinutes": slot.buffer_minutes,
+                "onSiteLikelihood": slot.on_site
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "slots": slots_json,
+            "count": slots_json.len(),
+            "timeZone": zone.name()
+        }
+    })))
+}
+
+/// When an occurrence on `date` starts and ends, in UTC. An end time at or
+/// before the start means the event runs past midnight.
+pub fn event_interval(event: &Event, date: NaiveDate) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    let zone = TimeZone::named(&event.timezone).unwrap_or_else(|_| TimeZone::utc());
+    let start = date.and_time(parse_time(&event.start_time).ok()?);
+    let end = match event.end_time.as_deref().map(parse_time) {
+        Some(Ok(end)) if end > start.time() => date.and_time(end),
+        Some(Ok(end)) => (date + Duration::days(1)).and_time(end),
+        _ => start + Duration::minutes(DEFAULT_EVENT_MINUTES),
+    };
+    Some((zone.to_utc(start), zone.to_utc(end)))
+}
+
+/// Times overlapping the UTC period `from..to` taken up by events the user
+/// created or has not declined, deadlines aside.
+pub async fn busy_intervals(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> Result<Vec<BusyTime>, AppError> {
+    let mut filters = Filters::new();
+    filters
+        .condition(
+            "(e.created_by = ? OR EXISTS (SELECT 1 FROM event_attendees ea WHERE ea.event_id = e.id AND ea.user_id = ? AND ea.status != 'declined'))",
+            vec![user_id.into(), user_id.into()],
+        )
+        .raw("e.event_type != 'deadline'");
+
+    // Event dates are local to each event, at most a day either side of UTC,
+    // and an event may run past midnight
+    let occurrences = events_in_range(
+        conn,
+        &filters,
+        from.date() - Duration::days(2),
+        to.date() + Duration::days(1),
+    )
+    .await?;
+    Ok(occurrences
+        .into_iter()
+        .filter_map(|occurrence| {
+            let date = recurrence::parse_date(&occurrence.date).ok()?;
+            let (start, end) = event_interval(&occurrence.event, date)?;
+            Some(BusyTime {
+                start,
+                end,
+                occurrence,
+            })
+        })
+        .filter(|b| b.start < to && b.end > from)
+        .collect())
+}
+
+/// Other events of `user_ids` that overlap `event` as it is saved on `conn`,
+/// with recurring events expanded on both sides. A series is checked from its
+/// first date, or today if later, for `CONFLICT_HORIZON_DAYS`. Occurrences of
+/// the event's own series never conflict with it, and deadlines take no time.
+pub async fn find_conflicts(
+    conn: &mut SqliteConnection,
+    event: &Event,
+    user_ids: &[String],
+) -> Result<Vec<Conflict>, AppError> {
+    if event.event_type == "deadline" {
+        return Ok(Vec::new());
+    }
+
+    let first = recurrence::parse_date(&event.event_date)?;
+    let (from, to) = if event.is_series() {
+        let zone = TimeZone::named(&event.timezone).unwrap_or_else(|_| TimeZone::utc());
+        let from = first.max(zone.today(timezone::now_utc()));
+        (from, from + Duration::days(CONFLICT_HORIZON_DAYS))
+    } else {
+        (first, first)
+    };
+    let mut own = Filters::new();
+    own.eq("e.id", event.id.clone());
+    let intervals: Vec<(String, NaiveDateTime, NaiveDateTime)> =
+        events_in_range(conn, &own, from, to)
+            .await?
+            .into_iter()
+            .filter_map(|o| {
+                let (start, end) = event_interval(event, recurrence::parse_date(&o.date).ok()?)?;
+                Some((o.date, start, end))
+            })
+            .collect();
+    let (Some(first), Some(last)) = (intervals.first(), intervals.last()) else {
+        return Ok(Vec::new());
+    };
+    let (from, to) = (first.1, last.2);
+
+    let series_id = event.parent_event_id.as_deref().unwrap_or(&event.id);
+    let mut conflicts = Vec::new();
+    for user_id in unique(user_ids) {
+        for busy in busy_intervals(conn, &user_id, from, to).await? {
+            let other = &busy.occurrence.event;
+            if other.id == series_id || other.parent_event_id.as_deref() == Some(series_id) {
+                continue;
+            }
+            let overlapping = intervals
+                .iter()
+                .find(|(_, start, end)| busy.start < *end && busy.end > *start);
+            if let Some((date, _, _)) = overlapping {
+                conflicts.push(Conflict {
+                    user_id: user_id.clone(),
+                    occurrence_date: date.clone(),
+                    event_id: other.id.clone(),
+                    title: other.title.clone(),
+                    date: busy.occurrence.date.clone(),
+                    start_time: other.start_time.clone(),
+                    end_time: other.end_time.clone(),
+                    time_zone: other.timezone.clone(),
+                });
+            }
+        }
+    }
+    conflicts.sort_by(|a, b| {
+        (&a.occurrence_date, &a.date, &a.start_time, &a.user_id).cmp(&(
+            &b.occurrence_date,
+            &b.date,
+            &b.start_time,
+            &b.user_id,
+        ))
+    });
+    Ok(conflicts)
+}
+
+/// Fails with `409 EVENT_CONFLICT` listing what `event` overlaps, unless
+/// `force` is set.
+pub async fn ensure_no_conflicts(
+    conn: &mut SqliteConnection,
+    event: &Event,
+    user_ids: &[String],
+    force: bool,
+) -> Result<(), AppError> {
+    if force {
+        return Ok(());
+    }
+    let conflicts = find_conflicts(conn, event, user_ids).await?;
+    if conflicts.is_empty() {
+        return Ok(());
+    }
+
+    let total = conflicts.len();
+    let listed = &conflicts[..total.min(MAX_REPORTED_CONFLICTS)];
+    Err(AppError::Conflict(format!(
+        "The event overlaps {} other event(s) of its attendees; send force=true to save it anyway",
+        total
+    ))
+    .with_code("EVENT_CONFLICT")
+    .with_details(serde_json::json!({
+        "conflicts": listed,
+        "total": total
+    })))
+}
+
+/// Free slots of `duration` on `date`, starting every `SLOT_STEP_MINUTES`
+/// from `work_start` and ending by `work_end`.
+fn day_slots(
+    date: NaiveDate,
+    work_start: NaiveTime,
+    work_end: NaiveTime,
+    duration: Duration,
+    busy: &[(NaiveDateTime, NaiveDateTime)],
+) -> Vec<Slot> {
+    let day_end = date.and_time(work_end);
+    let mut slots = Vec::new();
+    let mut start = date.and_time(work_start);
+
+    while start + duration <= day_end {
+        let end = start + duration;
+        if !busy
+            .iter()
+            .any(|(b_start, b_end)| *b_start < end && *b_end > start)
+        {
+            let before = busy
+                .iter()
+                .filter(|(_, b_end)| *b_end <= start)
+                .map(|(_, b_end)| (start - *b_end).num_minutes())
+                .min();
+            let after = busy
+                .iter()
+                .filter(|(b_start, _)| *b_start >= end)
+                .map(|(b_start, _)| (*b_start - end).num_minutes())
+                .min();
+            let buffer_minutes = before
+                .into_iter()
+                .chain(after)
+                .min()
+                .unwrap_or(MAX_BUFFER_MINUTES)
+                .min(MAX_BUFFER_MINUTES);
+            slots.push(Slot {
+                start,
+                end,
+                buffer_minutes,
+                on_site: None,
+            });
+        }
+        start += Duration::minutes(SLOT_STEP_MINUTES);
+    }
+    slots
+}
+
+/// Share of the days in `lookback` falling on the slot's weekday when a
+/// check-in covered the slot's time of day.
+fn on_site_likelihood(
+    check_ins: &[(NaiveDateTime, NaiveDateTime)],
+    slot: &Slot,
+    lookback: (NaiveDate, NaiveDate),
+) -> f64 {
+    let weekday = slot.start.weekday();
+    let (start, end) = (slot.start.time(), slot.end.time());
+    let days: Vec<NaiveDate> = lookback
+        .0
+        .iter_days()
+        .take_while(|d| *d <= lookback.1)
+        .filter(|d| d.weekday() == weekday)
+        .collect();
+    if days.is_empty() {
+        return 0.0;
+    }
+
+    let on_site = days
+        .iter()
+        .filter(|day| {
+            check_ins.iter().any(|(check_in, check_out)| {
+                *check_in <= day.and_time(start) && *check_out >= day.and_time(end)
+            })
+        })
+        .count();
+    on_site as f64 / days.len() as f64
+}
+
+/// The best `limit` slots, leaving out any that overlap a better one.
+fn rank_slots(mut candidates: Vec<Slot>, limit: usize) -> Vec<Slot> {
+    candidates.sort_by(|a, b| {
+        b.on_site
+            .partial_cmp(&a.on_site)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(b.buffer_minutes.cmp(&a.buffer_minutes))
+            .then(a.start.cmp(&b.start))
+    });
+
+    let mut picked: Vec<Slot> = Vec::new();
+    for slot in candidates {
+        if picked.len() == limit {
+            break;
+        }
+        if !picked
+            .iter()
+            .any(|p| p.start < slot.end && p.end > slot.start)
+        {
+            picked.push(slot);
+        }
+    }
+    picked
+}
+
+/// Days within `from..=to` on which any of the equipment has an active booking.
+async fn booked_days(
+    pool: &SqlitePool,
+    equipment_ids: &[String],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<HashSet<NaiveDate>, AppError> {
+    if equipment_ids.is_empty() {
+        return Ok(HashSet::new());
+    }
+
+    let mut sql = QueryBuilder::<Sqlite>::new(
+        "SELECT start_date, end_date FROM bookings WHERE status = 'active' AND start_date <= ",
+    );
+    sql.push_bind(to.format("%Y-%m-%d").to_string())
+        .push(" AND end_date >= ")
+        .push_bind(from.format("%Y-%m-%d").to_string())
+        .push(" AND equipment_id IN (");
+    push_list(&mut sql, equipment_ids);
+    let bookings = sql
+        .build_query_as::<(String, String)>()
+        .fetch_all(pool)
+        .await?;
+
+    let mut days = HashSet::new();
+    for (start, end) in bookings {
+        let (Ok(start), Ok(end)) = (recurrence::parse_date(&start), recurrence::parse_date(&end))
+        else {
+            continue;
+        };
+        days.extend(
+            start
+                .max(from)
+                .iter_days()
+                .take_while(|d| *d <= end.min(to)),
+        );
+    }
+    Ok(days)
+}
+
+/// Check-ins since the start of the local date `since` by user, in `zone`'s
+/// local time. A check-in never checked out is taken to last until the end of
+/// its day.
+async fn check_ins(
+    pool: &SqlitePool,
+    user_ids: &[String],
+    since: NaiveDate,
+    zone: &TimeZone,
+) -> Result<HashMap<String, Vec<(NaiveDateTime, NaiveDateTime)>>, AppError> {
+    let mut sql = QueryBuilder::<Sqlite>::new(
+        "SELECT user_id, check_in_time, check_out_time FROM check_in_records WHERE check_in_time >= ",
+    );
+    sql.push_bind(timezone::format_sql_datetime(zone.day_bounds(since).0))
+        .push(" AND user_id IN (");
+    push_list(&mut sql, user_ids);
+    let records = sql
+        .build_query_as::<(String, String, Option<String>)>()
+        .fetch_all(pool)
+        .await?;
+
+    let mut by_user: HashMap<String, Vec<(NaiveDateTime, NaiveDateTime)>> = HashMap::new();
+    for (user_id, check_in, check_out) in records {
+        let Some(check_in) = parse_date_time(&check_in).map(|at| zone.to_local(at)) else {
+            continue;
+        };
+        let check_out = check_out
+            .as_deref()
+            .and_then(parse_date_time)
+            .map(|at| zone.to_local(at))
+            .unwrap_or_else(|| check_in.date().and_hms_opt(23, 59, 59).unwrap());
+        by_user
+            .entry(user_id)
+            .or_default()
+            .push((check_in, check_out));
+    }
+    Ok(by_user)
+}
+
+/// The ids that `query`, ending in `IN (`, does not return.
+pub async fn missing_ids(
+    pool: &SqlitePool,
+    query: &'static str,
+    ids: &[String],
+) -> Result<Vec<String>, AppError> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut sql = QueryBuilder::<Sqlite>::new(query);
+    push_list(&mut sql, ids);
+    let found: HashSet<String> = sql
+        .build_query_scalar::<String>()
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect();
+
+    Ok(ids
+        .iter()
+        .filter(|id| !found.contains(*id))
+        .cloned()
+        .collect())
+}
+
+/// Binds `values` separated by commas and closes the `IN (` list.
+fn push_list(sql: &mut QueryBuilder<'_, Sqlite>, values: &[String]) {
+    let mut list = sql.separated(", ");
+    for value in values {
+        list.push_bind(value.clone());
+    }
+    list.push_unseparated(")");
+}
+
+/// `ids` without repeats, in their original order.
+pub fn unique(ids: &[String]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    ids.iter()
+        .filter(|id| seen.insert(id.as_str()))
+        .cloned()
+        .collect()
+}
+
+fn parse_date_time(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S"))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: &str, time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn time(value: &str) -> NaiveTime {
+        parse_time(value).unwrap()
+    }
+
+    fn event(start: &str, end: Option<&str>) -> Event {
+        Event::new(
+            "Standup".to_string(),
+            None,
+            "meeting".to_string(),
+            "2026-03-02".to_string(),
+            start.to_string(),
+            end.map(str::to_string),
+            None,
+            None,
+            "user".to_string(),
+            None,
+            false,
+            None,
+        )
+    }
+
+    #[test]
+    fn event_intervals_default_and_overnight() {
+        let date = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        assert_eq!(
+            event_interval(&event("09:00", Some("09:30")), date),
+            Some((at("2026-03-02", "09:00"), at("2026-03-02", "09:30")))
+        );
+        assert_eq!(
+            event_interval(&event("15:00:00", None), date),
+            Some((at("2026-03-02", "15:00"), at("2026-03-02", "16:00")))
+        );
+        assert_eq!(
+            event_interval(&event("22:00", Some("02:00")), date),
+            Some((at("2026-03-02", "22:00"), at("2026-03-03", "02:00")))
+        );
+    }
+
+    #[test]
+    fn slots_avoid_busy_times_and_measure_buffers() {
+        let date = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        let busy = [
+            (at("2026-03-01", "23:00"), at("2026-03-02", "10:00")),
+            (at("2026-03-02", "11:30"), at("2026-03-02", "12:00")),
+        ];
+        let slots = day_slots(
+            date,
+            time("09:00"),
+            time("13:00"),
+            Duration::minutes(60),
+            &busy,
+        );
+
+        let starts: Vec<(String, i64)> = slots
+            .iter()
+            .map(|s| (s.start.format("%H:%M").to_string(), s.buffer_minutes))
+            .collect();
+        assert_eq!(
+            starts,
+            [
+                ("10:00".to_string(), 0),
+                ("10:30".to_string(), 0),
+                ("12:00".to_string(), 0)
+            ]
+        );
+
+        let slots = day_slots(
+            date,
+            time("09:00"),
+            time("13:00"),
+            Duration::minutes(30),
+            &busy,
+        );
+        let best = rank_slots(slots, 1);
+        assert_eq!(best[0].start, at("2026-03-02", "10:30"));
+        assert_eq!(best[0].buffer_minutes, 30);
+    }
+
+    #[test]
+    fn ranking_skips_overlapping_slots() {
+        let slot = |start: &str, buffer_minutes| Slot {
+            start: at("2026-03-02", start),
+            end: at("2026-03-02", start) + Duration::minutes(60),
+            buffer_minutes,
+            on_site: None,
+        };
+        let ranked = rank_slots(
+            vec![
+                slot("09:00", 60),
+                slot("09:30", 60),
+                slot("10:00", 60),
+                slot("14:00", 30),
+            ],
+            3,
+        );
+        let starts: Vec<NaiveDateTime> = ranked.iter().map(|s| s.start).collect();
+        assert_eq!(
+            starts,
+            [
+                at("2026-03-02", "09:00"),
+                at("2026-03-02", "10:00"),
+                at("2026-03-02", "14:00")
+            ]
+        );
+    }
+
+    #[test]
+    fn on_site_likelihood_follows_weekday_pattern() {
+        // Four Mondays in the window; on-site from 08:30 to 17:30 on three
+        let lookback = (
+            NaiveDate::from_ymd_opt(2026, 2, 2).unwrap(),
+            NaiveDate::from_ymd_opt(2026, 3, 1).unwrap(),
+        );
+        let check_ins = [
+            (at("2026-02-02", "08:30"), at("2026-02-02", "17:30")),
+            (at("2026-02-09", "08:30"), at("2026-02-09", "17:30")),
+            (at("2026-02-16", "08:30"), at("2026-02-16", "12:00")),
+            (at("2026-02-23", "08:30"), at("2026-02-23", "17:30")),
+        ];
+        let slot = |start: &str| Slot {
+            start: at("2026-03-02", start),
+            end: at("2026-03-02", start) + Duration::minutes(60),
+            buffer_minutes: 0,
+            on_site: None,
+        };
+
+        assert_eq!(
+            on_site_likelihood(&check_ins, &slot("10:00"), lookback),
+            1.0
+        );
+        assert_eq!(
+            on_site_likelihood(&check_ins, &slot("14:00"), lookback),
+            0.75
+        );
+        assert_eq!(
+            on_site_likelihood(&check_ins, &slot("18:00"), lookback),
+            0.0
+        );
+    }
+}
@@ -1,35 +1,28 @@
 use actix_web::{HttpResponse, web};
-use sqlx::SqlitePool;
 use serde::Deserialize;
+use sqlx::SqlitePool;
+
+use crate::error::AppError;
 
 #[derive(Deserialize)]
 pub struct SearchQuery {
     pub q: String,
-    pub types: Option<String>,  // comma-separated: events,tasks,equipment,glossary,users
+    pub types: Option<String>, // comma-separated: events,tasks,equipment,glossary,users
     pub department: Option<String>,
     pub limit: Option<i32>,
 }
 
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
-    cfg.service(
-        web::resource("/search")
-            .route(web::get().to(global_search)),
-    );
+    cfg.service(web::resource("/search").route(web::get().to(global_search)));
 }
 
 async fn global_search(
     pool: web::Data<SqlitePool>,
     query: web::Query<SearchQuery>,
-) -> HttpResponse {
+) -> Result<HttpResponse, AppError> {
     let search = &query.q;
     if search.is_empty() {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "BAD_REQUEST",
-                "message": "Search query is required"
-            }
-        }));
+        return Err(AppError::Validation("Search query is required".to_string()));
     }
 
     let search_pattern = format!("%{}%", search);
@@ -188,11 +181,11 @@ async fn global_search(
         results["users"] = serde_json::json!(users_json);
     }
 
-    HttpResponse::Ok().json(serde_json::json!({
+    Ok(HttpResponse::Ok().json(serde_json::json!({
         "success": true,
         "data": {
             "query": search,
             "results": results
         }
-    }))
+    })))
 }
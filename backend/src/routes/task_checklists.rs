@@ -0,0 +1,519 @@
+//! Task checklists: the steps of a task, such as the points of a BMS
+//! point-to-point verification, each ticked off on its own.
+//!
+//! Items are kept in order by `position`. A task with
+//! `checklistAutoComplete` follows its checklist: ticking the last open item
+//! completes the task and unticking one reopens it, through the same status
+//! change as `PATCH /api/tasks/{id}/status`. A blocked task is left as it is.
+
+use actix_web::{HttpResponse, web};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::middleware::guards::{AuthUser, Member, RequireRole};
+use crate::models::tasks::{ChecklistItem, CreateChecklistItemRequest, UpdateChecklistItemRequest};
+use crate::routes::task_dependencies;
+use crate::routes::tasks::{can_manage_task, ensure_can_manage_task, set_task_status};
+
+const ITEM_COLUMNS: &str = "id, task_id, title, position, is_completed, assignee_id, completed_by, completed_at, created_by, created_at, updated_at";
+
+/// Longest item title
+const MAX_TITLE_LENGTH: usize = 500;
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered ahead of the `/tasks` scope in `tasks`, like dependencies
+    cfg.service(
+        web::resource("/tasks/{task_id}/checklist")
+            .route(web::get().to(get_checklist))
+            .route(web::post().to(add_item)),
+    )
+    .service(
+        web::resource("/tasks/{task_id}/checklist/{item_id}")
+            .route(web::put().to(update_item))
+            .route(web::delete().to(delete_item)),
+    );
+}
+
+/// How far a task's checklist has come.
+#[derive(Debug, Default, PartialEq)]
+pub struct Progress {
+    pub total: i64,
+    pub completed: i64,
+    pub auto_complete: bool,
+}
+
+impl Progress {
+    /// Whole percent of items completed, 0 for an empty checklist.
+    pub fn percent(&self) -> i64 {
+        if self.total == 0 {
+            0
+        } else {
+            self.completed * 100 / self.total
+        }
+    }
+
+    /// The status a task in `status` moves to under auto-completion, if any.
+    fn transition(&self, status: &str) -> Option<&'static str> {
+        if !self.auto_complete || self.total == 0 {
+            return None;
+        }
+        if self.completed == self.total {
+            (status != "completed").then_some("completed")
+        } else {
+            (status == "completed").then_some("in-progress")
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "total": self.total,
+            "completed": self.completed,
+            "percent": self.percent(),
+            "autoComplete": self.auto_complete
+        })
+    }
+}
+
+/// Checklist progress of a task; empty for a task that doesn't exist.
+pub async fn progress(pool: &SqlitePool, task_id: &str) -> Result<Progress, AppError> {
+    let progress = sqlx::query_as::<_, (i64, i64, bool)>(
+        r#"
+        SELECT COUNT(i.id), COALESCE(SUM(i.is_completed), 0), t.checklist_auto_complete
+        FROM tasks t
+        LEFT JOIN task_checklist_items i ON i.task_id = t.id
+        WHERE t.id = ?
+        GROUP BY t.id
+        "#,
+    )
+    .bind(task_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(progress
+        .map(|(total, completed, auto_complete)| Progress {
+            total,
+            completed,
+            auto_complete,
+        })
+        .unwrap_or_default())
+}
+
+/// Moves `id` to `position` in `ids`, clamped to the ends of the list.
+fn move_to(ids: &mut Vec<String>, id: &str, position: i64) {
+    let Some(from) = ids.iter().position(|other| other == id) else {
+        return;
+    };
+    let item = ids.remove(from);
+    let to = position.clamp(0, ids.len() as i64) as usize;
+    ids.insert(to, item);
+}
+
+fn item_json(item: &ChecklistItem) -> serde_json::Value {
+    serde_json::json!({
+        "id": item.id,
+        "taskId": item.task_id,
+        "title": item.title,
+        "position": item.position,
+        "isCompleted": item.is_completed,
+        "assigneeId": item.assignee_id,
+        "completedBy": item.completed_by,
+        "completedAt": item.completed_at,
+        "createdBy": item.created_by,
+        "createdAt": item.created_at,
+        "updatedAt": item.updated_at
+    })
+}
+
+async fn task_status(pool: &SqlitePool, task_id: &str) -> Result<String, AppError> {
+    sqlx::query_scalar::<_, String>("SELECT status FROM tasks WHERE id = ?")
+        .bind(task_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Task not found".to_string()))
+}
+
+async fn find_item(
+    pool: &SqlitePool,
+    task_id: &str,
+    item_id: &str,
+) -> Result<ChecklistItem, AppError> {
+    sqlx::query_as::<_, ChecklistItem>(&format!(
+        "SELECT {} FROM task_checklist_items WHERE id = ? AND task_id = ?",
+        ITEM_COLUMNS
+    ))
+    .bind(item_id)
+    .bind(task_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Checklist item not found".to_string()))
+}
+
+async fn item_ids(pool: &SqlitePool, task_id: &str) -> Result<Vec<String>, AppError> {
+    let ids = sqlx::query_scalar::<_, String>(
+        "SELECT id FROM task_checklist_items WHERE task_id = ? ORDER BY position ASC, created_at ASC",
+    )
+    .bind(task_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(ids)
+}
+
+/// Numbers the items from zero in the order of `ids`.
+async fn save_order(pool: &SqlitePool, ids: &[String]) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    for (position, id) in ids.iter().enumerate() {
+        sqlx::query("UPDATE task_checklist_items SET position = ? WHERE id = ?")
+            .bind(position as i64)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+fn checked_title(title: &str) -> Result<String, AppError> {
+    let title = title.trim();
+    if title.is_empty() {
+        return Err(AppError::Validation(
+            "Checklist item title is required".to_string(),
+        ));
+    }
+    if title.chars().count() > MAX_TITLE_LENGTH {
+        return Err(AppError::Validation(format!(
+            "Checklist item title must be at most {} characters",
+            MAX_TITLE_LENGTH
+        )));
+    }
+    Ok(title.to_string())
+}
+
+/// `None` for an empty id, which unassigns.
+async fn checked_assignee(pool: &SqlitePool, user_id: &str) -> Result<Option<String>, AppError> {
+    let user_id = user_id.trim();
+    if user_id.is_empty() {
+        return Ok(None);
+    }
+
+    sqlx::query_scalar::<_, String>("SELECT id FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .map(Some)
+        .ok_or_else(|| {
+            AppError::NotFound("Assignee not found".to_string())
+                .with_code("USER_NOT_FOUND")
+                .with_details(serde_json::json!({ "userIds": [user_id] }))
+        })
+}
+
+/// Completes or reopens the task when its checklist says so, returning the
+/// new status. A task blocked by its dependencies is not completed, and the
+/// status only follows changes by users who may manage the task.
+async fn follow_checklist(
+    pool: &SqlitePool,
+    task_id: &str,
+    auth: &AuthUser,
+) -> Result<Option<&'static str>, AppError> {
+    let status = task_status(pool, task_id).await?;
+    let Some(next) = progress(pool, task_id).await?.transition(&status) else {
+        return Ok(None);
+    };
+    if !can_manage_task(pool, auth, task_id).await? {
+        return Ok(None);
+    }
+    if next == "completed"
+        && !task_dependencies::open_prerequisites(pool, task_id)
+            .await?
+            .is_empty()
+    {
+        return Ok(None);
+    }
+
+    set_task_status(pool, task_id, &auth.id, next).await?;
+    Ok(Some(next))
+}
+
+/// The body shared by every change to a checklist.
+async fn changed(
+    pool: &SqlitePool,
+    task_id: &str,
+    item: Option<ChecklistItem>,
+    transitioned_to: Option<&str>,
+) -> Result<serde_json::Value, AppError> {
+    let progress = progress(pool, task_id).await?;
+    let status = task_status(pool, task_id).await?;
+
+    Ok(serde_json::json!({
+        "item": item.as_ref().map(item_json),
+        "checklist": progress.to_json(),
+        "task": {
+            "id": task_id,
+            "status": status,
+            "autoTransitioned": transitioned_to.is_some()
+        }
+    }))
+}
+
+async fn get_checklist(
+    pool: web::Data<SqlitePool>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let task_id = path.into_inner();
+    task_status(pool.get_ref(), &task_id).await?;
+
+    let items = sqlx::query_as::<_, ChecklistItem>(&format!(
+        "SELECT {} FROM task_checklist_items WHERE task_id = ? ORDER BY position ASC, created_at ASC",
+        ITEM_COLUMNS
+    ))
+    .bind(&task_id)
+    .fetch_all(pool.get_ref())
+    .await?;
+    let progress = progress(pool.get_ref(), &task_id).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "taskId": task_id,
+            "items": items.iter().map(item_json).collect::<Vec<_>>(),
+            "checklist": progress.to_json()
+        }
+    })))
+}
+
+async fn add_item(
+    pool: web::Data<SqlitePool>,
+    auth: RequireRole<Member>,
+    path: web::Path<String>,
+    body: web::Json<CreateChecklistItemRequest>,
+) -> Result<HttpResponse, AppError> {
+    let task_id = path.into_inner();
+    ensure_can_manage_task(pool.get_ref(), &auth, &task_id).await?;
+    let title = checked_title(&body.title)?;
+    let assignee_id = match body.assignee_id.as_deref() {
+        Some(id) => checked_assignee(pool.get_ref(), id).await?,
+        None => None,
+    };
+
+    let mut ids = item_ids(pool.get_ref(), &task_id).await?;
+    let item_id = Uuid::new_v4().to_string();
+    sqlx::query(
+        "INSERT INTO task_checklist_items (id, task_id, title, position, assignee_id, created_by) VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&item_id)
+    .bind(&task_id)
+    .bind(&title)
+    .bind(ids.len() as i64)
+    .bind(&assignee_id)
+    .bind(&auth.id)
+    .execute(pool.get_ref())
+    .await?;
+
+    if let Some(position) = body.position {
+        ids.push(item_id.clone());
+        move_to(&mut ids, &item_id, position);
+        save_order(pool.get_ref(), &ids).await?;
+    }
+
+    // A new open item reopens a task that followed its checklist to completion
+    let transitioned_to = follow_checklist(pool.get_ref(), &task_id, &auth).await?;
+    let item = find_item(pool.get_ref(), &task_id, &item_id).await?;
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "success": true,
+        "data": changed(pool.get_ref(), &task_id, Some(item), transitioned_to).await?
+    })))
+}
+
+async fn update_item(
+    pool: web::Data<SqlitePool>,
+    auth: RequireRole<Member>,
+    path: web::Path<(String, String)>,
+    body: web::Json<UpdateChecklistItemRequest>,
+) -> Result<HttpResponse, AppError> {
+    let (task_id, item_id) = path.into_inner();
+    let item = find_item(pool.get_ref(), &task_id, &item_id).await?;
+
+    let edits = body.title.is_some() || body.assignee_id.is_some() || body.position.is_some();
+    if !edits && body.is_completed.is_none() {
+        return Err(AppError::Validation("No fields to update".to_string()));
+    }
+    // Anyone can tick items off, though the task only follows its checklist
+    // for those who can manage it; editing items is like editing the task
+    if edits {
+        ensure_can_manage_task(pool.get_ref(), &auth, &task_id).await?;
+    }
+
+    let title = match body.title.as_deref() {
+        Some(title) => checked_title(title)?,
+        None => item.title.clone(),
+    };
+    let assignee_id = match body.assignee_id.as_deref() {
+        Some(id) => checked_assignee(pool.get_ref(), id).await?,
+        None => item.assignee_id.clone(),
+    };
+
+    sqlx::query(
+        "UPDATE task_checklist_items SET title = ?, assignee_id = ?, updated_at = datetime('now') WHERE id = ?",
+    )
+    .bind(&title)
+    .bind(&assignee_id)
+    .bind(&item_id)
+    .execute(pool.get_ref())
+    .await?;
+
+    match body.is_completed {
+        Some(true) if !item.is_completed => {
+            sqlx::query(
+                "UPDATE task_checklist_items SET is_completed = 1, completed_by = ?, completed_at = datetime('now') WHERE id = ?",
+            )
+            .bind(&auth.id)
+            .bind(&item_id)
+            .execute(pool.get_ref())
+            .await?;
+        }
+        Some(false) if item.is_completed => {
+            sqlx::query(
+                "UPDATE task_checklist_items SET is_completed = 0, completed_by = NULL, completed_at = NULL WHERE id = ?",
+            )
+            .bind(&item_id)
+            .execute(pool.get_ref())
+            .await?;
+        }
+        _ => {}
+    }
+
+    if let Some(position) = body.position {
+        let mut ids = item_ids(pool.get_ref(), &task_id).await?;
+        move_to(&mut ids, &item_id, position);
+        save_order(pool.get_ref(), &ids).await?;
+    }
+
+    let transitioned_to = follow_checklist(pool.get_ref(), &task_id, &auth).await?;
+    let item = find_item(pool.get_ref(), &task_id, &item_id).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": changed(pool.get_ref(), &task_id, Some(item), transitioned_to).await?
+    })))
+}
+
+async fn delete_item(
+    pool: web::Data<SqlitePool>,
+    auth: RequireRole<Member>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, AppError> {
+    let (task_id, item_id) = path.into_inner();
+    find_item(pool.get_ref(), &task_id, &item_id).await?;
+    ensure_can_manage_task(pool.get_ref(), &auth, &task_id).await?;
+
+    sqlx::query("DELETE FROM task_checklist_items WHERE id = ?")
+        .bind(&item_id)
+        .execute(pool.get_ref())
+        .await?;
+    let ids = item_ids(pool.get_ref(), &task_id).await?;
+    save_order(pool.get_ref(), &ids).await?;
+
+    // Removing the last open item may leave nothing but completed ones
+    let transitioned_to = follow_checklist(pool.get_ref(), &task_id, &auth).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": changed(pool.get_ref(), &task_id, None, transitioned_to).await?
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn moving_an_item_is_clamped_to_the_list() {
+        let mut order = ids(&["a", "b", "c", "d"]);
+        move_to(&mut order, "d", 1);
+        assert_eq!(order, ids(&["a", "d", "b", "c"]));
+        move_to(&mut order, "a", 99);
+        assert_eq!(order, ids(&["d", "b", "c", "a"]));
+        move_to(&mut order, "c", -3);
+        assert_eq!(order, ids(&["c", "d", "b", "a"]));
+        move_to(&mut order, "missing", 0);
+        assert_eq!(order, ids(&["c", "d", "b", "a"]));
+    }
+
+    #[test]
+    fn tasks_follow_their_checklist_only_with_auto_complete() {
+        let progress = |completed, auto_complete| Progress {
+            total: 3,
+            completed,
+            auto_complete,
+        };
+
+        assert_eq!(
+            progress(3, true).transition("in-progress"),
+            Some("completed")
+        );
+        assert_eq!(progress(3, true).transition("completed"), None);
+        assert_eq!(
+            progress(2, true).transition("completed"),
+            Some("in-progress")
+        );
+        assert_eq!(progress(2, true).transition("pending"), None);
+        assert_eq!(progress(3, false).transition("pending"), None);
+        assert_eq!(Progress::default().transition("pending"), None);
+        assert_eq!(progress(2, false).percent(), 66);
+        assert_eq!(Progress::default().percent(), 0);
+    }
+
+    fn member(id: &str) -> AuthUser {
+        AuthUser {
+            id: id.to_string(),
+            role: crate::middleware::guards::Role::Member,
+            department: "IT".to_string(),
+            session_id: "test-session".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn only_those_who_manage_the_task_complete_it_by_checklist() {
+        let pool = crate::migrations::test_pool().await;
+        for id in ["owner", "member"] {
+            sqlx::query(
+                "INSERT INTO users (id, email, password_hash, first_name, last_name, department, role) VALUES (?, ?, 'x', 'Test', 'User', 'IT', 'Member')",
+            )
+            .bind(id)
+            .bind(format!("{}@example.com", id))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_by) VALUES ('project', 'Chiller upgrade', 'owner');
+             INSERT INTO project_members (id, project_id, user_id, role) VALUES ('m', 'project', 'owner', 'owner');
+             INSERT INTO tasks (id, title, urgency, status, department, project_id, created_by, deadline, checklist_auto_complete)
+                VALUES ('task', 'Commission chiller', 'medium', 'in-progress', 'IT', 'project', 'owner', '2026-03-01', 1);
+             INSERT INTO task_checklist_items (id, task_id, title, is_completed) VALUES ('item', 'task', 'Flush loop', 1);",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        // The last item was ticked by someone outside the project
+        let transitioned = follow_checklist(&pool, "task", &member("member"))
+            .await
+            .unwrap();
+        assert_eq!(transitioned, None);
+        assert_eq!(task_status(&pool, "task").await.unwrap(), "in-progress");
+
+        let transitioned = follow_checklist(&pool, "task", &member("owner"))
+            .await
+            .unwrap();
+        assert_eq!(transitioned, Some("completed"));
+        assert_eq!(task_status(&pool, "task").await.unwrap(), "completed");
+    }
+}
@@ -0,0 +1,444 @@
+//! Task dependencies: "commission the AHU controller" cannot be completed
+//! before "install the network switch" is.
+//!
+//! A task is *blocked* while any of its prerequisites is still open, and
+//! *blocking* while it is open itself and some open task depends on it.
+//! Edges that would form a cycle are refused, so the graph stays acyclic and
+//! a project's critical path is always defined.
+
+use actix_web::{HttpResponse, web};
+use sqlx::{SqliteExecutor, SqlitePool};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::error::AppError;
+use crate::middleware::guards::{Member, RequireRole};
+use crate::models::tasks::AddTaskDependencyRequest;
+use crate::routes::task_history::record_change;
+use crate::routes::tasks::ensure_can_manage_task;
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered ahead of the `/tasks` scope in `tasks`, which would
+    // otherwise claim these paths
+    cfg.service(
+        web::resource("/tasks/{task_id}/dependencies")
+            .route(web::get().to(get_dependencies))
+            .route(web::post().to(add_dependency)),
+    )
+    .service(
+        web::resource("/tasks/{task_id}/dependencies/{depends_on_id}")
+            .route(web::delete().to(remove_dependency)),
+    );
+}
+
+/// A task on either end of a dependency.
+#[derive(sqlx::FromRow)]
+pub struct LinkedTask {
+    pub id: String,
+    pub title: String,
+    pub status: String,
+    pub urgency: String,
+    pub deadline: String,
+    pub project_id: Option<String>,
+}
+
+impl LinkedTask {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "title": self.title,
+            "status": self.status,
+            "urgency": self.urgency,
+            "deadline": self.deadline,
+            "projectId": self.project_id,
+            "isOpen": self.status != "completed"
+        })
+    }
+}
+
+/// Whether a task is blocked by an open prerequisite, and whether it is
+/// itself open and holding up an open dependent.
+pub async fn flags(pool: &SqlitePool, task_id: &str) -> Result<(bool, bool), AppError> {
+    let flags = sqlx::query_as::<_, (bool, bool)>(
+        r#"
+        SELECT
+            EXISTS (
+                SELECT 1 FROM task_dependencies d
+                JOIN tasks p ON p.id = d.depends_on_id
+                WHERE d.task_id = t.id AND p.status != 'completed'
+            ),
+            t.status != 'completed' AND EXISTS (
+                SELECT 1 FROM task_dependencies d
+                JOIN tasks n ON n.id = d.task_id
+                WHERE d.depends_on_id = t.id AND n.status != 'completed'
+            )
+        FROM tasks t
+        WHERE t.id = ?
+        "#,
+    )
+    .bind(task_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(flags.unwrap_or((false, false)))
+}
+
+/// Prerequisites of `task_id` that are not completed yet.
+pub async fn open_prerequisites(
+    executor: impl SqliteExecutor<'_>,
+    task_id: &str,
+) -> Result<Vec<LinkedTask>, AppError> {
+    let tasks = sqlx::query_as::<_, LinkedTask>(
+        r#"
+        SELECT t.id, t.title, t.status, t.urgency, t.deadline, t.project_id
+        FROM task_dependencies d
+        JOIN tasks t ON t.id = d.depends_on_id
+        WHERE d.task_id = ? AND t.status != 'completed'
+        ORDER BY t.deadline ASC
+        "#,
+    )
+    .bind(task_id)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(tasks)
+}
+
+/// Refuses to complete a task while it has open prerequisites.
+pub async fn ensure_not_blocked(
+    executor: impl SqliteExecutor<'_>,
+    task_id: &str,
+) -> Result<(), AppError> {
+    let open = open_prerequisites(executor, task_id).await?;
+    if open.is_empty() {
+        return Ok(());
+    }
+
+    Err(AppError::Conflict(format!(
+        "Task is blocked by {} open prerequisite(s)",
+        open.len()
+    ))
+    .with_code("TASK_BLOCKED")
+    .with_details(serde_json::json!({
+        "blockedBy": open.iter().map(LinkedTask::to_json).collect::<Vec<_>>()
+    })))
+}
+
+/// If `task_id` depending on `depends_on_id` would close a cycle, the cycle
+/// as a chain of task ids that starts and ends with `task_id`. `edges` are
+/// the existing `(task, prerequisite)` pairs.
+pub fn find_cycle(
+    edges: &[(String, String)],
+    task_id: &str,
+    depends_on_id: &str,
+) -> Option<Vec<String>> {
+    let mut prerequisites: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (task, prerequisite) in edges {
+        prerequisites
+            .entry(task.as_str())
+            .or_default()
+            .push(prerequisite.as_str());
+    }
+
+    // Breadth-first from the new prerequisite, remembering how each task was
+    // reached so the cycle can be reported
+    let mut reached_from: HashMap<&str, &str> = HashMap::new();
+    let mut seen: HashSet<&str> = HashSet::from([depends_on_id]);
+    let mut queue = VecDeque::from([depends_on_id]);
+    while let Some(current) = queue.pop_front() {
+        if current == task_id {
+            let mut chain = vec![task_id.to_string()];
+            let mut step = current;
+            while step != depends_on_id {
+                step = reached_from[step];
+                chain.push(step.to_string());
+            }
+            chain[1..].reverse();
+            chain.push(task_id.to_string());
+            return Some(chain);
+        }
+        for &next in prerequisites.get(current).into_iter().flatten() {
+            if seen.insert(next) {
+                reached_from.insert(next, current);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    None
+}
+
+/// The longest chain of `task_ids`, from the first prerequisite to the last
+/// dependent. Tasks carry no duration, so each one counts as a single step;
+/// ties go to the task listed first. Edges to tasks outside `task_ids` are
+/// ignored.
+pub fn critical_path(task_ids: &[String], edges: &[(String, String)]) -> Vec<String> {
+    let index: HashMap<&str, usize> = task_ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| (id.as_str(), i))
+        .collect();
+
+    let mut dependents = vec![Vec::new(); task_ids.len()];
+    let mut waiting = vec![0usize; task_ids.len()];
+    for (task, prerequisite) in edges {
+        if let (Some(&task), Some(&prerequisite)) =
+            (index.get(task.as_str()), index.get(prerequisite.as_str()))
+        {
+            dependents[prerequisite].push(task);
+            waiting[task] += 1;
+        }
+    }
+
+    // Walk the tasks in dependency order, extending the longest chain that
+    // ends at each one
+    let mut length = vec![1usize; task_ids.len()];
+    let mut previous: Vec<Option<usize>> = vec![None; task_ids.len()];
+    let mut ready: VecDeque<usize> = (0..task_ids.len()).filter(|&i| waiting[i] == 0).collect();
+    while let Some(prerequisite) = ready.pop_front() {
+        for &task in &dependents[prerequisite] {
+            if length[prerequisite] + 1 > length[task] {
+                length[task] = length[prerequisite] + 1;
+                previous[task] = Some(prerequisite);
+            }
+            waiting[task] -= 1;
+            if waiting[task] == 0 {
+                ready.push_back(task);
+            }
+        }
+    }
+
+    let Some(mut last) = (0..task_ids.len()).max_by_key(|&i| (length[i], std::cmp::Reverse(i)))
+    else {
+        return Vec::new();
+    };
+    let mut path = vec![task_ids[last].clone()];
+    while let Some(prerequisite) = previous[last] {
+        path.push(task_ids[prerequisite].clone());
+        last = prerequisite;
+    }
+    path.reverse();
+    path
+}
+
+async fn find_task(pool: &SqlitePool, task_id: &str) -> Result<LinkedTask, AppError> {
+    sqlx::query_as::<_, LinkedTask>(
+        "SELECT id, title, status, urgency, deadline, project_id FROM tasks WHERE id = ?",
+    )
+    .bind(task_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Task not found".to_string()))
+}
+
+async fn get_dependencies(
+    pool: web::Data<SqlitePool>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let task_id = path.into_inner();
+    find_task(pool.get_ref(), &task_id).await?;
+
+    let depends_on = sqlx::query_as::<_, LinkedTask>(
+        r#"
+        SELECT t.id, t.title, t.status, t.urgency, t.deadline, t.project_id
+        FROM task_dependencies d
+        JOIN tasks t ON t.id = d.depends_on_id
+        WHERE d.task_id = ?
+        ORDER BY t.deadline ASC
+        "#,
+    )
+    .bind(&task_id)
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    let dependents = sqlx::query_as::<_, LinkedTask>(
+        r#"
+        SELECT t.id, t.title, t.status, t.urgency, t.deadline, t.project_id
+        FROM task_dependencies d
+        JOIN tasks t ON t.id = d.task_id
+        WHERE d.depends_on_id = ?
+        ORDER BY t.deadline ASC
+        "#,
+    )
+    .bind(&task_id)
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    let (is_blocked, is_blocking) = flags(pool.get_ref(), &task_id).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "taskId": task_id,
+            "isBlocked": is_blocked,
+            "isBlocking": is_blocking,
+            "dependsOn": depends_on.iter().map(LinkedTask::to_json).collect::<Vec<_>>(),
+            "dependents": dependents.iter().map(LinkedTask::to_json).collect::<Vec<_>>()
+        }
+    })))
+}
+
+async fn add_dependency(
+    pool: web::Data<SqlitePool>,
+    auth: RequireRole<Member>,
+    path: web::Path<String>,
+    body: web::Json<AddTaskDependencyRequest>,
+) -> Result<HttpResponse, AppError> {
+    let task_id = path.into_inner();
+    let depends_on_id = body.depends_on_id.trim();
+    ensure_can_manage_task(pool.get_ref(), &auth, &task_id).await?;
+
+    if depends_on_id == task_id {
+        return Err(AppError::Validation(
+            "A task cannot depend on itself".to_string(),
+        ));
+    }
+    let prerequisite = find_task(pool.get_ref(), depends_on_id)
+        .await
+        .map_err(|_| {
+            AppError::NotFound("Prerequisite task not found".to_string())
+                .with_code("TASK_NOT_FOUND")
+                .with_details(serde_json::json!({ "dependsOnId": depends_on_id }))
+        })?;
+
+    // Read the edges on the transaction so the cycle check holds at insert
+    let mut tx = pool.begin().await?;
+    let edges = sqlx::query_as::<_, (String, String)>(
+        "SELECT task_id, depends_on_id FROM task_dependencies",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    if edges
+        .iter()
+        .any(|(task, prerequisite)| *task == task_id && prerequisite == depends_on_id)
+    {
+        return Err(
+            AppError::Conflict("Task already depends on this task".to_string())
+                .with_code("DEPENDENCY_EXISTS"),
+        );
+    }
+    if let Some(cycle) = find_cycle(&edges, &task_id, depends_on_id) {
+        return Err(
+            AppError::Conflict("This dependency would create a cycle".to_string())
+                .with_code("DEPENDENCY_CYCLE")
+                .with_details(serde_json::json!({ "cycle": cycle })),
+        );
+    }
+
+    sqlx::query(
+        "INSERT INTO task_dependencies (task_id, depends_on_id, created_by) VALUES (?, ?, ?)",
+    )
+    .bind(&task_id)
+    .bind(depends_on_id)
+    .bind(&auth.id)
+    .execute(&mut *tx)
+    .await?;
+    record_change(
+        &mut *tx,
+        &task_id,
+        &auth.id,
+        "dependencies",
+        None,
+        Some(depends_on_id),
+    )
+    .await?;
+    tx.commit().await?;
+
+    let (is_blocked, _) = flags(pool.get_ref(), &task_id).await?;
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "taskId": task_id,
+            "dependsOn": prerequisite.to_json(),
+            "isBlocked": is_blocked
+        }
+    })))
+}
+
+async fn remove_dependency(
+    pool: web::Data<SqlitePool>,
+    auth: RequireRole<Member>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, AppError> {
+    let (task_id, depends_on_id) = path.into_inner();
+    ensure_can_manage_task(pool.get_ref(), &auth, &task_id).await?;
+
+    let mut tx = pool.begin().await?;
+    let rows = sqlx::query("DELETE FROM task_dependencies WHERE task_id = ? AND depends_on_id = ?")
+        .bind(&task_id)
+        .bind(&depends_on_id)
+        .execute(&mut *tx)
+        .await?;
+
+    if rows.rows_affected() == 0 {
+        return Err(AppError::NotFound("Dependency not found".to_string()));
+    }
+    record_change(
+        &mut *tx,
+        &task_id,
+        &auth.id,
+        "dependencies",
+        Some(&depends_on_id),
+        None,
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Dependency removed successfully"
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edges(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(task, prerequisite)| (task.to_string(), prerequisite.to_string()))
+            .collect()
+    }
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn cycles_are_reported_from_the_new_dependent() {
+        // commission -> switch -> cabling
+        let existing = edges(&[("commission", "switch"), ("switch", "cabling")]);
+
+        assert_eq!(find_cycle(&existing, "commission", "cabling"), None);
+        assert_eq!(
+            find_cycle(&existing, "cabling", "commission"),
+            Some(ids(&["cabling", "commission", "switch", "cabling"]))
+        );
+        assert_eq!(
+            find_cycle(&existing, "switch", "commission"),
+            Some(ids(&["switch", "commission", "switch"]))
+        );
+    }
+
+    #[test]
+    fn critical_path_follows_the_longest_chain() {
+        let tasks = ids(&["survey", "cabling", "switch", "commission", "labels"]);
+        let graph = edges(&[
+            ("cabling", "survey"),
+            ("switch", "cabling"),
+            ("commission", "switch"),
+            ("commission", "survey"),
+            ("labels", "survey"),
+            // Prerequisites outside the list are ignored
+            ("survey", "elsewhere"),
+        ]);
+
+        assert_eq!(
+            critical_path(&tasks, &graph),
+            ids(&["survey", "cabling", "switch", "commission"])
+        );
+        assert_eq!(critical_path(&ids(&["a", "b"]), &[]), ids(&["a"]));
+        assert!(critical_path(&[], &graph).is_empty());
+    }
+}
@@ -0,0 +1,565 @@
+//! Task history: one row per changed field, and the task as it was at any
+//! point in time.
+//!
+//! Field changes are `updated` rows (`status_changed` for the status) with
+//! the column in `field_changed` and its values before and after. Flags are
+//! recorded as `true`/`false`. Assignees and dependencies are sets: adding
+//! one records the user or task in `new_value`, removing one in `old_value`.
+//!
+//! A past state is rebuilt by undoing, newest first, the changes made since
+//! then. Changes recorded before field-level history existed carry no field
+//! and cannot be undone, so states from before them may be incomplete.
+
+use actix_web::{HttpResponse, web};
+use chrono::{DateTime, NaiveDateTime};
+use sqlx::{Row, SqliteConnection, SqliteExecutor, SqlitePool};
+use std::collections::{BTreeSet, HashMap};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::middleware::guards::AuthUser;
+use crate::timezone;
+
+/// Columns of `tasks` whose changes are recorded, with their API names.
+const FIELDS: [(&str, &str); 10] = [
+    ("title", "title"),
+    ("description", "description"),
+    ("urgency", "urgency"),
+    ("status", "status"),
+    ("department", "department"),
+    ("project_id", "projectId"),
+    ("assignee_id", "assigneeId"),
+    ("deadline", "deadline"),
+    ("is_completed", "isCompleted"),
+    ("checklist_auto_complete", "checklistAutoComplete"),
+];
+
+const FLAGS: [&str; 2] = ["is_completed", "checklist_auto_complete"];
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GetTaskHistoryQuery {
+    as_of: Option<String>,
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered ahead of the `/tasks` scope in `tasks`, like dependencies
+    cfg.service(web::resource("/tasks/{task_id}/history").route(web::get().to(get_task_history)));
+}
+
+/// The recorded fields of a task, its assignees and its prerequisites.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskState {
+    pub id: String,
+    pub created_at: Option<String>,
+    values: HashMap<&'static str, Option<String>>,
+    assignee_ids: BTreeSet<String>,
+    depends_on_ids: BTreeSet<String>,
+}
+
+impl TaskState {
+    /// The current state of a task.
+    pub async fn load(conn: &mut SqliteConnection, task_id: &str) -> Result<TaskState, AppError> {
+        let columns = FIELDS
+            .iter()
+            .map(|(column, _)| {
+                if FLAGS.contains(column) {
+                    format!(
+                        "CASE WHEN COALESCE({}, 0) THEN 'true' ELSE 'false' END",
+                        column
+                    )
+                } else {
+                    format!("CAST({} AS TEXT)", column)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let row = sqlx::query(&format!(
+            "SELECT {}, created_at FROM tasks WHERE id = ?",
+            columns
+        ))
+        .bind(task_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Task not found".to_string()))?;
+
+        let mut values = HashMap::new();
+        for (i, (column, _)) in FIELDS.iter().enumerate() {
+            values.insert(*column, row.try_get::<Option<String>, _>(i)?);
+        }
+        let assignee_ids =
+            sqlx::query_scalar("SELECT user_id FROM task_assignees WHERE task_id = ?")
+                .bind(task_id)
+                .fetch_all(&mut *conn)
+                .await?;
+        let depends_on_ids =
+            sqlx::query_scalar("SELECT depends_on_id FROM task_dependencies WHERE task_id = ?")
+                .bind(task_id)
+                .fetch_all(&mut *conn)
+                .await?;
+
+        Ok(TaskState {
+            id: task_id.to_string(),
+            created_at: row.try_get(FIELDS.len())?,
+            values,
+            assignee_ids: assignee_ids.into_iter().collect(),
+            depends_on_ids: depends_on_ids.into_iter().collect(),
+        })
+    }
+
+    pub fn get(&self, column: &str) -> Option<&str> {
+        self.values.get(column).and_then(|value| value.as_deref())
+    }
+
+    /// Reverts one recorded change. Fields that are not tracked are ignored.
+    pub fn undo(&mut self, field: &str, old_value: Option<&str>, new_value: Option<&str>) {
+        let set = match field {
+            "assignees" => &mut self.assignee_ids,
+            "dependencies" => &mut self.depends_on_ids,
+            _ => {
+                if let Some((column, _)) = FIELDS.iter().find(|(column, _)| *column == field) {
+                    self.values.insert(column, old_value.map(str::to_string));
+                }
+                return;
+            }
+        };
+        if let Some(added) = new_value {
+            set.remove(added);
+        }
+        if let Some(removed) = old_value {
+            set.insert(removed.to_string());
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut task = serde_json::Map::new();
+        task.insert("id".to_string(), self.id.clone().into());
+        for (column, name) in FIELDS {
+            let value = match self.get(column) {
+                Some(flag) if FLAGS.contains(&column) => (flag == "true").into(),
+                Some(value) => value.into(),
+                None => serde_json::Value::Null,
+            };
+            task.insert(name.to_string(), value);
+        }
+        task.insert(
+            "assigneeIds".to_string(),
+            self.assignee_ids.iter().cloned().collect(),
+        );
+        task.insert(
+            "dependsOnIds".to_string(),
+            self.depends_on_ids.iter().cloned().collect(),
+        );
+        task.insert("createdAt".to_string(), self.created_at.clone().into());
+        serde_json::Value::Object(task)
+    }
+}
+
+/// `task_history.source` of changes made by urgency escalation
+pub const ESCALATION_SOURCE: &str = "escalation";
+
+/// Records one changed field, or one assignee or dependency added or removed.
+pub async fn record_change(
+    executor: impl SqliteExecutor<'_>,
+    task_id: &str,
+    user_id: &str,
+    field: &str,
+    old_value: Option<&str>,
+    new_value: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    insert_change(
+        executor,
+        task_id,
+        Some(user_id),
+        "user",
+        field,
+        old_value,
+        new_value,
+    )
+    .await
+}
+
+/// Records a change made by the system rather than a user, with no
+/// `user_id` and `source` saying what made it.
+pub async fn record_system_change(
+    executor: impl SqliteExecutor<'_>,
+    task_id: &str,
+    source: &str,
+    field: &str,
+    old_value: Option<&str>,
+    new_value: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    insert_change(executor, task_id, None, source, field, old_value, new_value).await
+}
+
+async fn insert_change(
+    executor: impl SqliteExecutor<'_>,
+    task_id: &str,
+    user_id: Option<&str>,
+    source: &str,
+    field: &str,
+    old_value: Option<&str>,
+    new_value: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO task_history (id, task_id, user_id, source, action, field_changed, old_value, new_value) VALUES (?, ?, ?, ?, 'updated', ?, ?, ?)",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(task_id)
+    .bind(user_id)
+    .bind(source)
+    .bind(field)
+    .bind(old_value)
+    .bind(new_value)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Records each of `changes` whose value differs from `before`, and returns
+/// how many did.
+pub async fn record_changes(
+    conn: &mut SqliteConnection,
+    before: &TaskState,
+    user_id: &str,
+    changes: &[(&str, Option<String>)],
+) -> Result<usize, AppError> {
+    let mut recorded = 0;
+    for (field, value) in changes {
+        let old_value = before.get(field);
+        if old_value == value.as_deref() {
+            continue;
+        }
+        record_change(
+            &mut *conn,
+            &before.id,
+            user_id,
+            field,
+            old_value,
+            value.as_deref(),
+        )
+        .await?;
+        recorded += 1;
+    }
+    Ok(recorded)
+}
+
+/// The task as it was at `as_of` (UTC), or as it is now.
+pub async fn state_as_of(
+    conn: &mut SqliteConnection,
+    task_id: &str,
+    as_of: Option<NaiveDateTime>,
+) -> Result<TaskState, AppError> {
+    let mut state = TaskState::load(conn, task_id).await?;
+    let Some(as_of) = as_of else {
+        return Ok(state);
+    };
+    let as_of = timezone::format_sql_datetime(as_of);
+    if state
+        .created_at
+        .as_deref()
+        .is_some_and(|created| *created > *as_of)
+    {
+        return Err(
+            AppError::NotFound(format!("The task did not exist yet at {}", as_of))
+                .with_code("TASK_NOT_CREATED"),
+        );
+    }
+
+    let later = sqlx::query_as::<_, (String, Option<String>, Option<String>)>(
+        "SELECT field_changed, old_value, new_value FROM task_history
+         WHERE task_id = ? AND created_at > ? AND field_changed IS NOT NULL
+         ORDER BY created_at DESC, rowid DESC",
+    )
+    .bind(task_id)
+    .bind(&as_of)
+    .fetch_all(&mut *conn)
+    .await?;
+    for (field, old_value, new_value) in &later {
+        state.undo(field, old_value.as_deref(), new_value.as_deref());
+    }
+    Ok(state)
+}
+
+/// An `asOf` parameter: RFC 3339, or `YYYY-MM-DD HH:MM:SS` in UTC.
+fn parse_as_of(value: &str) -> Result<NaiveDateTime, AppError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|at| at.naive_utc())
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S"))
+        .map_err(|_| {
+            AppError::Validation(format!(
+                "asOf must be an RFC 3339 or YYYY-MM-DD HH:MM:SS timestamp, got '{}'",
+                value
+            ))
+        })
+}
+
+async fn get_task_history(
+    pool: web::Data<SqlitePool>,
+    _auth: AuthUser,
+    path: web::Path<String>,
+    query: web::Query<GetTaskHistoryQuery>,
+) -> Result<HttpResponse, AppError> {
+    let task_id = path.into_inner();
+    let as_of = query.as_of.as_deref().map(parse_as_of).transpose()?;
+    let mut conn = pool.acquire().await?;
+    let task = state_as_of(&mut conn, &task_id, as_of).await?;
+
+    let history = sqlx::query_as::<
+        _,
+        (
+            String,
+            String,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            String,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+        ),
+    >(
+        r#"
+        SELECT th.id, th.action, th.field_changed, th.old_value, th.new_value, th.user_id,
+               th.source, u.first_name, u.last_name, u.email, th.created_at
+        FROM task_history th
+        LEFT JOIN users u ON th.user_id = u.id
+        WHERE th.task_id = ? AND (? IS NULL OR th.created_at <= ?)
+        ORDER BY th.created_at DESC, th.rowid DESC
+        "#,
+    )
+    .bind(&task_id)
+    .bind(as_of.map(timezone::format_sql_datetime))
+    .bind(as_of.map(timezone::format_sql_datetime))
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let history_json: Vec<serde_json::Value> = history
+        .iter()
+        .map(
+            |(
+                id,
+                action,
+                field_changed,
+                old_value,
+                new_value,
+                user_id,
+                source,
+                first_name,
+                last_name,
+                email,
+                created_at,
+            )| {
+                let name = match (first_name, last_name) {
+                    (Some(first), Some(last)) => Some(format!("{} {}", first, last)),
+                    _ => None,
+                };
+                // System changes have no user
+                let changed_by = user_id.as_ref().map(|user_id| {
+                    serde_json::json!({
+                        "id": user_id,
+                        "firstName": first_name,
+                        "lastName": last_name,
+                        "email": email,
+                        "name": name
+                    })
+                });
+                serde_json::json!({
+                    "id": id,
+                    "action": action,
+                    "fieldChanged": field_changed,
+                    "oldValue": old_value,
+                    "newValue": new_value,
+                    "changedBy": changed_by,
+                    "source": source,
+                    "createdAt": created_at
+                })
+            },
+        )
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "asOf": as_of.map(timezone::format_sql_datetime),
+            "task": task.to_json(),
+            "history": history_json
+        }
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn test_pool() -> SqlitePool {
+        let pool = crate::migrations::test_pool().await;
+        for id in ["lead", "technician"] {
+            sqlx::query(
+                "INSERT INTO users (id, email, password_hash, first_name, last_name, department, role) VALUES (?, ?, 'x', 'Test', 'User', 'IT', 'Member')",
+            )
+            .bind(id)
+            .bind(format!("{}@company.com", id))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        pool
+    }
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    /// Applies a change to the task now and records it at `created_at`.
+    async fn change(
+        conn: &mut SqliteConnection,
+        field: &str,
+        old_value: Option<&str>,
+        new_value: Option<&str>,
+        created_at: &str,
+    ) {
+        match field {
+            "assignees" => {
+                sqlx::query(
+                    "INSERT INTO task_assignees (id, task_id, user_id) VALUES ('a', 'task', ?)",
+                )
+                .bind(new_value)
+                .execute(&mut *conn)
+                .await
+                .unwrap();
+            }
+            _ => {
+                sqlx::query(&format!("UPDATE tasks SET {} = ? WHERE id = 'task'", field))
+                    .bind(new_value)
+                    .execute(&mut *conn)
+                    .await
+                    .unwrap();
+            }
+        }
+        record_change(&mut *conn, "task", "lead", field, old_value, new_value)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE task_history SET created_at = ? WHERE rowid = last_insert_rowid()")
+            .bind(created_at)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn rebuilds_the_task_as_it_was() {
+        let pool = test_pool().await;
+        let mut conn = pool.acquire().await.unwrap();
+        sqlx::query(
+            "INSERT INTO tasks (id, title, urgency, department, created_by, deadline, created_at) VALUES ('task', 'Patch firewall', 'low', 'IT', 'lead', '2026-03-10', '2026-03-01 09:00:00')",
+        )
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+        change(
+            &mut conn,
+            "urgency",
+            Some("low"),
+            Some("high"),
+            "2026-03-02 09:00:00",
+        )
+        .await;
+        change(
+            &mut conn,
+            "assignees",
+            None,
+            Some("technician"),
+            "2026-03-03 09:00:00",
+        )
+        .await;
+        change(
+            &mut conn,
+            "title",
+            Some("Patch firewall"),
+            Some("Patch edge firewall"),
+            "2026-03-04 09:00:00",
+        )
+        .await;
+
+        let now = state_as_of(&mut conn, "task", None).await.unwrap();
+        assert_eq!(now.get("title"), Some("Patch edge firewall"));
+
+        let before_title = state_as_of(&mut conn, "task", Some(at("2026-03-03 12:00:00")))
+            .await
+            .unwrap();
+        assert_eq!(before_title.get("title"), Some("Patch firewall"));
+        assert_eq!(before_title.get("urgency"), Some("high"));
+        assert_eq!(
+            before_title.to_json()["assigneeIds"],
+            serde_json::json!(["technician"])
+        );
+
+        let at_creation = state_as_of(&mut conn, "task", Some(at("2026-03-01 09:00:00")))
+            .await
+            .unwrap();
+        assert_eq!(at_creation.get("urgency"), Some("low"));
+        assert_eq!(at_creation.to_json()["assigneeIds"], serde_json::json!([]));
+        assert_eq!(
+            at_creation.to_json()["isCompleted"],
+            serde_json::json!(false)
+        );
+
+        let err = state_as_of(&mut conn, "task", Some(at("2026-02-28 09:00:00")))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), "TASK_NOT_CREATED");
+    }
+
+    #[tokio::test]
+    async fn records_only_fields_that_changed() {
+        let pool = test_pool().await;
+        let mut conn = pool.acquire().await.unwrap();
+        sqlx::query(
+            "INSERT INTO tasks (id, title, urgency, department, created_by, deadline) VALUES ('task', 'Patch firewall', 'low', 'IT', 'lead', '2026-03-10')",
+        )
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+        let before = TaskState::load(&mut conn, "task").await.unwrap();
+
+        let recorded = record_changes(
+            &mut conn,
+            &before,
+            "lead",
+            &[
+                ("title", Some("Patch firewall".to_string())),
+                ("urgency", Some("urgent".to_string())),
+                ("project_id", None),
+                ("is_completed", Some("true".to_string())),
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(recorded, 2);
+
+        let rows: Vec<(String, Option<String>, Option<String>)> = sqlx::query_as(
+            "SELECT field_changed, old_value, new_value FROM task_history ORDER BY field_changed",
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .unwrap();
+        assert_eq!(
+            rows,
+            [
+                (
+                    "is_completed".to_string(),
+                    Some("false".to_string()),
+                    Some("true".to_string())
+                ),
+                (
+                    "urgency".to_string(),
+                    Some("low".to_string()),
+                    Some("urgent".to_string())
+                ),
+            ]
+        );
+    }
+}
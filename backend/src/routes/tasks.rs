@@ -1,10 +1,19 @@
-use actix_web::{HttpMessage, HttpRequest, HttpResponse, web};
-use sqlx::SqlitePool;
+use actix_web::{HttpResponse, web};
+use chrono::NaiveDate;
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
 use uuid::Uuid;
 
+use crate::clock::SystemClock;
+use crate::error::AppError;
+use crate::escalation;
+use crate::filters::{Filters, push_limit};
+use crate::middleware::guards::{AuthUser, Member, RequireRole};
 use crate::models::tasks::{
     CreateTaskRequest, GetTasksQuery, UpdateTaskRequest, UpdateTaskStatusRequest,
 };
+use crate::routes::task_history::{self, TaskState};
+use crate::routes::{task_checklists, task_dependencies};
+use crate::timezone::{self, TimeZone};
 
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -16,7 +25,6 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
             .route("/{task_id}", web::put().to(update_task))
             .route("/{task_id}", web::delete().to(delete_task))
             .route("/{task_id}/status", web::patch().to(update_task_status))
-            .route("/{task_id}/history", web::get().to(get_task_history))
             .route("/{task_id}/assignees", web::get().to(get_task_assignees))
             .route("/{task_id}/assignees", web::post().to(add_task_assignees))
             .route(
@@ -26,56 +34,46 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     );
 }
 
-async fn get_tasks(pool: web::Data<SqlitePool>, query: web::Query<GetTasksQuery>) -> HttpResponse {
-    let mut base_where = String::from("WHERE 1=1");
-
-    if let Some(ref status) = query.status {
-        base_where.push_str(&format!(" AND t.status = '{}'", status));
-    }
-    if let Some(ref urgency) = query.urgency {
-        base_where.push_str(&format!(" AND t.urgency = '{}'", urgency));
-    }
-    if let Some(ref department) = query.department {
-        base_where.push_str(&format!(" AND t.department = '{}'", department));
-    }
-    if let Some(ref project_id) = query.project_id {
-        base_where.push_str(&format!(" AND t.project_id = '{}'", project_id));
-    }
-    if let Some(ref assignee_id) = query.assignee_id {
-        base_where.push_str(&format!(" AND t.assignee_id = '{}'", assignee_id));
-    }
-    if let Some(is_completed) = query.is_completed {
-        base_where.push_str(&format!(
-            " AND t.is_completed = {}",
-            if is_completed { 1 } else { 0 }
-        ));
-    }
+async fn get_tasks(
+    pool: web::Data<SqlitePool>,
+    query: web::Query<GetTasksQuery>,
+) -> Result<HttpResponse, AppError> {
+    // Urgency is filtered and shown, so bring it up to date first
+    escalation::escalate_tasks(pool.get_ref(), &SystemClock).await?;
+
+    let mut filters = Filters::new();
+    filters
+        .eq_opt("t.status", query.status.as_ref())
+        .eq_opt("t.urgency", query.urgency.as_ref())
+        .eq_opt("t.department", query.department.as_ref())
+        .eq_opt("t.project_id", query.project_id.as_ref())
+        .eq_opt("t.assignee_id", query.assignee_id.as_ref())
+        .eq_opt("t.is_completed", query.is_completed)
+        .eq_opt("t.recurring_task_id", query.recurring_task_id.as_ref());
 
     // Count query for total
-    let count_sql = format!("SELECT COUNT(*) as count FROM tasks t {}", base_where);
+    let mut count_query = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM tasks t");
+    filters.push_where(&mut count_query);
 
-    let total: i32 = match sqlx::query_as::<_, (i32,)>(&count_sql)
+    let total: i32 = count_query
+        .build_query_scalar()
         .fetch_one(pool.get_ref())
         .await
-    {
-        Ok((count,)) => count,
-        Err(_) => 0,
-    };
+        .unwrap_or(0);
 
-    let mut sql = format!(
-        "SELECT t.id, t.title, t.description, t.urgency, t.status, t.department, t.project_id, t.assignee_id, t.created_by, t.deadline, t.completed_at, t.created_at, t.updated_at, t.is_completed, p.name as project_name FROM tasks t LEFT JOIN projects p ON t.project_id = p.id {}",
-        base_where
+    let mut sql = QueryBuilder::<Sqlite>::new(
+        "SELECT t.id, t.title, t.description, t.urgency, t.status, t.department, t.project_id, t.assignee_id, t.created_by, t.deadline, t.completed_at, t.created_at, t.updated_at, t.is_completed, p.name as project_name, t.recurring_task_id FROM tasks t LEFT JOIN projects p ON t.project_id = p.id",
     );
+    filters.push_where(&mut sql);
 
-    sql.push_str(" ORDER BY t.deadline ASC");
+    sql.push(" ORDER BY t.deadline ASC");
 
     let limit = query.limit.unwrap_or(50);
     let offset = query.offset.unwrap_or(0);
-    sql.push_str(&format!(" LIMIT {} OFFSET {}", limit, offset));
+    push_limit(&mut sql, limit.into(), offset.into());
 
-    let result = sqlx::query_as::<
-        _,
-        (
+    let tasks = sql
+        .build_query_as::<(
             String,
             String,
             Option<String>,
@@ -91,190 +89,166 @@ async fn get_tasks(pool: web::Data<SqlitePool>, query: web::Query<GetTasksQuery>
             Option<String>,
             Option<bool>,
             Option<String>,
-        ),
-    >(&sql)
-    .fetch_all(pool.get_ref())
-    .await;
+            Option<String>,
+        )>()
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    let mut tasks_json: Vec<serde_json::Value> = Vec::new();
+
+    for (
+        id,
+        title,
+        description,
+        urgency,
+        status,
+        department,
+        project_id,
+        assignee_id,
+        _created_by,
+        deadline,
+        completed_at,
+        created_at,
+        updated_at,
+        is_completed,
+        project_name,
+        recurring_task_id,
+    ) in tasks
+    {
+        // Fetch assignees for this task
+        let assignees_result = sqlx::query_as::<_, (String, String, String, String)>(
+            r#"
+            SELECT u.id, u.first_name, u.last_name, u.email
+            FROM task_assignees ta
+            JOIN users u ON ta.user_id = u.id
+            WHERE ta.task_id = ?
+            "#,
+        )
+        .bind(&id)
+        .fetch_all(pool.get_ref())
+        .await;
+
+        let assignees: Vec<serde_json::Value> = match assignees_result {
+            Ok(a) => a
+                .iter()
+                .map(|(uid, first, last, email)| {
+                    serde_json::json!({
+                        "id": uid,
+                        "firstName": first,
+                        "lastName": last,
+                        "email": email,
+                        "name": format!("{} {}", first, last)
+                    })
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        let (is_blocked, is_blocking) = task_dependencies::flags(pool.get_ref(), &id).await?;
+        let checklist = task_checklists::progress(pool.get_ref(), &id).await?;
+
+        tasks_json.push(serde_json::json!({
+            "id": id,
+            "title": title,
+            "description": description,
+            "urgency": urgency,
+            "status": status,
+            "department": department,
+            "projectId": project_id,
+            "projectName": project_name,
+            "assigneeId": assignee_id,
+            "assignees": assignees,
+            "deadline": deadline,
+            "completedAt": completed_at,
+            "createdAt": created_at,
+            "updatedAt": updated_at,
+            "isCompleted": is_completed.unwrap_or(false),
+            "isBlocked": is_blocked,
+            "isBlocking": is_blocking,
+            "checklist": checklist.to_json(),
+            "recurringTaskId": recurring_task_id
+        }));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "tasks": tasks_json,
+            "pagination": {
+                "total": total,
+                "limit": limit,
+                "offset": offset
+            }
+        }
+    })))
+}
 
-    match result {
-        Ok(tasks) => {
-            let mut tasks_json: Vec<serde_json::Value> = Vec::new();
+async fn get_urgent_tasks(pool: web::Data<SqlitePool>) -> Result<HttpResponse, AppError> {
+    escalation::escalate_tasks(pool.get_ref(), &SystemClock).await?;
 
-            for (
+    let tasks = sqlx::query_as::<_, (String, String, Option<String>, String, String, String, Option<String>, Option<String>, String, String, Option<String>, Option<String>, Option<String>, Option<bool>)>(
+        "SELECT id, title, description, urgency, status, department, project_id, assignee_id, created_by, deadline, completed_at, created_at, updated_at, is_completed FROM tasks WHERE urgency = 'urgent' AND status != 'completed' ORDER BY deadline ASC"
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    let tasks_json: Vec<serde_json::Value> = tasks
+        .iter()
+        .map(
+            |(
                 id,
                 title,
-                description,
+                _description,
                 urgency,
                 status,
-                department,
+                _department,
                 project_id,
-                assignee_id,
+                _assignee_id,
                 _created_by,
                 deadline,
-                completed_at,
-                created_at,
-                updated_at,
+                _completed_at,
+                _created_at,
+                _updated_at,
                 is_completed,
-                project_name,
-            ) in tasks
-            {
-                // Fetch assignees for this task
-                let assignees_result = sqlx::query_as::<_, (String, String, String, String)>(
-                    r#"
-                    SELECT u.id, u.first_name, u.last_name, u.email
-                    FROM task_assignees ta
-                    JOIN users u ON ta.user_id = u.id
-                    WHERE ta.task_id = ?
-                    "#,
-                )
-                .bind(&id)
-                .fetch_all(pool.get_ref())
-                .await;
-
-                let assignees: Vec<serde_json::Value> = match assignees_result {
-                    Ok(a) => a
-                        .iter()
-                        .map(|(uid, first, last, email)| {
-                            serde_json::json!({
-                                "id": uid,
-                                "firstName": first,
-                                "lastName": last,
-                                "email": email,
-                                "name": format!("{} {}", first, last)
-                            })
-                        })
-                        .collect(),
-                    Err(_) => Vec::new(),
-                };
-
-                tasks_json.push(serde_json::json!({
+            )| {
+                serde_json::json!({
                     "id": id,
                     "title": title,
-                    "description": description,
                     "urgency": urgency,
                     "status": status,
-                    "department": department,
                     "projectId": project_id,
-                    "projectName": project_name,
-                    "assigneeId": assignee_id,
-                    "assignees": assignees,
                     "deadline": deadline,
-                    "completedAt": completed_at,
-                    "createdAt": created_at,
-                    "updatedAt": updated_at,
                     "isCompleted": is_completed.unwrap_or(false)
-                }));
-            }
-
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "tasks": tasks_json,
-                    "pagination": {
-                        "total": total,
-                        "limit": limit,
-                        "offset": offset
-                    }
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
-        }
-    }
-}
-
-async fn get_urgent_tasks(pool: web::Data<SqlitePool>) -> HttpResponse {
-    let result = sqlx::query_as::<_, (String, String, Option<String>, String, String, String, Option<String>, Option<String>, String, String, Option<String>, Option<String>, Option<String>, Option<bool>)>(
-        "SELECT id, title, description, urgency, status, department, project_id, assignee_id, created_by, deadline, completed_at, created_at, updated_at, is_completed FROM tasks WHERE urgency = 'urgent' AND status != 'completed' ORDER BY deadline ASC"
-    )
-    .fetch_all(pool.get_ref())
-    .await;
+                })
+            },
+        )
+        .collect();
 
-    match result {
-        Ok(tasks) => {
-            let tasks_json: Vec<serde_json::Value> = tasks
-                .iter()
-                .map(
-                    |(
-                        id,
-                        title,
-                        _description,
-                        urgency,
-                        status,
-                        _department,
-                        project_id,
-                        _assignee_id,
-                        _created_by,
-                        deadline,
-                        _completed_at,
-                        _created_at,
-                        _updated_at,
-                        is_completed,
-                    )| {
-                        serde_json::json!({
-                            "id": id,
-                            "title": title,
-                            "urgency": urgency,
-                            "status": status,
-                            "projectId": project_id,
-                            "deadline": deadline,
-                            "isCompleted": is_completed.unwrap_or(false)
-                        })
-                    },
-                )
-                .collect();
-
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "tasks": tasks_json,
-                    "count": tasks_json.len()
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "tasks": tasks_json,
+            "count": tasks_json.len()
         }
-    }
+    })))
 }
 
 async fn create_task(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: RequireRole<Member>,
     body: web::Json<CreateTaskRequest>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
+    let deadline = checked_deadline(&body.deadline)?;
+    // Adding a task to a project is managing that project's tasks
+    if let Some(project_id) = body.project_id.as_deref().filter(|id| !id.is_empty()) {
+        ensure_can_manage_project_tasks(pool.get_ref(), &auth, project_id).await?;
+    }
 
     let task_id = Uuid::new_v4().to_string();
 
-    let result = sqlx::query(
-        "INSERT INTO tasks (id, title, description, urgency, department, project_id, assignee_id, created_by, deadline, is_completed) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    sqlx::query(
+        "INSERT INTO tasks (id, title, description, urgency, department, project_id, assignee_id, created_by, deadline, is_completed, checklist_auto_complete) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
     )
     .bind(&task_id)
     .bind(&body.title)
@@ -284,136 +258,161 @@ async fn create_task(
     .bind(&body.project_id)
     .bind(&body.assignee_id)
     .bind(&user_id)
-    .bind(&body.deadline)
+    .bind(&deadline)
     .bind(body.is_completed.unwrap_or(false))
+    .bind(body.checklist_auto_complete.unwrap_or(false))
+    .execute(pool.get_ref()).await?;
+
+    // Add to task history
+    let history_id = Uuid::new_v4().to_string();
+    let _ = sqlx::query(
+        "INSERT INTO task_history (id, task_id, user_id, action) VALUES (?, ?, ?, 'created')",
+    )
+    .bind(&history_id)
+    .bind(&task_id)
+    .bind(&user_id)
     .execute(pool.get_ref())
     .await;
 
-    match result {
-        Ok(_) => {
-            // Add to task history
-            let history_id = Uuid::new_v4().to_string();
-            let _ = sqlx::query(
-                "INSERT INTO task_history (id, task_id, user_id, action) VALUES (?, ?, ?, 'created')"
-            )
-            .bind(&history_id)
-            .bind(&task_id)
-            .bind(&user_id)
-            .execute(pool.get_ref())
-            .await;
-
-            // Handle assignees
-            let mut assignee_user_ids: Vec<String> = Vec::new();
+    let mut conn = pool.acquire().await?;
+    let assignee_user_ids = assign_new_task(
+        &mut conn,
+        &task_id,
+        body.project_id.as_deref(),
+        body.assignee_ids.as_deref(),
+        &user_id,
+    )
+    .await;
 
-            // If specific assignee_ids are provided, use those
-            if let Some(ref ids) = body.assignee_ids {
-                assignee_user_ids.extend(ids.clone());
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "task": {
+                "id": task_id,
+                "title": body.title,
+                "description": body.description,
+                "urgency": body.urgency,
+                "status": "pending",
+                "department": body.department,
+                "projectId": body.project_id,
+                "assigneeId": body.assignee_id,
+                "assigneeIds": assignee_user_ids,
+                "deadline": deadline,
+                "isCompleted": body.is_completed.unwrap_or(false),
+                "checklistAutoComplete": body.checklist_auto_complete.unwrap_or(false)
             }
+        }
+    })))
+}
 
-            // If task is under a project and no specific assignees provided, assign all project members
-            if assignee_user_ids.is_empty() {
-                if let Some(ref project_id) = body.project_id {
-                    let members = sqlx::query_as::<_, (String,)>(
-                        "SELECT user_id FROM project_members WHERE project_id = ?",
-                    )
-                    .bind(project_id)
-                    .fetch_all(pool.get_ref())
-                    .await;
-
-                    if let Ok(members) = members {
-                        assignee_user_ids.extend(members.into_iter().map(|(id,)| id));
-                    }
-                }
-            }
+/// Assigns a new task to `assignee_ids`, or to every member of its project
+/// when there are none. Returns who it was assigned to.
+pub async fn assign_new_task(
+    conn: &mut SqliteConnection,
+    task_id: &str,
+    project_id: Option<&str>,
+    assignee_ids: Option<&[String]>,
+    assigned_by: &str,
+) -> Vec<String> {
+    let mut assignee_user_ids: Vec<String> = Vec::new();
+
+    // If specific assignee_ids are provided, use those
+    if let Some(ids) = assignee_ids {
+        assignee_user_ids.extend(ids.iter().cloned());
+    }
 
-            // Insert assignees
-            for assignee_uid in &assignee_user_ids {
-                let assignee_id = Uuid::new_v4().to_string();
-                let _ = sqlx::query(
-                    "INSERT OR IGNORE INTO task_assignees (id, task_id, user_id, assigned_by) VALUES (?, ?, ?, ?)"
-                )
-                .bind(&assignee_id)
-                .bind(&task_id)
-                .bind(assignee_uid)
-                .bind(&user_id)
-                .execute(pool.get_ref())
-                .await;
-            }
+    // If task is under a project and no specific assignees provided, assign all project members
+    if assignee_user_ids.is_empty() {
+        if let Some(project_id) = project_id {
+            let members = sqlx::query_as::<_, (String,)>(
+                "SELECT user_id FROM project_members WHERE project_id = ?",
+            )
+            .bind(project_id)
+            .fetch_all(&mut *conn)
+            .await;
 
-            HttpResponse::Created().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "task": {
-                        "id": task_id,
-                        "title": body.title,
-                        "description": body.description,
-                        "urgency": body.urgency,
-                        "status": "pending",
-                        "department": body.department,
-                        "projectId": body.project_id,
-                        "assigneeId": body.assignee_id,
-                        "assigneeIds": assignee_user_ids,
-                        "deadline": body.deadline,
-                        "isCompleted": body.is_completed.unwrap_or(false)
-                    }
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to create task"
-                }
-            }))
+            if let Ok(members) = members {
+                assignee_user_ids.extend(members.into_iter().map(|(id,)| id));
+            }
         }
     }
+
+    // Insert assignees
+    for assignee_uid in &assignee_user_ids {
+        let assignee_id = Uuid::new_v4().to_string();
+        let _ = sqlx::query(
+            "INSERT OR IGNORE INTO task_assignees (id, task_id, user_id, assigned_by) VALUES (?, ?, ?, ?)"
+        )
+        .bind(&assignee_id)
+        .bind(task_id)
+        .bind(assignee_uid)
+        .bind(assigned_by)
+        .execute(&mut *conn)
+        .await;
+    }
+
+    assignee_user_ids
 }
 
 async fn update_task(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: RequireRole<Member>,
     path: web::Path<String>,
     body: web::Json<UpdateTaskRequest>,
-) -> HttpResponse {
-    let user_id = req
-        .extensions()
-        .get::<String>()
-        .cloned()
-        .unwrap_or_default();
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
     let task_id = path.into_inner();
+    ensure_can_manage_task(pool.get_ref(), &auth, &task_id).await?;
+    // Moving the task into a project needs the same rights there
+    if let Some(project_id) = body.project_id.as_deref().filter(|id| !id.is_empty()) {
+        ensure_can_manage_project_tasks(pool.get_ref(), &auth, project_id).await?;
+    }
     let mut updates = Vec::new();
     let mut params: Vec<String> = Vec::new();
+    // New value of each field, as recorded in the history
+    let mut changes: Vec<(&str, Option<String>)> = Vec::new();
 
     if let Some(ref title) = body.title {
         updates.push("title = ?");
         params.push(title.clone());
+        changes.push(("title", Some(title.clone())));
     }
     if let Some(ref description) = body.description {
         updates.push("description = ?");
         params.push(description.clone());
+        changes.push(("description", Some(description.clone())));
     }
     if let Some(ref urgency) = body.urgency {
         updates.push("urgency = ?");
         params.push(urgency.clone());
+        changes.push(("urgency", Some(urgency.clone())));
     }
     if let Some(ref department) = body.department {
         updates.push("department = ?");
         params.push(department.clone());
+        changes.push(("department", Some(department.clone())));
     }
-    if body.project_id.is_some() {
-        updates.push("project_id = ?");
-        params.push(body.project_id.clone().unwrap_or_default());
+    if let Some(ref project_id) = body.project_id {
+        // An empty string takes the task out of its project
+        updates.push("project_id = NULLIF(?, '')");
+        params.push(project_id.clone());
+        changes.push((
+            "project_id",
+            Some(project_id.clone()).filter(|id| !id.is_empty()),
+        ));
     }
     if let Some(ref assignee_id) = body.assignee_id {
         updates.push("assignee_id = ?");
         params.push(assignee_id.clone());
+        changes.push(("assignee_id", Some(assignee_id.clone())));
     }
-    if let Some(ref deadline) = body.deadline {
+    let deadline = body.deadline.as_deref().map(checked_deadline).transpose()?;
+    if let Some(ref deadline) = deadline {
         updates.push("deadline = ?");
         params.push(deadline.clone());
+        changes.push(("deadline", Some(deadline.clone())));
+        // A new deadline is escalated afresh
+        updates.push("escalated_urgency = NULL");
     }
     if let Some(is_completed) = body.is_completed {
         updates.push("is_completed = ?");
@@ -422,16 +421,27 @@ async fn update_task(
         } else {
             "0".to_string()
         });
+        changes.push(("is_completed", Some(is_completed.to_string())));
+    }
+    if let Some(auto_complete) = body.checklist_auto_complete {
+        updates.push("checklist_auto_complete = ?");
+        params.push(if auto_complete {
+            "1".to_string()
+        } else {
+            "0".to_string()
+        });
+        changes.push(("checklist_auto_complete", Some(auto_complete.to_string())));
     }
 
     if updates.is_empty() {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "INVALID_REQUEST",
-                "message": "No fields to update"
-            }
-        }));
+        return Err(AppError::Validation("No fields to update".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    let before = TaskState::load(&mut tx, &task_id).await?;
+    // Completing through `isCompleted` is held to the same rule as the status
+    if body.is_completed == Some(true) && before.get("is_completed") != Some("true") {
+        task_dependencies::ensure_not_blocked(&mut *tx, &task_id).await?;
     }
 
     updates.push("updated_at = datetime('now')");
@@ -443,163 +453,107 @@ async fn update_task(
     }
     query_builder = query_builder.bind(&task_id);
 
-    match query_builder.execute(pool.get_ref()).await {
-        Ok(result) => {
-            if result.rows_affected() == 0 {
-                return HttpResponse::NotFound().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "NOT_FOUND",
-                        "message": "Task not found"
-                    }
-                }));
-            }
+    let result = query_builder.execute(&mut *tx).await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Task not found".to_string()));
+    }
 
-            // Add to task history
-            let history_id = Uuid::new_v4().to_string();
-            let _ = sqlx::query(
-                "INSERT INTO task_history (id, task_id, user_id, action) VALUES (?, ?, ?, 'updated')"
-            )
-            .bind(&history_id)
-            .bind(&task_id)
-            .bind(&user_id)
-            .execute(pool.get_ref())
-            .await;
+    // Add to task history, one row per field that changed
+    task_history::record_changes(&mut tx, &before, &user_id, &changes).await?;
+    tx.commit().await?;
 
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "task": {
-                        "id": task_id,
-                        "title": body.title,
-                        "urgency": body.urgency,
-                        "deadline": body.deadline
-                    }
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to update task"
-                }
-            }))
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "task": {
+                "id": task_id,
+                "title": body.title,
+                "urgency": body.urgency,
+                "deadline": deadline
+            }
         }
-    }
+    })))
 }
 
 async fn update_task_status(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: RequireRole<Member>,
     path: web::Path<String>,
     body: web::Json<UpdateTaskStatusRequest>,
-) -> HttpResponse {
-    let user_id = req
-        .extensions()
-        .get::<String>()
-        .cloned()
-        .unwrap_or_default();
+) -> Result<HttpResponse, AppError> {
     let task_id = path.into_inner();
+    ensure_can_manage_task(pool.get_ref(), &auth, &task_id).await?;
+    set_task_status(pool.get_ref(), &task_id, &auth.id, &body.status).await?;
 
-    // Get current status for history
-    let current = sqlx::query_as::<_, (String,)>("SELECT status FROM tasks WHERE id = ?")
-        .bind(&task_id)
-        .fetch_optional(pool.get_ref())
-        .await;
-
-    let old_status = match current {
-        Ok(Some((status,))) => status,
-        Ok(None) => {
-            return HttpResponse::NotFound().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "NOT_FOUND",
-                    "message": "Task not found"
-                }
-            }));
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            return HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }));
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "task": {
+                "id": task_id,
+                "status": body.status
+            }
         }
-    };
+    })))
+}
 
-    let completed_at = if body.status == "completed" {
-        Some("datetime('now')")
-    } else {
-        None
-    };
+/// Moves a task to `status` and records the change in its history. Shared by
+/// `PATCH /tasks/{id}/status` and checklists that complete their task.
+pub async fn set_task_status(
+    pool: &SqlitePool,
+    task_id: &str,
+    user_id: &str,
+    status: &str,
+) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+
+    // Get current status for history
+    let (old_status,) = sqlx::query_as::<_, (String,)>("SELECT status FROM tasks WHERE id = ?")
+        .bind(task_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Task not found".to_string()))?;
+
+    // A task can't be completed while its prerequisites are open
+    if status == "completed" && old_status != "completed" {
+        task_dependencies::ensure_not_blocked(&mut *tx, task_id).await?;
+    }
 
-    let query = if completed_at.is_some() {
+    let query = if status == "completed" {
         "UPDATE tasks SET status = ?, completed_at = datetime('now'), updated_at = datetime('now') WHERE id = ?"
     } else {
         "UPDATE tasks SET status = ?, completed_at = NULL, updated_at = datetime('now') WHERE id = ?"
     };
 
-    let result = sqlx::query(query)
-        .bind(&body.status)
-        .bind(&task_id)
-        .execute(pool.get_ref())
-        .await;
-
-    match result {
-        Ok(_) => {
-            // Add to task history
-            let history_id = Uuid::new_v4().to_string();
-            let _ = sqlx::query(
-                "INSERT INTO task_history (id, task_id, user_id, action, field_changed, old_value, new_value) VALUES (?, ?, ?, 'status_changed', 'status', ?, ?)"
-            )
-            .bind(&history_id)
-            .bind(&task_id)
-            .bind(&user_id)
-            .bind(&old_status)
-            .bind(&body.status)
-            .execute(pool.get_ref())
-            .await;
+    sqlx::query(query)
+        .bind(status)
+        .bind(task_id)
+        .execute(&mut *tx)
+        .await?;
 
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "task": {
-                        "id": task_id,
-                        "status": body.status
-                    }
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to update task status"
-                }
-            }))
-        }
-    }
+    // Add to task history
+    sqlx::query(
+        "INSERT INTO task_history (id, task_id, user_id, action, field_changed, old_value, new_value) VALUES (?, ?, ?, 'status_changed', 'status', ?, ?)"
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(task_id)
+    .bind(user_id)
+    .bind(&old_status)
+    .bind(status)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(())
 }
 
 async fn delete_task(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: RequireRole<Member>,
     path: web::Path<String>,
-) -> HttpResponse {
-    let user_id = req
-        .extensions()
-        .get::<String>()
-        .cloned()
-        .unwrap_or_default();
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
     let task_id = path.into_inner();
+    ensure_can_manage_task(pool.get_ref(), &auth, &task_id).await?;
 
     // Add to task history before delete
     let history_id = Uuid::new_v4().to_string();
@@ -612,113 +566,129 @@ async fn delete_task(
     .execute(pool.get_ref())
     .await;
 
-    let result = sqlx::query("DELETE FROM tasks WHERE id = ?")
+    let rows = sqlx::query("DELETE FROM tasks WHERE id = ?")
         .bind(&task_id)
         .execute(pool.get_ref())
-        .await;
+        .await?;
 
-    match result {
-        Ok(rows) => {
-            if rows.rows_affected() > 0 {
-                HttpResponse::Ok().json(serde_json::json!({
-                    "success": true,
-                    "message": "Task deleted successfully"
-                }))
-            } else {
-                HttpResponse::NotFound().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "NOT_FOUND",
-                        "message": "Task not found"
-                    }
-                }))
-            }
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to delete task"
-                }
-            }))
-        }
+    if rows.rows_affected() == 0 {
+        return Err(AppError::NotFound("Task not found".to_string()));
     }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Task deleted successfully"
+    })))
 }
 
-async fn get_task_history(pool: web::Data<SqlitePool>, path: web::Path<String>) -> HttpResponse {
-    let task_id = path.into_inner();
+/// A deadline as stored: a `YYYY-MM-DD` date as sent, and one with a time in
+/// UTC. A time without an offset is taken as UTC.
+fn checked_deadline(value: &str) -> Result<String, AppError> {
+    let value = value.trim();
+    if NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok() {
+        return Ok(value.to_string());
+    }
+    escalation::deadline_instant(value, &TimeZone::utc())
+        .map(timezone::format_sql_datetime)
+        .ok_or_else(|| {
+            AppError::Validation(format!(
+                "Invalid deadline `{}`, expected YYYY-MM-DD or a date and time",
+                value
+            ))
+        })
+}
 
-    let result = sqlx::query_as::<_, (String, String, Option<String>, Option<String>, Option<String>, String, Option<String>)>(
-        "SELECT th.id, th.action, th.field_changed, th.old_value, th.new_value, th.user_id, th.created_at FROM task_history th WHERE th.task_id = ? ORDER BY th.created_at DESC"
-    )
-    .bind(&task_id)
-    .fetch_all(pool.get_ref())
-    .await;
+/// Tasks that belong to a project may only be edited or deleted by one of the
+/// project's owners or an Admin. Tasks outside a project are open to Members.
+pub async fn ensure_can_manage_task(
+    pool: &SqlitePool,
+    auth: &AuthUser,
+    task_id: &str,
+) -> Result<(), AppError> {
+    if !can_manage_task(pool, auth, task_id).await? {
+        return Err(not_project_owner());
+    }
+    Ok(())
+}
 
-    match result {
-        Ok(history) => {
-            let history_json: Vec<serde_json::Value> = history
-                .iter()
-                .map(
-                    |(id, action, field_changed, old_value, new_value, user_id, created_at)| {
-                        serde_json::json!({
-                            "id": id,
-                            "action": action,
-                            "fieldChanged": field_changed,
-                            "oldValue": old_value,
-                            "newValue": new_value,
-                            "changedBy": {
-                                "id": user_id
-                            },
-                            "createdAt": created_at
-                        })
-                    },
-                )
-                .collect();
-
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "history": history_json
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
-        }
+/// Whether `auth` may manage the task, without failing when they may not.
+pub async fn can_manage_task(
+    pool: &SqlitePool,
+    auth: &AuthUser,
+    task_id: &str,
+) -> Result<bool, AppError> {
+    let project_id =
+        sqlx::query_scalar::<_, Option<String>>("SELECT project_id FROM tasks WHERE id = ?")
+            .bind(task_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Task not found".to_string()))?;
+
+    match project_id {
+        Some(project_id) => can_manage_project_tasks(pool, auth, &project_id).await,
+        None => Ok(true),
     }
 }
 
-// Get tasks assigned to the current user for today (based on deadline)
-async fn get_my_tasks_today(pool: web::Data<SqlitePool>, req: HttpRequest) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+/// Only a project's owners and Admins may manage its tasks, including creating
+/// one in it or moving one into it.
+pub async fn ensure_can_manage_project_tasks(
+    pool: &SqlitePool,
+    auth: &AuthUser,
+    project_id: &str,
+) -> Result<(), AppError> {
+    if !can_manage_project_tasks(pool, auth, project_id).await? {
+        return Err(not_project_owner());
+    }
+    Ok(())
+}
+
+async fn can_manage_project_tasks(
+    pool: &SqlitePool,
+    auth: &AuthUser,
+    project_id: &str,
+) -> Result<bool, AppError> {
+    if auth.is_admin() {
+        return Ok(true);
+    }
+
+    let role = sqlx::query_scalar::<_, String>(
+        "SELECT role FROM project_members WHERE project_id = ? AND user_id = ?",
+    )
+    .bind(project_id)
+    .bind(&auth.id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(role.as_deref() == Some("owner"))
+}
+
+fn not_project_owner() -> AppError {
+    AppError::Forbidden(
+        "Only a project owner or an admin can modify this project's tasks".to_string(),
+    )
+    .with_code("NOT_PROJECT_OWNER")
+}
+
+// Get tasks assigned to the current user for today (based on deadline),
+// where today is the date in the user's time zone
+async fn get_my_tasks_today(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    escalation::escalate_tasks(pool.get_ref(), &SystemClock).await?;
+
+    let user_id = auth.id.clone();
+    let zone = timezone::user_time_zone(pool.get_ref(), &user_id).await?;
+    let today = zone.today(timezone::now_utc());
 
     // Get tasks where:
     // 1. User is directly assigned (legacy assignee_id), OR
     // 2. User is in task_assignees table, OR
     // 3. Task belongs to a project where the user is a member
-    // AND the deadline is today
-    let result = sqlx::query_as::<_, (String, String, Option<String>, String, String, String, Option<String>, Option<String>, String, Option<String>, Option<String>, Option<bool>)>(
+    // AND the deadline is today. Timed deadlines are in UTC, so the query
+    // takes the days either side and the user's day is picked below.
+    let tasks = sqlx::query_as::<_, (String, String, Option<String>, String, String, String, Option<String>, Option<String>, String, Option<String>, Option<String>, Option<bool>)>(
         r#"
         SELECT DISTINCT 
             t.id, t.title, t.description, t.urgency, t.status, t.department,
@@ -728,7 +698,7 @@ async fn get_my_tasks_today(pool: web::Data<SqlitePool>, req: HttpRequest) -> Ht
         LEFT JOIN task_assignees ta ON t.id = ta.task_id
         LEFT JOIN project_members pm ON t.project_id = pm.project_id
         WHERE 
-            date(t.deadline) = date('now')
+            date(t.deadline) BETWEEN date(?, '-1 day') AND date(?, '+1 day')
             AND t.status != 'completed'
             AND (
                 t.assignee_id = ?
@@ -745,103 +715,97 @@ async fn get_my_tasks_today(pool: web::Data<SqlitePool>, req: HttpRequest) -> Ht
             t.deadline ASC
         "#
     )
+    .bind(today.format("%Y-%m-%d").to_string())
+    .bind(today.format("%Y-%m-%d").to_string())
     .bind(&user_id)
     .bind(&user_id)
     .bind(&user_id)
     .fetch_all(pool.get_ref())
-    .await;
-
-    match result {
-        Ok(tasks) => {
-            let mut tasks_json: Vec<serde_json::Value> = Vec::new();
+    .await?;
+    let tasks = tasks.into_iter().filter(|task| {
+        escalation::local_deadline(&task.8, &zone).is_some_and(|(date, _)| date == today)
+    });
+
+    let mut tasks_json: Vec<serde_json::Value> = Vec::new();
+
+    for (
+        id,
+        title,
+        description,
+        urgency,
+        status,
+        department,
+        project_id,
+        project_name,
+        deadline,
+        created_at,
+        updated_at,
+        is_completed,
+    ) in tasks
+    {
+        // Get assignees for this task
+        let assignees_result = sqlx::query_as::<_, (String, String, String, String)>(
+            r#"
+            SELECT u.id, u.first_name, u.last_name, u.email
+            FROM task_assignees ta
+            JOIN users u ON ta.user_id = u.id
+            WHERE ta.task_id = ?
+            "#,
+        )
+        .bind(&id)
+        .fetch_all(pool.get_ref())
+        .await;
 
-            for (
-                id,
-                title,
-                description,
-                urgency,
-                status,
-                department,
-                project_id,
-                project_name,
-                deadline,
-                created_at,
-                updated_at,
-                is_completed,
-            ) in tasks
-            {
-                // Get assignees for this task
-                let assignees_result = sqlx::query_as::<_, (String, String, String, String)>(
-                    r#"
-                    SELECT u.id, u.first_name, u.last_name, u.email
-                    FROM task_assignees ta
-                    JOIN users u ON ta.user_id = u.id
-                    WHERE ta.task_id = ?
-                    "#,
-                )
-                .bind(&id)
-                .fetch_all(pool.get_ref())
-                .await;
-
-                let assignees: Vec<serde_json::Value> = match assignees_result {
-                    Ok(a) => a
-                        .iter()
-                        .map(|(uid, first, last, email)| {
-                            serde_json::json!({
-                                "id": uid,
-                                "firstName": first,
-                                "lastName": last,
-                                "email": email,
-                                "name": format!("{} {}", first, last)
-                            })
-                        })
-                        .collect(),
-                    Err(_) => Vec::new(),
-                };
-
-                tasks_json.push(serde_json::json!({
-                    "id": id,
-                    "title": title,
-                    "description": description,
-                    "urgency": urgency,
-                    "status": status,
-                    "department": department,
-                    "projectId": project_id,
-                    "projectName": project_name,
-                    "deadline": deadline,
-                    "createdAt": created_at,
-                    "updatedAt": updated_at,
-                    "isCompleted": is_completed.unwrap_or(false),
-                    "assignees": assignees
-                }));
-            }
+        let assignees: Vec<serde_json::Value> = match assignees_result {
+            Ok(a) => a
+                .iter()
+                .map(|(uid, first, last, email)| {
+                    serde_json::json!({
+                        "id": uid,
+                        "firstName": first,
+                        "lastName": last,
+                        "email": email,
+                        "name": format!("{} {}", first, last)
+                    })
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        tasks_json.push(serde_json::json!({
+            "id": id,
+            "title": title,
+            "description": description,
+            "urgency": urgency,
+            "status": status,
+            "department": department,
+            "projectId": project_id,
+            "projectName": project_name,
+            "deadline": deadline,
+            "createdAt": created_at,
+            "updatedAt": updated_at,
+            "isCompleted": is_completed.unwrap_or(false),
+            "assignees": assignees
+        }));
+    }
 
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "tasks": tasks_json,
-                    "count": tasks_json.len()
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "tasks": tasks_json,
+            "count": tasks_json.len()
         }
-    }
+    })))
 }
 
 // Get assignees for a task
-async fn get_task_assignees(pool: web::Data<SqlitePool>, path: web::Path<String>) -> HttpResponse {
+async fn get_task_assignees(
+    pool: web::Data<SqlitePool>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
     let task_id = path.into_inner();
 
-    let result = sqlx::query_as::<_, (String, String, String, String, Option<String>)>(
+    let assignees = sqlx::query_as::<_, (String, String, String, String, Option<String>)>(
         r#"
         SELECT u.id, u.first_name, u.last_name, u.email, ta.assigned_at
         FROM task_assignees ta
@@ -852,42 +816,28 @@ async fn get_task_assignees(pool: web::Data<SqlitePool>, path: web::Path<String>
     )
     .bind(&task_id)
     .fetch_all(pool.get_ref())
-    .await;
-
-    match result {
-        Ok(assignees) => {
-            let assignees_json: Vec<serde_json::Value> = assignees
-                .iter()
-                .map(|(id, first_name, last_name, email, assigned_at)| {
-                    serde_json::json!({
-                        "id": id,
-                        "firstName": first_name,
-                        "lastName": last_name,
-                        "email": email,
-                        "name": format!("{} {}", first_name, last_name),
-                        "assignedAt": assigned_at
-                    })
-                })
-                .collect();
-
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "assignees": assignees_json
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
+    .await?;
+
+    let assignees_json: Vec<serde_json::Value> = assignees
+        .iter()
+        .map(|(id, first_name, last_name, email, assigned_at)| {
+            serde_json::json!({
+                "id": id,
+                "firstName": first_name,
+                "lastName": last_name,
+                "email": email,
+                "name": format!("{} {}", first_name, last_name),
+                "assignedAt": assigned_at
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "assignees": assignees_json
         }
-    }
+    })))
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -899,41 +849,17 @@ struct AddAssigneesRequest {
 // Add assignees to a task
 async fn add_task_assignees(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: RequireRole<Member>,
     path: web::Path<String>,
     body: web::Json<AddAssigneesRequest>,
-) -> HttpResponse {
-    let assigner_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let assigner_id = auth.id.clone();
 
     let task_id = path.into_inner();
+    // Also checks that the task exists
+    ensure_can_manage_task(pool.get_ref(), &auth, &task_id).await?;
 
-    // Check if task exists
-    let task_exists = sqlx::query_as::<_, (i32,)>("SELECT 1 FROM tasks WHERE id = ?")
-        .bind(&task_id)
-        .fetch_optional(pool.get_ref())
-        .await;
-
-    if let Ok(None) = task_exists {
-        return HttpResponse::NotFound().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "NOT_FOUND",
-                "message": "Task not found"
-            }
-        }));
-    }
-
+    let mut tx = pool.begin().await?;
     let mut added_count = 0;
     for user_id in &body.user_ids {
         let assignee_id = Uuid::new_v4().to_string();
@@ -944,61 +870,179 @@ async fn add_task_assignees(
         .bind(&task_id)
         .bind(user_id)
         .bind(&assigner_id)
-        .execute(pool.get_ref())
+        .execute(&mut *tx)
         .await;
 
         if let Ok(r) = result {
+            if r.rows_affected() > 0 {
+                task_history::record_change(
+                    &mut *tx,
+                    &task_id,
+                    &assigner_id,
+                    "assignees",
+                    None,
+                    Some(user_id),
+                )
+                .await?;
+            }
             added_count += r.rows_affected();
         }
     }
+    tx.commit().await?;
 
-    HttpResponse::Ok().json(serde_json::json!({
+    Ok(HttpResponse::Ok().json(serde_json::json!({
         "success": true,
         "data": {
             "addedCount": added_count
         }
-    }))
+    })))
 }
 
 // Remove an assignee from a task
 async fn remove_task_assignee(
     pool: web::Data<SqlitePool>,
+    auth: RequireRole<Member>,
     path: web::Path<(String, String)>,
-) -> HttpResponse {
+) -> Result<HttpResponse, AppError> {
     let (task_id, user_id) = path.into_inner();
+    ensure_can_manage_task(pool.get_ref(), &auth, &task_id).await?;
 
-    let result = sqlx::query("DELETE FROM task_assignees WHERE task_id = ? AND user_id = ?")
+    let mut tx = pool.begin().await?;
+    let rows = sqlx::query("DELETE FROM task_assignees WHERE task_id = ? AND user_id = ?")
         .bind(&task_id)
         .bind(&user_id)
-        .execute(pool.get_ref())
-        .await;
+        .execute(&mut *tx)
+        .await?;
 
-    match result {
-        Ok(rows) => {
-            if rows.rows_affected() > 0 {
-                HttpResponse::Ok().json(serde_json::json!({
-                    "success": true,
-                    "message": "Assignee removed successfully"
-                }))
-            } else {
-                HttpResponse::NotFound().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "NOT_FOUND",
-                        "message": "Assignee not found"
-                    }
-                }))
-            }
+    if rows.rows_affected() == 0 {
+        return Err(AppError::NotFound("Assignee not found".to_string()));
+    }
+    task_history::record_change(
+        &mut *tx,
+        &task_id,
+        &auth.id,
+        "assignees",
+        Some(&user_id),
+        None,
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Assignee removed successfully"
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::App;
+    use actix_web::HttpMessage;
+    use actix_web::dev::Service;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{TestRequest, call_service, init_service, read_body_json};
+
+    use crate::middleware::guards::Role;
+
+    const USER_HEADER: &str = "x-test-user";
+
+    /// Stands in for the `Auth` middleware: `x-test-user` names a Member.
+    macro_rules! test_app {
+        ($pool:expr) => {
+            init_service(
+                App::new()
+                    .app_data(web::Data::new($pool.clone()))
+                    .service(web::scope("/api").configure(configure_routes))
+                    .wrap_fn(|req, srv| {
+                        if let Some(id) = req.headers().get(USER_HEADER) {
+                            let id = id.to_str().unwrap().to_string();
+                            req.extensions_mut().insert(AuthUser {
+                                id,
+                                role: Role::Member,
+                                department: "IT".to_string(),
+                                session_id: "test-session".to_string(),
+                            });
+                        }
+                        srv.call(req)
+                    }),
+            )
+            .await
+        };
+    }
+
+    #[actix_web::test]
+    async fn only_project_owners_create_tasks_in_a_project() {
+        let pool = crate::migrations::test_pool().await;
+        for id in ["owner", "member"] {
+            sqlx::query(
+                "INSERT INTO users (id, email, password_hash, first_name, last_name, department, role) VALUES (?, ?, 'x', 'Test', 'User', 'IT', 'Member')",
+            )
+            .bind(id)
+            .bind(format!("{}@example.com", id))
+            .execute(&pool)
+            .await
+            .unwrap();
         }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to remove assignee"
-                }
-            }))
+        sqlx::query(
+            "INSERT INTO projects (id, name, created_by) VALUES ('project', 'Chiller upgrade', 'owner');
+             INSERT INTO project_members (id, project_id, user_id, role) VALUES
+                ('m1', 'project', 'owner', 'owner'),
+                ('m2', 'project', 'member', 'member');",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let app = test_app!(pool);
+        let create = |user: &str| {
+            TestRequest::post()
+                .uri("/api/tasks")
+                .insert_header((USER_HEADER, user))
+                .set_json(serde_json::json!({
+                    "title": "Commission chiller",
+                    "urgency": "medium",
+                    "department": "IT",
+                    "projectId": "project",
+                    "deadline": "2026-03-01"
+                }))
+                .to_request()
+        };
+
+        let res = call_service(&app, create("member")).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let body: serde_json::Value = read_body_json(res).await;
+        assert_eq!(body["error"]["code"], "NOT_PROJECT_OWNER");
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tasks")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+
+        let res = call_service(&app, create("owner")).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+    }
+
+    #[test]
+    fn deadlines_are_stored_as_dates_or_in_utc() {
+        assert_eq!(checked_deadline("2026-01-05").unwrap(), "2026-01-05");
+        assert_eq!(
+            checked_deadline("2026-01-05T10:00:00+08:00").unwrap(),
+            "2026-01-05 02:00:00"
+        );
+        assert_eq!(
+            checked_deadline("2026-01-05T10:00:00Z").unwrap(),
+            "2026-01-05 10:00:00"
+        );
+        assert_eq!(
+            checked_deadline("2026-01-05 10:00").unwrap(),
+            "2026-01-05 10:00:00"
+        );
+        for invalid in ["", "tomorrow", "2026-13-01", "05/01/2026"] {
+            assert_eq!(
+                checked_deadline(invalid).unwrap_err().code(),
+                "VALIDATION_ERROR",
+                "{invalid}"
+            );
         }
     }
 }
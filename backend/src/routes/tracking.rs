@@ -1,7 +1,9 @@
-use actix_web::{HttpMessage, HttpRequest, HttpResponse, web};
+use actix_web::{HttpResponse, web};
 use sqlx::SqlitePool;
 use uuid::Uuid;
 
+use crate::error::AppError;
+use crate::middleware::guards::AuthUser;
 use crate::models::tracking::TrackUserRequest;
 
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
@@ -16,23 +18,16 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
 }
 
 /// Get list of users that the current user is tracking
-async fn get_tracked_users(pool: web::Data<SqlitePool>, req: HttpRequest) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+async fn get_tracked_users(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
-    let result = sqlx::query_as::<_, (String, String, String, String, String, Option<String>)>(
-        r#"
-        SELECT 
+    let tracked_users =
+        sqlx::query_as::<_, (String, String, String, String, String, Option<String>)>(
+            r#"
+        SELECT
             ut.id,
             ut.tracked_user_id,
             u.first_name || ' ' || u.last_name as tracked_user_name,
@@ -44,68 +39,46 @@ async fn get_tracked_users(pool: web::Data<SqlitePool>, req: HttpRequest) -> Htt
         WHERE ut.tracker_user_id = ?
         ORDER BY ut.created_at DESC
         "#,
-    )
-    .bind(&user_id)
-    .fetch_all(pool.get_ref())
-    .await;
+        )
+        .bind(&user_id)
+        .fetch_all(pool.get_ref())
+        .await?;
 
-    match result {
-        Ok(tracked_users) => {
-            let users_json: Vec<serde_json::Value> = tracked_users
-                .iter()
-                .map(
-                    |(id, tracked_user_id, name, email, department, created_at)| {
-                        serde_json::json!({
-                            "id": id,
-                            "trackedUserId": tracked_user_id,
-                            "trackedUserName": name,
-                            "trackedUserEmail": email,
-                            "trackedUserDepartment": department,
-                            "createdAt": created_at
-                        })
-                    },
-                )
-                .collect();
+    let users_json: Vec<serde_json::Value> = tracked_users
+        .iter()
+        .map(
+            |(id, tracked_user_id, name, email, department, created_at)| {
+                serde_json::json!({
+                    "id": id,
+                    "trackedUserId": tracked_user_id,
+                    "trackedUserName": name,
+                    "trackedUserEmail": email,
+                    "trackedUserDepartment": department,
+                    "createdAt": created_at
+                })
+            },
+        )
+        .collect();
 
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "trackedUsers": users_json,
-                    "total": users_json.len()
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to fetch tracked users"
-                }
-            }))
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "trackedUsers": users_json,
+            "total": users_json.len()
         }
-    }
+    })))
 }
 
 /// Get list of users who are tracking the current user
-async fn get_my_trackers(pool: web::Data<SqlitePool>, req: HttpRequest) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+async fn get_my_trackers(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
-    let result = sqlx::query_as::<_, (String, String, String, String, Option<String>)>(
+    let trackers = sqlx::query_as::<_, (String, String, String, String, Option<String>)>(
         r#"
-        SELECT 
+        SELECT
             ut.id,
             ut.tracker_user_id,
             u.first_name || ' ' || u.last_name as tracker_user_name,
@@ -119,102 +92,51 @@ async fn get_my_trackers(pool: web::Data<SqlitePool>, req: HttpRequest) -> HttpR
     )
     .bind(&user_id)
     .fetch_all(pool.get_ref())
-    .await;
+    .await?;
 
-    match result {
-        Ok(trackers) => {
-            let trackers_json: Vec<serde_json::Value> = trackers
-                .iter()
-                .map(|(id, tracker_user_id, name, email, created_at)| {
-                    serde_json::json!({
-                        "id": id,
-                        "trackerUserId": tracker_user_id,
-                        "trackerUserName": name,
-                        "trackerUserEmail": email,
-                        "createdAt": created_at
-                    })
-                })
-                .collect();
+    let trackers_json: Vec<serde_json::Value> = trackers
+        .iter()
+        .map(|(id, tracker_user_id, name, email, created_at)| {
+            serde_json::json!({
+                "id": id,
+                "trackerUserId": tracker_user_id,
+                "trackerUserName": name,
+                "trackerUserEmail": email,
+                "createdAt": created_at
+            })
+        })
+        .collect();
 
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "trackers": trackers_json,
-                    "total": trackers_json.len()
-                }
-            }))
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "trackers": trackers_json,
+            "total": trackers_json.len()
         }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to fetch trackers"
-                }
-            }))
-        }
-    }
+    })))
 }
 
 /// Start tracking a user
 async fn track_user(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: AuthUser,
     body: web::Json<TrackUserRequest>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     // Can't track yourself
     if user_id == body.user_id {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "INVALID_REQUEST",
-                "message": "You cannot track yourself"
-            }
-        }));
+        return Err(AppError::Validation(
+            "You cannot track yourself".to_string(),
+        ));
     }
 
     // Check if user exists
-    let user_exists = sqlx::query_as::<_, (String,)>("SELECT id FROM users WHERE id = ?")
+    sqlx::query_as::<_, (String,)>("SELECT id FROM users WHERE id = ?")
         .bind(&body.user_id)
         .fetch_optional(pool.get_ref())
-        .await;
-
-    match user_exists {
-        Ok(Some(_)) => {}
-        Ok(None) => {
-            return HttpResponse::NotFound().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "NOT_FOUND",
-                    "message": "User not found"
-                }
-            }));
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            return HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }));
-        }
-    }
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
 
     // Check if already tracking
     let already_tracking = sqlx::query_as::<_, (String,)>(
@@ -223,195 +145,107 @@ async fn track_user(
     .bind(&user_id)
     .bind(&body.user_id)
     .fetch_optional(pool.get_ref())
-    .await;
+    .await?;
 
-    match already_tracking {
-        Ok(Some(_)) => {
-            return HttpResponse::Conflict().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "ALREADY_TRACKING",
-                    "message": "You are already tracking this user"
-                }
-            }));
-        }
-        Ok(None) => {}
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            return HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }));
-        }
+    if already_tracking.is_some() {
+        return Err(
+            AppError::Conflict("You are already tracking this user".to_string())
+                .with_code("ALREADY_TRACKING"),
+        );
     }
 
     let tracking_id = Uuid::new_v4().to_string();
 
-    let result = sqlx::query(
+    sqlx::query(
         "INSERT INTO user_tracking (id, tracker_user_id, tracked_user_id) VALUES (?, ?, ?)",
     )
     .bind(&tracking_id)
     .bind(&user_id)
     .bind(&body.user_id)
     .execute(pool.get_ref())
-    .await;
+    .await?;
 
-    match result {
-        Ok(_) => {
-            // Get tracked user info
-            let tracked_user = sqlx::query_as::<_, (String, String, String)>(
-                "SELECT first_name, last_name, email FROM users WHERE id = ?",
-            )
-            .bind(&body.user_id)
-            .fetch_optional(pool.get_ref())
-            .await;
+    // Get tracked user info
+    let tracked_user = sqlx::query_as::<_, (String, String, String)>(
+        "SELECT first_name, last_name, email FROM users WHERE id = ?",
+    )
+    .bind(&body.user_id)
+    .fetch_optional(pool.get_ref())
+    .await;
 
-            let (first_name, last_name, email) = tracked_user.ok().flatten().unwrap_or((
-                "Unknown".to_string(),
-                "User".to_string(),
-                "".to_string(),
-            ));
+    let (first_name, last_name, email) = tracked_user.ok().flatten().unwrap_or((
+        "Unknown".to_string(),
+        "User".to_string(),
+        "".to_string(),
+    ));
 
-            HttpResponse::Created().json(serde_json::json!({
-                "success": true,
-                "message": "Now tracking user",
-                "data": {
-                    "tracking": {
-                        "id": tracking_id,
-                        "trackedUserId": body.user_id,
-                        "trackedUserName": format!("{} {}", first_name, last_name),
-                        "trackedUserEmail": email
-                    }
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to track user"
-                }
-            }))
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "success": true,
+        "message": "Now tracking user",
+        "data": {
+            "tracking": {
+                "id": tracking_id,
+                "trackedUserId": body.user_id,
+                "trackedUserName": format!("{} {}", first_name, last_name),
+                "trackedUserEmail": email
+            }
         }
-    }
+    })))
 }
 
 /// Stop tracking a user
 async fn untrack_user(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: AuthUser,
     path: web::Path<String>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     let tracked_user_id = path.into_inner();
 
-    let result =
+    let rows =
         sqlx::query("DELETE FROM user_tracking WHERE tracker_user_id = ? AND tracked_user_id = ?")
             .bind(&user_id)
             .bind(&tracked_user_id)
             .execute(pool.get_ref())
-            .await;
+            .await?;
 
-    match result {
-        Ok(rows) => {
-            if rows.rows_affected() > 0 {
-                HttpResponse::Ok().json(serde_json::json!({
-                    "success": true,
-                    "message": "Stopped tracking user"
-                }))
-            } else {
-                HttpResponse::NotFound().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "NOT_FOUND",
-                        "message": "You were not tracking this user"
-                    }
-                }))
-            }
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to untrack user"
-                }
-            }))
-        }
+    if rows.rows_affected() == 0 {
+        return Err(AppError::NotFound(
+            "You were not tracking this user".to_string(),
+        ));
     }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Stopped tracking user"
+    })))
 }
 
 /// Check if currently tracking a specific user
 async fn check_if_tracking(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: AuthUser,
     path: web::Path<String>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     let tracked_user_id = path.into_inner();
 
-    let result = sqlx::query_as::<_, (String,)>(
+    let tracking = sqlx::query_as::<_, (String,)>(
         "SELECT id FROM user_tracking WHERE tracker_user_id = ? AND tracked_user_id = ?",
     )
     .bind(&user_id)
     .bind(&tracked_user_id)
     .fetch_optional(pool.get_ref())
-    .await;
+    .await?;
 
-    match result {
-        Ok(Some(_)) => HttpResponse::Ok().json(serde_json::json!({
-            "success": true,
-            "data": {
-                "isTracking": true
-            }
-        })),
-        Ok(None) => HttpResponse::Ok().json(serde_json::json!({
-            "success": true,
-            "data": {
-                "isTracking": false
-            }
-        })),
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "isTracking": tracking.is_some()
         }
-    }
+    })))
 }
 
 /// Helper function to get all users tracking a specific user (used by check-in logic)
@@ -1,9 +1,16 @@
-use actix_web::{HttpMessage, HttpRequest, HttpResponse, web};
+use actix_web::{HttpResponse, web};
 use bcrypt::{DEFAULT_COST, hash};
 use sqlx::SqlitePool;
 use uuid::Uuid;
 
+use crate::config::Config;
+use crate::error::AppError;
+use crate::lockout;
+use crate::middleware::guards::{Admin, AuthUser, RequireRole};
 use crate::models::users::{CreateUserRequest, UpdateUserRequest, User, UserResponse};
+use crate::routes::auth::{Revoke, active_sessions, revoke_sessions};
+use crate::timezone;
+use crate::tokens::RevokedSessions;
 
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -13,75 +20,48 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
             .route("", web::get().to(get_all_users))
             .route("", web::post().to(create_user))
             .route("/{user_id}", web::put().to(update_user))
-            .route("/{user_id}", web::delete().to(delete_user)),
+            .route("/{user_id}", web::delete().to(delete_user))
+            .route("/{user_id}/unlock", web::post().to(unlock_user))
+            .route("/{user_id}/sessions", web::get().to(get_user_sessions))
+            .route(
+                "/{user_id}/sessions",
+                web::delete().to(revoke_user_sessions),
+            )
+            .route(
+                "/{user_id}/sessions/{session_id}",
+                web::delete().to(revoke_user_session),
+            ),
     );
 }
 
-async fn get_current_user(pool: web::Data<SqlitePool>, req: HttpRequest) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+async fn get_current_user(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
-    let result = sqlx::query_as::<_, User>(
-        "SELECT id, email, password_hash, first_name, last_name, department, role, is_active, created_at, updated_at, last_login FROM users WHERE id = ?"
+    let user = sqlx::query_as::<_, User>(
+        "SELECT id, email, password_hash, first_name, last_name, department, role, is_active, created_at, updated_at, last_login, timezone FROM users WHERE id = ?"
     )
     .bind(&user_id)
     .fetch_optional(pool.get_ref())
-    .await;
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
 
-    match result {
-        Ok(Some(user)) => HttpResponse::Ok().json(serde_json::json!({
-            "success": true,
-            "data": {
-                "user": UserResponse::from(user)
-            }
-        })),
-        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "NOT_FOUND",
-                "message": "User not found"
-            }
-        })),
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "user": UserResponse::from(user)
         }
-    }
+    })))
 }
 
 async fn update_current_user(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    auth: AuthUser,
     body: web::Json<UpdateUserRequest>,
-) -> HttpResponse {
-    let user_id = match req.extensions().get::<String>() {
-        Some(id) => id.clone(),
-        None => {
-            return HttpResponse::Unauthorized().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "UNAUTHORIZED",
-                    "message": "Not authenticated"
-                }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let user_id = auth.id.clone();
 
     let mut updates = Vec::new();
     let mut params: Vec<String> = Vec::new();
@@ -98,15 +78,14 @@ async fn update_current_user(
         updates.push("department = ?");
         params.push(department.clone());
     }
+    if let Some(ref time_zone) = body.time_zone {
+        timezone::validate(time_zone)?;
+        updates.push("timezone = ?");
+        params.push(time_zone.clone());
+    }
 
     if updates.is_empty() {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "INVALID_REQUEST",
-                "message": "No fields to update"
-            }
-        }));
+        return Err(AppError::Validation("No fields to update".to_string()));
     }
 
     updates.push("updated_at = datetime('now')");
@@ -118,119 +97,58 @@ async fn update_current_user(
     }
     query_builder = query_builder.bind(&user_id);
 
-    match query_builder.execute(pool.get_ref()).await {
-        Ok(_) => {
-            // Fetch updated user
-            let user = sqlx::query_as::<_, User>(
-                "SELECT id, email, password_hash, first_name, last_name, department, role, is_active, created_at, updated_at, last_login FROM users WHERE id = ?"
-            )
-            .bind(&user_id)
-            .fetch_one(pool.get_ref())
-            .await;
-
-            match user {
-                Ok(u) => HttpResponse::Ok().json(serde_json::json!({
-                    "success": true,
-                    "data": {
-                        "user": UserResponse::from(u)
-                    }
-                })),
-                Err(e) => {
-                    eprintln!("Database error: {:?}", e);
-                    HttpResponse::InternalServerError().json(serde_json::json!({
-                        "success": false,
-                        "error": {
-                            "code": "INTERNAL_ERROR",
-                            "message": "Failed to fetch updated user"
-                        }
-                    }))
-                }
-            }
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to update user"
-                }
-            }))
+    query_builder.execute(pool.get_ref()).await?;
+
+    // Fetch updated user
+    let user = sqlx::query_as::<_, User>(
+        "SELECT id, email, password_hash, first_name, last_name, department, role, is_active, created_at, updated_at, last_login, timezone FROM users WHERE id = ?"
+    )
+    .bind(&user_id)
+    .fetch_one(pool.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "user": UserResponse::from(user)
         }
-    }
+    })))
 }
 
-async fn get_all_users(pool: web::Data<SqlitePool>, req: HttpRequest) -> HttpResponse {
-    let is_admin = req.extensions().get::<bool>().cloned().unwrap_or(false);
-
-    let query = if is_admin {
-        "SELECT id, email, password_hash, first_name, last_name, department, role, is_active, created_at, updated_at, last_login FROM users ORDER BY first_name, last_name"
+async fn get_all_users(
+    pool: web::Data<SqlitePool>,
+    auth: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let query = if auth.is_admin() {
+        "SELECT id, email, password_hash, first_name, last_name, department, role, is_active, created_at, updated_at, last_login, timezone FROM users ORDER BY first_name, last_name"
     } else {
-        "SELECT id, email, password_hash, first_name, last_name, department, role, is_active, created_at, updated_at, last_login FROM users WHERE is_active = 1 ORDER BY first_name, last_name"
+        "SELECT id, email, password_hash, first_name, last_name, department, role, is_active, created_at, updated_at, last_login, timezone FROM users WHERE is_active = 1 ORDER BY first_name, last_name"
     };
 
-    let result = sqlx::query_as::<_, User>(query)
+    let users = sqlx::query_as::<_, User>(query)
         .fetch_all(pool.get_ref())
-        .await;
-
-    match result {
-        Ok(users) => {
-            let user_responses: Vec<UserResponse> =
-                users.into_iter().map(UserResponse::from).collect();
-            HttpResponse::Ok().json(serde_json::json!({
-                "success": true,
-                "data": {
-                    "users": user_responses,
-                    "pagination": {
-                        "total": user_responses.len(),
-                        "count": user_responses.len()
-                    }
-                }
-            }))
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Database error"
-                }
-            }))
+        .await?;
+
+    let user_responses: Vec<UserResponse> = users.into_iter().map(UserResponse::from).collect();
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "users": user_responses,
+            "pagination": {
+                "total": user_responses.len(),
+                "count": user_responses.len()
+            }
         }
-    }
+    })))
 }
 
 async fn create_user(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    _auth: RequireRole<Admin>,
     body: web::Json<CreateUserRequest>,
-) -> HttpResponse {
-    let is_admin = req.extensions().get::<bool>().cloned().unwrap_or(false);
-
-    if !is_admin {
-        return HttpResponse::Forbidden().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "FORBIDDEN",
-                "message": "Only administrators can create users"
-            }
-        }));
-    }
-
-    let password_hash = match hash(&body.password, DEFAULT_COST) {
-        Ok(h) => h,
-        Err(e) => {
-            eprintln!("Hashing error: {:?}", e);
-            return HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to hash password"
-                }
-            }));
-        }
-    };
+) -> Result<HttpResponse, AppError> {
+    let password_hash = hash(&body.password, DEFAULT_COST)
+        .map_err(|e| AppError::Internal(format!("Failed to hash password: {}", e)))?;
 
     let user_id = Uuid::new_v4().to_string();
 
@@ -247,75 +165,39 @@ async fn create_user(
     .execute(pool.get_ref())
     .await;
 
-    match result {
-        Ok(_) => {
-            let user = sqlx::query_as::<_, User>(
-                "SELECT id, email, password_hash, first_name, last_name, department, role, is_active, created_at, updated_at, last_login FROM users WHERE id = ?"
-            )
-            .bind(&user_id)
-            .fetch_one(pool.get_ref())
-            .await;
-
-            match user {
-                Ok(u) => HttpResponse::Created().json(serde_json::json!({
-                    "success": true,
-                    "data": {
-                        "user": UserResponse::from(u)
-                    }
-                })),
-                Err(e) => {
-                    eprintln!("Database error: {:?}", e);
-                    HttpResponse::InternalServerError().json(serde_json::json!({
-                        "success": false,
-                        "error": {
-                            "code": "INTERNAL_ERROR",
-                            "message": "User created but failed to fetch"
-                        }
-                    }))
-                }
-            }
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            if e.to_string().contains("UNIQUE constraint failed") {
-                HttpResponse::Conflict().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "CONFLICT",
-                        "message": "Email already exists"
-                    }
-                }))
-            } else {
-                HttpResponse::InternalServerError().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "INTERNAL_ERROR",
-                        "message": "Failed to create user"
-                    }
-                }))
-            }
+    if let Err(e) = result {
+        let is_unique_violation = e
+            .as_database_error()
+            .is_some_and(|db_err| db_err.is_unique_violation());
+        if is_unique_violation {
+            return Err(AppError::Conflict("Email already exists".to_string()));
         }
+        return Err(AppError::Database(e));
     }
+
+    let user = sqlx::query_as::<_, User>(
+        "SELECT id, email, password_hash, first_name, last_name, department, role, is_active, created_at, updated_at, last_login, timezone FROM users WHERE id = ?"
+    )
+    .bind(&user_id)
+    .fetch_one(pool.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "user": UserResponse::from(user)
+        }
+    })))
 }
 
 async fn update_user(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    config: web::Data<Config>,
+    revoked: web::Data<RevokedSessions>,
+    _auth: RequireRole<Admin>,
     path: web::Path<String>,
     body: web::Json<UpdateUserRequest>,
-) -> HttpResponse {
-    let is_admin = req.extensions().get::<bool>().cloned().unwrap_or(false);
-
-    if !is_admin {
-        return HttpResponse::Forbidden().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "FORBIDDEN",
-                "message": "Only administrators can update users"
-            }
-        }));
-    }
-
+) -> Result<HttpResponse, AppError> {
     let user_id = path.into_inner();
     let mut updates = Vec::new();
     let mut params: Vec<String> = Vec::new();
@@ -332,6 +214,11 @@ async fn update_user(
         updates.push("department = ?");
         params.push(department.clone());
     }
+    if let Some(ref time_zone) = body.time_zone {
+        timezone::validate(time_zone)?;
+        updates.push("timezone = ?");
+        params.push(time_zone.clone());
+    }
     if let Some(ref role) = body.role {
         updates.push("role = ?");
         params.push(role.clone());
@@ -346,15 +233,15 @@ async fn update_user(
     }
 
     if updates.is_empty() {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "INVALID_REQUEST",
-                "message": "No fields to update"
-            }
-        }));
+        return Err(AppError::Validation("No fields to update".to_string()));
     }
 
+    let old_role = sqlx::query_scalar::<_, String>("SELECT role FROM users WHERE id = ?")
+        .bind(&user_id)
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
     updates.push("updated_at = datetime('now')");
     let query = format!("UPDATE users SET {} WHERE id = ?", updates.join(", "));
 
@@ -364,109 +251,130 @@ async fn update_user(
     }
     query_builder = query_builder.bind(&user_id);
 
-    match query_builder.execute(pool.get_ref()).await {
-        Ok(result) => {
-            if result.rows_affected() == 0 {
-                return HttpResponse::NotFound().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "NOT_FOUND",
-                        "message": "User not found"
-                    }
-                }));
-            }
+    let result = query_builder.execute(pool.get_ref()).await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("User not found".to_string()));
+    }
 
-            let user = sqlx::query_as::<_, User>(
-                "SELECT id, email, password_hash, first_name, last_name, department, role, is_active, created_at, updated_at, last_login FROM users WHERE id = ?"
-            )
-            .bind(&user_id)
-            .fetch_one(pool.get_ref())
-            .await;
-
-            match user {
-                Ok(u) => HttpResponse::Ok().json(serde_json::json!({
-                    "success": true,
-                    "data": {
-                        "user": UserResponse::from(u)
-                    }
-                })),
-                Err(e) => {
-                    eprintln!("Database error: {:?}", e);
-                    HttpResponse::InternalServerError().json(serde_json::json!({
-                        "success": false,
-                        "error": {
-                            "code": "INTERNAL_ERROR",
-                            "message": "Failed to fetch updated user"
-                        }
-                    }))
-                }
-            }
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to update user"
-                }
-            }))
-        }
+    // Access tokens carry the role, so a new role logs the user out
+    // everywhere, as does deactivation
+    let role_changed = body.role.as_ref().is_some_and(|role| *role != old_role);
+    if body.is_active == Some(false) || role_changed {
+        revoke_sessions(pool.get_ref(), &config, &revoked, &user_id, Revoke::All).await?;
     }
+
+    let user = sqlx::query_as::<_, User>(
+        "SELECT id, email, password_hash, first_name, last_name, department, role, is_active, created_at, updated_at, last_login, timezone FROM users WHERE id = ?"
+    )
+    .bind(&user_id)
+    .fetch_one(pool.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": {
+            "user": UserResponse::from(user)
+        }
+    })))
 }
 
 async fn delete_user(
     pool: web::Data<SqlitePool>,
-    req: HttpRequest,
+    config: web::Data<Config>,
+    revoked: web::Data<RevokedSessions>,
+    _auth: RequireRole<Admin>,
     path: web::Path<String>,
-) -> HttpResponse {
-    let is_admin = req.extensions().get::<bool>().cloned().unwrap_or(false);
-
-    if !is_admin {
-        return HttpResponse::Forbidden().json(serde_json::json!({
-            "success": false,
-            "error": {
-                "code": "FORBIDDEN",
-                "message": "Only administrators can delete users"
-            }
-        }));
-    }
-
+) -> Result<HttpResponse, AppError> {
     let user_id = path.into_inner();
 
     // Soft delete - just set is_active to false
-    let result =
+    let rows =
         sqlx::query("UPDATE users SET is_active = 0, updated_at = datetime('now') WHERE id = ?")
             .bind(&user_id)
             .execute(pool.get_ref())
-            .await;
-
-    match result {
-        Ok(rows) => {
-            if rows.rows_affected() > 0 {
-                HttpResponse::Ok().json(serde_json::json!({
-                    "success": true,
-                    "message": "User deactivated successfully"
-                }))
-            } else {
-                HttpResponse::NotFound().json(serde_json::json!({
-                    "success": false,
-                    "error": {
-                        "code": "NOT_FOUND",
-                        "message": "User not found"
-                    }
-                }))
-            }
-        }
-        Err(e) => {
-            eprintln!("Database error: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "success": false,
-                "error": {
-                    "code": "INTERNAL_ERROR",
-                    "message": "Failed to delete user"
-                }
-            }))
-        }
+            .await?;
+
+    if rows.rows_affected() == 0 {
+        return Err(AppError::NotFound("User not found".to_string()));
+    }
+
+    // A deactivated user is logged out everywhere at once
+    revoke_sessions(pool.get_ref(), &config, &revoked, &user_id, Revoke::All).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "User deactivated successfully"
+    })))
+}
+
+/// Lifts a login lockout before it expires.
+async fn unlock_user(
+    pool: web::Data<SqlitePool>,
+    auth: RequireRole<Admin>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = path.into_inner();
+    lockout::unlock_account(pool.get_ref(), &user_id, &auth.id).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "User unlocked successfully"
+    })))
+}
+
+async fn get_user_sessions(
+    pool: web::Data<SqlitePool>,
+    auth: RequireRole<Admin>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = path.into_inner();
+    let sessions = active_sessions(pool.get_ref(), &user_id, &auth.session_id).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": sessions
+    })))
+}
+
+async fn revoke_user_sessions(
+    pool: web::Data<SqlitePool>,
+    config: web::Data<Config>,
+    revoked: web::Data<RevokedSessions>,
+    _auth: RequireRole<Admin>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = path.into_inner();
+    let ended = revoke_sessions(pool.get_ref(), &config, &revoked, &user_id, Revoke::All).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "data": { "revoked": ended }
+    })))
+}
+
+async fn revoke_user_session(
+    pool: web::Data<SqlitePool>,
+    config: web::Data<Config>,
+    revoked: web::Data<RevokedSessions>,
+    _auth: RequireRole<Admin>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, AppError> {
+    let (user_id, session_id) = path.into_inner();
+    let ended = revoke_sessions(
+        pool.get_ref(),
+        &config,
+        &revoked,
+        &user_id,
+        Revoke::Only(&session_id),
+    )
+    .await?;
+
+    if ended == 0 {
+        return Err(AppError::NotFound("Session not found".to_string()));
     }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Session revoked"
+    })))
 }
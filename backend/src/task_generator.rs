@@ -0,0 +1,285 @@
+//! Recurring task generator.
+//!
+//! A background task started in `main` calls [`generate_due_tasks`] every
+//! `task_generation_interval_secs`. Every active recurring task gets an
+//! instance for each occurrence of its rule whose deadline is at most
+//! `lead_days` away, counted in its creator's time zone. Instances are plain
+//! tasks linked back through `tasks.recurring_task_id`.
+//!
+//! `recurring_tasks.last_generated_date` is advanced in the same transaction
+//! as each instance, so an occurrence is created exactly once even across
+//! restarts, and completing or deleting an instance never brings it back.
+//! Occurrences that are skipped, or whose deadline passed while the series
+//! was paused or the server was down, are not created.
+
+use chrono::{Duration, NaiveDate};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::clock::Clock;
+use crate::error::AppError;
+use crate::models::tasks::RecurringTask;
+use crate::recurrence::{self, RRule};
+use crate::routes::tasks::assign_new_task;
+use crate::timezone;
+
+pub const RECURRING_TASK_COLUMNS: &str = "r.id, r.title, r.description, r.urgency, r.department, r.project_id, r.recurrence_rule, r.start_date, r.lead_days, r.skipped_dates, r.last_generated_date, r.is_active, r.created_by, r.created_at, r.updated_at";
+
+/// Deadlines of a series that are due to be created on `today`: those within
+/// `lead_days` that are neither past, skipped nor already generated.
+pub fn due_dates(
+    rule: &RRule,
+    start_date: NaiveDate,
+    skipped: &[NaiveDate],
+    last_generated: Option<NaiveDate>,
+    today: NaiveDate,
+    lead_days: i64,
+) -> Vec<NaiveDate> {
+    let from = match last_generated {
+        Some(last) => today.max(last + Duration::days(1)),
+        None => today,
+    };
+    rule.occurrences(start_date, skipped, from, today + Duration::days(lead_days))
+}
+
+/// Creates the instances that are due, returning how many were created.
+pub async fn generate_due_tasks(pool: &SqlitePool, clock: &dyn Clock) -> Result<usize, AppError> {
+    let series = sqlx::query_as::<_, RecurringTask>(&format!(
+        "SELECT {} FROM recurring_tasks r WHERE r.is_active = 1",
+        RECURRING_TASK_COLUMNS
+    ))
+    .fetch_all(pool)
+    .await?;
+
+    let mut created = 0;
+    for recurring in series {
+        // A series whose rule no longer parses is left alone rather than
+        // stopping the others
+        let (Ok(rule), Ok(start_date)) = (
+            RRule::parse(&recurring.recurrence_rule),
+            recurrence::parse_date(&recurring.start_date),
+        ) else {
+            continue;
+        };
+        let today = timezone::user_time_zone(pool, &recurring.created_by)
+            .await?
+            .today(clock.now());
+        let last_generated = recurring
+            .last_generated_date
+            .as_deref()
+            .and_then(|date| recurrence::parse_date(date).ok());
+
+        for date in due_dates(
+            &rule,
+            start_date,
+            &recurrence::parse_exdates(recurring.skipped_dates.as_deref()),
+            last_generated,
+            today,
+            recurring.lead_days,
+        ) {
+            create_instance(pool, &recurring, date).await?;
+            created += 1;
+        }
+    }
+
+    Ok(created)
+}
+
+/// Creates the instance due on `date` and moves the series past it.
+async fn create_instance(
+    pool: &SqlitePool,
+    recurring: &RecurringTask,
+    date: NaiveDate,
+) -> Result<(), AppError> {
+    let date = date.format("%Y-%m-%d").to_string();
+    let task_id = Uuid::new_v4().to_string();
+    let assignee_ids = sqlx::query_scalar::<_, String>(
+        "SELECT user_id FROM recurring_task_assignees WHERE recurring_task_id = ?",
+    )
+    .bind(&recurring.id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO tasks (id, title, description, urgency, department, project_id, created_by, deadline, recurring_task_id, occurrence_date) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&task_id)
+    .bind(&recurring.title)
+    .bind(&recurring.description)
+    .bind(&recurring.urgency)
+    .bind(&recurring.department)
+    .bind(&recurring.project_id)
+    .bind(&recurring.created_by)
+    .bind(&date)
+    .bind(&recurring.id)
+    .bind(&date)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO task_history (id, task_id, user_id, action) VALUES (?, ?, ?, 'created')",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(&task_id)
+    .bind(&recurring.created_by)
+    .execute(&mut *tx)
+    .await?;
+
+    assign_new_task(
+        &mut tx,
+        &task_id,
+        recurring.project_id.as_deref(),
+        Some(&assignee_ids),
+        &recurring.created_by,
+    )
+    .await;
+
+    sqlx::query("UPDATE recurring_tasks SET last_generated_date = ? WHERE id = ?")
+        .bind(&date)
+        .bind(&recurring.id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use chrono::NaiveDateTime;
+
+    fn date(value: &str) -> NaiveDate {
+        recurrence::parse_date(value).unwrap()
+    }
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    async fn test_pool() -> SqlitePool {
+        let pool = crate::migrations::test_pool().await;
+        for id in ["planner", "technician"] {
+            sqlx::query(
+                "INSERT INTO users (id, email, password_hash, first_name, last_name, department, role) VALUES (?, ?, 'x', 'Test', 'User', 'Engineering', 'Member')",
+            )
+            .bind(id)
+            .bind(format!("{}@company.com", id))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        pool
+    }
+
+    /// Monthly filter checks due on the 15th, created a week ahead.
+    async fn add_series(pool: &SqlitePool) -> String {
+        let id = Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO recurring_tasks (id, title, urgency, department, recurrence_rule, start_date, lead_days, created_by) VALUES (?, 'Check AHU filters', 'medium', 'Engineering', 'FREQ=MONTHLY', '2026-01-15', 7, 'planner')",
+        )
+        .bind(&id)
+        .execute(pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO recurring_task_assignees (recurring_task_id, user_id) VALUES (?, 'technician')")
+            .bind(&id)
+            .execute(pool)
+            .await
+            .unwrap();
+        id
+    }
+
+    async fn instances(pool: &SqlitePool) -> Vec<(String, String)> {
+        sqlx::query_as("SELECT id, deadline FROM tasks ORDER BY deadline")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn due_dates_look_ahead_from_today_or_the_last_generated() {
+        let monthly = RRule::parse("FREQ=MONTHLY").unwrap();
+        let start = date("2026-01-15");
+
+        assert_eq!(
+            due_dates(&monthly, start, &[], None, date("2026-01-08"), 7),
+            [date("2026-01-15")]
+        );
+        assert!(due_dates(&monthly, start, &[], None, date("2026-01-08"), 6).is_empty());
+        assert!(
+            due_dates(
+                &monthly,
+                start,
+                &[],
+                Some(date("2026-01-15")),
+                date("2026-01-10"),
+                7
+            )
+            .is_empty()
+        );
+        // Missed deadlines are not backfilled, skipped ones not created
+        assert_eq!(
+            due_dates(&monthly, start, &[], None, date("2026-04-10"), 7),
+            [date("2026-04-15")]
+        );
+        assert!(
+            due_dates(
+                &monthly,
+                start,
+                &[date("2026-04-15")],
+                None,
+                date("2026-04-10"),
+                7
+            )
+            .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn creates_each_instance_once_ahead_of_its_deadline() {
+        let pool = test_pool().await;
+        let series = add_series(&pool).await;
+        let clock = FixedClock::new(at("2026-01-07 12:00"));
+
+        assert_eq!(generate_due_tasks(&pool, &clock).await.unwrap(), 0);
+
+        clock.set(at("2026-01-08 00:00"));
+        assert_eq!(generate_due_tasks(&pool, &clock).await.unwrap(), 1);
+        assert_eq!(generate_due_tasks(&pool, &clock).await.unwrap(), 0);
+        let created = instances(&pool).await;
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0].1, "2026-01-15");
+
+        let (recurring_task_id, assignee): (String, String) = sqlx::query_as(
+            "SELECT t.recurring_task_id, a.user_id FROM tasks t JOIN task_assignees a ON a.task_id = t.id",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(recurring_task_id, series);
+        assert_eq!(assignee, "technician");
+    }
+
+    #[tokio::test]
+    async fn deleting_an_instance_does_not_recreate_it() {
+        let pool = test_pool().await;
+        add_series(&pool).await;
+        let clock = FixedClock::new(at("2026-01-10 09:00"));
+        assert_eq!(generate_due_tasks(&pool, &clock).await.unwrap(), 1);
+
+        let (task_id, _) = instances(&pool).await.remove(0);
+        sqlx::query("DELETE FROM tasks WHERE id = ?")
+            .bind(&task_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(generate_due_tasks(&pool, &clock).await.unwrap(), 0);
+
+        clock.set(at("2026-02-09 09:00"));
+        assert_eq!(generate_due_tasks(&pool, &clock).await.unwrap(), 1);
+        assert_eq!(instances(&pool).await[0].1, "2026-02-15");
+    }
+}
@@ -0,0 +1,353 @@
+//! IANA time zones, from the time zone database bundled by `chrono-tz`.
+//!
+//! Timestamps are stored in UTC as `YYYY-MM-DD HH:MM:SS`, the format of
+//! SQLite's `datetime('now')`. Users pick a zone that decides what "today"
+//! means for them, and events keep the zone their wall-clock times are in,
+//! so a weekly 09:00 meeting stays at 09:00 across daylight saving changes.
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Offset, TimeZone as _, Utc, Weekday};
+use chrono_tz::Tz;
+use sqlx::SqliteExecutor;
+
+use crate::error::AppError;
+
+pub const UTC: &str = "UTC";
+
+/// Format of every stored timestamp.
+pub const SQL_DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeZone(Tz);
+
+/// The current time in UTC.
+pub fn now_utc() -> NaiveDateTime {
+    Utc::now().naive_utc()
+}
+
+pub fn format_sql_datetime(at: NaiveDateTime) -> String {
+    at.format(SQL_DATETIME_FORMAT).to_string()
+}
+
+impl TimeZone {
+    pub fn utc() -> TimeZone {
+        TimeZone(Tz::UTC)
+    }
+
+    /// Looks up an IANA zone such as `Europe/Berlin`.
+    pub fn named(name: &str) -> Result<TimeZone, AppError> {
+        name.parse().map(TimeZone).map_err(|_| {
+            AppError::Validation(format!("Unknown time zone '{}'", name))
+                .with_code("INVALID_TIME_ZONE")
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        self.0.name()
+    }
+
+    /// Offset from UTC, in seconds, at the UTC instant `at`.
+    pub fn offset_at(self, at: NaiveDateTime) -> i32 {
+        self.0.offset_from_utc_datetime(&at).fix().local_minus_utc()
+    }
+
+    pub fn to_local(self, at: NaiveDateTime) -> NaiveDateTime {
+        at + Duration::seconds(self.offset_at(at).into())
+    }
+
+    /// The UTC instant of a wall-clock time. A time skipped by a daylight
+    /// saving change is moved forward by the size of the gap; a time that
+    /// occurs twice is taken the first time.
+    pub fn to_utc(self, local: NaiveDateTime) -> NaiveDateTime {
+        let before = self.offset_at(local - Duration::days(1));
+        let after = self.offset_at(local + Duration::days(1));
+        [before, after]
+            .into_iter()
+            .map(|offset| local - Duration::seconds(offset.into()))
+            .filter(|at| self.to_local(*at) == local)
+            .min()
+            .unwrap_or(local - Duration::seconds(before.into()))
+    }
+
+    /// The local date at the UTC instant `now`.
+    pub fn today(self, now: NaiveDateTime) -> NaiveDate {
+        self.to_local(now).date()
+    }
+
+    /// The UTC instants at which the local `date` begins and ends.
+    pub fn day_bounds(self, date: NaiveDate) -> (NaiveDateTime, NaiveDateTime) {
+        let start = self.to_utc(date.and_time(chrono::NaiveTime::MIN));
+        let end = self.to_utc((date + Duration::days(1)).and_time(chrono::NaiveTime::MIN));
+        (start, end)
+    }
+
+    /// How the zone keeps time now and in future years, for describing it to
+    /// other systems. Read from next year's offset changes; daylight saving
+    /// time is described only if it starts and ends on a weekday of the same
+    /// week of the month every year.
+    pub fn current_rules(self) -> CurrentRules {
+        self.rules_in(now_utc().year() + 1)
+    }
+
+    fn rules_in(self, year: i32) -> CurrentRules {
+        let no_daylight_saving = CurrentRules {
+            std_offset: self.offset_at(year_start(year + 1)),
+            daylight_saving: None,
+        };
+        let [first, second] = self.transitions_in(year)[..] else {
+            return no_daylight_saving;
+        };
+        // (instant, offset before, offset after)
+        let (start, end) = if first.2 > first.1 {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        if start.1 != end.2 || start.2 != end.1 {
+            return no_daylight_saving;
+        }
+
+        let next_year = self.transitions_in(year + 1);
+        let change = |(at, before, _): (NaiveDateTime, i32, i32)| {
+            let next = next_year
+                .iter()
+                .find(|t| t.1 == before)
+                .map(|t| t.0 + Duration::seconds(t.1.into()));
+            YearlyChange::from_local(at + Duration::seconds(before.into()), next)
+        };
+        CurrentRules {
+            std_offset: start.1,
+            daylight_saving: Some(DaylightSaving {
+                offset: start.2,
+                start: change(start),
+                end: change(end),
+            }),
+        }
+    }
+
+    /// Each instant in `year` at which the offset changes, with the offsets
+    /// before and after.
+    fn transitions_in(self, year: i32) -> Vec<(NaiveDateTime, i32, i32)> {
+        let mut transitions = Vec::new();
+        let mut day = year_start(year);
+        while day < year_start(year + 1) {
+            let next = day + Duration::days(1);
+            let (before, after) = (self.offset_at(day), self.offset_at(next));
+            if before != after {
+                // The first second of the day with the new offset
+                let (mut low, mut high) = (0, 86_400);
+                while high - low > 1 {
+                    let mid = (low + high) / 2;
+                    if self.offset_at(day + Duration::seconds(mid)) == before {
+                        low = mid;
+                    } else {
+                        high = mid;
+                    }
+                }
+                let at = day + Duration::seconds(high);
+                transitions.push((at, before, self.offset_at(at)));
+            }
+            day = next;
+        }
+        transitions
+    }
+}
+
+fn year_start(year: i32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(year, 1, 1)
+        .unwrap_or_default()
+        .and_time(chrono::NaiveTime::MIN)
+}
+
+/// A zone's standard offset from UTC in seconds and, if it observes it, its
+/// yearly daylight saving time.
+pub struct CurrentRules {
+    pub std_offset: i32,
+    pub daylight_saving: Option<DaylightSaving>,
+}
+
+pub struct DaylightSaving {
+    pub offset: i32,
+    /// In local standard time
+    pub start: YearlyChange,
+    /// In local daylight saving time
+    pub end: YearlyChange,
+}
+
+/// A change on weekday `weekday` (0 = Sunday) of week `week` (5 = last) of
+/// `month`, `time` seconds after local midnight.
+#[derive(Debug, PartialEq)]
+pub struct YearlyChange {
+    pub month: u32,
+    pub week: u32,
+    pub weekday: u32,
+    pub time: i32,
+}
+
+impl YearlyChange {
+    /// The change at local time `at`, taken as the last such weekday of the
+    /// month if it is, and still is at `next`, the change a year later.
+    fn from_local(at: NaiveDateTime, next: Option<NaiveDateTime>) -> YearlyChange {
+        let date = at.date();
+        let mut change = YearlyChange {
+            month: date.month(),
+            week: (date.day() - 1) / 7 + 1,
+            weekday: date.weekday().num_days_from_sunday(),
+            time: at
+                .time()
+                .signed_duration_since(chrono::NaiveTime::MIN)
+                .num_seconds() as i32,
+        };
+        let is_last = (date + Duration::days(7)).month() != date.month();
+        if is_last {
+            let last = YearlyChange { week: 5, ..change };
+            if next.is_none_or(|next| last.local_time(next.year()) == next) {
+                change = last;
+            }
+        }
+        change
+    }
+
+    /// When the change first happens in `year`, in local time.
+    pub fn local_time(&self, year: i32) -> NaiveDateTime {
+        let weekday = Weekday::try_from(((self.weekday + 6) % 7) as u8).unwrap_or(Weekday::Sun);
+        let date = NaiveDate::from_weekday_of_month_opt(year, self.month, weekday, self.week as u8)
+            .or_else(|| NaiveDate::from_weekday_of_month_opt(year, self.month, weekday, 4))
+            .unwrap_or_default();
+        date.and_time(chrono::NaiveTime::MIN) + Duration::seconds(self.time.into())
+    }
+}
+
+/// Checks a time zone name sent by a client.
+pub fn validate(name: &str) -> Result<(), AppError> {
+    TimeZone::named(name).map(|_| ())
+}
+
+/// The time zone a user has chosen; UTC for unknown users or zones that are
+/// no longer available.
+pub async fn user_time_zone(
+    executor: impl SqliteExecutor<'_>,
+    user_id: &str,
+) -> Result<TimeZone, AppError> {
+    let name: Option<String> = sqlx::query_scalar("SELECT timezone FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(executor)
+        .await?;
+    Ok(name
+        .and_then(|name| TimeZone::named(&name).ok())
+        .unwrap_or_else(TimeZone::utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn zone(name: &str) -> TimeZone {
+        TimeZone::named(name).unwrap()
+    }
+
+    #[test]
+    fn offsets_switch_at_local_times() {
+        let berlin = zone("Europe/Berlin");
+        // 2030: DST from 31 March 01:00 UTC to 27 October 01:00 UTC.
+        assert_eq!(berlin.offset_at(at("2030-03-31 00:59")), 3600);
+        assert_eq!(berlin.offset_at(at("2030-03-31 01:00")), 7200);
+        assert_eq!(berlin.offset_at(at("2030-10-27 00:59")), 7200);
+        assert_eq!(berlin.offset_at(at("2030-10-27 01:00")), 3600);
+
+        // Southern hemisphere: daylight time spans the new year.
+        let sydney = zone("Australia/Sydney");
+        assert_eq!(sydney.offset_at(at("2030-01-15 00:00")), 11 * 3600);
+        assert_eq!(sydney.offset_at(at("2030-06-15 00:00")), 10 * 3600);
+
+        let new_york = zone("America/New_York");
+        assert_eq!(new_york.offset_at(at("2020-01-01 12:00")), -5 * 3600);
+        assert_eq!(new_york.offset_at(at("2090-07-01 12:00")), -4 * 3600);
+    }
+
+    #[test]
+    fn local_times_in_gaps_and_overlaps() {
+        let berlin = zone("Europe/Berlin");
+        // 02:30 does not exist on 31 March 2030 and becomes 03:30.
+        assert_eq!(
+            berlin.to_utc(at("2030-03-31 02:30")),
+            at("2030-03-31 01:30")
+        );
+        // 02:30 happens twice on 27 October 2030; the first is taken.
+        assert_eq!(
+            berlin.to_utc(at("2030-10-27 02:30")),
+            at("2030-10-27 00:30")
+        );
+        assert_eq!(
+            berlin.to_utc(at("2030-07-01 09:00")),
+            at("2030-07-01 07:00")
+        );
+
+        let date = NaiveDate::from_ymd_opt(2030, 3, 31).unwrap();
+        assert_eq!(
+            berlin.day_bounds(date),
+            (at("2030-03-30 23:00"), at("2030-03-31 22:00"))
+        );
+        assert_eq!(berlin.today(at("2030-03-30 23:30")), date);
+    }
+
+    #[test]
+    fn rules_describe_yearly_changes() {
+        let rules = zone("Europe/Berlin").rules_in(2030);
+        assert_eq!(rules.std_offset, 3600);
+        let dst = rules.daylight_saving.unwrap();
+        assert_eq!(dst.offset, 7200);
+        // Last Sunday of March at 02:00, last Sunday of October at 03:00
+        assert_eq!(
+            (dst.start, dst.end),
+            (
+                YearlyChange {
+                    month: 3,
+                    week: 5,
+                    weekday: 0,
+                    time: 2 * 3600
+                },
+                YearlyChange {
+                    month: 10,
+                    week: 5,
+                    weekday: 0,
+                    time: 3 * 3600
+                }
+            )
+        );
+
+        // Second Sunday of March, first Sunday of November
+        let dst = zone("America/New_York")
+            .rules_in(2030)
+            .daylight_saving
+            .unwrap();
+        assert_eq!((dst.start.month, dst.start.week), (3, 2));
+        assert_eq!((dst.end.month, dst.end.week), (11, 1));
+
+        let sydney = zone("Australia/Sydney").rules_in(2030);
+        assert_eq!(sydney.std_offset, 10 * 3600);
+        assert_eq!(sydney.daylight_saving.unwrap().start.month, 10);
+
+        let tokyo = zone("Asia/Tokyo").rules_in(2030);
+        assert_eq!(tokyo.std_offset, 9 * 3600);
+        assert!(tokyo.daylight_saving.is_none());
+    }
+
+    #[test]
+    fn unknown_names_are_rejected() {
+        for name in [
+            "",
+            "../etc/passwd",
+            "/etc/localtime",
+            "Europe//Berlin",
+            "Mars/Olympus",
+        ] {
+            assert!(TimeZone::named(name).is_err(), "{name}");
+        }
+        assert_eq!(zone("UTC").offset_at(at("2030-01-01 00:00")), 0);
+        assert_eq!(zone("Europe/Berlin").name(), "Europe/Berlin");
+    }
+}
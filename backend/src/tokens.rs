@@ -0,0 +1,261 @@
+//! Access and refresh tokens.
+//!
+//! Access tokens are short-lived HS256 JWTs. The `Auth` middleware validates
+//! them with the configured secret alone, without touching the database.
+//!
+//! Refresh tokens are opaque random strings. Only their SHA-256 hash is stored,
+//! in `sessions.token`, and every refresh rotates the token. Rotated hashes are
+//! kept in `rotated_refresh_tokens`; presenting one again means the token was
+//! copied, so the whole session is revoked.
+//!
+//! Password reset tokens are opaque too and stored hashed in
+//! `password_reset_tokens`; each can be used once.
+//!
+//! Revoking a session deletes its row, which stops refreshes at once. Access
+//! tokens already issued for it are blocked by [`RevokedSessions`] until they
+//! would have expired anyway.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode, errors::ErrorKind,
+};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::middleware::guards::{AuthUser, Role};
+
+const OPAQUE_TOKEN_BYTES: usize = 32;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    /// User id
+    pub sub: String,
+    /// Session the token was issued for
+    pub sid: String,
+    pub role: String,
+    pub department: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+impl From<Claims> for AuthUser {
+    fn from(claims: Claims) -> Self {
+        AuthUser {
+            id: claims.sub,
+            role: Role::from_db(&claims.role),
+            department: claims.department,
+            session_id: claims.sid,
+        }
+    }
+}
+
+/// A signed access token and its expiry (RFC 3339).
+pub struct AccessToken {
+    pub token: String,
+    pub expires_at: String,
+}
+
+pub fn issue_access_token(
+    config: &Config,
+    session_id: &str,
+    user_id: &str,
+    role: &str,
+    department: &str,
+) -> Result<AccessToken, AppError> {
+    let now = Utc::now();
+    let expires_at = now + config.access_token_ttl();
+
+    let claims = Claims {
+        sub: user_id.to_string(),
+        sid: session_id.to_string(),
+        role: role.to_string(),
+        department: department.to_string(),
+        iat: now.timestamp(),
+        exp: expires_at.timestamp(),
+    };
+
+    let token = encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(config.jwt_secret.as_ref()),
+    )
+    .map_err(|e| AppError::Internal(format!("Failed to sign access token: {}", e)))?;
+
+    Ok(AccessToken {
+        token,
+        expires_at: expires_at.to_rfc3339(),
+    })
+}
+
+pub fn verify_access_token(config: &Config, token: &str) -> Result<Claims, AppError> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.leeway = 0;
+
+    decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(config.jwt_secret.as_ref()),
+        &validation,
+    )
+    .map(|data| data.claims)
+    .map_err(|e| match e.kind() {
+        ErrorKind::ExpiredSignature => {
+            AppError::Unauthorized("Access token expired! Please refresh.".to_string())
+                .with_code("TOKEN_EXPIRED")
+        }
+        _ => AppError::Unauthorized("Invalid access token! Please log in again.".to_string())
+            .with_code("INVALID_TOKEN"),
+    })
+}
+
+/// Returns a new random token, used for refresh and password reset tokens.
+/// Hand it to the client; store only its hash.
+pub fn new_opaque_token() -> String {
+    let mut bytes = [0u8; OPAQUE_TOKEN_BYTES];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+pub fn hash_opaque_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+pub fn session_expires_at(config: &Config) -> DateTime<Utc> {
+    Utc::now() + config.session_lifetime()
+}
+
+/// Formats a timestamp like SQLite's `datetime('now')`, so stored expiries
+/// compare correctly against it in SQL.
+pub fn to_sql_datetime(at: &DateTime<Utc>) -> String {
+    at.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// Parses a timestamp written by [`to_sql_datetime`] or `datetime('now')`.
+pub fn from_sql_datetime(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|at| at.and_utc())
+}
+
+/// In-memory deny list of revoked session ids, checked by the `Auth`
+/// middleware. An entry only has to outlive the access tokens issued before
+/// the revocation, so it is dropped after one access token lifetime.
+#[derive(Default)]
+pub struct RevokedSessions {
+    until: Mutex<HashMap<String, DateTime<Utc>>>,
+}
+
+impl RevokedSessions {
+    pub fn revoke<I>(&self, config: &Config, session_ids: I)
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let now = Utc::now();
+        let until = now + config.access_token_ttl();
+        let mut revoked = self.until.lock().unwrap_or_else(|e| e.into_inner());
+        revoked.retain(|_, expires| *expires > now);
+        for id in session_ids {
+            revoked.insert(id, until);
+        }
+    }
+
+    pub fn is_revoked(&self, session_id: &str) -> bool {
+        let revoked = self.until.lock().unwrap_or_else(|e| e.into_inner());
+        revoked
+            .get(session_id)
+            .is_some_and(|expires| *expires > Utc::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        Config {
+            jwt_secret: "0123456789abcdef0123456789abcdef".to_string(),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn access_tokens_carry_the_session() {
+        let config = config();
+        let issued = issue_access_token(&config, "session", "user", "Admin", "IT").unwrap();
+
+        let claims = verify_access_token(&config, &issued.token).unwrap();
+        assert_eq!(claims.sid, "session");
+        assert_eq!(claims.exp - claims.iat, 15 * 60);
+
+        let user = AuthUser::from(claims);
+        assert_eq!(user.id, "user");
+        assert_eq!(user.role, Role::Admin);
+        assert_eq!(user.department, "IT");
+        assert_eq!(user.session_id, "session");
+    }
+
+    #[test]
+    fn rejects_tokens_not_signed_with_the_secret() {
+        let other = Config {
+            jwt_secret: "another-secret-of-at-least-32-chars".to_string(),
+            ..Config::default()
+        };
+        let issued = issue_access_token(&other, "session", "user", "Member", "IT").unwrap();
+
+        for token in [issued.token.as_str(), "not-a-jwt", ""] {
+            let err = verify_access_token(&config(), token).unwrap_err();
+            assert_eq!(err.code(), "INVALID_TOKEN");
+        }
+    }
+
+    #[test]
+    fn rejects_expired_tokens() {
+        let expired = Config {
+            access_token_ttl_minutes: -1,
+            ..config()
+        };
+        let issued = issue_access_token(&expired, "session", "user", "Member", "IT").unwrap();
+
+        let err = verify_access_token(&config(), &issued.token).unwrap_err();
+        assert_eq!(err.code(), "TOKEN_EXPIRED");
+    }
+
+    #[test]
+    fn opaque_tokens_are_random_and_stored_hashed() {
+        let token = new_opaque_token();
+        assert_eq!(token.len(), OPAQUE_TOKEN_BYTES * 2);
+        assert_ne!(token, new_opaque_token());
+
+        let hash = hash_opaque_token(&token);
+        assert_eq!(hash, hash_opaque_token(&token));
+        assert_ne!(hash, token);
+    }
+
+    #[test]
+    fn sql_datetimes_round_trip() {
+        let at = from_sql_datetime("2026-03-10 14:30:00").unwrap();
+        assert_eq!(to_sql_datetime(&at), "2026-03-10 14:30:00");
+        assert_eq!(from_sql_datetime("2026-03-10T14:30:00Z"), None);
+    }
+
+    #[test]
+    fn revoked_sessions_are_rejected_for_one_token_lifetime() {
+        let revoked = RevokedSessions::default();
+        revoked.revoke(&config(), ["revoked".to_string()]);
+        assert!(revoked.is_revoked("revoked"));
+        assert!(!revoked.is_revoked("other"));
+
+        // Tokens issued before the revocation have expired by the time the
+        // entry does, so it no longer blocks anything
+        let no_lifetime = Config {
+            access_token_ttl_minutes: 0,
+            ..config()
+        };
+        revoked.revoke(&no_lifetime, ["expired".to_string()]);
+        assert!(!revoked.is_revoked("expired"));
+    }
+}
@@ -32,6 +32,8 @@ export interface AuthResponse {
     user: User;
     token: string;
     expiresAt: string;
+    refreshToken: string;
+    refreshExpiresAt: string;
   };
   error?: {
     code: string;
@@ -125,10 +127,35 @@ const dispatchSessionExpired = () => {
   window.dispatchEvent(new CustomEvent(SESSION_EXPIRED_EVENT));
 };
 
+// Exchange the refresh token cookie for a new access token.
+// Concurrent callers share one request so the refresh token is only rotated once.
+let refreshInFlight: Promise<boolean> | null = null;
+
+const refreshAccessToken = (): Promise<boolean> => {
+  if (!refreshInFlight) {
+    refreshInFlight = fetch(`${API_BASE_URL}/auth/refresh`, {
+      method: 'POST',
+      credentials: 'include',
+    })
+      .then(async (response) => {
+        if (!response.ok) return false;
+        const data = await response.json();
+        localStorage.setItem('token', data.data.token);
+        return true;
+      })
+      .catch(() => false)
+      .finally(() => {
+        refreshInFlight = null;
+      });
+  }
+  return refreshInFlight;
+};
+
 // API helper
 async function apiRequest<T>(
   endpoint: string,
-  options: RequestInit = {}
+  options: RequestInit = {},
+  retried = false
 ): Promise<T> {
   const token = localStorage.getItem('token');
 
@@ -147,8 +174,13 @@ async function apiRequest<T>(
     credentials: 'include',
   });
 
-  // Handle 401 Unauthorized - clear stored auth and trigger logout
+  // Handle 401 Unauthorized - refresh an expired access token once,
+  // otherwise clear stored auth and trigger logout
   if (response.status === 401) {
+    const body = await response.json().catch(() => null);
+    if (!retried && body?.error?.code === 'TOKEN_EXPIRED' && (await refreshAccessToken())) {
+      return apiRequest<T>(endpoint, options, true);
+    }
     dispatchSessionExpired();
     throw new Error('Session expired. Please log in again.');
   }
@@ -215,18 +247,7 @@ export const authApi = {
     }
   },
 
-  refreshToken: async (): Promise<{ token: string; expiresAt: string }> => {
-    const response = await apiRequest<{ success: boolean; data: { token: string; expiresAt: string } }>(
-      '/auth/refresh',
-      { method: 'POST' }
-    );
-
-    if (response.success && response.data) {
-      localStorage.setItem('token', response.data.token);
-    }
-
-    return response.data;
-  },
+  refreshToken: async (): Promise<boolean> => refreshAccessToken(),
 
   getCurrentUser: (): User | null => {
     const userStr = localStorage.getItem('user');
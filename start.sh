@@ -128,21 +128,18 @@ start_backend() {
     print_status "Starting backend server..."
     cd "$BACKEND_DIR"
     
-    # Check if database exists, if not initialize it
+    # The backend applies schema migrations itself on startup. Demo data is
+    # only seeded the first time, so existing data survives restarts.
+    # Set SEED_DATABASE=true to force a wipe and re-seed.
     if [ ! -f "database.db" ]; then
-        print_status "Initializing database..."
-        if [ -f "schema.sql" ]; then
-            if command_exists sqlite3; then
-                sqlite3 database.db < schema.sql
-                print_success "Database initialized"
-            else
-                print_warning "sqlite3 not found, database will be created on first run by the backend"
-            fi
-        else
-            print_warning "schema.sql not found, database will be created on first run"
-        fi
+        print_status "No database found, it will be created and seeded with demo data..."
+        export SEED_DATABASE=true
     fi
     
+    # start.sh is the local development entry point, so allow the built-in
+    # dev defaults (e.g. the placeholder JWT secret) unless told otherwise
+    export DEV_MODE="${DEV_MODE:-true}"
+    
     # Build and run the backend
     cargo run --bin it_engineering_dashboard &
     BACKEND_PID=$!